@@ -0,0 +1,122 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use ringring_rs::model::{Activity, GuildConfig, Participant, VoiceStateFlags};
+use ringring_rs::service::asset::MemberVisual;
+use ringring_rs::service::renderer::draw::{TextAnchor, TextRenderer};
+use ringring_rs::service::renderer::encoder::ImageFormat;
+use ringring_rs::service::renderer::theme::ThemeKind;
+use ringring_rs::service::renderer::timeline::{create_hatching_pattern, TimelineRenderer};
+use ringring_rs::service::renderer::transformer::transform;
+use ringring_rs::service::report::RoomDTO;
+use serenity::all::{ChannelId, GuildId, Timestamp, UserId};
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::sync::Arc;
+use std::time::Duration;
+use tiny_skia::{Color, Pixmap};
+use tokio::time::Instant;
+
+const ENTRY_COUNTS: [usize; 3] = [5, 20, 50];
+const CALL_DURATION: Duration = Duration::from_hours(2);
+
+fn flags(i: usize) -> VoiceStateFlags {
+    VoiceStateFlags {
+        is_muted: i % 3 == 1,
+        is_deafened: i % 7 == 3,
+        is_sharing_screen: i % 5 == 2,
+        is_audience: false,
+        is_video: i % 11 == 4,
+    }
+}
+
+// every member switches states a few times and stays connected, so no row is cached between iterations.
+fn room(now: Instant, entries: usize) -> RoomDTO {
+    let created_at = now - CALL_DURATION;
+    let step = CALL_DURATION / 8;
+    let participants = (0..entries).map(|i| {
+        let history = (0..8).map(|j| {
+            let start = created_at + step * j;
+            let end = if j == 7 { None } else { Some(start + step) };
+            Activity::restore(start, end, flags(i + j as usize))
+        }).collect();
+        Participant::restore(UserId::new(i as u64 + 1), format!("member {}", i), String::new(), history)
+    }).collect();
+
+    RoomDTO {
+        created_at,
+        timestamp: Timestamp::now(),
+        guild_id: GuildId::new(1),
+        channel_id: ChannelId::new(1),
+        category_id: None,
+        user_limit: None,
+        channel_name: None,
+        participants,
+        peak_participants: entries,
+        scheduled_event: None,
+        paused_spans: Vec::new(),
+        revision: 0,
+        session: 1,
+        expires_at: None,
+    }
+}
+
+fn visuals(room: &RoomDTO) -> HashMap<UserId, MemberVisual> {
+    room.participants.iter().map(|participant| {
+        let visual = MemberVisual {
+            avatar: Pixmap::new(64, 64).unwrap(),
+            active_color: Color::from_rgba8(88, 101, 242, 255),
+            inactive_color: Color::from_rgba8(88, 101, 242, 80),
+            streaming_color: Color::from_rgba8(235, 69, 158, 255),
+        };
+        (participant.user_id(), visual)
+    }).collect()
+}
+
+fn bench_transform(c: &mut Criterion) {
+    let now = Instant::now();
+    let config = GuildConfig::default();
+    let mut group = c.benchmark_group("transform");
+    for entries in ENTRY_COUNTS {
+        let room = room(now, entries);
+        let visuals = visuals(&room);
+        group.bench_with_input(BenchmarkId::from_parameter(entries), &room, |b, room| {
+            b.iter(|| transform(now, black_box(room), &visuals, true, &config))
+        });
+    }
+    group.finish();
+}
+
+fn bench_generate_image(c: &mut Criterion) {
+    let now = Instant::now();
+    let config = GuildConfig::default();
+    let theme = ThemeKind::default().theme();
+    let renderer = TimelineRenderer::new(Arc::new(TextRenderer::new()));
+    let mut group = c.benchmark_group("generate_image");
+    group.sample_size(20);
+    for entries in ENTRY_COUNTS {
+        let room = room(now, entries);
+        let timeline = transform(now, &room, &visuals(&room), true, &config);
+        group.bench_with_input(BenchmarkId::from_parameter(entries), &timeline, |b, timeline| {
+            b.iter(|| renderer.generate_image(black_box(timeline), &theme, ImageFormat::Png, 80).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_hatching(c: &mut Criterion) {
+    let active = Color::from_rgba8(88, 101, 242, 255);
+    let inactive = Color::from_rgba8(88, 101, 242, 80);
+    c.bench_function("create_hatching_pattern", |b| {
+        b.iter(|| create_hatching_pattern(black_box(active), black_box(inactive)))
+    });
+}
+
+fn bench_text(c: &mut Criterion) {
+    let text_renderer = TextRenderer::new();
+    let mut pixmap = Pixmap::new(400, 40).unwrap();
+    c.bench_function("draw_text", |b| {
+        b.iter(|| text_renderer.draw_text(&mut pixmap, black_box("member 42 · 1:02:03"), 14.0, (10.0, 28.0), Color::BLACK, TextAnchor::Start))
+    });
+}
+
+criterion_group!(benches, bench_transform, bench_generate_image, bench_hatching, bench_text);
+criterion_main!(benches);
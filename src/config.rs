@@ -0,0 +1,477 @@
+use crate::service::asset::{KmeansConfig, DEFAULT_KMEANS_ITERATIONS, DEFAULT_KMEANS_RUNS};
+use crate::service::report::{StageTimeouts, DEFAULT_ASSET_FETCH_TIMEOUT, DEFAULT_RENDER_TIMEOUT, DEFAULT_SEND_TIMEOUT};
+use crate::service::renderer::theme::ThemeKind;
+use crate::service::tracker::{TrackPolicy, DEFAULT_MAX_TRACKS, DEFAULT_TRACK_TTL};
+use serde::Deserialize;
+use serenity::all::ChannelId;
+use std::env;
+use std::num::NonZeroU64;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use thiserror::Error;
+
+const DEFAULT_TOKEN_ENV: &str = "DISCORD_TOKEN";
+const DEFAULT_DATABASE_URL: &str = "sqlite://ringring.db";
+const DEFAULT_DASHBOARD_ADDR: &str = "0.0.0.0:8080";
+const DEFAULT_CLEANUP_INTERVAL_SECS: u64 = 30;
+// granularity of the report loop; each guild's own update interval is checked on every tick.
+const DEFAULT_REPORT_TICK_SECS: u64 = 10;
+const DEFAULT_REPORT_CONCURRENCY: usize = 4;
+const DEFAULT_LEASE_SECS: u64 = 30;
+const DEFAULT_ASSET_CACHE_SIZE: u64 = 512;
+const DEFAULT_ASSET_CACHE_TTL_SECS: u64 = 6 * 60 * 60;
+const DEFAULT_RENDER_WORKERS: usize = 2;
+const DEFAULT_RENDER_QUEUE_SIZE: usize = 32;
+const DEFAULT_RENDER_SCALE: f32 = 1.0;
+const DEFAULT_HIGH_WATER_STEP_MIB: u64 = 32;
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("failed to read config file {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+
+    #[error("failed to parse config file {0}: {1}")]
+    Parse(PathBuf, toml::de::Error),
+
+    #[error("no bot token configured; set discord.token or the {0} environment variable")]
+    MissingToken(String),
+
+    #[error("invalid {0}({1}): {2}")]
+    InvalidEnv(String, String, String),
+}
+
+pub type ConfigResult<T> = Result<T, ConfigError>;
+
+// settings of the bot itself, read from a TOML file at startup.
+// environment variables still override the file, so existing deployments keep working.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub discord: DiscordConfig,
+    pub storage: StorageConfig,
+    pub intervals: IntervalConfig,
+    pub render: RenderConfig,
+    pub assets: AssetConfig,
+    pub timeouts: TimeoutConfig,
+    pub tracker: TrackerConfig,
+    pub coordination: CoordinationConfig,
+    pub room_store: RoomStoreConfig,
+    pub journal: JournalConfig,
+    pub dashboard: DashboardConfig,
+    pub health: HealthConfig,
+    pub memory: MemoryConfig,
+    pub logging: LoggingConfig,
+    // the only section applied again on SIGHUP; everything else needs a restart.
+    pub defaults: DefaultsConfig,
+    // problems found while loading, logged by the caller once logging is set up.
+    #[serde(skip)]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DiscordConfig {
+    // kept out of the file when possible; `token_env` names the variable to read it from instead.
+    pub token: Option<String>,
+    pub token_env: String,
+    // None runs a single shard.
+    pub shards: Option<u32>,
+    // requests the privileged GUILD_MEMBERS intent so changed avatars show up;
+    // it must be enabled in the developer portal first, or the gateway rejects the connection.
+    pub member_updates: bool,
+}
+
+impl Default for DiscordConfig {
+    fn default() -> Self {
+        DiscordConfig {
+            token: None,
+            token_env: String::from(DEFAULT_TOKEN_ENV),
+            shards: None,
+            member_updates: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct StorageConfig {
+    pub database_url: String,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        StorageConfig {
+            database_url: String::from(DEFAULT_DATABASE_URL),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct IntervalConfig {
+    pub cleanup_secs: u64,
+    pub report_tick_secs: u64,
+    // guilds whose scheduled reports are sent side by side.
+    pub report_concurrency: usize,
+}
+
+impl Default for IntervalConfig {
+    fn default() -> Self {
+        IntervalConfig {
+            cleanup_secs: DEFAULT_CLEANUP_INTERVAL_SECS,
+            report_tick_secs: DEFAULT_REPORT_TICK_SECS,
+            report_concurrency: DEFAULT_REPORT_CONCURRENCY,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RenderConfig {
+    pub workers: usize,
+    pub queue_size: usize,
+    pub font_dir: Option<PathBuf>,
+    pub font_family: Option<String>,
+    // renders timelines this many times larger for high-DPI screens; images over the upload limit are rendered smaller again.
+    pub scale: f32,
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        RenderConfig {
+            workers: DEFAULT_RENDER_WORKERS,
+            queue_size: DEFAULT_RENDER_QUEUE_SIZE,
+            font_dir: None,
+            font_family: None,
+            scale: DEFAULT_RENDER_SCALE,
+        }
+    }
+}
+
+// budgets of each stage of a report, so one slow room does not hold up the others.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TimeoutConfig {
+    pub asset_fetch_secs: u64,
+    pub render_secs: u64,
+    pub send_secs: u64,
+}
+
+impl TimeoutConfig {
+    pub fn stages(&self) -> StageTimeouts {
+        StageTimeouts {
+            asset_fetch: Duration::from_secs(self.asset_fetch_secs),
+            render: Duration::from_secs(self.render_secs),
+            send: Duration::from_secs(self.send_secs),
+        }
+    }
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        TimeoutConfig {
+            asset_fetch_secs: DEFAULT_ASSET_FETCH_TIMEOUT.as_secs(),
+            render_secs: DEFAULT_RENDER_TIMEOUT.as_secs(),
+            send_secs: DEFAULT_SEND_TIMEOUT.as_secs(),
+        }
+    }
+}
+
+// bounds of the report messages kept for editing.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TrackerConfig {
+    pub max_tracks: usize,
+    pub ttl_secs: u64,
+}
+
+impl TrackerConfig {
+    pub fn policy(&self) -> TrackPolicy {
+        TrackPolicy {
+            max_tracks: self.max_tracks,
+            ttl: Duration::from_secs(self.ttl_secs),
+        }
+    }
+}
+
+impl Default for TrackerConfig {
+    fn default() -> Self {
+        TrackerConfig {
+            max_tracks: DEFAULT_MAX_TRACKS,
+            ttl_secs: DEFAULT_TRACK_TTL.as_secs(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CoordinationMode {
+    // a single instance reports for every guild.
+    #[default]
+    None,
+    // instances sharing the database hold a lease row per guild; only the holder reports, delivers webhooks and records calls.
+    Lease,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CoordinationConfig {
+    pub mode: CoordinationMode,
+    // must differ between instances; defaults to the host name and process id.
+    pub instance_id: Option<String>,
+    // a standby takes over this long after the active instance stopped renewing.
+    pub lease_secs: u64,
+}
+
+impl CoordinationConfig {
+    pub fn instance_id(&self) -> String {
+        self.instance_id.clone().unwrap_or_else(|| {
+            let host = std::env::var("HOSTNAME").unwrap_or_else(|_| String::from("ringring"));
+            format!("{}-{}", host, std::process::id())
+        })
+    }
+
+    pub fn lease_ttl(&self) -> Duration {
+        Duration::from_secs(self.lease_secs.max(1))
+    }
+}
+
+impl Default for CoordinationConfig {
+    fn default() -> Self {
+        CoordinationConfig {
+            mode: CoordinationMode::default(),
+            instance_id: None,
+            lease_secs: DEFAULT_LEASE_SECS,
+        }
+    }
+}
+
+// where rooms are shared with other processes; None keeps them to this one.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct RoomStoreConfig {
+    // needs the redis-store feature.
+    pub redis_url: Option<String>,
+}
+
+// a local file of voice events, replayed on startup to rebuild rooms after a crash; None disables it.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct JournalConfig {
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AssetConfig {
+    pub cache_size: u64,
+    pub cache_ttl_secs: u64,
+    // fewer runs or iterations make dominant colors cheaper but less stable.
+    pub kmeans_runs: u64,
+    pub kmeans_iterations: usize,
+}
+
+impl AssetConfig {
+    pub fn kmeans(&self) -> KmeansConfig {
+        KmeansConfig {
+            runs: self.kmeans_runs,
+            iterations: self.kmeans_iterations,
+        }
+    }
+}
+
+impl Default for AssetConfig {
+    fn default() -> Self {
+        AssetConfig {
+            cache_size: DEFAULT_ASSET_CACHE_SIZE,
+            cache_ttl_secs: DEFAULT_ASSET_CACHE_TTL_SECS,
+            kmeans_runs: DEFAULT_KMEANS_RUNS,
+            kmeans_iterations: DEFAULT_KMEANS_ITERATIONS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DashboardConfig {
+    // the dashboard is only served when a token protecting it is configured.
+    pub token: Option<String>,
+    pub addr: String,
+}
+
+impl Default for DashboardConfig {
+    fn default() -> Self {
+        DashboardConfig {
+            token: None,
+            addr: String::from(DEFAULT_DASHBOARD_ADDR),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct HealthConfig {
+    // the health endpoint is only served when an address is configured.
+    pub addr: Option<String>,
+    // reports unhealthy after this many seconds without gateway events; 0 disables the check.
+    pub max_event_age_secs: u64,
+}
+
+impl HealthConfig {
+    pub fn max_event_age(&self) -> Option<Duration> {
+        (self.max_event_age_secs > 0).then(|| Duration::from_secs(self.max_event_age_secs))
+    }
+}
+
+// allocator statistics are only available with the jemalloc feature.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MemoryConfig {
+    // logs the resident size after renders that raised it this many MiB above the last logged mark; 0 disables it.
+    pub high_water_step_mib: u64,
+}
+
+impl MemoryConfig {
+    pub fn high_water_step(&self) -> u64 {
+        self.high_water_step_mib * 1024 * 1024
+    }
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        MemoryConfig {
+            high_water_step_mib: DEFAULT_HIGH_WATER_STEP_MIB,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    // one object per line, carrying the fields of the enclosing spans such as guild_id and channel_id.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("unknown log format {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    pub format: LogFormat,
+}
+
+// what guilds get until they configure these themselves.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct DefaultsConfig {
+    pub report_channel_id: Option<ChannelId>,
+    pub theme: ThemeKind,
+}
+
+impl Config {
+    // a missing file is not an error; the defaults and the environment are used instead.
+    pub fn load(path: &Path) -> ConfigResult<Config> {
+        let mut config = match std::fs::read_to_string(path) {
+            Ok(content) => toml::from_str(&content).map_err(|err| ConfigError::Parse(path.to_path_buf(), err))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Config::default(),
+            Err(err) => return Err(ConfigError::Io(path.to_path_buf(), err)),
+        };
+        config.apply_env()?;
+        Ok(config)
+    }
+
+    pub fn token(&self) -> ConfigResult<String> {
+        self.discord.token.clone()
+            .or_else(|| env::var(&self.discord.token_env).ok())
+            .ok_or_else(|| ConfigError::MissingToken(self.discord.token_env.clone()))
+    }
+
+    fn apply_env(&mut self) -> ConfigResult<()> {
+        // reports would silently go to the voice channels instead, so this one must not be ignored.
+        if let Some(id) = env_require::<NonZeroU64>("REPORT_CHANNEL_ID")? {
+            self.defaults.report_channel_id = Some(ChannelId::from(id));
+        }
+        if let Some(member_updates) = self.env_parse("MEMBER_UPDATES") {
+            self.discord.member_updates = member_updates;
+        }
+        if let Ok(url) = env::var("DATABASE_URL") {
+            self.storage.database_url = url;
+        }
+        if let Ok(font_dir) = env::var("FONT_DIR") {
+            self.render.font_dir = Some(PathBuf::from(font_dir));
+        }
+        if let Ok(font_family) = env::var("FONT_FAMILY") {
+            self.render.font_family = Some(font_family);
+        }
+        if let Some(workers) = self.env_parse("RENDER_WORKERS") {
+            self.render.workers = workers;
+        }
+        if let Some(queue_size) = self.env_parse("RENDER_QUEUE_SIZE") {
+            self.render.queue_size = queue_size;
+        }
+        if let Some(cache_size) = self.env_parse("ASSET_CACHE_SIZE") {
+            self.assets.cache_size = cache_size;
+        }
+        if let Some(cache_ttl_secs) = self.env_parse("ASSET_CACHE_TTL_SECS") {
+            self.assets.cache_ttl_secs = cache_ttl_secs;
+        }
+        if let Ok(token) = env::var("DASHBOARD_TOKEN") {
+            self.dashboard.token = Some(token);
+        }
+        if let Ok(addr) = env::var("DASHBOARD_ADDR") {
+            self.dashboard.addr = addr;
+        }
+        if let Ok(addr) = env::var("HEALTH_ADDR") {
+            self.health.addr = Some(addr);
+        }
+        if let Some(format) = self.env_parse("LOG_FORMAT") {
+            self.logging.format = format;
+        }
+        Ok(())
+    }
+
+    // a malformed variable is reported and ignored, keeping the value from the file.
+    fn env_parse<T>(&mut self, name: &str) -> Option<T>
+    where
+        T: FromStr,
+        T::Err: std::fmt::Display,
+    {
+        let value = env::var(name).ok()?;
+        match value.parse() {
+            Ok(parsed) => Some(parsed),
+            Err(err) => {
+                self.warnings.push(format!("failed to parse {}({}): {}", name, value, err));
+                None
+            },
+        }
+    }
+}
+
+// like `Config::env_parse`, but a malformed variable fails the whole config.
+fn env_require<T>(name: &str) -> ConfigResult<Option<T>>
+where
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    let value = match env::var(name) {
+        Ok(value) => value,
+        Err(_) => return Ok(None),
+    };
+    match value.parse() {
+        Ok(parsed) => Ok(Some(parsed)),
+        Err(err) => Err(ConfigError::InvalidEnv(name.to_string(), value, err.to_string())),
+    }
+}
@@ -0,0 +1,99 @@
+mod pages;
+
+use crate::model::RoomManager;
+use crate::service::report::{ReportService, ReportServiceError};
+use crate::storage::{SqliteStorage, StorageError};
+use axum::extract::{Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::net::TcpListener;
+use tokio::sync::watch;
+use tracing::{error, info};
+
+#[derive(Debug, Error)]
+pub enum DashboardError {
+    #[error("room not found")]
+    RoomNotFound,
+
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+
+    #[error(transparent)]
+    Report(#[from] ReportServiceError),
+}
+
+pub type DashboardResult<T> = Result<T, DashboardError>;
+
+impl IntoResponse for DashboardError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            DashboardError::RoomNotFound => StatusCode::NOT_FOUND,
+            _ => {
+                error!("Dashboard error: {}", self);
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        };
+        (status, self.to_string()).into_response()
+    }
+}
+
+// Read-only web pages over the live rooms and the stored history.
+pub struct DashboardState {
+    room_manager: Arc<RoomManager>,
+    report_service: Arc<ReportService>,
+    storage: Arc<SqliteStorage>,
+    token: String,
+}
+
+impl DashboardState {
+    pub fn new(room_manager: Arc<RoomManager>, report_service: Arc<ReportService>, storage: Arc<SqliteStorage>, token: String) -> Self {
+        DashboardState { room_manager, report_service, storage, token }
+    }
+}
+
+pub fn router(state: Arc<DashboardState>) -> Router {
+    Router::new()
+        .route("/", get(pages::rooms))
+        .route("/rooms/{channel_id}/timeline", get(pages::timeline))
+        .route("/guilds/{guild_id}/history", get(pages::history))
+        .route("/guilds/{guild_id}/overview", get(pages::overview))
+        .layer(middleware::from_fn_with_state(state.clone(), authorize))
+        .with_state(state)
+}
+
+pub async fn serve(addr: SocketAddr, state: Arc<DashboardState>, mut shutdown: watch::Receiver<bool>) -> std::io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    info!("dashboard is listening on {}", addr);
+
+    axum::serve(listener, router(state))
+        .with_graceful_shutdown(async move {
+            let _ = shutdown.changed().await;
+        })
+        .await
+}
+
+// accepts the token either as a bearer token or as the `token` query parameter, so links work in a browser.
+async fn authorize(State(state): State<Arc<DashboardState>>, request: Request, next: Next) -> Response {
+    let bearer = request.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let query = request.uri().query()
+        .and_then(|query| query.split('&').find_map(|pair| pair.strip_prefix("token=")));
+
+    match bearer.or(query) {
+        Some(token) if constant_time_eq(token.as_bytes(), state.token.as_bytes()) => next.run(request).await,
+        _ => StatusCode::UNAUTHORIZED.into_response(),
+    }
+}
+
+// compares every byte regardless of where they differ, so timing does not reveal the token.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
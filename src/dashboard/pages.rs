@@ -0,0 +1,128 @@
+use crate::dashboard::{DashboardError, DashboardResult, DashboardState};
+use crate::service::report::RoomDTO;
+use axum::extract::{Path, Query, State};
+use axum::http::header;
+use axum::response::{Html, IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serenity::all::{ChannelId, GuildId};
+use std::fmt::Write;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::Instant;
+
+const DEFAULT_HISTORY_DAYS: u64 = 7;
+const MAX_HISTORY_DAYS: u64 = 366;
+
+#[derive(Deserialize)]
+pub struct TokenQuery {
+    token: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    token: Option<String>,
+    days: Option<u64>,
+}
+
+pub async fn rooms(State(state): State<Arc<DashboardState>>, Query(query): Query<TokenQuery>) -> Html<String> {
+    let token = token_param(query.token.as_deref());
+
+    let mut rooms = Vec::new();
+    for room in state.room_manager.get_all_rooms().await {
+        rooms.push(RoomDTO::from_room(&*room.lock().await));
+    }
+    rooms.sort_by_key(|room| room.created_at);
+
+    let mut body = String::from("<h1>Active rooms</h1>");
+    if rooms.is_empty() {
+        body.push_str("<p>No room is active.</p>");
+    }
+    for room in rooms {
+        let connected = room.participants.iter().filter(|participant| participant.is_connected()).count();
+        let _ = write!(
+            body,
+            r#"<section><h2>Channel {channel} (guild <a href="/guilds/{guild}/history{token}">{guild}</a>)</h2><p>started {started}, {connected}/{total} connected</p><img src="/rooms/{channel}/timeline{token}" alt="timeline of channel {channel}"></section>"#,
+            channel = room.channel_id,
+            guild = room.guild_id,
+            started = room.timestamp.format("%Y-%m-%d %H:%M UTC"),
+            total = room.participants.len(),
+        );
+    }
+
+    Html(page("ringring rooms", &body))
+}
+
+pub async fn timeline(State(state): State<Arc<DashboardState>>, Path(channel_id): Path<u64>) -> DashboardResult<Response> {
+    let room = state.room_manager.get_room(ChannelId::new(channel_id)).await.ok_or(DashboardError::RoomNotFound)?;
+    let room_dto = RoomDTO::from_room(&*room.lock().await);
+
+    let (image, format) = state.report_service.render_room_image(Instant::now(), &room_dto, true).await?;
+    Ok(([(header::CONTENT_TYPE, format.content_type())], image).into_response())
+}
+
+pub async fn overview(State(state): State<Arc<DashboardState>>, Path(guild_id): Path<u64>) -> DashboardResult<Response> {
+    let guild_id = GuildId::new(guild_id);
+    let mut rooms = Vec::new();
+    for room in state.room_manager.get_guild_rooms(guild_id).await {
+        let room_dto = RoomDTO::from_room(&*room.lock().await);
+        rooms.push((format!("Channel {}", room_dto.channel_id), room_dto));
+    }
+    if rooms.is_empty() {
+        return Err(DashboardError::RoomNotFound);
+    }
+    rooms.sort_by_key(|(_, room)| room.created_at);
+
+    let (image, format) = state.report_service.render_overview_image(Instant::now(), guild_id, &rooms).await?;
+    Ok(([(header::CONTENT_TYPE, format.content_type())], image).into_response())
+}
+
+pub async fn history(State(state): State<Arc<DashboardState>>, Path(guild_id): Path<u64>, Query(query): Query<HistoryQuery>) -> DashboardResult<Html<String>> {
+    let days = query.days.unwrap_or(DEFAULT_HISTORY_DAYS).min(MAX_HISTORY_DAYS);
+    let until = SystemTime::now();
+    let since = until.checked_sub(Duration::from_hours(24 * days)).unwrap_or(UNIX_EPOCH);
+
+    let rooms = state.storage.load_rooms(GuildId::new(guild_id), unix_millis(since), unix_millis(until)).await?;
+
+    let token = token_param(query.token.as_deref());
+    let mut body = format!(r#"<h1>History of guild {guild_id}</h1><p>past {days} days, <a href="/{token}">active rooms</a>, <a href="/guilds/{guild_id}/overview{token}">overview</a></p>"#);
+    body.push_str("<table><tr><th>channel</th><th>start</th><th>end</th><th>participants</th></tr>");
+    for room in rooms {
+        let _ = write!(
+            body,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            room.channel_id,
+            format_millis(room.started_at),
+            room.ended_at.map_or(String::from("ongoing"), format_millis),
+            room.participants,
+        );
+    }
+    body.push_str("</table>");
+
+    Ok(Html(page("ringring history", &body)))
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!(r#"<!DOCTYPE html><html><head><meta charset="utf-8"><title>{title}</title></head><body>{body}</body></html>"#)
+}
+
+// carries the token over to linked pages.
+fn token_param(token: Option<&str>) -> String {
+    match token {
+        Some(token) => format!("?token={}", escape(token)),
+        None => String::new(),
+    }
+}
+
+fn unix_millis(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as i64)
+}
+
+fn format_millis(millis: i64) -> String {
+    DateTime::<Utc>::from_timestamp_millis(millis)
+        .map_or(String::from("-"), |time| time.format("%Y-%m-%d %H:%M UTC").to_string())
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
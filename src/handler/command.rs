@@ -0,0 +1,221 @@
+mod admin;
+mod color;
+mod config;
+mod debug;
+mod export;
+mod heatmap;
+mod history;
+mod leaderboard;
+mod myreport;
+mod nickname;
+mod overview;
+mod privacy;
+mod report;
+mod stats;
+mod template;
+mod tracking;
+mod usertimeline;
+
+use crate::model::{GuildConfig, RoomManager};
+use crate::service::config::ConfigService;
+use crate::service::history::{HistoryError, HistoryService};
+use crate::service::nickname::NicknameService;
+use crate::service::privacy::PrivacyService;
+use crate::service::report::{ReportService, ReportServiceError};
+use crate::service::stats::StatsService;
+use crate::service::template::TemplateError;
+use crate::storage::StorageError;
+use serenity::all::{
+    Command, CommandInteraction, Context, CreateCommand, CreateInteractionResponse,
+    CreateInteractionResponseMessage, EditInteractionResponse, EventHandler, GuildId, Interaction,
+    Member, ResolvedOption, ResolvedValue,
+};
+use serenity::async_trait;
+use serenity::prelude::SerenityError;
+use std::sync::Arc;
+use thiserror::Error;
+use tracing::{debug, error};
+
+#[derive(Debug, Error)]
+pub enum CommandError {
+    #[error("This command can only be used in a server.")]
+    GuildOnly,
+
+    #[error("You need to be in a voice channel to use this command.")]
+    NotInVoiceChannel,
+
+    #[error("No call is being tracked in this channel.")]
+    RoomNotFound,
+
+    #[error("Invalid command arguments.")]
+    InvalidArguments,
+
+    #[error("You need the Manage Server permission or the manager role to use this command.")]
+    NotManager,
+
+    #[error("You need the Manage Server permission to use this command.")]
+    MissingManageGuild,
+
+    #[error("Failed to access the storage.")]
+    Storage(#[from] StorageError),
+
+    #[error("Failed to render the report.")]
+    Report(#[from] ReportServiceError),
+
+    #[error("Failed to load the call history.")]
+    History(#[from] HistoryError),
+
+    #[error("Invalid template: {0}.")]
+    Template(#[from] TemplateError),
+
+    #[error("Failed to serialize the room.")]
+    Serialization(#[from] serde_json::Error),
+
+    // boxed, as serenity errors are large and this error is returned everywhere.
+    #[error("Failed to respond to Discord.")]
+    Serenity(#[source] Box<SerenityError>),
+}
+
+pub type CommandResult<T> = Result<T, CommandError>;
+
+impl From<SerenityError> for CommandError {
+    fn from(err: SerenityError) -> Self {
+        CommandError::Serenity(Box::new(err))
+    }
+}
+
+pub struct CommandHandler {
+    room_manager: Arc<RoomManager>,
+    report_service: Arc<ReportService>,
+    config_service: Arc<ConfigService>,
+    stats_service: Arc<StatsService>,
+    privacy_service: Arc<PrivacyService>,
+    history_service: Arc<HistoryService>,
+    nickname_service: Arc<NicknameService>,
+}
+
+impl CommandHandler {
+    pub fn new(room_manager: Arc<RoomManager>, report_service: Arc<ReportService>, config_service: Arc<ConfigService>, stats_service: Arc<StatsService>, privacy_service: Arc<PrivacyService>, history_service: Arc<HistoryService>, nickname_service: Arc<NicknameService>) -> Self {
+        CommandHandler { room_manager, report_service, config_service, stats_service, privacy_service, history_service, nickname_service }
+    }
+
+    // members with Manage Server, or the guild's manager role, may change how the bot behaves.
+    async fn ensure_manager(&self, command: &CommandInteraction) -> CommandResult<()> {
+        let guild_id = command.guild_id.ok_or(CommandError::GuildOnly)?;
+        let member = command.member.as_ref().ok_or(CommandError::GuildOnly)?;
+        match is_manager(member, &self.config_service.get(guild_id).await) {
+            true => Ok(()),
+            false => Err(CommandError::NotManager),
+        }
+    }
+
+    fn commands() -> Vec<CreateCommand> {
+        vec![
+            report::register(),
+            config::register(),
+            stats::register(),
+            leaderboard::register(),
+            heatmap::register(),
+            privacy::register(),
+            color::register(),
+            debug::register(),
+            overview::register(),
+            history::register(),
+            export::register(),
+            myreport::register(),
+            tracking::register(),
+            template::register(),
+            usertimeline::register(),
+            nickname::register(),
+            admin::register(),
+        ]
+    }
+
+    async fn dispatch(&self, ctx: &Context, command: &CommandInteraction) -> CommandResult<()> {
+        match command.data.name.as_str() {
+            "report" => report::run(self, ctx, command).await,
+            "config" => config::run(self, ctx, command).await,
+            "stats" => stats::run(self, ctx, command).await,
+            "leaderboard" => leaderboard::run(self, ctx, command).await,
+            "heatmap" => heatmap::run(self, ctx, command).await,
+            "privacy" => privacy::run(self, ctx, command).await,
+            "color" => color::run(self, ctx, command).await,
+            "debug" => debug::run(self, ctx, command).await,
+            "overview" => overview::run(self, ctx, command).await,
+            "history" => history::run(self, ctx, command).await,
+            "export" => export::run(self, ctx, command).await,
+            "myreport" => myreport::run(self, ctx, command).await,
+            "tracking" => tracking::run(self, ctx, command).await,
+            "template" => template::run(self, ctx, command).await,
+            "usertimeline" => usertimeline::run(self, ctx, command).await,
+            "nickname" => nickname::run(self, ctx, command).await,
+            "admin" => admin::run(self, ctx, command).await,
+            name => {
+                debug!("unknown command: {}", name);
+                Ok(())
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl EventHandler for CommandHandler {
+    async fn cache_ready(&self, ctx: Context, _guilds: Vec<GuildId>) {
+        match Command::set_global_commands(&ctx.http, Self::commands()).await {
+            Ok(commands) => debug!("registered {} commands", commands.len()),
+            Err(err) => error!("Failed to register commands: {}", err),
+        }
+    }
+
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        let command = match interaction {
+            Interaction::Command(command) => command,
+            _ => return,
+        };
+
+        if let Err(err) = self.dispatch(&ctx, &command).await {
+            error!("Error handling /{} command: {:?}", command.data.name, err);
+            reply_error(&ctx, &command, &err).await;
+        }
+    }
+}
+
+pub(crate) fn is_manager(member: &Member, config: &GuildConfig) -> bool {
+    member.permissions.is_some_and(|permissions| permissions.manage_guild())
+        || config.manager_role_id.is_some_and(|role_id| member.roles.contains(&role_id))
+}
+
+// the manager role itself can only be changed with the permission, so it cannot hand itself out.
+fn ensure_manage_guild(command: &CommandInteraction) -> CommandResult<()> {
+    let member = command.member.as_ref().ok_or(CommandError::GuildOnly)?;
+    match member.permissions.is_some_and(|permissions| permissions.manage_guild()) {
+        true => Ok(()),
+        false => Err(CommandError::MissingManageGuild),
+    }
+}
+
+// returns the name and the options of the invoked subcommand.
+fn subcommand<'a>(options: &'a [ResolvedOption<'a>]) -> Option<(&'a str, &'a [ResolvedOption<'a>])> {
+    options.iter().find_map(|option| match &option.value {
+        ResolvedValue::SubCommand(options) => Some((option.name, options.as_slice())),
+        _ => None,
+    })
+}
+
+fn find_option<'a>(options: &'a [ResolvedOption<'a>], name: &str) -> Option<&'a ResolvedValue<'a>> {
+    options.iter().find(|option| option.name == name).map(|option| &option.value)
+}
+
+// replies with an ephemeral error message, whether or not the interaction was deferred.
+async fn reply_error(ctx: &Context, command: &CommandInteraction, err: &CommandError) {
+    let message = err.to_string();
+    let response = CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new().content(&message).ephemeral(true),
+    );
+
+    if command.create_response(&ctx.http, response).await.is_err()
+        && let Err(err) = command.edit_response(&ctx.http, EditInteractionResponse::new().content(message)).await
+    {
+        error!("Failed to reply error message: {}", err);
+    }
+}
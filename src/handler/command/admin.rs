@@ -0,0 +1,167 @@
+use crate::handler::command::{find_option, subcommand, CommandError, CommandHandler, CommandResult};
+use crate::service::renderer::timeline::TimelineRenderer;
+use crate::service::report::RoomDTO;
+use chrono::TimeDelta;
+use serenity::all::{
+    ChannelId, ChannelType, CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    CreateEmbed, CreateEmbedFooter, CreateInteractionResponse, CreateInteractionResponseMessage,
+    EditInteractionResponse, GuildId, Mentionable, Permissions, ResolvedOption, ResolvedValue,
+};
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::info;
+
+// embeds take at most 25 fields; the rest of the participants are only counted in the footer.
+const MAX_PARTICIPANT_FIELDS: usize = 25;
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("admin")
+        .description("Inspect tracked calls and recover from problems with them")
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "close", "End a call now and post its final report")
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::Channel, "channel", "Voice channel of the call")
+                        .channel_types(vec![ChannelType::Voice, ChannelType::Stage])
+                        .required(true),
+                ),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "inspect", "Show the internal state of a call")
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::Channel, "channel", "Voice channel of the call")
+                        .channel_types(vec![ChannelType::Voice, ChannelType::Stage])
+                        .required(true),
+                ),
+        )
+}
+
+pub async fn run(handler: &CommandHandler, ctx: &Context, command: &CommandInteraction) -> CommandResult<()> {
+    let guild_id = command.guild_id.ok_or(CommandError::GuildOnly)?;
+    handler.ensure_manager(command).await?;
+    let options = command.data.options();
+    let (name, options) = subcommand(&options).ok_or(CommandError::InvalidArguments)?;
+    match name {
+        "close" => close(handler, ctx, command, guild_id, options).await,
+        "inspect" => inspect(handler, ctx, command, guild_id, options).await,
+        _ => Err(CommandError::InvalidArguments),
+    }
+}
+
+// resolves the channel option to a room of this guild.
+async fn find_room(handler: &CommandHandler, guild_id: GuildId, options: &[ResolvedOption<'_>]) -> CommandResult<ChannelId> {
+    let channel_id = match find_option(options, "channel") {
+        Some(ResolvedValue::Channel(channel)) => channel.id,
+        _ => return Err(CommandError::InvalidArguments),
+    };
+    // channel ids are global; never touch another guild's room.
+    let room = handler.room_manager.get_room(channel_id).await.ok_or(CommandError::RoomNotFound)?;
+    if room.lock().await.guild_id() != guild_id {
+        return Err(CommandError::RoomNotFound);
+    }
+    Ok(channel_id)
+}
+
+async fn close(handler: &CommandHandler, ctx: &Context, command: &CommandInteraction, guild_id: GuildId, options: &[ResolvedOption<'_>]) -> CommandResult<()> {
+    let channel_id = find_room(handler, guild_id, options).await?;
+
+    command.defer_ephemeral(&ctx.http).await?;
+
+    // members still connected are disconnected, like when the room expires.
+    let now = Instant::now();
+    let room = handler.room_manager.close_room(now, channel_id).await.ok_or(CommandError::RoomNotFound)?;
+    info!(%guild_id, %channel_id, user_id = %command.user.id, "room closed by command");
+    let room_dto = {
+        let room = room.lock().await;
+        RoomDTO::from_room(&room)
+    };
+    let result = handler.report_service.send_room_report(&ctx.http, now, &room_dto, false).await;
+    let open_channel_ids = handler.room_manager.open_channel_ids().await;
+    handler.report_service.prune_tracks(now, &[channel_id], &open_channel_ids).await;
+    // reports are skipped while another instance holds the lease, or for calls too short to report.
+    let content = match result? {
+        true => format!("Closed the call in {} and posted its final report.", channel_id.mention()),
+        false => format!("Closed the call in {}; no final report was posted for it.", channel_id.mention()),
+    };
+
+    let response = EditInteractionResponse::new().content(content);
+    command.edit_response(&ctx.http, response).await?;
+
+    Ok(())
+}
+
+async fn inspect(handler: &CommandHandler, ctx: &Context, command: &CommandInteraction, guild_id: GuildId, options: &[ResolvedOption<'_>]) -> CommandResult<()> {
+    let channel_id = find_room(handler, guild_id, options).await?;
+    let room = handler.room_manager.get_room(channel_id).await.ok_or(CommandError::RoomNotFound)?;
+    let room_dto = {
+        let room = room.lock().await;
+        RoomDTO::from_room(&room)
+    };
+    let now = Instant::now();
+
+    // members the gateway sees in the channel; a connected participant missing here is a ghost.
+    let in_channel: Option<HashSet<_>> = ctx.cache.guild(guild_id).map(|guild| {
+        guild.voice_states.values()
+            .filter(|state| state.channel_id == Some(channel_id))
+            .map(|state| state.user_id)
+            .collect()
+    });
+
+    let mut description = String::new();
+    let _ = writeln!(description, "channel: {}", channel_id.mention());
+    let _ = writeln!(description, "running for {} · session #{} · revision {}", format_duration(room_dto.wall_clock(now)), room_dto.session, room_dto.revision);
+    match room_dto.expires_at {
+        Some(expires_at) => {
+            let _ = writeln!(description, "expires in {}", format_duration(expires_at.saturating_duration_since(now)));
+        }
+        None => description.push_str("does not expire while someone is connected\n"),
+    }
+    match handler.report_service.get_track(channel_id).await {
+        Some(track) => {
+            let revision = track.revision.map_or("unknown".to_string(), |revision| revision.to_string());
+            let _ = writeln!(
+                description,
+                "report message {} · shows revision {} · edited {} ago · {} edits in the last hour",
+                track.message_id, revision, format_duration(now.saturating_duration_since(track.last_updated_at)), track.recent_edits(now),
+            );
+        }
+        None => description.push_str("no report message is tracked\n"),
+    }
+
+    let mut embed = CreateEmbed::new()
+        .title(format!("{} participants", room_dto.participants.len()))
+        .description(description);
+    for participant in room_dto.participants.iter().take(MAX_PARTICIPANT_FIELDS) {
+        let status = match (participant.is_connected(), participant.is_departed()) {
+            (true, _) => "connected",
+            (false, true) => "departed",
+            (false, false) => "disconnected",
+        };
+        let mut value = format!(
+            "{} · {} segments · {} sessions · {} speaking spans",
+            status, participant.history().len(), participant.sessions(), participant.speaking().len(),
+        );
+        if participant.is_connected() && in_channel.as_ref().is_some_and(|users| !users.contains(&participant.user_id())) {
+            value.push_str("\nnot in the channel according to the gateway");
+        }
+        embed = embed.field(format!("{} ({})", participant.name(), participant.user_id()), value, false);
+    }
+    if room_dto.participants.len() > MAX_PARTICIPANT_FIELDS {
+        embed = embed.footer(CreateEmbedFooter::new(format!("{} more participants are not shown", room_dto.participants.len() - MAX_PARTICIPANT_FIELDS)));
+    }
+
+    let response = CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new()
+            .embed(embed)
+            .ephemeral(true),
+    );
+    command.create_response(&ctx.http, response).await?;
+
+    Ok(())
+}
+
+fn format_duration(duration: Duration) -> String {
+    TimelineRenderer::format_time_delta(TimeDelta::from_std(duration).unwrap_or(TimeDelta::zero()))
+}
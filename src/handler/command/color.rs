@@ -0,0 +1,59 @@
+use crate::handler::command::{find_option, subcommand, CommandError, CommandHandler, CommandResult};
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    CreateInteractionResponse, CreateInteractionResponseMessage, ResolvedValue,
+};
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("color")
+        .description("Choose the color of your timeline bars")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "set", "Use a custom color instead of one taken from your avatar")
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::String, "color", "Hex color such as #ff8800")
+                        .required(true),
+                ),
+        )
+        .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "reset", "Go back to the color taken from your avatar"))
+}
+
+pub async fn run(handler: &CommandHandler, ctx: &Context, command: &CommandInteraction) -> CommandResult<()> {
+    let options = command.data.options();
+    let (name, options) = subcommand(&options).ok_or(CommandError::InvalidArguments)?;
+    let user_id = command.user.id;
+    let asset_service = handler.report_service.asset_service();
+
+    let message = match name {
+        "set" => {
+            let rgb = match find_option(options, "color") {
+                Some(ResolvedValue::String(color)) => parse_hex_color(color).ok_or(CommandError::InvalidArguments)?,
+                _ => return Err(CommandError::InvalidArguments),
+            };
+            asset_service.set_custom_color(user_id, Some(rgb)).await?;
+            format!("Your timeline color is now #{:06x}.", rgb)
+        },
+        "reset" => {
+            asset_service.set_custom_color(user_id, None).await?;
+            String::from("Your timeline color is taken from your avatar again.")
+        },
+        _ => return Err(CommandError::InvalidArguments),
+    };
+
+    command.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().content(message).ephemeral(true),
+        ),
+    ).await?;
+
+    Ok(())
+}
+
+// accepts "#rrggbb" and "rrggbb".
+pub(crate) fn parse_hex_color(color: &str) -> Option<u32> {
+    let hex = color.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None
+    }
+    u32::from_str_radix(hex, 16).ok()
+}
@@ -0,0 +1,702 @@
+use crate::handler::command::{ensure_manage_guild, find_option, subcommand, CommandError, CommandHandler, CommandResult};
+use crate::handler::command::report::sort_option;
+use crate::model::{ChannelFilter, ContinuationMode, DigestFrequency, GuildConfig, RoleFilter};
+use crate::service::i18n::Language;
+use crate::service::renderer::encoder::ImageFormat;
+use crate::service::renderer::theme::ThemeKind;
+use crate::service::renderer::timeline::policy::AspectRatioKind;
+use crate::service::renderer::transformer::SortOrder;
+use crate::service::webhook::check_endpoint;
+use chrono_tz::Tz;
+use serenity::all::{
+    ChannelType, CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage, Mentionable,
+    ResolvedValue,
+};
+
+const MAX_WEBHOOKS: usize = 5;
+const MAX_IDLE_TIMEOUT_SECS: u64 = 24 * 60 * 60;
+const MAX_REJOIN_WINDOW_SECS: u64 = 5 * 60;
+const MAX_CONTINUATION_WINDOW_SECS: u64 = 60 * 60;
+const MAX_ROWS_PER_IMAGE: u64 = 100;
+
+pub fn register() -> CreateCommand {
+    let language_option = Language::ALL.into_iter().fold(
+        CreateCommandOption::new(CommandOptionType::String, "language", "Language of report embeds").required(true),
+        |option, language| option.add_string_choice(language.name(), language.code()),
+    );
+
+    let command = CreateCommand::new("config")
+        .description("Configure ringring for this server")
+        .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "show", "Show the current configuration"))
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "manager-role", "Let members with a role change the configuration")
+                .add_sub_option(CreateCommandOption::new(CommandOptionType::Role, "role", "Manager role; omit to allow Manage Server only")),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "report-channel", "Set the channel reports are posted to")
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::Channel, "channel", "Channel for reports; omit to post in the voice channel itself")
+                        .channel_types(vec![ChannelType::Text, ChannelType::Voice]),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::Channel, "source", "Only route this voice channel or category; omit the channel to remove its route")
+                        .channel_types(vec![ChannelType::Voice, ChannelType::Stage, ChannelType::Category]),
+                ),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "audit-channel", "Post a line for every join, leave and stream")
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::Channel, "channel", "Channel for the audit log; omit to turn it off")
+                        .channel_types(vec![ChannelType::Text]),
+                ),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "threshold", "Set when a call becomes worth reporting; 0 disables a threshold")
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::Integer, "participants", "Minimum number of distinct participants")
+                        .min_int_value(0),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::Integer, "minutes", "Minimum call duration in minutes")
+                        .min_int_value(0),
+                ),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "locale", "Set the language of reports")
+                .add_sub_option(language_option),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "timezone", "Set the timezone of timeline labels")
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::String, "name", "IANA timezone such as Asia/Tokyo; omit to use the bot's local time"),
+                ),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "timeout", "Set how long an empty call is kept before it ends")
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::Integer, "seconds", "Seconds to wait for someone to rejoin")
+                        .min_int_value(0)
+                        .max_int_value(MAX_IDLE_TIMEOUT_SECS)
+                        .required(true),
+                ),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "rejoin", "Merge brief drops into one activity")
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::Integer, "seconds", "Longest gap to merge; 0 disables merging")
+                        .min_int_value(0)
+                        .max_int_value(MAX_REJOIN_WINDOW_SECS),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::Boolean, "show", "Mark merged gaps on the timeline"),
+                ),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "gaps", "Mark the time members were out of the call between sessions")
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::Boolean, "show", "Whether to draw the gaps")
+                        .required(true),
+                ),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "migration", "Carry the call along when a group switches channels together")
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::Integer, "members", "Members moving within seconds to count as a group; 0 disables it")
+                        .min_int_value(0)
+                        .required(true),
+                ),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "continuation", "Continue a call that starts again shortly after the previous one ended")
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::Integer, "seconds", "Longest break to continue over; 0 disables it")
+                        .min_int_value(0)
+                        .max_int_value(MAX_CONTINUATION_WINDOW_SECS)
+                        .required(true),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::String, "mode", "How the continued call is reported")
+                        .add_string_choice("tag as the next session", "tag")
+                        .add_string_choice("merge into the previous call", "merge"),
+                ),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "attendance", "Set how much of a scheduled event counts as attending")
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::Integer, "percent", "Share of the event a member must be present for")
+                        .min_int_value(1)
+                        .max_int_value(100)
+                        .required(true),
+                ),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "report-policy", "Set how often ongoing reports are updated")
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::Integer, "interval", "Seconds between scheduled updates")
+                        .min_int_value(10),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::Integer, "debounce", "Minimum seconds between two edits")
+                        .min_int_value(0),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::Integer, "max-edits", "Maximum edits per hour for a report")
+                        .min_int_value(1),
+                ),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "theme", "Set the color theme of timeline images")
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::String, "theme", "Theme name")
+                        .add_string_choice("light", "light")
+                        .add_string_choice("dark", "dark")
+                        .required(true),
+                ),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "aspect-ratio", "Set the aspect ratio timeline images aim for")
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::String, "ratio", "Aspect ratio")
+                        .add_string_choice("4:3", "standard")
+                        .add_string_choice("16:9", "wide")
+                        .add_string_choice("1:1", "square")
+                        .required(true),
+                ),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "sort", "Set the order of participants in timelines")
+                .add_sub_option(sort_option("Order of participants").required(true)),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "rows-per-image", "Split large calls into several images")
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::Integer, "rows", "Participants per image; 0 keeps everyone in one image")
+                        .min_int_value(0)
+                        .max_int_value(MAX_ROWS_PER_IMAGE)
+                        .required(true),
+                ),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "attach-json", "Attach raw activity data as JSON to every report")
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::Boolean, "enabled", "Whether to attach JSON")
+                        .required(true),
+                ),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "dm-starter", "Send the final report to whoever started the call by DM")
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::Boolean, "enabled", "Whether to send the DM")
+                        .required(true),
+                ),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "channels", "Allow or deny tracking of voice channels or categories")
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::String, "action", "What to do with the channel")
+                        .add_string_choice("allow", "allow")
+                        .add_string_choice("deny", "deny")
+                        .add_string_choice("remove", "remove")
+                        .add_string_choice("clear", "clear")
+                        .required(true),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::Channel, "channel", "Voice channel or category; not needed for clear")
+                        .channel_types(vec![ChannelType::Voice, ChannelType::Stage, ChannelType::Category]),
+                ),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "roles", "Only track members holding allowed roles, or exclude roles")
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::String, "action", "What to do with the role")
+                        .add_string_choice("allow", "allow")
+                        .add_string_choice("deny", "deny")
+                        .add_string_choice("remove", "remove")
+                        .add_string_choice("clear", "clear")
+                        .required(true),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::Role, "role", "Role; not needed for clear"),
+                ),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "image-format", "Set the image format of report attachments")
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::String, "format", "Image format")
+                        .add_string_choice("png", "png")
+                        .add_string_choice("webp (lossless)", "webp")
+                        .add_string_choice("avif", "avif")
+                        .add_string_choice("apng (animated)", "apng")
+                        .required(true),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::Integer, "quality", "Quality of lossy formats")
+                        .min_int_value(1)
+                        .max_int_value(100),
+                ),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "webhook", "Post room starts, ends and summaries as JSON to an endpoint")
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::String, "action", "What to do with the endpoint")
+                        .add_string_choice("add", "add")
+                        .add_string_choice("remove", "remove")
+                        .add_string_choice("clear", "clear")
+                        .required(true),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::String, "url", "Public HTTPS endpoint; not needed for clear"),
+                ),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "digest", "Post a periodic summary of the server's calls")
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::String, "frequency", "How often to post; weekly digests are posted on mondays")
+                        .add_string_choice("off", "off")
+                        .add_string_choice("daily", "daily")
+                        .add_string_choice("weekly", "weekly")
+                        .required(true),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::Integer, "hour", "Hour of the day in the configured timezone")
+                        .min_int_value(0)
+                        .max_int_value(23),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::Channel, "channel", "Channel for digests; omit to use the report channel")
+                        .channel_types(vec![ChannelType::Text]),
+                ),
+        );
+
+    #[cfg(feature = "voice-receive")]
+    let command = command.add_option(
+        CreateCommandOption::new(CommandOptionType::SubCommand, "speaking", "Join voice channels to record who is speaking")
+            .add_sub_option(
+                CreateCommandOption::new(CommandOptionType::Boolean, "enabled", "Whether to record speaking")
+                    .required(true),
+            ),
+    );
+
+    command
+}
+
+pub async fn run(handler: &CommandHandler, ctx: &Context, command: &CommandInteraction) -> CommandResult<()> {
+    let guild_id = command.guild_id.ok_or(CommandError::GuildOnly)?;
+
+    let options = command.data.options();
+    let (name, options) = subcommand(&options).ok_or(CommandError::InvalidArguments)?;
+
+    // anyone may look at the configuration, but changing it takes a manager.
+    match name {
+        "show" => {},
+        "manager-role" => ensure_manage_guild(command)?,
+        _ => handler.ensure_manager(command).await?,
+    }
+
+    let config = match name {
+        "show" => handler.config_service.get(guild_id).await,
+        "manager-role" => {
+            let role_id = match find_option(options, "role") {
+                Some(ResolvedValue::Role(role)) => Some(role.id),
+                _ => None,
+            };
+            handler.config_service.update(guild_id, |config| config.manager_role_id = role_id).await?
+        },
+        "report-channel" => {
+            let channel_id = match find_option(options, "channel") {
+                Some(ResolvedValue::Channel(channel)) => Some(channel.id),
+                _ => None,
+            };
+            match find_option(options, "source") {
+                Some(ResolvedValue::Channel(source)) => {
+                    let source = source.id;
+                    handler.config_service.update(guild_id, |config| config.set_report_route(source, channel_id)).await?
+                },
+                _ => handler.config_service.update(guild_id, |config| config.report_channel_id = channel_id).await?,
+            }
+        },
+        "audit-channel" => {
+            let channel_id = match find_option(options, "channel") {
+                Some(ResolvedValue::Channel(channel)) => Some(channel.id),
+                _ => None,
+            };
+            handler.config_service.update(guild_id, |config| config.audit_channel_id = channel_id).await?
+        },
+        "threshold" => {
+            let integer = |name| match find_option(options, name) {
+                Some(ResolvedValue::Integer(value)) => Some(*value),
+                _ => None,
+            };
+            let participants = integer("participants");
+            let minutes = integer("minutes");
+            handler.config_service.update(guild_id, |config| {
+                if let Some(participants) = participants {
+                    config.min_participants = participants as usize;
+                }
+                if let Some(minutes) = minutes {
+                    config.min_call_duration_secs = minutes as u64 * 60;
+                }
+            }).await?
+        },
+        "locale" => {
+            let language = match find_option(options, "language") {
+                Some(ResolvedValue::String(code)) => Language::from_locale(code),
+                _ => return Err(CommandError::InvalidArguments),
+            };
+            handler.config_service.update(guild_id, |config| config.locale = language.code().to_string()).await?
+        },
+        "timezone" => {
+            let timezone = match find_option(options, "name") {
+                Some(ResolvedValue::String(name)) => Some(name.parse::<Tz>().map_err(|_| CommandError::InvalidArguments)?),
+                _ => None,
+            };
+            handler.config_service.update(guild_id, |config| config.timezone = timezone).await?
+        },
+        "timeout" => {
+            let seconds = match find_option(options, "seconds") {
+                Some(ResolvedValue::Integer(seconds)) => *seconds as u64,
+                _ => return Err(CommandError::InvalidArguments),
+            };
+            let config = handler.config_service.update(guild_id, |config| config.idle_timeout_secs = seconds).await?;
+            handler.room_manager.set_idle_timeout(guild_id, config.idle_timeout()).await;
+            config
+        },
+        "rejoin" => {
+            let seconds = match find_option(options, "seconds") {
+                Some(ResolvedValue::Integer(seconds)) => Some(*seconds as u64),
+                _ => None,
+            };
+            let show = match find_option(options, "show") {
+                Some(ResolvedValue::Boolean(show)) => Some(*show),
+                _ => None,
+            };
+            handler.config_service.update(guild_id, |config| {
+                if let Some(seconds) = seconds {
+                    config.rejoin_window_secs = seconds;
+                }
+                if let Some(show) = show {
+                    config.show_reconnects = show;
+                }
+            }).await?
+        },
+        "gaps" => {
+            let show = matches!(find_option(options, "show"), Some(ResolvedValue::Boolean(true)));
+            handler.config_service.update(guild_id, |config| config.show_gaps = show).await?
+        },
+        "migration" => {
+            let members = match find_option(options, "members") {
+                Some(ResolvedValue::Integer(members)) => (*members).max(0) as usize,
+                _ => return Err(CommandError::InvalidArguments),
+            };
+            handler.config_service.update(guild_id, |config| config.migration_min_members = members).await?
+        },
+        "continuation" => {
+            let seconds = match find_option(options, "seconds") {
+                Some(ResolvedValue::Integer(seconds)) => *seconds as u64,
+                _ => return Err(CommandError::InvalidArguments),
+            };
+            let mode = match find_option(options, "mode") {
+                Some(ResolvedValue::String("tag")) => Some(ContinuationMode::Tag),
+                Some(ResolvedValue::String("merge")) => Some(ContinuationMode::Merge),
+                _ => None,
+            };
+            handler.config_service.update(guild_id, |config| {
+                config.continuation_window_secs = seconds;
+                if let Some(mode) = mode {
+                    config.continuation_mode = mode;
+                }
+            }).await?
+        },
+        "attendance" => {
+            let percent = match find_option(options, "percent") {
+                Some(ResolvedValue::Integer(percent)) => (*percent).clamp(1, 100) as u8,
+                _ => return Err(CommandError::InvalidArguments),
+            };
+            handler.config_service.update(guild_id, |config| config.event_attendance_percent = percent).await?
+        },
+        "report-policy" => {
+            let integer = |name| match find_option(options, name) {
+                Some(ResolvedValue::Integer(value)) => Some(*value),
+                _ => None,
+            };
+            let interval = integer("interval");
+            let debounce = integer("debounce");
+            let max_edits = integer("max-edits");
+            handler.config_service.update(guild_id, |config| {
+                let policy = &mut config.report_policy;
+                if let Some(interval) = interval {
+                    policy.update_interval_secs = interval as u64;
+                }
+                if let Some(debounce) = debounce {
+                    policy.debounce_secs = debounce as u64;
+                }
+                if let Some(max_edits) = max_edits {
+                    policy.max_edits_per_hour = max_edits as u32;
+                }
+            }).await?
+        },
+        "theme" => {
+            let theme = match find_option(options, "theme") {
+                Some(ResolvedValue::String("light")) => ThemeKind::Light,
+                Some(ResolvedValue::String("dark")) => ThemeKind::Dark,
+                _ => return Err(CommandError::InvalidArguments),
+            };
+            handler.config_service.update(guild_id, |config| config.theme = theme).await?
+        },
+        "aspect-ratio" => {
+            let aspect_ratio = match find_option(options, "ratio") {
+                Some(ResolvedValue::String("standard")) => AspectRatioKind::Standard,
+                Some(ResolvedValue::String("wide")) => AspectRatioKind::Wide,
+                Some(ResolvedValue::String("square")) => AspectRatioKind::Square,
+                _ => return Err(CommandError::InvalidArguments),
+            };
+            handler.config_service.update(guild_id, |config| config.aspect_ratio = aspect_ratio).await?
+        },
+        "sort" => {
+            let sort = match find_option(options, "sort") {
+                Some(ResolvedValue::String(name)) => SortOrder::from_name(name).ok_or(CommandError::InvalidArguments)?,
+                _ => return Err(CommandError::InvalidArguments),
+            };
+            handler.config_service.update(guild_id, |config| config.sort_order = sort).await?
+        },
+        "rows-per-image" => {
+            let rows = match find_option(options, "rows") {
+                Some(ResolvedValue::Integer(rows)) => *rows as usize,
+                _ => return Err(CommandError::InvalidArguments),
+            };
+            handler.config_service.update(guild_id, |config| config.rows_per_image = rows).await?
+        },
+        "attach-json" => {
+            let enabled = matches!(find_option(options, "enabled"), Some(ResolvedValue::Boolean(true)));
+            handler.config_service.update(guild_id, |config| config.attach_json = enabled).await?
+        },
+        "dm-starter" => {
+            let enabled = matches!(find_option(options, "enabled"), Some(ResolvedValue::Boolean(true)));
+            handler.config_service.update(guild_id, |config| config.dm_starter = enabled).await?
+        },
+        "channels" => {
+            let channel_id = match find_option(options, "channel") {
+                Some(ResolvedValue::Channel(channel)) => Some(channel.id),
+                _ => None,
+            };
+            match (find_option(options, "action"), channel_id) {
+                (Some(ResolvedValue::String("clear")), _) => {
+                    handler.config_service.update(guild_id, |config| config.channel_filter = ChannelFilter::default()).await?
+                },
+                (Some(ResolvedValue::String("allow")), Some(channel_id)) => {
+                    handler.config_service.update(guild_id, |config| config.channel_filter.allow(channel_id)).await?
+                },
+                (Some(ResolvedValue::String("deny")), Some(channel_id)) => {
+                    handler.config_service.update(guild_id, |config| config.channel_filter.deny(channel_id)).await?
+                },
+                (Some(ResolvedValue::String("remove")), Some(channel_id)) => {
+                    handler.config_service.update(guild_id, |config| config.channel_filter.remove(channel_id)).await?
+                },
+                _ => return Err(CommandError::InvalidArguments),
+            }
+        },
+        "roles" => {
+            let role_id = match find_option(options, "role") {
+                Some(ResolvedValue::Role(role)) => Some(role.id),
+                _ => None,
+            };
+            match (find_option(options, "action"), role_id) {
+                (Some(ResolvedValue::String("clear")), _) => {
+                    handler.config_service.update(guild_id, |config| config.role_filter = RoleFilter::default()).await?
+                },
+                (Some(ResolvedValue::String("allow")), Some(role_id)) => {
+                    handler.config_service.update(guild_id, |config| config.role_filter.allow(role_id)).await?
+                },
+                (Some(ResolvedValue::String("deny")), Some(role_id)) => {
+                    handler.config_service.update(guild_id, |config| config.role_filter.deny(role_id)).await?
+                },
+                (Some(ResolvedValue::String("remove")), Some(role_id)) => {
+                    handler.config_service.update(guild_id, |config| config.role_filter.remove(role_id)).await?
+                },
+                _ => return Err(CommandError::InvalidArguments),
+            }
+        },
+        "image-format" => {
+            let format = match find_option(options, "format") {
+                Some(ResolvedValue::String("png")) => ImageFormat::Png,
+                Some(ResolvedValue::String("webp")) => ImageFormat::Webp,
+                Some(ResolvedValue::String("avif")) => ImageFormat::Avif,
+                Some(ResolvedValue::String("apng")) => ImageFormat::Apng,
+                _ => return Err(CommandError::InvalidArguments),
+            };
+            let quality = match find_option(options, "quality") {
+                Some(ResolvedValue::Integer(quality)) => Some(*quality as u8),
+                _ => None,
+            };
+            handler.config_service.update(guild_id, |config| {
+                config.image_format = format;
+                if let Some(quality) = quality {
+                    config.image_quality = quality;
+                }
+            }).await?
+        },
+        "webhook" => {
+            let url = match find_option(options, "url") {
+                Some(ResolvedValue::String(url)) => Some(url.to_string()),
+                _ => None,
+            };
+            match (find_option(options, "action"), url) {
+                (Some(ResolvedValue::String("clear")), _) => {
+                    handler.config_service.update(guild_id, |config| config.webhook_urls.clear()).await?
+                },
+                (Some(ResolvedValue::String("add")), Some(url)) => {
+                    if check_endpoint(&url).await.is_err() {
+                        return Err(CommandError::InvalidArguments);
+                    }
+                    handler.config_service.update(guild_id, |config| {
+                        if !config.webhook_urls.contains(&url) && config.webhook_urls.len() < MAX_WEBHOOKS {
+                            config.webhook_urls.push(url);
+                        }
+                    }).await?
+                },
+                (Some(ResolvedValue::String("remove")), Some(url)) => {
+                    handler.config_service.update(guild_id, |config| config.webhook_urls.retain(|existing| *existing != url)).await?
+                },
+                _ => return Err(CommandError::InvalidArguments),
+            }
+        },
+        "digest" => {
+            let frequency = match find_option(options, "frequency") {
+                Some(ResolvedValue::String("off")) => DigestFrequency::Off,
+                Some(ResolvedValue::String("daily")) => DigestFrequency::Daily,
+                Some(ResolvedValue::String("weekly")) => DigestFrequency::Weekly,
+                _ => return Err(CommandError::InvalidArguments),
+            };
+            let hour = match find_option(options, "hour") {
+                Some(ResolvedValue::Integer(hour)) => Some(*hour as u32),
+                _ => None,
+            };
+            let channel_id = match find_option(options, "channel") {
+                Some(ResolvedValue::Channel(channel)) => Some(channel.id),
+                _ => None,
+            };
+            handler.config_service.update(guild_id, |config| {
+                config.digest.frequency = frequency;
+                if let Some(hour) = hour {
+                    config.digest.hour = hour;
+                }
+                config.digest.channel_id = channel_id;
+            }).await?
+        },
+        #[cfg(feature = "voice-receive")]
+        "speaking" => {
+            let enabled = matches!(find_option(options, "enabled"), Some(ResolvedValue::Boolean(true)));
+            handler.config_service.update(guild_id, |config| config.track_speaking = enabled).await?
+        },
+        _ => return Err(CommandError::InvalidArguments),
+    };
+
+    command.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .embed(describe_config(&config))
+                .ephemeral(true),
+        ),
+    ).await?;
+
+    Ok(())
+}
+
+fn describe_config(config: &GuildConfig) -> CreateEmbed {
+    let mut report_channel = match config.report_channel_id {
+        Some(channel_id) => channel_id.mention().to_string(),
+        None => String::from("voice channel"),
+    };
+    for route in &config.report_routes {
+        report_channel.push_str(&format!("\n{} → {}", route.source.mention(), route.report_channel_id.mention()));
+    }
+    // embeds take at most 25 fields, so the audit log shares the report channel's.
+    if let Some(audit_channel_id) = config.audit_channel_id {
+        report_channel.push_str(&format!("\naudit log → {}", audit_channel_id.mention()));
+    }
+
+    CreateEmbed::new()
+        .title("Configuration")
+        .field("manager role", config.manager_role_id.map_or(String::from("-"), |role_id| role_id.mention().to_string()), true)
+        .field("report channel", report_channel, true)
+        .field("locale", Language::from_locale(&config.locale).name(), true)
+        .field("timezone", config.timezone.map_or("local", |timezone| timezone.name()), true)
+        .field("idle timeout", format!("{}s", config.idle_timeout_secs), true)
+        .field(
+            "rejoin window",
+            format!("{}s{}", config.rejoin_window_secs, if config.show_reconnects { ", shown" } else { "" }),
+            true,
+        )
+        .field("show gaps", config.show_gaps.to_string(), true)
+        .field(
+            "migration / continuation",
+            format!(
+                "{} / {}",
+                match config.migration_min_members {
+                    0 => String::from("off"),
+                    members => format!("{}+ members", members),
+                },
+                match config.continuation_window_secs {
+                    0 => String::from("off"),
+                    seconds => format!("{}s, {}", seconds, config.continuation_mode.name()),
+                },
+            ),
+            true,
+        )
+        .field("event attendance", format!("{}%", config.event_attendance_percent), true)
+        .field("min duration", format!("{}m", config.min_call_duration_secs / 60), true)
+        .field("min participants", config.min_participants.to_string(), true)
+        .field("theme", config.theme.name(), true)
+        .field("aspect ratio", config.aspect_ratio.name(), true)
+        .field("sort", config.sort_order.name(), true)
+        .field(
+            "rows per image",
+            match config.rows_per_image {
+                0 => String::from("unlimited"),
+                rows => rows.to_string(),
+            },
+            true,
+        )
+        // embeds take at most 25 fields, so related toggles share one.
+        .field("attach json / dm starter", format!("{} / {}", config.attach_json, config.dm_starter), true)
+        .field("track speaking", config.track_speaking.to_string(), true)
+        .field("image format", format!("{} (quality {})", config.image_format.name(), config.image_quality), true)
+        // urls often embed secrets, so only their number is shown.
+        .field("webhooks", config.webhook_urls.len().to_string(), true)
+        .field(
+            "digest",
+            match config.digest.frequency {
+                DigestFrequency::Off => String::from("off"),
+                frequency => format!(
+                    "{} at {:02}:00 in {}",
+                    frequency.name(),
+                    config.digest.hour,
+                    config.digest.channel_id.or(config.report_channel_id).map_or(String::from("-"), |channel_id| channel_id.mention().to_string()),
+                ),
+            },
+            true,
+        )
+        .field(
+            "report policy",
+            format!(
+                "every {}s, debounce {}s, up to {} edits/hour",
+                config.report_policy.update_interval_secs,
+                config.report_policy.debounce_secs,
+                config.report_policy.max_edits_per_hour,
+            ),
+            false,
+        )
+        .field("allowed channels", mention_all(&config.channel_filter.allowed), false)
+        .field("denied channels", mention_all(&config.channel_filter.denied), false)
+        .field("allowed roles", mention_all(&config.role_filter.allowed), false)
+        .field("denied roles", mention_all(&config.role_filter.denied), false)
+}
+
+fn mention_all(ids: &[impl Mentionable]) -> String {
+    match ids.is_empty() {
+        true => String::from("-"),
+        false => ids.iter().map(|id| id.mention().to_string()).collect::<Vec<_>>().join(" "),
+    }
+}
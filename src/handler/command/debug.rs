@@ -0,0 +1,52 @@
+use crate::handler::command::{find_option, subcommand, CommandError, CommandHandler, CommandResult};
+use serenity::all::{
+    ChannelType, CommandInteraction, CommandOptionType, Context, CreateAttachment, CreateCommand,
+    CreateCommandOption, CreateInteractionResponse, CreateInteractionResponseMessage, Permissions,
+    ResolvedValue,
+};
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("debug")
+        .description("Tools for reporting bugs")
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "dump", "Attach the tracked state of a call as JSON")
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::Channel, "channel", "Voice channel to dump; omit to use the one you are in")
+                        .channel_types(vec![ChannelType::Voice, ChannelType::Stage]),
+                ),
+        )
+}
+
+pub async fn run(handler: &CommandHandler, ctx: &Context, command: &CommandInteraction) -> CommandResult<()> {
+    let guild_id = command.guild_id.ok_or(CommandError::GuildOnly)?;
+    let options = command.data.options();
+    let (name, options) = subcommand(&options).ok_or(CommandError::InvalidArguments)?;
+    if name != "dump" {
+        return Err(CommandError::InvalidArguments);
+    }
+
+    let channel_id = match find_option(options, "channel") {
+        Some(ResolvedValue::Channel(channel)) => channel.id,
+        _ => ctx.cache.guild(guild_id)
+            .and_then(|guild| guild.voice_states.get(&command.user.id).and_then(|state| state.channel_id))
+            .ok_or(CommandError::NotInVoiceChannel)?,
+    };
+
+    let room = handler.room_manager.get_room(channel_id).await.ok_or(CommandError::RoomNotFound)?;
+    let snapshot = room.lock().await.snapshot();
+    // channel ids are global; never hand out another guild's room.
+    if snapshot.guild_id != guild_id {
+        return Err(CommandError::RoomNotFound);
+    }
+
+    let attachment = CreateAttachment::bytes(snapshot.to_json()?, format!("room-{}.json", channel_id));
+    let response = CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new()
+            .add_file(attachment)
+            .ephemeral(true),
+    );
+    command.create_response(&ctx.http, response).await?;
+
+    Ok(())
+}
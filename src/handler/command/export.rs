@@ -0,0 +1,97 @@
+use crate::handler::command::history::resolve_period;
+use crate::handler::command::{find_option, subcommand, CommandError, CommandHandler, CommandResult};
+use chrono::{Datelike, Local, Months, NaiveDate, TimeZone, Utc};
+use serenity::all::{
+    ChannelType, CommandInteraction, CommandOptionType, Context, CreateAttachment, CreateCommand,
+    CreateCommandOption, EditInteractionResponse, GuildId, ResolvedOption, ResolvedValue,
+};
+
+// members listed in the leaderboard pages of the monthly document.
+const DOCUMENT_LEADERBOARD_SIZE: usize = 30;
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("export")
+        .description("Export recorded calls")
+        .add_option(period_subcommand("csv", "Attach one row per activity segment as CSV"))
+        .add_option(period_subcommand("ics", "Attach the calls as calendar events with their attendees"))
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "pdf", "Attach a month of voice activity as a PDF report")
+                .add_sub_option(CreateCommandOption::new(CommandOptionType::String, "month", "Month as YYYY-MM; defaults to last month")),
+        )
+}
+
+fn period_subcommand(name: &str, description: &str) -> CreateCommandOption {
+    CreateCommandOption::new(CommandOptionType::SubCommand, name, description)
+        .add_sub_option(
+            CreateCommandOption::new(CommandOptionType::Channel, "channel", "Only export calls of this voice channel")
+                .channel_types(vec![ChannelType::Voice, ChannelType::Stage]),
+        )
+        .add_sub_option(CreateCommandOption::new(CommandOptionType::String, "from", "First day as YYYY-MM-DD; defaults to a week ago"))
+        .add_sub_option(CreateCommandOption::new(CommandOptionType::String, "to", "Last day as YYYY-MM-DD; defaults to today"))
+}
+
+pub async fn run(handler: &CommandHandler, ctx: &Context, command: &CommandInteraction) -> CommandResult<()> {
+    let guild_id = command.guild_id.ok_or(CommandError::GuildOnly)?;
+    handler.ensure_manager(command).await?;
+    let options = command.data.options();
+    let (name, options) = subcommand(&options).ok_or(CommandError::InvalidArguments)?;
+    if name == "pdf" {
+        return export_pdf(handler, ctx, command, guild_id, options).await;
+    }
+
+    let channel_id = match find_option(options, "channel") {
+        Some(ResolvedValue::Channel(channel)) => Some(channel.id),
+        _ => None,
+    };
+    let (since, until) = resolve_period(handler, guild_id, options).await?;
+
+    command.defer_ephemeral(&ctx.http).await?;
+
+    let attachment = match name {
+        "csv" => CreateAttachment::bytes(
+            handler.history_service.export_csv(guild_id, channel_id, since, until).await?,
+            format!("activities-{}.csv", guild_id),
+        ),
+        "ics" => CreateAttachment::bytes(
+            handler.history_service.export_ics(guild_id, channel_id, since, until).await?,
+            format!("calls-{}.ics", guild_id),
+        ),
+        _ => return Err(CommandError::InvalidArguments),
+    };
+    command.edit_response(&ctx.http, EditInteractionResponse::new().new_attachment(attachment)).await?;
+
+    Ok(())
+}
+
+async fn export_pdf(handler: &CommandHandler, ctx: &Context, command: &CommandInteraction, guild_id: GuildId, options: &[ResolvedOption<'_>]) -> CommandResult<()> {
+    let timezone = handler.config_service.get(guild_id).await.timezone;
+    let month = match find_option(options, "month") {
+        Some(ResolvedValue::String(month)) => NaiveDate::parse_from_str(&format!("{}-01", month.trim()), "%Y-%m-%d")
+            .map_err(|_| CommandError::InvalidArguments)?,
+        _ => match timezone {
+            Some(timezone) => last_month(&timezone),
+            None => last_month(&Local),
+        }.ok_or(CommandError::InvalidArguments)?,
+    };
+
+    command.defer_ephemeral(&ctx.http).await?;
+
+    let summary = handler.stats_service.monthly_summary(guild_id, month, timezone, DOCUMENT_LEADERBOARD_SIZE).await?;
+    let mut response = EditInteractionResponse::new();
+    if summary.is_empty() {
+        response = response.content("No voice activity was recorded in this month.");
+    } else {
+        let attachment = handler.report_service.render_monthly_document(guild_id, "Voice report", summary).await?;
+        response = response.new_attachment(attachment);
+    }
+    command.edit_response(&ctx.http, response).await?;
+
+    Ok(())
+}
+
+// the first day of the previous month in the timezone.
+fn last_month<T: TimeZone>(timezone: &T) -> Option<NaiveDate> {
+    Utc::now().with_timezone(timezone).date_naive()
+        .with_day(1)?
+        .checked_sub_months(Months::new(1))
+}
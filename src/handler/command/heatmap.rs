@@ -0,0 +1,52 @@
+use crate::handler::command::{find_option, CommandError, CommandHandler, CommandResult};
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption, CreateEmbed,
+    EditInteractionResponse, ResolvedValue,
+};
+use std::time::Duration;
+
+const DEFAULT_WEEKS: i64 = 4;
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("heatmap")
+        .description("Show when this server is active in voice, by weekday and hour")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::Integer, "weeks", "Number of past weeks to include; defaults to 4")
+                .min_int_value(1)
+                .max_int_value(12),
+        )
+}
+
+pub async fn run(handler: &CommandHandler, ctx: &Context, command: &CommandInteraction) -> CommandResult<()> {
+    let guild_id = command.guild_id.ok_or(CommandError::GuildOnly)?;
+
+    let options = command.data.options();
+    let weeks = match find_option(&options, "weeks") {
+        Some(ResolvedValue::Integer(weeks)) => *weeks,
+        _ => DEFAULT_WEEKS,
+    };
+
+    command.defer(&ctx.http).await?;
+
+    let config = handler.config_service.get(guild_id).await;
+    let period = Duration::from_hours(24 * 7 * weeks as u64);
+    let heatmap = handler.stats_service.heatmap(guild_id, period, config.timezone).await?;
+    let title = match weeks {
+        1 => String::from("Voice activity of the past week"),
+        _ => format!("Voice activity of the past {} weeks", weeks),
+    };
+
+    let mut response = EditInteractionResponse::new();
+    if heatmap.is_empty() {
+        response = response.content("No voice activity was recorded in this period.");
+    } else {
+        let attachment = handler.report_service.render_heatmap(guild_id, &title, heatmap).await?;
+        response = response
+            .embed(CreateEmbed::new().title(title).image(format!("attachment://{}", attachment.filename)))
+            .new_attachment(attachment);
+    }
+
+    command.edit_response(&ctx.http, response).await?;
+
+    Ok(())
+}
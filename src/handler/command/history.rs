@@ -0,0 +1,135 @@
+use crate::handler::command::{find_option, CommandError, CommandHandler, CommandResult};
+use crate::service::renderer::view::format_duration;
+use chrono::{Local, NaiveDate, TimeZone, Utc};
+use serenity::all::{
+    ChannelType, CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    CreateEmbed, CreateEmbedFooter, EditInteractionResponse, FormattedTimestamp, FormattedTimestampStyle,
+    GuildId, Mentionable, ResolvedOption, ResolvedValue, Timestamp,
+};
+use std::fmt::Write;
+use std::time::Duration;
+
+const CALLS_PER_PAGE: usize = 10;
+const DEFAULT_HISTORY_DAYS: u64 = 7;
+const DAY_MILLIS: i64 = 24 * 60 * 60 * 1000;
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("history")
+        .description("List past calls of this server")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::Channel, "channel", "Only list calls of this voice channel")
+                .channel_types(vec![ChannelType::Voice, ChannelType::Stage]),
+        )
+        .add_option(CreateCommandOption::new(CommandOptionType::String, "from", "First day as YYYY-MM-DD; defaults to a week ago"))
+        .add_option(CreateCommandOption::new(CommandOptionType::String, "to", "Last day as YYYY-MM-DD; defaults to today"))
+        .add_option(CreateCommandOption::new(CommandOptionType::Integer, "page", "Page of the list").min_int_value(1))
+        .add_option(CreateCommandOption::new(CommandOptionType::Integer, "call", "Render the timeline of the call with this number again").min_int_value(1))
+}
+
+pub async fn run(handler: &CommandHandler, ctx: &Context, command: &CommandInteraction) -> CommandResult<()> {
+    let guild_id = command.guild_id.ok_or(CommandError::GuildOnly)?;
+    let options = command.data.options();
+
+    if let Some(ResolvedValue::Integer(room_id)) = find_option(&options, "call") {
+        // rendering may exceed the 3 seconds Discord waits for an initial response.
+        command.defer(&ctx.http).await?;
+
+        let report = handler.history_service.render_call(guild_id, *room_id).await?.ok_or(CommandError::RoomNotFound)?;
+        let response = report.attachments.into_iter().fold(
+            EditInteractionResponse::new().embed(report.embed),
+            |response, attachment| response.new_attachment(attachment),
+        );
+        command.edit_response(&ctx.http, response).await?;
+        return Ok(())
+    }
+
+    let channel_id = match find_option(&options, "channel") {
+        Some(ResolvedValue::Channel(channel)) => Some(channel.id),
+        _ => None,
+    };
+    let page = match find_option(&options, "page") {
+        Some(ResolvedValue::Integer(page)) => *page as usize,
+        _ => 1,
+    };
+    let (since, until) = resolve_period(handler, guild_id, &options).await?;
+
+    let calls = handler.history_service.list_calls(guild_id, channel_id, since, until).await?;
+    let pages = calls.len().div_ceil(CALLS_PER_PAGE).max(1);
+    if page > pages {
+        return Err(CommandError::InvalidArguments);
+    }
+
+    command.defer(&ctx.http).await?;
+
+    let mut description = String::new();
+    for call in calls.iter().skip((page - 1) * CALLS_PER_PAGE).take(CALLS_PER_PAGE) {
+        let _ = write!(
+            description,
+            "`#{}` {} {} · {} · {} members",
+            call.id,
+            call.channel_id.mention(),
+            format_millis(call.started_at),
+            match call.ended_at {
+                Some(ended_at) => format_duration(Duration::from_millis((ended_at - call.started_at).max(0) as u64)),
+                None => String::from("ongoing"),
+            },
+            call.participants,
+        );
+        if call.scheduled_event_id.is_some() {
+            description.push_str(" · event");
+        }
+        if let Some((report_channel_id, message_id)) = call.summary_message {
+            let _ = write!(description, " · [report]({})", message_id.link(report_channel_id, Some(guild_id)));
+        }
+        description.push('\n');
+    }
+    if calls.is_empty() {
+        description.push_str("No call was recorded in this period.");
+    }
+
+    let embed = CreateEmbed::new()
+        .title(format!("{} past calls", calls.len()))
+        .description(description)
+        .footer(CreateEmbedFooter::new(format!("page {}/{} · /history call:<number> renders a call again", page, pages)));
+    command.edit_response(&ctx.http, EditInteractionResponse::new().embed(embed)).await?;
+
+    Ok(())
+}
+
+// reads the `from` and `to` options as days in the guild's timezone, as the timeline labels are.
+pub(crate) async fn resolve_period(handler: &CommandHandler, guild_id: GuildId, options: &[ResolvedOption<'_>]) -> CommandResult<(i64, i64)> {
+    let date = |name| match find_option(options, name) {
+        Some(ResolvedValue::String(date)) => NaiveDate::parse_from_str(date, "%Y-%m-%d").map(Some).map_err(|_| CommandError::InvalidArguments),
+        _ => Ok(None),
+    };
+    let (from, to) = (date("from")?, date("to")?);
+
+    let (since, until) = match handler.config_service.get(guild_id).await.timezone {
+        Some(timezone) => date_range(&timezone, from, to),
+        None => date_range(&Local, from, to),
+    };
+    if since >= until {
+        return Err(CommandError::InvalidArguments);
+    }
+    Ok((since, until))
+}
+
+// unix milliseconds from the start of `from` to the end of `to`.
+fn date_range<T: TimeZone>(timezone: &T, from: Option<NaiveDate>, to: Option<NaiveDate>) -> (i64, i64) {
+    let now = Utc::now();
+    let start_of = |date: NaiveDate| date.and_hms_opt(0, 0, 0)
+        .and_then(|naive| timezone.from_local_datetime(&naive).earliest())
+        .map(|time| time.timestamp_millis());
+
+    let since = from.and_then(start_of)
+        .unwrap_or(now.timestamp_millis() - DEFAULT_HISTORY_DAYS as i64 * DAY_MILLIS);
+    let until = to.and_then(|to| to.succ_opt())
+        .and_then(start_of)
+        .unwrap_or(now.timestamp_millis());
+    (since, until)
+}
+
+fn format_millis(millis: i64) -> String {
+    Timestamp::from_unix_timestamp(millis / 1000)
+        .map_or(String::from("-"), |timestamp| FormattedTimestamp::new(timestamp, Some(FormattedTimestampStyle::ShortDateTime)).to_string())
+}
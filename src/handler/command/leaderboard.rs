@@ -0,0 +1,58 @@
+use crate::handler::command::{find_option, CommandError, CommandHandler, CommandResult};
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption, CreateEmbed,
+    EditInteractionResponse, ResolvedValue,
+};
+use std::time::Duration;
+
+const DEFAULT_LIMIT: i64 = 10;
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("leaderboard")
+        .description("Rank the members who spent the most time in voice")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::String, "period", "Period to rank; defaults to week")
+                .add_string_choice("day", "day")
+                .add_string_choice("week", "week")
+                .add_string_choice("month", "month"),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::Integer, "limit", "Number of members to show")
+                .min_int_value(1)
+                .max_int_value(25),
+        )
+}
+
+pub async fn run(handler: &CommandHandler, ctx: &Context, command: &CommandInteraction) -> CommandResult<()> {
+    let guild_id = command.guild_id.ok_or(CommandError::GuildOnly)?;
+
+    let options = command.data.options();
+    let (label, period) = match find_option(&options, "period") {
+        Some(ResolvedValue::String("day")) => ("day", Duration::from_hours(24)),
+        Some(ResolvedValue::String("month")) => ("month", Duration::from_hours(24 * 30)),
+        _ => ("week", Duration::from_hours(24 * 7)),
+    };
+    let limit = match find_option(&options, "limit") {
+        Some(ResolvedValue::Integer(limit)) => *limit,
+        _ => DEFAULT_LIMIT,
+    };
+
+    command.defer(&ctx.http).await?;
+
+    let entries = handler.stats_service.leaderboard(guild_id, period, limit as usize).await?;
+    let title = format!("Voice leaderboard of the past {}", label);
+
+    let mut response = EditInteractionResponse::new();
+    if entries.is_empty() {
+        response = response.content("No voice activity was recorded in this period.");
+    } else {
+        let attachment = handler.report_service.render_leaderboard(guild_id, &title, &entries).await?;
+        response = response
+            .embed(CreateEmbed::new().title(title).image(format!("attachment://{}", attachment.filename)))
+            .new_attachment(attachment);
+    }
+
+    command.edit_response(&ctx.http, response).await?;
+
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use crate::handler::command::{CommandError, CommandHandler, CommandResult};
+use crate::service::report::RoomDTO;
+use serenity::all::{CommandInteraction, Context, CreateCommand, EditInteractionResponse};
+use tokio::time::Instant;
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("myreport")
+        .description("Show only your own timeline of the call you are in")
+}
+
+pub async fn run(handler: &CommandHandler, ctx: &Context, command: &CommandInteraction) -> CommandResult<()> {
+    let guild_id = command.guild_id.ok_or(CommandError::GuildOnly)?;
+    let user_id = command.user.id;
+
+    let channel_id = handler.room_manager.find_connected_channel(guild_id, user_id).await
+        .ok_or(CommandError::NotInVoiceChannel)?;
+    let room = handler.room_manager.get_room(channel_id).await.ok_or(CommandError::RoomNotFound)?;
+    let mut room_dto = {
+        let room = room.lock().await;
+        RoomDTO::from_room(&room)
+    };
+    room_dto.participants.retain(|participant| participant.user_id() == user_id);
+
+    // only the invoking user sees the response, so nobody else is notified.
+    command.defer_ephemeral(&ctx.http).await?;
+
+    let report = handler.report_service.render_room_report(Instant::now(), &room_dto, true, None).await?;
+    let response = report.attachments.into_iter().fold(
+        EditInteractionResponse::new().embed(report.embed),
+        |response, attachment| response.new_attachment(attachment),
+    );
+    command.edit_response(&ctx.http, response).await?;
+
+    Ok(())
+}
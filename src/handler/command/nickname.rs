@@ -0,0 +1,52 @@
+use crate::handler::command::{find_option, subcommand, CommandError, CommandHandler, CommandResult};
+use crate::service::nickname::{sanitize_nickname, MAX_NICKNAME_LENGTH};
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    CreateInteractionResponse, CreateInteractionResponseMessage, ResolvedValue,
+};
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("nickname")
+        .description("Choose the name shown for you in reports")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "set", "Use a custom name instead of your display name")
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::String, "name", "Name shown in reports of this server")
+                        .max_length(MAX_NICKNAME_LENGTH as u16)
+                        .required(true),
+                ),
+        )
+        .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "reset", "Go back to your display name"))
+}
+
+pub async fn run(handler: &CommandHandler, ctx: &Context, command: &CommandInteraction) -> CommandResult<()> {
+    let guild_id = command.guild_id.ok_or(CommandError::GuildOnly)?;
+    let options = command.data.options();
+    let (name, options) = subcommand(&options).ok_or(CommandError::InvalidArguments)?;
+    let user_id = command.user.id;
+
+    let message = match name {
+        "set" => {
+            let nickname = match find_option(options, "name") {
+                Some(ResolvedValue::String(nickname)) => sanitize_nickname(nickname).ok_or(CommandError::InvalidArguments)?,
+                _ => return Err(CommandError::InvalidArguments),
+            };
+            handler.nickname_service.set_nickname(guild_id, user_id, Some(nickname.clone())).await?;
+            format!("Reports show you as \"{}\" from your next join.", nickname)
+        },
+        "reset" => {
+            handler.nickname_service.set_nickname(guild_id, user_id, None).await?;
+            String::from("Reports show your display name again from your next join.")
+        },
+        _ => return Err(CommandError::InvalidArguments),
+    };
+
+    command.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().content(message).ephemeral(true),
+        ),
+    ).await?;
+
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use crate::handler::command::{CommandError, CommandHandler, CommandResult};
+use crate::service::report::RoomDTO;
+use serenity::all::{CommandInteraction, Context, CreateCommand, CreateEmbed, EditInteractionResponse};
+use tokio::time::Instant;
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("overview")
+        .description("Show the timelines of every active call in this server")
+}
+
+pub async fn run(handler: &CommandHandler, ctx: &Context, command: &CommandInteraction) -> CommandResult<()> {
+    let guild_id = command.guild_id.ok_or(CommandError::GuildOnly)?;
+
+    let mut rooms = Vec::new();
+    for room in handler.room_manager.get_guild_rooms(guild_id).await {
+        rooms.push(RoomDTO::from_room(&*room.lock().await));
+    }
+    if rooms.is_empty() {
+        return Err(CommandError::RoomNotFound);
+    }
+    rooms.sort_by_key(|room| room.created_at);
+
+    let rooms: Vec<(String, RoomDTO)> = {
+        let guild = ctx.cache.guild(guild_id);
+        rooms.into_iter()
+            .map(|room| {
+                let title = guild.as_ref()
+                    .and_then(|guild| guild.channels.get(&room.channel_id))
+                    .map_or_else(|| room.channel_id.to_string(), |channel| channel.name.clone());
+                (title, room)
+            })
+            .collect()
+    };
+
+    // rendering may exceed the 3 seconds Discord waits for an initial response.
+    command.defer(&ctx.http).await?;
+
+    let attachment = handler.report_service.render_overview(Instant::now(), guild_id, &rooms).await?;
+    let response = EditInteractionResponse::new()
+        .embed(
+            CreateEmbed::new()
+                .title(format!("{} active calls", rooms.len()))
+                .image(format!("attachment://{}", attachment.filename)),
+        )
+        .new_attachment(attachment);
+    command.edit_response(&ctx.http, response).await?;
+
+    Ok(())
+}
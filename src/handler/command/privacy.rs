@@ -0,0 +1,41 @@
+use crate::handler::command::{subcommand, CommandError, CommandHandler, CommandResult};
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    CreateInteractionResponse, CreateInteractionResponseMessage,
+};
+use tokio::time::Instant;
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("privacy")
+        .description("Control whether ringring tracks your voice activity")
+        .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "optout", "Stop tracking you and delete your recorded history"))
+        .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "optin", "Allow tracking you again"))
+}
+
+pub async fn run(handler: &CommandHandler, ctx: &Context, command: &CommandInteraction) -> CommandResult<()> {
+    let options = command.data.options();
+    let (name, _) = subcommand(&options).ok_or(CommandError::InvalidArguments)?;
+    let user_id = command.user.id;
+
+    let message = match name {
+        "optout" => {
+            handler.privacy_service.set_opted_out(user_id, true).await?;
+            handler.room_manager.forget_user(Instant::now(), user_id).await;
+            "You have opted out. Your voice activity is no longer tracked and your history was deleted."
+        },
+        "optin" => {
+            handler.privacy_service.set_opted_out(user_id, false).await?;
+            "You have opted in. Your voice activity will be tracked from your next join."
+        },
+        _ => return Err(CommandError::InvalidArguments),
+    };
+
+    command.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().content(message).ephemeral(true),
+        ),
+    ).await?;
+
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use crate::handler::command::{find_option, CommandError, CommandHandler, CommandResult};
+use crate::service::renderer::transformer::SortOrder;
+use crate::service::report::RoomDTO;
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    EditInteractionResponse, ResolvedValue,
+};
+use tokio::time::Instant;
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("report")
+        .description("Post the current timeline of the voice channel you are in")
+        .add_option(CreateCommandOption::new(CommandOptionType::Boolean, "svg", "Also attach the timeline as an SVG file"))
+        .add_option(CreateCommandOption::new(CommandOptionType::Boolean, "json", "Also attach the raw activity segments as JSON"))
+        .add_option(sort_option("Order of participants; defaults to the server setting"))
+}
+
+pub(crate) fn sort_option(description: &str) -> CreateCommandOption {
+    SortOrder::ALL.into_iter().fold(
+        CreateCommandOption::new(CommandOptionType::String, "sort", description),
+        |option, order| option.add_string_choice(order.name(), order.name()),
+    )
+}
+
+pub async fn run(handler: &CommandHandler, ctx: &Context, command: &CommandInteraction) -> CommandResult<()> {
+    let guild_id = command.guild_id.ok_or(CommandError::GuildOnly)?;
+    let options = command.data.options();
+    let with_svg = matches!(find_option(&options, "svg"), Some(ResolvedValue::Boolean(true)));
+    let with_json = matches!(find_option(&options, "json"), Some(ResolvedValue::Boolean(true)));
+    let sort = match find_option(&options, "sort") {
+        Some(ResolvedValue::String(name)) => Some(SortOrder::from_name(name).ok_or(CommandError::InvalidArguments)?),
+        _ => None,
+    };
+
+    let channel_id = ctx.cache.guild(guild_id)
+        .and_then(|guild| guild.voice_states.get(&command.user.id).and_then(|state| state.channel_id))
+        .ok_or(CommandError::NotInVoiceChannel)?;
+
+    let room = handler.room_manager.get_room(channel_id).await.ok_or(CommandError::RoomNotFound)?;
+    let room_dto = {
+        let room = room.lock().await;
+        RoomDTO::from_room(&room)
+    };
+
+    // rendering may exceed the 3 seconds Discord waits for an initial response.
+    command.defer(&ctx.http).await?;
+
+    let now = Instant::now();
+    let report = handler.report_service.render_room_report(now, &room_dto, true, sort).await?;
+
+    let mut response = report.attachments.into_iter().fold(
+        EditInteractionResponse::new().embed(report.embed),
+        |response, attachment| response.new_attachment(attachment),
+    );
+    if with_svg {
+        response = response.new_attachment(handler.report_service.render_room_svg(now, &room_dto, true, sort).await?);
+    }
+    if with_json {
+        response = response.new_attachment(handler.report_service.export_room_json(now, &room_dto)?);
+    }
+
+    command.edit_response(&ctx.http, response).await?;
+
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use crate::handler::command::{find_option, CommandError, CommandHandler, CommandResult};
+use crate::service::renderer::timeline::TimelineRenderer;
+use chrono::TimeDelta;
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption, CreateEmbed,
+    CreateEmbedAuthor, CreateInteractionResponse, CreateInteractionResponseMessage, ResolvedValue,
+};
+use std::time::Duration;
+
+const DEFAULT_DAYS: i64 = 30;
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("stats")
+        .description("Show voice statistics of a member")
+        .add_option(CreateCommandOption::new(CommandOptionType::User, "user", "Member to show; defaults to you"))
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::Integer, "days", "Number of days to aggregate")
+                .min_int_value(1)
+                .max_int_value(365),
+        )
+}
+
+pub async fn run(handler: &CommandHandler, ctx: &Context, command: &CommandInteraction) -> CommandResult<()> {
+    let guild_id = command.guild_id.ok_or(CommandError::GuildOnly)?;
+
+    let options = command.data.options();
+    let user = match find_option(&options, "user") {
+        Some(ResolvedValue::User(user, _)) => *user,
+        _ => &command.user,
+    };
+    let days = match find_option(&options, "days") {
+        Some(ResolvedValue::Integer(days)) => *days,
+        _ => DEFAULT_DAYS,
+    };
+
+    let stats = handler.stats_service
+        .user_stats(guild_id, user.id, Duration::from_hours(24 * days as u64))
+        .await?;
+
+    let embed = CreateEmbed::new()
+        .author(CreateEmbedAuthor::new(user.display_name()).icon_url(user.face()))
+        .title(format!("Voice statistics for the last {} days", days))
+        .field("voice time", format_duration(stats.voice_time), true)
+        .field("muted", format_duration(stats.muted_time), true)
+        .field("streaming", format!("{} ({} streams)", format_duration(stats.stream_time), stats.streams), true)
+        .field("camera", format_duration(stats.video_time), true)
+        .field("sessions", format!("{} ({:.1}/week)", stats.sessions, stats.sessions_per_week()), true);
+
+    command.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().embed(embed)),
+    ).await?;
+
+    Ok(())
+}
+
+fn format_duration(duration: Duration) -> String {
+    TimelineRenderer::format_time_delta(TimeDelta::from_std(duration).unwrap_or(TimeDelta::zero()))
+}
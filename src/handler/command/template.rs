@@ -0,0 +1,98 @@
+use crate::handler::command::color::parse_hex_color;
+use crate::handler::command::{find_option, subcommand, CommandError, CommandHandler, CommandResult};
+use crate::model::EmbedTemplate;
+use crate::service::template::{Placeholder, Template};
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption, CreateEmbed,
+    CreateInteractionResponse, CreateInteractionResponseMessage, ResolvedValue,
+};
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("template")
+        .description("Customize the embed of reports")
+        .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "show", "Show the current template"))
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "set", "Replace parts of the embed; texts may use {channel}, {elapsed} and {participants}")
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::String, "title", "Title text")
+                        .max_length(256),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::String, "description", "Description text")
+                        .max_length(4000),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::String, "footer", "Footer text")
+                        .max_length(2048),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::String, "color", "Hex color of the embed such as #5865f2"),
+                ),
+        )
+        .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "reset", "Go back to the default embed"))
+}
+
+pub async fn run(handler: &CommandHandler, ctx: &Context, command: &CommandInteraction) -> CommandResult<()> {
+    let guild_id = command.guild_id.ok_or(CommandError::GuildOnly)?;
+    let options = command.data.options();
+    let (name, options) = subcommand(&options).ok_or(CommandError::InvalidArguments)?;
+
+    if name != "show" {
+        handler.ensure_manager(command).await?;
+    }
+
+    let config = match name {
+        "show" => handler.config_service.get(guild_id).await,
+        "set" => {
+            // parsed once here, so a typo is reported instead of silently ignored in reports.
+            let text = |name| match find_option(options, name) {
+                Some(ResolvedValue::String(text)) => Template::parse(text).map(|_| Some(text.to_string())),
+                _ => Ok(None),
+            };
+            let (title, description, footer) = (text("title")?, text("description")?, text("footer")?);
+            let color = match find_option(options, "color") {
+                Some(ResolvedValue::String(color)) => Some(parse_hex_color(color).ok_or(CommandError::InvalidArguments)?),
+                _ => None,
+            };
+            handler.config_service.update(guild_id, |config| {
+                let template = &mut config.embed_template;
+                template.title = title.or(template.title.take());
+                template.description = description.or(template.description.take());
+                template.footer = footer.or(template.footer.take());
+                template.color = color.or(template.color);
+            }).await?
+        },
+        "reset" => handler.config_service.update(guild_id, |config| config.embed_template = EmbedTemplate::default()).await?,
+        _ => return Err(CommandError::InvalidArguments),
+    };
+
+    command.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().embed(describe_template(&config.embed_template)).ephemeral(true),
+        ),
+    ).await?;
+
+    Ok(())
+}
+
+fn describe_template(template: &EmbedTemplate) -> CreateEmbed {
+    // field values take at most 1024 characters.
+    let text = |text: &Option<String>| match text {
+        Some(text) => text.chars().take(1024).collect(),
+        None => String::from("default"),
+    };
+    let placeholders: Vec<String> = Placeholder::ALL.iter().map(|placeholder| format!("`{{{}}}`", placeholder.name())).collect();
+
+    let embed = CreateEmbed::new()
+        .title("Report template")
+        .field("title", text(&template.title), false)
+        .field("description", text(&template.description), false)
+        .field("footer", text(&template.footer), false)
+        .field("color", template.color.map_or(String::from("default"), |color| format!("#{:06x}", color)), true)
+        .field("placeholders", placeholders.join(" "), true);
+    match template.color {
+        Some(color) => embed.color(color),
+        None => embed,
+    }
+}
@@ -0,0 +1,71 @@
+use crate::handler::command::{find_option, subcommand, CommandError, CommandHandler, CommandResult};
+use crate::handler::voice::resync;
+use serenity::all::{
+    ChannelType, CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    CreateInteractionResponse, CreateInteractionResponseMessage, Mentionable, ResolvedValue,
+};
+use tokio::time::Instant;
+
+pub fn register() -> CreateCommand {
+    let channel_option = |description: &str| {
+        CreateCommandOption::new(CommandOptionType::Channel, "channel", description)
+            .channel_types(vec![ChannelType::Voice, ChannelType::Stage])
+    };
+
+    CreateCommand::new("tracking")
+        .description("Pause or resume tracking voice activity")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "pause", "Stop tracking until resumed")
+                .add_sub_option(channel_option("Only pause this channel; omit to pause the whole server")),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "resume", "Start tracking again")
+                .add_sub_option(channel_option("Only resume this channel; omit to resume everything")),
+        )
+}
+
+pub async fn run(handler: &CommandHandler, ctx: &Context, command: &CommandInteraction) -> CommandResult<()> {
+    let guild_id = command.guild_id.ok_or(CommandError::GuildOnly)?;
+    handler.ensure_manager(command).await?;
+
+    let options = command.data.options();
+    let (name, options) = subcommand(&options).ok_or(CommandError::InvalidArguments)?;
+    let channel_id = match find_option(options, "channel") {
+        Some(ResolvedValue::Channel(channel)) => Some(channel.id),
+        _ => None,
+    };
+
+    let config = match name {
+        "pause" => handler.config_service.update(guild_id, |config| config.pause.pause(channel_id)).await?,
+        "resume" => handler.config_service.update(guild_id, |config| config.pause.resume(channel_id)).await?,
+        _ => return Err(CommandError::InvalidArguments),
+    };
+
+    let now = Instant::now();
+    handler.room_manager.apply_pause(now, guild_id, &config.pause).await;
+
+    let target = match channel_id {
+        Some(channel_id) => channel_id.mention().to_string(),
+        None => String::from("this server"),
+    };
+    let message = match name {
+        "pause" => format!("Tracking is paused in {}. Joins are no longer recorded until `/tracking resume`.", target),
+        _ => {
+            // members who joined during the pause are picked up from the cache.
+            resync(&handler.room_manager, Some(&handler.config_service), Some(&handler.nickname_service), ctx, vec![guild_id]).await;
+            match config.pause.guild {
+                true => format!("Tracking is resumed in {}, but the whole server is still paused.", target),
+                false => format!("Tracking is resumed in {}.", target),
+            }
+        },
+    };
+
+    command.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().content(message).ephemeral(true),
+        ),
+    ).await?;
+
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use crate::handler::command::{find_option, CommandError, CommandHandler, CommandResult};
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption, CreateEmbed,
+    EditInteractionResponse, ResolvedValue,
+};
+use std::time::Duration;
+
+const DEFAULT_DAYS: i64 = 7;
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("usertimeline")
+        .description("Show when a member was in voice, one strip per day")
+        .add_option(CreateCommandOption::new(CommandOptionType::User, "user", "Member to show; defaults to you"))
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::Integer, "days", "Number of days to show; defaults to 7")
+                .min_int_value(1)
+                .max_int_value(31),
+        )
+}
+
+pub async fn run(handler: &CommandHandler, ctx: &Context, command: &CommandInteraction) -> CommandResult<()> {
+    let guild_id = command.guild_id.ok_or(CommandError::GuildOnly)?;
+
+    let options = command.data.options();
+    let (user, member) = match find_option(&options, "user") {
+        Some(ResolvedValue::User(user, member)) => (*user, *member),
+        _ => (&command.user, None),
+    };
+    let days = match find_option(&options, "days") {
+        Some(ResolvedValue::Integer(days)) => *days,
+        _ => DEFAULT_DAYS,
+    };
+    let name = member.and_then(|member| member.nick.clone())
+        .unwrap_or_else(|| user.display_name().to_string());
+
+    command.defer(&ctx.http).await?;
+
+    // one more day covers the part of the oldest day before this time of day.
+    let activities = handler.stats_service
+        .user_activities(guild_id, user.id, Duration::from_hours(24 * (days as u64 + 1)))
+        .await?;
+    let title = format!("Voice activity of {} in the last {} days", name, days);
+
+    let mut response = EditInteractionResponse::new();
+    if activities.is_empty() {
+        response = response.content("No voice activity was recorded in this period.");
+    } else {
+        let attachment = handler.report_service
+            .render_user_timeline(guild_id, user, &name, &title, &activities, days as u32)
+            .await?;
+        response = response
+            .embed(CreateEmbed::new().title(title).image(format!("attachment://{}", attachment.filename)))
+            .new_attachment(attachment);
+    }
+
+    command.edit_response(&ctx.http, response).await?;
+
+    Ok(())
+}
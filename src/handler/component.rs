@@ -0,0 +1,184 @@
+use crate::handler::command::is_manager;
+use crate::model::RoomManager;
+use crate::service::config::ConfigService;
+use crate::service::i18n::{Language, TextKey};
+use crate::service::renderer::transformer::calculate_stats;
+use crate::service::renderer::view::format_duration;
+use crate::service::report::{ReportService, ReportServiceError, RoomDTO, DETAILS_BUTTON, REFRESH_BUTTON, STOP_BUTTON};
+use serenity::all::{
+    ChannelId, ComponentInteraction, Context, CreateInteractionResponse, CreateInteractionResponseFollowup,
+    CreateInteractionResponseMessage, EventHandler, Interaction,
+};
+use serenity::async_trait;
+use serenity::prelude::SerenityError;
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::time::Instant;
+use tracing::{debug, error};
+
+// Discord rejects message contents longer than this.
+const MESSAGE_LIMIT: usize = 2000;
+
+#[derive(Debug, Error)]
+pub enum ComponentError {
+    #[error("This call is no longer tracked.")]
+    RoomNotFound,
+
+    #[error("You need the Manage Server permission or the manager role to stop tracking.")]
+    NotManager,
+
+    #[error("The report was edited too often in the last hour; try again later.")]
+    EditLimited,
+
+    #[error("Failed to render the report.")]
+    Report(#[from] ReportServiceError),
+
+    #[error("Failed to respond to Discord.")]
+    Serenity(#[from] SerenityError),
+}
+
+pub type ComponentResult<T> = Result<T, ComponentError>;
+
+// handles the buttons attached to ongoing reports.
+pub struct ComponentHandler {
+    room_manager: Arc<RoomManager>,
+    report_service: Arc<ReportService>,
+    config_service: Arc<ConfigService>,
+}
+
+impl ComponentHandler {
+    pub fn new(room_manager: Arc<RoomManager>, report_service: Arc<ReportService>, config_service: Arc<ConfigService>) -> Self {
+        ComponentHandler { room_manager, report_service, config_service }
+    }
+
+    async fn dispatch(&self, ctx: &Context, component: &ComponentInteraction) -> ComponentResult<()> {
+        let (prefix, channel_id) = match parse_custom_id(&component.data.custom_id) {
+            Some(parsed) => parsed,
+            None => {
+                debug!("unknown component: {}", component.data.custom_id);
+                return Ok(())
+            }
+        };
+
+        match prefix {
+            REFRESH_BUTTON => self.refresh(ctx, component, channel_id).await,
+            DETAILS_BUTTON => self.details(ctx, component, channel_id).await,
+            STOP_BUTTON => self.stop(ctx, component, channel_id).await,
+            _ => Ok(()),
+        }
+    }
+
+    async fn room_dto(&self, channel_id: ChannelId) -> ComponentResult<RoomDTO> {
+        let room = self.room_manager.get_room(channel_id).await.ok_or(ComponentError::RoomNotFound)?;
+        let room = room.lock().await;
+        Ok(RoomDTO::from_room(&room))
+    }
+
+    async fn refresh(&self, ctx: &Context, component: &ComponentInteraction, channel_id: ChannelId) -> ComponentResult<()> {
+        let room_dto = self.room_dto(channel_id).await?;
+        // the report service edits the message itself, so the interaction is only acknowledged.
+        component.create_response(&ctx.http, CreateInteractionResponse::Acknowledge).await?;
+        match self.report_service.refresh_room_report(&ctx.http, Instant::now(), &room_dto).await? {
+            true => Ok(()),
+            false => Err(ComponentError::EditLimited),
+        }
+    }
+
+    async fn details(&self, ctx: &Context, component: &ComponentInteraction, channel_id: ChannelId) -> ComponentResult<()> {
+        let room_dto = self.room_dto(channel_id).await?;
+        let language = Language::from_locale(&self.config_service.get(room_dto.guild_id).await.locale);
+
+        let now = Instant::now();
+        let mut participants: Vec<_> = room_dto.participants.iter().collect();
+        participants.sort_by_key(|participant| std::cmp::Reverse(participant.calculate_duration(now)));
+        let lines: Vec<String> = participants.into_iter()
+            .map(|participant| {
+                let stats = calculate_stats(now, participant);
+                format!(
+                    "**{}** {} · {} {}% · {} {} · {}",
+                    participant.name(),
+                    format_duration(stats.total),
+                    language.text(TextKey::Muted),
+                    stats.muted_percentage(),
+                    language.text(TextKey::Streaming),
+                    format_duration(stats.streaming),
+                    language.text(TextKey::Sessions).replace("{count}", &participant.sessions().to_string()),
+                )
+            })
+            .collect();
+
+        let response = CreateInteractionResponseMessage::new()
+            .content(truncate_lines(&lines, MESSAGE_LIMIT))
+            .ephemeral(true);
+        component.create_response(&ctx.http, CreateInteractionResponse::Message(response)).await?;
+        Ok(())
+    }
+
+    // ends the room right away and posts its final report, even though members are still connected.
+    async fn stop(&self, ctx: &Context, component: &ComponentInteraction, channel_id: ChannelId) -> ComponentResult<()> {
+        let guild_id = component.guild_id.ok_or(ComponentError::RoomNotFound)?;
+        let member = component.member.as_ref().ok_or(ComponentError::NotManager)?;
+        if !is_manager(member, &self.config_service.get(guild_id).await) {
+            return Err(ComponentError::NotManager)
+        }
+
+        component.create_response(&ctx.http, CreateInteractionResponse::Acknowledge).await?;
+        let now = Instant::now();
+        let room = self.room_manager.close_room(now, channel_id).await.ok_or(ComponentError::RoomNotFound)?;
+        let room_dto = {
+            let room = room.lock().await;
+            RoomDTO::from_room(&room)
+        };
+        self.report_service.send_room_report(&ctx.http, now, &room_dto, false).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EventHandler for ComponentHandler {
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        let component = match interaction {
+            Interaction::Component(component) => component,
+            _ => return,
+        };
+
+        if let Err(err) = self.dispatch(&ctx, &component).await {
+            error!("Error handling component {}: {:?}", component.data.custom_id, err);
+            reply_error(&ctx, &component, &err).await;
+        }
+    }
+}
+
+fn parse_custom_id(custom_id: &str) -> Option<(&str, ChannelId)> {
+    let (prefix, channel_id) = custom_id.split_once(':')?;
+    let channel_id = channel_id.parse::<u64>().ok().filter(|&id| id != 0)?;
+    Some((prefix, ChannelId::new(channel_id)))
+}
+
+// keeps whole lines only, dropping the tail once the limit is reached.
+fn truncate_lines(lines: &[String], limit: usize) -> String {
+    let mut content = String::new();
+    for line in lines {
+        if content.len() + line.len() + 1 > limit {
+            break
+        }
+        content.push_str(line);
+        content.push('\n');
+    }
+    content
+}
+
+// replies with an ephemeral error message, whether or not the interaction was acknowledged.
+async fn reply_error(ctx: &Context, component: &ComponentInteraction, err: &ComponentError) {
+    let message = err.to_string();
+    let response = CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new().content(&message).ephemeral(true),
+    );
+
+    if component.create_response(&ctx.http, response).await.is_err() {
+        let followup = CreateInteractionResponseFollowup::new().content(message).ephemeral(true);
+        if let Err(err) = component.create_followup(&ctx.http, followup).await {
+            error!("Failed to reply error message: {}", err);
+        }
+    }
+}
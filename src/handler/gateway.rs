@@ -0,0 +1,38 @@
+use std::sync::{Arc, Mutex};
+use serenity::all::{Context, Event, RawEventHandler};
+use serenity::async_trait;
+use tokio::time::{Duration, Instant};
+
+// remembers when the gateway last delivered anything, to tell a silently dead connection.
+#[derive(Default)]
+pub struct EventClock {
+    last_event_at: Mutex<Option<Instant>>,
+}
+
+impl EventClock {
+    pub fn tick(&self) {
+        *self.last_event_at.lock().unwrap() = Some(Instant::now());
+    }
+
+    // None until the first event arrives.
+    pub fn since_last_event(&self) -> Option<Duration> {
+        self.last_event_at.lock().unwrap().map(|at| at.elapsed())
+    }
+}
+
+pub struct GatewayHandler {
+    clock: Arc<EventClock>,
+}
+
+impl GatewayHandler {
+    pub fn new(clock: Arc<EventClock>) -> Self {
+        GatewayHandler { clock }
+    }
+}
+
+#[async_trait]
+impl RawEventHandler for GatewayHandler {
+    async fn raw_event(&self, _ctx: Context, _event: Event) {
+        self.clock.tick();
+    }
+}
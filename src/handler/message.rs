@@ -0,0 +1,28 @@
+use std::sync::Arc;
+use serenity::all::{ChannelId, Context, EventHandler, GuildId, MessageId};
+use serenity::async_trait;
+use crate::service::report::ReportService;
+
+// keeps the report tracker in sync with messages removed by users or moderators.
+pub struct MessageHandler {
+    report_service: Arc<ReportService>,
+}
+
+impl MessageHandler {
+    pub fn new(report_service: Arc<ReportService>) -> Self {
+        MessageHandler { report_service }
+    }
+}
+
+#[async_trait]
+impl EventHandler for MessageHandler {
+    async fn message_delete(&self, _ctx: Context, _channel_id: ChannelId, deleted_message_id: MessageId, _guild_id: Option<GuildId>) {
+        self.report_service.handle_message_deleted(deleted_message_id).await;
+    }
+
+    async fn message_delete_bulk(&self, _ctx: Context, _channel_id: ChannelId, multiple_deleted_messages_ids: Vec<MessageId>, _guild_id: Option<GuildId>) {
+        for message_id in multiple_deleted_messages_ids {
+            self.report_service.handle_message_deleted(message_id).await;
+        }
+    }
+}
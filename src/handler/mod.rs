@@ -1 +1,5 @@
-pub mod voice;
\ No newline at end of file
+pub mod voice;
+pub mod command;
+pub mod message;
+pub mod gateway;
+pub mod component;
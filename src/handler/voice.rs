@@ -1,21 +1,72 @@
 use std::sync::Arc;
-use serenity::all::{Context, EventHandler, GuildId, Message, Timestamp, VoiceState};
+use serenity::all::{ChannelId, ChannelType, Context, EventHandler, Guild, GuildChannel, GuildId, GuildMemberUpdateEvent, Member, Message, ResumedEvent, ScheduledEvent, ScheduledEventStatus, Timestamp, User, UserId, VoiceState};
 use serenity::async_trait;
 use tokio::sync::Mutex;
 use tokio::task::JoinSet;
 use tokio::time::Instant;
 use tracing::{debug, error};
-use crate::model::{Room, RoomManager};
+use crate::model::{ChannelInfo, ConnectingMember, GuildConfig, Room, RoleFilter, RoomManager, VoiceStateFlags};
+use crate::service::asset::member_face;
+use crate::service::config::ConfigService;
+use crate::service::nickname::NicknameService;
 use crate::service::report::{ReportService, RoomDTO};
+#[cfg(feature = "voice-receive")]
+use crate::service::speaking::SpeakingService;
 
 pub struct VoiceHandler {
     room_manager: Arc<RoomManager>,
     report_service: Arc<ReportService>,
+    config_service: Option<Arc<ConfigService>>,
+    nickname_service: Option<Arc<NicknameService>>,
+    #[cfg(feature = "voice-receive")]
+    speaking_service: Option<Arc<SpeakingService>>,
 }
 
 impl VoiceHandler {
     pub fn new(room_manager: Arc<RoomManager>, report_service: Arc<ReportService>) -> Self {
-        VoiceHandler { room_manager, report_service }
+        VoiceHandler {
+            room_manager,
+            report_service,
+            config_service: None,
+            nickname_service: None,
+            #[cfg(feature = "voice-receive")]
+            speaking_service: None,
+        }
+    }
+
+    pub fn with_config(mut self, config_service: Arc<ConfigService>) -> Self {
+        self.config_service = Some(config_service);
+        self
+    }
+
+    pub fn with_nicknames(mut self, nickname_service: Arc<NicknameService>) -> Self {
+        self.nickname_service = Some(nickname_service);
+        self
+    }
+
+    async fn guild_config(&self, guild_id: GuildId) -> GuildConfig {
+        match &self.config_service {
+            Some(config_service) => config_service.get(guild_id).await,
+            None => GuildConfig::default(),
+        }
+    }
+
+    #[cfg(feature = "voice-receive")]
+    pub fn with_speaking(mut self, speaking_service: Arc<SpeakingService>) -> Self {
+        self.speaking_service = Some(speaking_service);
+        self
+    }
+
+    async fn resync(&self, ctx: &Context, guilds: Vec<GuildId>) {
+        resync(&self.room_manager, self.config_service.as_deref(), self.nickname_service.as_deref(), ctx, guilds).await;
+    }
+
+    #[allow(unused_variables)]
+    async fn sync_speaking(&self, guild_id: Option<GuildId>, channel_id: ChannelId) {
+        #[cfg(feature = "voice-receive")]
+        if let (Some(speaking_service), Some(guild_id)) = (&self.speaking_service, guild_id) {
+            speaking_service.sync(guild_id, channel_id).await;
+        }
     }
 }
 
@@ -23,59 +74,80 @@ impl VoiceHandler {
 impl EventHandler for VoiceHandler {
     async fn cache_ready(&self, ctx: Context, guilds: Vec<GuildId>) {
         debug!("cache is ready for guilds: {:?}", guilds);
+        self.resync(&ctx, guilds).await;
+    }
 
-        let manager = self.room_manager.clone();
+    // events may have been missed while the gateway was away.
+    async fn resume(&self, ctx: Context, _event: ResumedEvent) {
+        debug!("gateway session resumed, resyncing voice states");
+        let guilds = ctx.cache.guilds();
+        self.resync(&ctx, guilds).await;
+    }
 
-        let now = Instant::now();
-        let timestamp = Timestamp::now();
+    // after a fresh session the guilds are sent again, each with its current voice states.
+    async fn guild_create(&self, ctx: Context, guild: Guild, is_new: Option<bool>) {
+        if is_new == Some(false) {
+            debug!("guild {} was sent again, resyncing voice states", guild.id);
+            self.resync(&ctx, vec![guild.id]).await;
+        }
+    }
 
-        let mut tasks = JoinSet::new();
+    async fn guild_member_update(&self, _ctx: Context, old_if_available: Option<Member>, new: Option<Member>, event: GuildMemberUpdateEvent) {
+        let face = match &new {
+            Some(member) => member.face(),
+            None => member_face(event.guild_id, &event.user, event.avatar.as_ref()),
+        };
 
-        for guild_id in guilds {
-            let guild = match ctx.cache.guild(guild_id) {
-                Some(guild) => guild,
-                None => {
-                    error!("CRITICAL: Guild ID {} reported by cache_ready event is missing from cache", guild_id);
-                    continue;
-                }
-            };
-            for (user_id, voice_state) in guild.voice_states.iter() {
-                let flags = voice_state.into();
-                let channel_id = match voice_state.channel_id {
-                    Some(channel_id) => channel_id,
-                    None => {
-                        debug!("Voice State for User {} reported by cache_ready event is not joining voice channel", voice_state.user_id);
-                        continue;
-                    }
-                };
-                let member = match guild.members.get(user_id) {
-                    Some(member) => member,
-                    None => {
-                        error!("CRITICAL: failed to get member for User ID {} on Guild ID {} from cache", user_id, guild_id);
-                        continue;
-                    }
-                };
-                let name = member.display_name().into();
-                let face = member.face();
-                let user_id = user_id.into();
-
-                let manager_for_task = manager.clone();
-
-                let connect_task = async move {
-                    manager_for_task
-                        .handle_connect_event(
-                            now, timestamp, channel_id, guild_id, user_id, name, face, flags,
-                        )
-                        .await
-                };
-                tasks.spawn(connect_task);
-            }
+        let mut stale = self.room_manager.update_face(event.guild_id, event.user.id, &face).await;
+        stale.extend(old_if_available.map(|member| member.face()).filter(|old| *old != face));
+        for avatar_url in stale {
+            debug!("avatar of user {} changed, dropping cached visual", event.user.id);
+            self.report_service.asset_service().invalidate(&avatar_url).await;
         }
+    }
 
-        while let Some(res) = tasks.join_next().await {
-            if let Err(why) = res {
-                debug!("error joining voice channel: {why:?}");
-            }
+    // a renamed channel or a changed user limit is shown on the report right away.
+    async fn channel_update(&self, ctx: Context, _old: Option<GuildChannel>, new: GuildChannel) {
+        let room = match self.room_manager.update_channel(new.id, ChannelInfo::from(&new)).await {
+            Some(room) => room,
+            None => return,
+        };
+        debug!("channel {} was renamed to {} or its user limit changed", new.id, new.name);
+
+        let room_dto = RoomDTO::from_room(&*room.lock().await);
+        if let Err(err) = self.report_service.send_room_report(&ctx.http, Instant::now(), &room_dto, true).await {
+            error!("Error sending room report: {:?}", err);
+        }
+    }
+
+    async fn guild_member_removal(&self, _ctx: Context, guild_id: GuildId, user: User, _member_data_if_available: Option<Member>) {
+        self.room_manager.mark_departed(guild_id, user.id).await;
+    }
+
+    // events held in a voice channel tag the call going on there.
+    async fn guild_scheduled_event_update(&self, _ctx: Context, event: ScheduledEvent) {
+        let channel_id = match event.channel_id {
+            Some(channel_id) => channel_id,
+            None => return,
+        };
+
+        let now = Instant::now();
+        match event.status {
+            ScheduledEventStatus::Active => {
+                debug!("scheduled event {} started in channel {}", event.id, channel_id);
+                self.room_manager.handle_event_started(now, channel_id, event.id, event.name).await;
+            },
+            ScheduledEventStatus::Completed | ScheduledEventStatus::Canceled => {
+                debug!("scheduled event {} ended in channel {}", event.id, channel_id);
+                self.room_manager.handle_event_ended(now, channel_id, event.id).await;
+            },
+            _ => {},
+        }
+    }
+
+    async fn guild_scheduled_event_delete(&self, _ctx: Context, event: ScheduledEvent) {
+        if let Some(channel_id) = event.channel_id {
+            self.room_manager.handle_event_ended(Instant::now(), channel_id, event.id).await;
         }
     }
 
@@ -93,54 +165,205 @@ impl EventHandler for VoiceHandler {
             old.as_ref().map(|x| format_voice_state_nicely(&x)),
             format_voice_state_nicely(&new)
         );
+        // other bots (music bots and the like) are never tracked.
+        if new.member.as_ref().is_some_and(|member| member.user.bot) {
+            debug!("voice state update of a bot, ignored");
+            return;
+        }
+
         let manager = self.room_manager.clone();
         let now = Instant::now();
         let timestamp = Timestamp::now();
-        // if newly connected
-        if old.is_none() {
-            match handle_connect_safely(&manager, now, timestamp, new).await {
-                Ok(room) => {
-                    let room = room.lock().await;
-                    if let Err(err) = self.report_service.send_room_report(&ctx.http, now, &RoomDTO::from_room(&room), true).await {
-                        error!("Error sending room report: {:?}", err);
+
+        let config = match new.guild_id {
+            Some(guild_id) => self.guild_config(guild_id).await,
+            None => GuildConfig::default(),
+        };
+        let is_tracked_member = match new.member.as_ref() {
+            Some(member) => config.role_filter.is_tracked(&member.roles),
+            None => true,
+        };
+        // leaving a paused channel is still recorded, so nobody stays connected through the pause.
+        let is_paused = new.channel_id.is_some_and(|channel_id| config.pause.is_paused(channel_id));
+
+        let (afk_channel_id, is_stage, info) = match new.guild_id.and_then(|guild_id| ctx.cache.guild(guild_id)) {
+            Some(guild) => (
+                afk_channel_id(&guild),
+                new.channel_id.is_some_and(|channel_id| is_stage_channel(&guild, channel_id)),
+                new.channel_id.map(|channel_id| channel_info(&guild, channel_id)).unwrap_or_default(),
+            ),
+            None => (None, false, ChannelInfo::default()),
+        };
+        let new_category_id = info.category_id;
+        let flags = VoiceStateFlags::from(&new).with_stage(&new, is_stage);
+
+        // the old state is missing when the cache did not know the user, so ask the manager instead.
+        let old_channel_id = match old.as_ref() {
+            Some(old) => old.channel_id,
+            None => match new.guild_id {
+                Some(guild_id) => manager.find_connected_channel(guild_id, new.user_id).await,
+                None => None,
+            },
+        };
+
+        // the AFK channel is never tracked: moving there counts as leaving.
+        let old_channel_id = old_channel_id.filter(|&channel_id| Some(channel_id) != afk_channel_id);
+        // members outside the role filter only matter while they are still in a room from before the filter.
+        let old_channel_id = match (is_tracked_member, new.guild_id) {
+            (false, Some(guild_id)) => manager.find_connected_channel(guild_id, new.user_id).await,
+            _ => old_channel_id,
+        };
+        let new_channel_id = new.channel_id.filter(|&channel_id| Some(channel_id) != afk_channel_id);
+
+        match (old_channel_id, new_channel_id) {
+            // if just disconnected
+            (Some(old_channel_id), None) => {
+                if let Err(err) = handle_disconnect_safely(&manager, now, old_channel_id, new.user_id).await {
+                    error!("Error handling disconnect event on channel: {err}");
+                }
+                self.sync_speaking(new.guild_id, old_channel_id).await;
+            },
+            // mute, deafen or stream changed in the same channel
+            (Some(old_channel_id), Some(new_channel_id)) if old_channel_id == new_channel_id => {
+                if is_paused {
+                    debug!("tracking is paused in channel {}, update ignored", new_channel_id);
+                    return;
+                }
+                if let Err(err) = handle_update_safely(&manager, now, &new, flags).await {
+                    error!("Error handling update event on channel: {err}");
+                }
+            },
+            (old_channel_id, Some(_)) => {
+                // switch channel
+                if let Some(old_channel_id) = old_channel_id {
+                    if let Err(err) = handle_disconnect_safely(&manager, now, old_channel_id, new.user_id).await {
+                        error!("Error handling disconnect event on channel: {err}");
                     }
-                },
-                Err(err) => {
-                    error!("Error handling connect event on channel: {err}");
+                    self.sync_speaking(new.guild_id, old_channel_id).await;
                 }
-            }
-            return;
+                if !is_tracked_member {
+                    debug!("User {} does not match the role filter, ignored", new.user_id);
+                    return;
+                }
+                if is_paused {
+                    debug!("tracking is paused in channel {:?}, connect ignored", new.channel_id);
+                    return;
+                }
+                let guild_id = new.guild_id;
+                let user_id = new.user_id;
+                match handle_connect_safely(&manager, self.nickname_service.as_deref(), now, timestamp, new, info, flags).await {
+                    Ok(None) => {
+                        debug!("connect event on an excluded channel, ignored");
+                    },
+                    Ok(Some(room)) => {
+                        let channel_id = room.lock().await.channel_id();
+                        // the group may have taken the call along from the old channel.
+                        let room = match (old_channel_id, guild_id) {
+                            (Some(old_channel_id), Some(guild_id)) if manager.handle_move_event(now, guild_id, old_channel_id, channel_id, user_id).await => {
+                                let old_category_id = ctx.cache.guild(guild_id).and_then(|guild| category_id(&guild, old_channel_id));
+                                self.report_service.move_track(&ctx.http, guild_id, old_channel_id, old_category_id, channel_id, new_category_id).await;
+                                manager.get_room(channel_id).await.unwrap_or(room)
+                            },
+                            _ => room,
+                        };
+                        let (room_dto, channel_id) = {
+                            let room = room.lock().await;
+                            (RoomDTO::from_room(&room), room.channel_id())
+                        };
+                        self.sync_speaking(guild_id, channel_id).await;
+                        if let Err(err) = self.report_service.send_room_report(&ctx.http, now, &room_dto, true).await {
+                            error!("Error sending room report: {:?}", err);
+                        }
+                    },
+                    Err(err) => {
+                        error!("Error handling connect event on channel: {err}");
+                    }
+                }
+            },
+            (None, None) => {
+                debug!("voice state update outside of voice channels, ignored");
+            },
         }
+    }
+}
 
-        // if just disconnected
-        if new.channel_id.is_none() {
-            if let Err(err) = handle_disconnect_safely(&manager, now, old).await{
-                error!("Error handling disconnect event on channel: {err}");
+// makes rooms match the cached voice states: ghosts are disconnected and missed members connected.
+pub(crate) async fn resync(room_manager: &Arc<RoomManager>, config_service: Option<&ConfigService>, nickname_service: Option<&NicknameService>, ctx: &Context, guilds: Vec<GuildId>) {
+    let now = Instant::now();
+    let timestamp = Timestamp::now();
+    let mut tasks = JoinSet::new();
+
+    for guild_id in guilds {
+        // fetched first, as the cached guild must not be held across awaits.
+        let config = match config_service {
+            Some(config_service) => config_service.get(guild_id).await,
+            None => GuildConfig::default(),
+        };
+        let present = match ctx.cache.guild(guild_id) {
+            Some(guild) => present_members(&guild, &config.role_filter),
+            None => {
+                error!("CRITICAL: Guild ID {} to resync is missing from cache", guild_id);
+                continue;
             }
-            return;
-        }
+        };
+        // restored rooms do not remember being paused.
+        room_manager.apply_pause(now, guild_id, &config.pause).await;
 
-        // switch channel
-        if let Err(err) = handle_disconnect_safely(&manager, now, old).await{
-            error!("Error handling disconnect event on channel: {err}");
-        }
-        match handle_connect_safely(&manager, now, timestamp, new).await {
-            Ok(room) => {
+        for room in room_manager.get_guild_rooms(guild_id).await {
+            let (channel_id, ghosts) = {
                 let room = room.lock().await;
-                if let Err(err) = self.report_service.send_room_report(&ctx.http, now, &RoomDTO::from_room(&room), true).await {
-                    error!("Error sending room report: {:?}", err);
+                let ghosts: Vec<UserId> = room.participants().iter()
+                    .filter(|participant| participant.is_connected())
+                    .map(|participant| participant.user_id())
+                    .filter(|&user_id| !present.iter().any(|member| member.user_id == user_id && member.channel_id == room.channel_id()))
+                    .collect();
+                (room.channel_id(), ghosts)
+            };
+            for user_id in ghosts {
+                debug!("user {} is no longer in channel {}, disconnecting", user_id, channel_id);
+                if let Err(err) = handle_disconnect_safely(room_manager, now, channel_id, user_id).await {
+                    error!("Error handling disconnect event on channel: {err}");
                 }
-            },
-            Err(err) => {
-                error!("Error handling connect event on channel: {err}");
             }
         }
-        return;
+
+        for member in present {
+            // members of paused channels are left as they are, neither connected nor disconnected.
+            if config.pause.is_paused(member.channel_id) {
+                continue;
+            }
+            let connected = match room_manager.get_room(member.channel_id).await {
+                Some(room) => room.lock().await.is_connected(member.user_id),
+                None => false,
+            };
+            if connected {
+                continue;
+            }
+
+            let name = match nickname_service {
+                Some(nickname_service) => nickname_service.resolve(guild_id, member.user_id, member.name).await,
+                None => member.name,
+            };
+            let manager = room_manager.clone();
+            tasks.spawn(async move {
+                manager
+                    .handle_connect_event(
+                        now, timestamp, member.channel_id, member.channel_info, guild_id,
+                        ConnectingMember { user_id: member.user_id, name, face: member.face, flags: member.flags },
+                    )
+                    .await
+            });
+        }
+    }
+
+    while let Some(res) = tasks.join_next().await {
+        if let Err(why) = res {
+            debug!("error joining voice channel: {why:?}");
+        }
     }
 }
 
-async fn handle_connect_safely(manager: &RoomManager, now: Instant, timestamp: Timestamp, new: VoiceState) -> Result<Arc<Mutex<Room>>, String> {
-    let flags = (&new).into();
+async fn handle_connect_safely(manager: &RoomManager, nickname_service: Option<&NicknameService>, now: Instant, timestamp: Timestamp, new: VoiceState, channel_info: ChannelInfo, flags: VoiceStateFlags) -> Result<Option<Arc<Mutex<Room>>>, String> {
     let member = match new.member {
         Some(member) => member,
         None => return Err(String::from("Voice State is missing member"))
@@ -155,17 +378,18 @@ async fn handle_connect_safely(manager: &RoomManager, now: Instant, timestamp: T
         Some(guild_id) => guild_id,
         None => return Err(String::from("Voice State is missing Guild ID"))
     };
-    let name = member.display_name().into();
+    let name = match nickname_service {
+        Some(nickname_service) => nickname_service.resolve(guild_id, new.user_id, member.display_name().into()).await,
+        None => member.display_name().into(),
+    };
     match manager
         .handle_connect_event(
             now,
             timestamp,
             channel_id,
+            channel_info,
             guild_id,
-            new.user_id,
-            name,
-            member.face(),
-            flags,
+            ConnectingMember { user_id: new.user_id, name, face: member.face(), flags },
         )
         .await {
         Ok(room) => Ok(room),
@@ -173,29 +397,96 @@ async fn handle_connect_safely(manager: &RoomManager, now: Instant, timestamp: T
     }
 }
 
-async fn handle_disconnect_safely(manager: &RoomManager, now: Instant, old: Option<VoiceState>) -> Result<(), String>{
-    let old = match old {
-        Some(old) => old,
-        None => {
-            return Err(String::from("Voice State Update is missing old voice channel"))
+async fn handle_disconnect_safely(manager: &RoomManager, now: Instant, channel_id: ChannelId, user_id: UserId) -> Result<(), String>{
+    match manager
+        .handle_disconnect_event(now, channel_id, user_id)
+        .await {
+        Ok(_) => { Ok(())},
+        Err(err) => {
+            Err(format!("Error handling disconnect event on manager: {:?}", err))
         }
-    };
+    }
+}
 
-    let channel_id = match old.channel_id {
+async fn handle_update_safely(manager: &RoomManager, now: Instant, new: &VoiceState, flags: VoiceStateFlags) -> Result<(), String> {
+    let channel_id = match new.channel_id {
         Some(channel_id) => channel_id,
-        None => {
-            return Err(String::from("Voice State Update is missing channel ID"))
-        }
+        None => return Err(String::from("Voice State is missing Channel ID"))
     };
 
     match manager
-        .handle_disconnect_event(now, channel_id, old.user_id)
+        .handle_update_event(now, channel_id, new.user_id, flags)
         .await {
-        Ok(_) => { Ok(())},
-        Err(err) => {
-            Err(format!("Error handling disconnect event on manager: {:?}", err))
+        Ok(_) => Ok(()),
+        Err(err) => Err(format!("Error handling update event on manager: {:?}", err)),
+    }
+}
+
+// a tracked member in a voice channel, according to the cache.
+struct PresentMember {
+    channel_id: ChannelId,
+    channel_info: ChannelInfo,
+    user_id: UserId,
+    name: String,
+    face: String,
+    flags: VoiceStateFlags,
+}
+
+fn present_members(guild: &Guild, role_filter: &RoleFilter) -> Vec<PresentMember> {
+    let afk_channel_id = afk_channel_id(guild);
+    let mut present = Vec::new();
+    for (user_id, voice_state) in guild.voice_states.iter() {
+        let channel_id = match voice_state.channel_id {
+            Some(channel_id) => channel_id,
+            None => {
+                debug!("Voice State for User {} is not joining voice channel", voice_state.user_id);
+                continue;
+            }
+        };
+        if afk_channel_id == Some(channel_id) {
+            debug!("User {} is in the AFK channel, ignored", voice_state.user_id);
+            continue;
+        }
+        let member = match guild.members.get(user_id) {
+            Some(member) => member,
+            None => {
+                error!("CRITICAL: failed to get member for User ID {} on Guild ID {} from cache", user_id, guild.id);
+                continue;
+            }
+        };
+        if member.user.bot {
+            continue;
         }
+        if !role_filter.is_tracked(&member.roles) {
+            debug!("User {} does not match the role filter, ignored", user_id);
+            continue;
+        }
+        present.push(PresentMember {
+            channel_id,
+            channel_info: channel_info(guild, channel_id),
+            user_id: *user_id,
+            name: member.display_name().into(),
+            face: member.face(),
+            flags: VoiceStateFlags::from(voice_state).with_stage(voice_state, is_stage_channel(guild, channel_id)),
+        });
     }
+    present
+}
+
+fn afk_channel_id(guild: &Guild) -> Option<ChannelId> {
+    guild.afk_metadata.as_ref().map(|afk| afk.afk_channel_id)
+}
+
+fn is_stage_channel(guild: &Guild, channel_id: ChannelId) -> bool {
+    guild.channels.get(&channel_id).is_some_and(|channel| channel.kind == ChannelType::Stage)
+}
+
+fn category_id(guild: &Guild, channel_id: ChannelId) -> Option<ChannelId> {
+    guild.channels.get(&channel_id).and_then(|channel| channel.parent_id)
+}
+
+fn channel_info(guild: &Guild, channel_id: ChannelId) -> ChannelInfo {
+    guild.channels.get(&channel_id).map(ChannelInfo::from).unwrap_or_default()
 }
 
 fn format_voice_state_nicely(voice_state: &VoiceState) -> String {
@@ -0,0 +1,143 @@
+use crate::handler::gateway::EventClock;
+use crate::memory::{MemoryMonitor, MemoryStats};
+use crate::model::RoomManager;
+use crate::service::report::{ReportService, TimeoutCounts};
+use axum::extract::State;
+use axum::http::{header, StatusCode};
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::Serialize;
+use serenity::all::ShardManager;
+use serenity::gateway::ConnectionStage;
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::watch;
+use tracing::info;
+
+// Unauthenticated liveness endpoint for orchestrators; it exposes counts only.
+pub struct HealthState {
+    room_manager: Arc<RoomManager>,
+    shard_manager: Arc<ShardManager>,
+    clock: Arc<EventClock>,
+    // the gateway counts as dead after this long without events; None only checks the shard stages.
+    max_event_age: Option<Duration>,
+    report_service: Option<Arc<ReportService>>,
+    memory: Option<Arc<MemoryMonitor>>,
+}
+
+impl HealthState {
+    pub fn new(room_manager: Arc<RoomManager>, shard_manager: Arc<ShardManager>, clock: Arc<EventClock>, max_event_age: Option<Duration>) -> Self {
+        HealthState { room_manager, shard_manager, clock, max_event_age, report_service: None, memory: None }
+    }
+
+    pub fn with_reports(mut self, report_service: Arc<ReportService>) -> Self {
+        self.report_service = Some(report_service);
+        self
+    }
+
+    pub fn with_memory(mut self, memory: Arc<MemoryMonitor>) -> Self {
+        self.memory = Some(memory);
+        self
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ShardHealth {
+    id: u32,
+    stage: String,
+    latency_ms: Option<u128>,
+}
+
+#[derive(Debug, Serialize)]
+struct HealthReport {
+    healthy: bool,
+    gateway_connected: bool,
+    shards: Vec<ShardHealth>,
+    last_event_secs_ago: Option<u64>,
+    rooms: usize,
+    // stage timeouts since startup; slow stages do not make the bot unhealthy.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    report_timeouts: Option<TimeoutCounts>,
+    // allocator statistics in bytes; missing without jemalloc.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    memory: Option<MemoryStats>,
+}
+
+pub fn router(state: Arc<HealthState>) -> Router {
+    Router::new()
+        .route("/health", get(health))
+        .route("/metrics", get(metrics))
+        .with_state(state)
+}
+
+pub async fn serve(addr: SocketAddr, state: Arc<HealthState>, mut shutdown: watch::Receiver<bool>) -> std::io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    info!("health endpoint is listening on {}", addr);
+
+    axum::serve(listener, router(state))
+        .with_graceful_shutdown(async move {
+            let _ = shutdown.changed().await;
+        })
+        .await
+}
+
+// answers 503 while no shard is connected or events stopped arriving, so the container gets restarted.
+async fn health(State(state): State<Arc<HealthState>>) -> impl IntoResponse {
+    let (shards, gateway_connected) = {
+        let runners = state.shard_manager.runners.lock().await;
+        let shards: Vec<_> = runners.iter()
+            .map(|(id, runner)| ShardHealth {
+                id: id.0,
+                stage: runner.stage.to_string(),
+                latency_ms: runner.latency.map(|latency| latency.as_millis()),
+            })
+            .collect();
+        let connected = !runners.is_empty() && runners.values().all(|runner| runner.stage == ConnectionStage::Connected);
+        (shards, connected)
+    };
+
+    let since_last_event = state.clock.since_last_event();
+    let stale = match (state.max_event_age, since_last_event) {
+        (Some(max_age), Some(elapsed)) => elapsed > max_age,
+        _ => false,
+    };
+
+    let report = HealthReport {
+        healthy: gateway_connected && !stale,
+        gateway_connected,
+        shards,
+        last_event_secs_ago: since_last_event.map(|elapsed| elapsed.as_secs()),
+        rooms: state.room_manager.get_all_rooms().await.len(),
+        report_timeouts: state.report_service.as_ref().map(|report_service| report_service.timeout_counts()),
+        memory: state.memory.as_ref().and_then(|memory| memory.observe("health")),
+    };
+    let status = match report.healthy {
+        true => StatusCode::OK,
+        false => StatusCode::SERVICE_UNAVAILABLE,
+    };
+    (status, Json(report))
+}
+
+// the same counts in the Prometheus text format, for scraping.
+async fn metrics(State(state): State<Arc<HealthState>>) -> impl IntoResponse {
+    let mut body = String::new();
+    let mut gauge = |name: &str, help: &str, value: u64| {
+        let _ = writeln!(body, "# HELP {} {}\n# TYPE {} gauge\n{} {}", name, help, name, name, value);
+    };
+    gauge("ringring_rooms", "Rooms currently tracked.", state.room_manager.get_all_rooms().await.len() as u64);
+    if let Some(memory) = &state.memory
+        && let Some(stats) = memory.observe("metrics")
+    {
+        gauge("ringring_heap_allocated_bytes", "Bytes allocated by the application.", stats.allocated);
+        gauge("ringring_heap_active_bytes", "Bytes in active pages of the allocator.", stats.active);
+        gauge("ringring_resident_bytes", "Bytes in physically resident pages of the allocator.", stats.resident);
+        gauge("ringring_mapped_bytes", "Bytes in extents mapped by the allocator.", stats.mapped);
+        gauge("ringring_retained_bytes", "Bytes retained by the allocator without being returned to the system.", stats.retained);
+        gauge("ringring_resident_peak_bytes", "Highest resident size seen since startup.", memory.peak_resident());
+    }
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
@@ -1,3 +1,8 @@
+pub mod config;
 pub mod model;
 pub mod service;
-pub mod handler;
\ No newline at end of file
+pub mod handler;
+pub mod storage;
+pub mod dashboard;
+pub mod health;
+pub mod memory;
@@ -2,115 +2,365 @@
 #[global_allocator]
 static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
 
+use ringring_rs::config::{Config, CoordinationMode, LogFormat};
+use ringring_rs::dashboard::{self, DashboardState};
+use ringring_rs::health::{self, HealthState};
+use ringring_rs::handler::command::CommandHandler;
+use ringring_rs::handler::component::ComponentHandler;
+use ringring_rs::handler::gateway::{EventClock, GatewayHandler};
+use ringring_rs::handler::message::MessageHandler;
 use ringring_rs::handler::voice::VoiceHandler;
+use ringring_rs::memory::MemoryMonitor;
 use ringring_rs::model::RoomManager;
 use ringring_rs::service::asset::AssetService;
-use ringring_rs::service::report::{ReportService, RoomDTO};
-use serenity::all::{ChannelId, Timestamp};
+use ringring_rs::service::audit::AuditService;
+use ringring_rs::service::config::ConfigService;
+use ringring_rs::service::digest::DigestService;
+use ringring_rs::service::history::HistoryService;
+use ringring_rs::service::maintenance::MaintenanceService;
+use ringring_rs::service::lease::LeaseService;
+use ringring_rs::service::nickname::NicknameService;
+use ringring_rs::service::privacy::PrivacyService;
+use ringring_rs::service::renderer::draw::TextRenderer;
+use ringring_rs::service::renderer::pool::RenderPool;
+use ringring_rs::service::report::ReportService;
+use ringring_rs::service::room_sync::RoomSyncService;
+use ringring_rs::service::scheduler::ReportScheduler;
+use ringring_rs::service::stats::StatsService;
+use ringring_rs::service::webhook::WebhookService;
+#[cfg(feature = "voice-receive")]
+use ringring_rs::service::speaking::SpeakingService;
+#[cfg(feature = "voice-receive")]
+use songbird::SerenityInit;
 use serenity::prelude::*;
 use std::env;
+use std::path::PathBuf;
 use std::sync::Arc;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::watch;
 use tokio::time::Instant;
-use tokio::time::{self, Duration};
-use tracing::{error,};
+use tokio::time::Duration;
+use tracing::{error, info, warn};
+use tracing_subscriber::EnvFilter;
 use ringring_rs::service::tracker::Tracker;
+#[cfg(feature = "redis-store")]
+use ringring_rs::storage::RedisRoomMirror;
+use ringring_rs::storage::{EventJournal, RoomMirror, SqliteStorage};
 
-const CLEANUP_INTERVAL_SECS: u64 = 30;
+const DEFAULT_CONFIG_PATH: &str = "ringring.toml";
 
 #[tokio::main]
 async fn main() {
-    tracing_subscriber::fmt::init();
-
-    // Login with a bot token from the environment
-    let token = env::var("DISCORD_TOKEN").expect("Expected a token in the environment");
-
-    let report_channel_id = env::var("REPORT_CHANNEL_ID").ok()
-        .and_then(|string_id| {
-            match string_id.parse::<u64>() {
-                Ok(id) => Some(id),
-                Err(err) => {
-                    error!("failed to parse REPORT_CHANNEL_ID({}): {}", string_id, err);
-                    std::process::exit(1);
-                },
-            }
-        })
-        .map(ChannelId::new);
+    let config_path = PathBuf::from(env::var("RINGRING_CONFIG").unwrap_or_else(|_| String::from(DEFAULT_CONFIG_PATH)));
+    // logging needs the config to pick its format, so a broken config is reported right after.
+    let config = Config::load(&config_path);
+    init_logging(config.as_ref().map_or(LogFormat::default(), |config| config.logging.format));
+    let config = match config {
+        Ok(config) => config,
+        Err(err) => {
+            error!("{}", err);
+            std::process::exit(1);
+        },
+    };
+    for warning in &config.warnings {
+        warn!("{}", warning);
+    }
+    if !config_path.exists() {
+        info!("no config file at {}, using defaults", config_path.display());
+    }
+
+    // `ringring-rs healthcheck` probes a running instance, for container healthchecks.
+    if env::args().nth(1).as_deref() == Some("healthcheck") {
+        std::process::exit(match probe_health(config.health.addr.as_deref()).await {
+            true => 0,
+            false => 1,
+        });
+    }
+
+    // Login with a bot token from the config or the environment
+    let token = match config.token() {
+        Ok(token) => token,
+        Err(err) => {
+            error!("{}", err);
+            std::process::exit(1);
+        },
+    };
 
     // Set gateway intents, which decides what events the bot will be notified about
-    let intents = GatewayIntents::GUILDS | GatewayIntents::GUILD_VOICE_STATES;
+    // GUILD_MESSAGES delivers deletions of tracked report messages.
+    // GUILD_SCHEDULED_EVENTS tells when events held in voice channels start and end.
+    let mut intents = GatewayIntents::GUILDS | GatewayIntents::GUILD_VOICE_STATES | GatewayIntents::GUILD_MESSAGES
+        | GatewayIntents::GUILD_SCHEDULED_EVENTS;
+    // GUILD_MEMBERS is privileged; it delivers member updates so changed avatars show up.
+    // without it, avatars are only refreshed when members join a call.
+    if config.discord.member_updates {
+        intents |= GatewayIntents::GUILD_MEMBERS;
+    }
+
+    let storage = Arc::new(SqliteStorage::connect(&config.storage.database_url).await.expect("Failed to open storage"));
+    let lease_service = match config.coordination.mode {
+        CoordinationMode::Lease => {
+            let instance_id = config.coordination.instance_id();
+            info!("coordinating reports with other instances as {}", instance_id);
+            Some(Arc::new(LeaseService::new(storage.clone(), instance_id, config.coordination.lease_ttl())))
+        },
+        CoordinationMode::None => None,
+    };
 
     // Create a new instance of the Client, logging in as a bot.
-    let room_manager = Arc::new(RoomManager::new(16));
-    let report_service = Arc::new(ReportService::new(AssetService::new(reqwest::Client::new()), report_channel_id));
-    let handler = VoiceHandler::new(room_manager.clone(), report_service.clone());
+    let config_service = Arc::new(ConfigService::new(storage.clone(), config.defaults.clone()));
+    let privacy_service = Arc::new(PrivacyService::new(storage.clone()));
+    let nickname_service = Arc::new(NicknameService::new(storage.clone()));
+    let mut room_manager = RoomManager::new(16)
+        .with_storage(storage.clone())
+        .with_config(config_service.clone())
+        .with_privacy(privacy_service.clone());
+    if let Some(lease_service) = &lease_service {
+        room_manager = room_manager.with_lease(lease_service.clone());
+    }
+    if let Some(path) = config.journal.path.as_deref() {
+        match EventJournal::open(path) {
+            Ok(journal) => room_manager = room_manager.with_journal(Arc::new(journal)),
+            Err(err) => error!("Failed to open the event journal at {}: {}", path, err),
+        }
+    }
+    let room_manager = Arc::new(room_manager);
+    room_manager.restore(Instant::now()).await;
+    room_manager.replay(Instant::now()).await;
+    let text_renderer = Arc::new(TextRenderer::with_fonts(config.render.font_dir.as_deref(), config.render.font_family.as_deref()));
+    let asset_cache_ttl = Duration::from_secs(config.assets.cache_ttl_secs);
+    let asset_service = AssetService::new(reqwest::Client::new(), config.assets.cache_size, asset_cache_ttl)
+        .with_storage(storage.clone())
+        .with_text_renderer(text_renderer.clone())
+        .with_kmeans(config.assets.kmeans());
+    let webhook_client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .expect("Failed to build the webhook client");
+    let mut webhook_service = WebhookService::new(webhook_client, config_service.clone());
+    if let Some(lease_service) = &lease_service {
+        webhook_service = webhook_service.with_lease(lease_service.clone());
+    }
+    let webhook_service = Arc::new(webhook_service);
+    webhook_service.clone().spawn(room_manager.subscribe());
+    if let Some(url) = config.room_store.redis_url.as_deref() {
+        match connect_room_mirror(url).await {
+            Some(store) => {
+                Arc::new(RoomSyncService::new(room_manager.clone(), store)).spawn(room_manager.subscribe());
+            },
+            None => error!("rooms are not shared, as the room store could not be opened"),
+        }
+    }
+    let memory_monitor = Arc::new(MemoryMonitor::new(config.memory.high_water_step()));
+    let render_pool = Arc::new(RenderPool::new(config.render.workers, config.render.queue_size).with_memory(memory_monitor.clone()));
+    let mut report_service = ReportService::new(asset_service, config_service.clone(), text_renderer, render_pool)
+        .with_render_scale(config.render.scale)
+        .with_timeouts(config.timeouts.stages())
+        .with_track_policy(config.tracker.policy())
+        .with_webhooks(webhook_service)
+        .with_storage(storage.clone());
+    if let Some(lease_service) = &lease_service {
+        report_service = report_service.with_lease(lease_service.clone());
+    }
+    let report_service = Arc::new(report_service);
+    let handler = VoiceHandler::new(room_manager.clone(), report_service.clone())
+        .with_config(config_service.clone())
+        .with_nicknames(nickname_service.clone());
+    #[cfg(feature = "voice-receive")]
+    let songbird = songbird::Songbird::serenity_from_config(
+        songbird::Config::default().decode_mode(songbird::driver::DecodeMode::Decrypt),
+    );
+    #[cfg(feature = "voice-receive")]
+    let handler = handler.with_speaking(Arc::new(SpeakingService::new(songbird.clone(), room_manager.clone(), config_service.clone())));
+    let stats_service = Arc::new(StatsService::new(storage.clone()));
+    let history_service = Arc::new(HistoryService::new(storage.clone(), report_service.clone()));
+    let command_handler = CommandHandler::new(room_manager.clone(), report_service.clone(), config_service.clone(), stats_service.clone(), privacy_service.clone(), history_service, nickname_service);
 
-    let mut client = Client::builder(&token, intents)
+    let event_clock = Arc::new(EventClock::default());
+    let builder = Client::builder(&token, intents)
+        .raw_event_handler(GatewayHandler::new(event_clock.clone()))
         .event_handler(handler)
-        .await
-        .expect("Err creating client");
+        .event_handler(command_handler)
+        .event_handler(ComponentHandler::new(room_manager.clone(), report_service.clone(), config_service.clone()))
+        .event_handler(MessageHandler::new(report_service.clone()));
+    #[cfg(feature = "voice-receive")]
+    let builder = builder.register_songbird_with(songbird);
+    let mut client = builder.await.expect("Err creating client");
 
-    let manager = room_manager.clone();
-    let http = client.http.clone();
-    let reporter = report_service.clone();
-    tokio::spawn(async move {
-        let mut interval = time::interval(Duration::from_secs(CLEANUP_INTERVAL_SECS));
-
-        interval.tick().await;
-
-        loop {
-            interval.tick().await;
-
-            let now = Instant::now();
-            match manager.cleanup(now).await {
-                Ok(removed) => {
-                    for room in removed {
-                        let room_guard = room.lock().await;
-                        match reporter.send_room_report(&http, now, &RoomDTO::from_room(&room_guard), false).await {
-                            Ok(_) => {},
-                            Err(err) => {
-                                error!("Failed to send report report: {}", err);
-                                // log error and just ignore
-                                // it may be better if there is retry behavior.
-                            }
-                        }
+    let mut open_channels = Vec::new();
+    for room in room_manager.get_all_rooms().await {
+        open_channels.push(room.lock().await.channel_id());
+    }
+    report_service.restore_tracks(&client.http, &open_channels).await;
+
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let shutdown_tx = Arc::new(shutdown_tx);
+
+    let maintenance = Arc::new(
+        MaintenanceService::new(
+            room_manager.clone(),
+            report_service.clone(),
+            Duration::from_secs(config.intervals.cleanup_secs),
+        )
+        .with_cache(client.cache.clone()),
+    );
+    let cleanup_task = maintenance.clone().spawn(client.http.clone(), shutdown_rx.clone());
+
+    let scheduler = Arc::new(ReportScheduler::new(
+        room_manager.clone(),
+        report_service.clone(),
+        Duration::from_secs(config.intervals.report_tick_secs),
+    ).with_concurrency(config.intervals.report_concurrency));
+    scheduler.spawn(client.http.clone(), shutdown_rx.clone());
+
+    let digest_service = Arc::new(DigestService::new(
+        storage.clone(),
+        config_service.clone(),
+        stats_service.clone(),
+        report_service.clone(),
+    ));
+    digest_service.spawn(client.http.clone(), shutdown_rx.clone());
+
+    let mut audit_service = AuditService::new(config_service.clone());
+    if let Some(lease_service) = &lease_service {
+        audit_service = audit_service.with_lease(lease_service.clone());
+    }
+    let audit_service = Arc::new(audit_service);
+    audit_service.spawn(client.http.clone(), room_manager.subscribe(), shutdown_rx.clone());
+
+    // the dashboard is only served when a token protecting it is configured.
+    if let Some(token) = config.dashboard.token.clone() {
+        let addr = config.dashboard.addr.clone();
+        match addr.parse() {
+            Ok(addr) => {
+                let state = Arc::new(DashboardState::new(room_manager.clone(), report_service.clone(), storage.clone(), token));
+                let shutdown = shutdown_rx.clone();
+                tokio::spawn(async move {
+                    if let Err(err) = dashboard::serve(addr, state, shutdown).await {
+                        error!("Dashboard stopped: {}", err);
                     }
-                },
-                Err(e) => {
-                    error!("Error during room cleanup: {:?}", e);
-                }
-            }
+                });
+            },
+            Err(err) => error!("failed to parse DASHBOARD_ADDR({}): {}", addr, err),
         }
+    }
+
+    if let Some(addr) = config.health.addr.clone() {
+        match addr.parse() {
+            Ok(addr) => {
+                let state = Arc::new(HealthState::new(room_manager.clone(), client.shard_manager.clone(), event_clock, config.health.max_event_age())
+                    .with_reports(report_service.clone())
+                    .with_memory(memory_monitor.clone()));
+                let shutdown = shutdown_rx.clone();
+                tokio::spawn(async move {
+                    if let Err(err) = health::serve(addr, state, shutdown).await {
+                        error!("Health endpoint stopped: {}", err);
+                    }
+                });
+            },
+            Err(err) => error!("failed to parse HEALTH_ADDR({}): {}", addr, err),
+        }
+    }
+
+    let shard_manager = client.shard_manager.clone();
+    let signal_shutdown_tx = shutdown_tx.clone();
+    tokio::spawn(async move {
+        wait_for_shutdown_signal().await;
+        info!("shutdown signal received");
+        let _ = signal_shutdown_tx.send(true);
+        shard_manager.shutdown_all().await;
     });
 
-    let manager = room_manager.clone();
-    let reporter = report_service.clone();
-    let http = client.http.clone();
+    let reload_config_service = config_service.clone();
     tokio::spawn(async move {
-        let mut interval = time::interval(Duration::from_mins(1));
-        interval.tick().await;
-
-        loop {
-            interval.tick().await;
-
-            for room in manager.get_all_rooms().await {
-                let http = http.clone();
-                let room_dto = {
-                    let room = room.lock().await;
-                    RoomDTO::from_room(&room)
-                };
-                let now = Instant::now();
-                match reporter.send_room_report(&http, now, &room_dto, true).await{
-                    Ok(_) => {},
-                    Err(e) => {
-                        error!("Error sending room report: {:?}", e);
+        let mut sighup = signal(SignalKind::hangup()).expect("Failed to install SIGHUP handler");
+        while sighup.recv().await.is_some() {
+            match Config::load(&config_path) {
+                Ok(config) => {
+                    for warning in &config.warnings {
+                        warn!("{}", warning);
                     }
-                }
+                    reload_config_service.set_defaults(config.defaults);
+                    info!("reloaded guild defaults from {}", config_path.display());
+                },
+                Err(err) => error!("Failed to reload config: {}", err),
             }
         }
     });
 
-    // Start listening for events by starting a single shard
-    if let Err(why) = client.start().await {
+    // Start listening for events, on a single shard unless configured otherwise
+    let started = match config.discord.shards {
+        Some(shards) => client.start_shards(shards).await,
+        None => client.start().await,
+    };
+    if let Err(why) = started {
         println!("Client error: {why:?}");
     }
+    let _ = shutdown_tx.send(true);
+
+    // the gateway is closed; finish the remaining rooms before exiting.
+    if let Err(err) = cleanup_task.await {
+        error!("Cleanup task panicked: {}", err);
+    }
+    maintenance.finalize_all(&client.http).await;
+    // a standby takes over right away instead of waiting for the leases to expire.
+    if let Some(lease_service) = &lease_service {
+        lease_service.release_all().await;
+    }
+}
+
+#[cfg(feature = "redis-store")]
+async fn connect_room_mirror(url: &str) -> Option<Arc<dyn RoomMirror>> {
+    match RedisRoomMirror::connect(url).await {
+        Ok(store) => {
+            info!("sharing rooms through redis");
+            Some(Arc::new(store))
+        },
+        Err(err) => {
+            error!("Failed to connect to redis: {}", err);
+            None
+        },
+    }
+}
+
+#[cfg(not(feature = "redis-store"))]
+async fn connect_room_mirror(_url: &str) -> Option<Arc<dyn RoomMirror>> {
+    error!("room_store.redis_url is set, but this build lacks the redis-store feature");
+    None
+}
+
+// RUST_LOG filters as before; info is the default level.
+fn init_logging(format: LogFormat) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    match format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder.json().with_current_span(true).with_span_list(true).init(),
+    }
+}
+
+// asks the health endpoint of the instance running on this host.
+async fn probe_health(addr: Option<&str>) -> bool {
+    let port = match addr.and_then(|addr| addr.parse::<std::net::SocketAddr>().ok()) {
+        Some(addr) => addr.port(),
+        None => {
+            error!("no health address configured");
+            return false
+        },
+    };
+    match reqwest::get(format!("http://127.0.0.1:{}/health", port)).await {
+        Ok(response) => response.status().is_success(),
+        Err(err) => {
+            error!("health probe failed: {}", err);
+            false
+        },
+    }
+}
+
+async fn wait_for_shutdown_signal() {
+    let mut sigterm = signal(SignalKind::terminate()).expect("Failed to install SIGTERM handler");
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {},
+        _ = sigterm.recv() => {},
+    }
 }
@@ -0,0 +1,73 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::info;
+
+const MIB: u64 = 1024 * 1024;
+
+// allocator statistics in bytes; only jemalloc builds report them.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct MemoryStats {
+    pub allocated: u64,
+    pub active: u64,
+    pub resident: u64,
+    pub mapped: u64,
+    pub retained: u64,
+}
+
+#[cfg(feature = "jemalloc")]
+pub fn stats() -> Option<MemoryStats> {
+    use tikv_jemalloc_ctl::{epoch, stats};
+
+    // jemalloc caches its statistics until the epoch advances.
+    epoch::advance().ok()?;
+    Some(MemoryStats {
+        allocated: stats::allocated::read().ok()? as u64,
+        active: stats::active::read().ok()? as u64,
+        resident: stats::resident::read().ok()? as u64,
+        mapped: stats::mapped::read().ok()? as u64,
+        retained: stats::retained::read().ok()? as u64,
+    })
+}
+
+#[cfg(not(feature = "jemalloc"))]
+pub fn stats() -> Option<MemoryStats> {
+    None
+}
+
+// remembers the highest resident size seen, to help size containers.
+pub struct MemoryMonitor {
+    // a new high-water mark is only logged this far above the last logged one; 0 never logs.
+    step: u64,
+    peak: AtomicU64,
+    logged: AtomicU64,
+}
+
+impl MemoryMonitor {
+    pub fn new(step: u64) -> Self {
+        MemoryMonitor { step, peak: AtomicU64::new(0), logged: AtomicU64::new(0) }
+    }
+
+    pub fn peak_resident(&self) -> u64 {
+        self.peak.load(Ordering::Relaxed)
+    }
+
+    // called after large allocations such as renders; returns the current statistics.
+    pub fn observe(&self, context: &str) -> Option<MemoryStats> {
+        let stats = stats()?;
+        self.peak.fetch_max(stats.resident, Ordering::Relaxed);
+        if self.step == 0 {
+            return Some(stats)
+        }
+        let logged = self.logged.load(Ordering::Relaxed);
+        if stats.resident >= logged + self.step
+            && self.logged.compare_exchange(logged, stats.resident, Ordering::Relaxed, Ordering::Relaxed).is_ok() {
+            info!(
+                context,
+                resident_mib = stats.resident / MIB,
+                allocated_mib = stats.allocated / MIB,
+                "new memory high-water mark"
+            );
+        }
+        Some(stats)
+    }
+}
@@ -1,4 +1,5 @@
 use std::time::Duration;
+use serde::{Deserialize, Serialize};
 use serenity::all::VoiceState;
 use thiserror::Error;
 use tokio::time::Instant;
@@ -33,6 +34,14 @@ impl Activity {
         }
     }
 
+    pub fn restore(start: Instant, end: Option<Instant>, flags: VoiceStateFlags) -> Self {
+        Activity{
+            start,
+            end,
+            flags,
+        }
+    }
+
     pub fn end_at(&mut self, now: Instant) -> ActivityResult<()> {
         match self.end {
             Some(_) => Err(ActivityError::AlreadyEnded),
@@ -43,6 +52,17 @@ impl Activity {
         }
     }
 
+    // continues an ended activity, e.g. when the user rejoins right after dropping.
+    pub fn reopen(&mut self) -> ActivityResult<()> {
+        match self.end {
+            Some(_) => {
+                self.end = None;
+                Ok(())
+            },
+            None => Err(ActivityError::AlreadyStarted),
+        }
+    }
+
     pub fn is_ended(&self) -> bool {
         self.end.is_some()
     }
@@ -76,19 +96,51 @@ impl Activity {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct VoiceStateFlags {
     pub is_muted: bool,
     pub is_deafened: bool,
     pub is_sharing_screen: bool,
+    // listening from the audience of a stage channel
+    #[serde(default)]
+    pub is_audience: bool,
+    #[serde(default)]
+    pub is_video: bool,
 }
 
 impl From<&VoiceState> for VoiceStateFlags {
     fn from(state: &VoiceState) -> Self {
         VoiceStateFlags {
+            // suppress depends on the kind of channel, see `with_stage`.
             is_muted: state.mute || state.self_mute,
             is_deafened: state.deaf || state.self_deaf,
-            is_sharing_screen: state.self_stream.unwrap_or(false)
+            is_sharing_screen: state.self_stream.unwrap_or(false),
+            // whether the channel is a stage is unknown here, see `with_stage`.
+            is_audience: false,
+            is_video: state.self_video,
+        }
+    }
+}
+
+impl VoiceStateFlags {
+    // on stage channels, suppressed users are the audience rather than speakers;
+    // elsewhere suppress means the user may not speak at all.
+    pub fn with_stage(mut self, state: &VoiceState, is_stage: bool) -> Self {
+        match is_stage {
+            true => self.is_audience = state.suppress,
+            false => self.is_muted |= state.suppress,
         }
+        self
     }
-}
\ No newline at end of file
+
+    // the flags set in either.
+    pub fn union(self, other: Self) -> Self {
+        VoiceStateFlags {
+            is_muted: self.is_muted || other.is_muted,
+            is_deafened: self.is_deafened || other.is_deafened,
+            is_sharing_screen: self.is_sharing_screen || other.is_sharing_screen,
+            is_audience: self.is_audience || other.is_audience,
+            is_video: self.is_video || other.is_video,
+        }
+    }
+}
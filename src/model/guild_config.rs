@@ -0,0 +1,379 @@
+use crate::model::room::{DEFAULT_IDLE_TIMEOUT, DEFAULT_REJOIN_WINDOW};
+use crate::service::renderer::encoder::ImageFormat;
+use crate::service::renderer::theme::ThemeKind;
+use crate::service::renderer::timeline::policy::AspectRatioKind;
+use crate::service::renderer::transformer::SortOrder;
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use serenity::all::{ChannelId, RoleId};
+use std::time::Duration;
+
+const DEFAULT_LOCALE: &str = "en";
+const DEFAULT_UPDATE_INTERVAL_SECS: u64 = 60;
+const DEFAULT_DEBOUNCE_SECS: u64 = 20;
+const DEFAULT_MAX_EDITS_PER_HOUR: u32 = 60;
+const DEFAULT_IMAGE_QUALITY: u8 = 80;
+const DEFAULT_DIGEST_HOUR: u32 = 9;
+const DEFAULT_ROWS_PER_IMAGE: usize = 25;
+const DEFAULT_EVENT_ATTENDANCE_PERCENT: u8 = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GuildConfig {
+    // members with this role may change the configuration besides those with Manage Server.
+    pub manager_role_id: Option<RoleId>,
+    pub report_channel_id: Option<ChannelId>,
+    // sends reports of some channels or categories elsewhere than `report_channel_id`.
+    pub report_routes: Vec<ReportRoute>,
+    pub locale: String,
+    // IANA timezone of tick labels; None falls back to the host's local time.
+    pub timezone: Option<Tz>,
+    pub idle_timeout_secs: u64,
+    // rejoins within this many seconds continue the previous activity; 0 disables merging.
+    pub rejoin_window_secs: u64,
+    // this many members moving to the same channel within seconds take the call along; 0 disables it.
+    pub migration_min_members: usize,
+    // a call starting in the same channel within this many seconds after the previous one ended continues it; 0 disables it.
+    pub continuation_window_secs: u64,
+    pub continuation_mode: ContinuationMode,
+    // marks the gaps of merged rejoins on the timeline.
+    pub show_reconnects: bool,
+    // draws a faint line where a member was out of the call between two sessions.
+    pub show_gaps: bool,
+    pub min_call_duration_secs: u64,
+    pub min_participants: usize,
+    pub report_policy: ReportPolicy,
+    pub theme: ThemeKind,
+    pub aspect_ratio: AspectRatioKind,
+    // larger rooms are split into several images; 0 keeps everyone in one.
+    pub rows_per_image: usize,
+    pub sort_order: SortOrder,
+    pub attach_json: bool,
+    // sends the final report to the member who started the call as well.
+    pub dm_starter: bool,
+    // receives a line for every join, leave and stream as it happens.
+    pub audit_channel_id: Option<ChannelId>,
+    pub embed_template: EmbedTemplate,
+    pub channel_filter: ChannelFilter,
+    // limits tracking to members by their roles, e.g. for staff meetings.
+    pub role_filter: RoleFilter,
+    pub pause: PauseState,
+    pub image_format: ImageFormat,
+    // 1 to 100, only used by lossy formats.
+    pub image_quality: u8,
+    // joins voice channels to record speaking; needs the voice-receive feature.
+    pub track_speaking: bool,
+    // endpoints receiving room starts, ends and summaries as JSON.
+    pub webhook_urls: Vec<String>,
+    pub digest: DigestConfig,
+    // share of a scheduled event a member must be present for to count as attending.
+    pub event_attendance_percent: u8,
+}
+
+impl GuildConfig {
+    pub fn idle_timeout(&self) -> Duration {
+        Duration::from_secs(self.idle_timeout_secs)
+    }
+
+    pub fn rejoin_window(&self) -> Duration {
+        Duration::from_secs(self.rejoin_window_secs)
+    }
+
+    pub fn continuation_window(&self) -> Duration {
+        Duration::from_secs(self.continuation_window_secs)
+    }
+
+    pub fn min_call_duration(&self) -> Duration {
+        Duration::from_secs(self.min_call_duration_secs)
+    }
+
+    // a route of the voice channel itself wins over one of its category.
+    pub fn report_channel_for(&self, channel_id: ChannelId, category_id: Option<ChannelId>) -> Option<ChannelId> {
+        let route = |source: ChannelId| self.report_routes.iter()
+            .find(|route| route.source == source)
+            .map(|route| route.report_channel_id);
+        route(channel_id)
+            .or_else(|| category_id.and_then(route))
+            .or(self.report_channel_id)
+    }
+
+    // None removes the route of the source.
+    pub fn set_report_route(&mut self, source: ChannelId, report_channel_id: Option<ChannelId>) {
+        self.report_routes.retain(|route| route.source != source);
+        if let Some(report_channel_id) = report_channel_id {
+            self.report_routes.push(ReportRoute { source, report_channel_id });
+        }
+    }
+
+    // a room is reported once either configured threshold is reached; zero disables a threshold.
+    pub fn meets_report_threshold(&self, participants: usize, elapsed: Duration) -> bool {
+        if self.min_participants == 0 && self.min_call_duration_secs == 0 {
+            return true
+        }
+
+        let by_participants = self.min_participants > 0 && participants >= self.min_participants;
+        let by_duration = self.min_call_duration_secs > 0 && elapsed >= self.min_call_duration();
+        by_participants || by_duration
+    }
+}
+
+impl Default for GuildConfig {
+    fn default() -> Self {
+        GuildConfig {
+            manager_role_id: None,
+            report_channel_id: None,
+            report_routes: Vec::new(),
+            locale: String::from(DEFAULT_LOCALE),
+            timezone: None,
+            idle_timeout_secs: DEFAULT_IDLE_TIMEOUT.as_secs(),
+            rejoin_window_secs: DEFAULT_REJOIN_WINDOW.as_secs(),
+            migration_min_members: 0,
+            continuation_window_secs: 0,
+            continuation_mode: ContinuationMode::default(),
+            show_reconnects: false,
+            show_gaps: false,
+            min_call_duration_secs: 0,
+            min_participants: 0,
+            report_policy: ReportPolicy::default(),
+            theme: ThemeKind::default(),
+            aspect_ratio: AspectRatioKind::default(),
+            rows_per_image: DEFAULT_ROWS_PER_IMAGE,
+            sort_order: SortOrder::default(),
+            attach_json: false,
+            dm_starter: false,
+            audit_channel_id: None,
+            embed_template: EmbedTemplate::default(),
+            channel_filter: ChannelFilter::default(),
+            role_filter: RoleFilter::default(),
+            pause: PauseState::default(),
+            image_format: ImageFormat::default(),
+            image_quality: DEFAULT_IMAGE_QUALITY,
+            track_speaking: false,
+            webhook_urls: Vec::new(),
+            digest: DigestConfig::default(),
+            event_attendance_percent: DEFAULT_EVENT_ATTENDANCE_PERCENT,
+        }
+    }
+}
+
+// controls how often ongoing report messages are edited.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReportPolicy {
+    pub update_interval_secs: u64,
+    pub debounce_secs: u64,
+    pub max_edits_per_hour: u32,
+}
+
+impl ReportPolicy {
+    pub fn update_interval(&self) -> Duration {
+        Duration::from_secs(self.update_interval_secs)
+    }
+
+    pub fn debounce(&self) -> Duration {
+        Duration::from_secs(self.debounce_secs)
+    }
+}
+
+impl Default for ReportPolicy {
+    fn default() -> Self {
+        ReportPolicy {
+            update_interval_secs: DEFAULT_UPDATE_INTERVAL_SECS,
+            debounce_secs: DEFAULT_DEBOUNCE_SECS,
+            max_edits_per_hour: DEFAULT_MAX_EDITS_PER_HOUR,
+        }
+    }
+}
+
+// how a call continuing the previous one of its channel is reported.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContinuationMode {
+    // a new room titled with its session number, e.g. "session #2".
+    #[default]
+    Tag,
+    // the previous room is reopened and goes on; its final report waits until the window passes.
+    Merge,
+}
+
+impl ContinuationMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ContinuationMode::Tag => "tag",
+            ContinuationMode::Merge => "merge",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DigestFrequency {
+    #[default]
+    Off,
+    Daily,
+    // posted on mondays.
+    Weekly,
+}
+
+impl DigestFrequency {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DigestFrequency::Off => "off",
+            DigestFrequency::Daily => "daily",
+            DigestFrequency::Weekly => "weekly",
+        }
+    }
+
+    // the span a digest covers.
+    pub fn period(&self) -> Option<Duration> {
+        match self {
+            DigestFrequency::Off => None,
+            DigestFrequency::Daily => Some(Duration::from_hours(24)),
+            DigestFrequency::Weekly => Some(Duration::from_hours(24 * 7)),
+        }
+    }
+}
+
+// a summary of the guild's calls posted periodically.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DigestConfig {
+    pub frequency: DigestFrequency,
+    // hour of the day in the guild's timezone.
+    pub hour: u32,
+    // falls back to the report channel.
+    pub channel_id: Option<ChannelId>,
+}
+
+impl Default for DigestConfig {
+    fn default() -> Self {
+        DigestConfig {
+            frequency: DigestFrequency::default(),
+            hour: DEFAULT_DIGEST_HOUR,
+            channel_id: None,
+        }
+    }
+}
+
+// replaces parts of report embeds; texts may hold placeholders such as {channel}, None keeps the default.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmbedTemplate {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub footer: Option<String>,
+    pub color: Option<u32>,
+}
+
+impl EmbedTemplate {
+    pub fn is_default(&self) -> bool {
+        self.title.is_none() && self.description.is_none() && self.footer.is_none() && self.color.is_none()
+    }
+}
+
+// the source may be a voice channel or a category.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportRoute {
+    pub source: ChannelId,
+    pub report_channel_id: ChannelId,
+}
+
+// ids may refer to a voice channel or to the category containing it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChannelFilter {
+    pub allowed: Vec<ChannelId>,
+    pub denied: Vec<ChannelId>,
+}
+
+impl ChannelFilter {
+    // a deny always wins; an empty allowlist allows everything else.
+    pub fn is_tracked(&self, channel_id: ChannelId, category_id: Option<ChannelId>) -> bool {
+        let matches = |ids: &Vec<ChannelId>| {
+            ids.contains(&channel_id) || category_id.is_some_and(|category_id| ids.contains(&category_id))
+        };
+
+        if matches(&self.denied) {
+            return false
+        }
+        self.allowed.is_empty() || matches(&self.allowed)
+    }
+
+    pub fn allow(&mut self, id: ChannelId) {
+        self.remove(id);
+        self.allowed.push(id);
+    }
+
+    pub fn deny(&mut self, id: ChannelId) {
+        self.remove(id);
+        self.denied.push(id);
+    }
+
+    pub fn remove(&mut self, id: ChannelId) {
+        self.allowed.retain(|allowed| *allowed != id);
+        self.denied.retain(|denied| *denied != id);
+    }
+}
+
+// tracking stops for the whole guild or single channels until resumed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PauseState {
+    pub guild: bool,
+    pub channels: Vec<ChannelId>,
+}
+
+impl PauseState {
+    pub fn is_paused(&self, channel_id: ChannelId) -> bool {
+        self.guild || self.channels.contains(&channel_id)
+    }
+
+    // None pauses the whole guild.
+    pub fn pause(&mut self, channel_id: Option<ChannelId>) {
+        match channel_id {
+            Some(channel_id) if !self.channels.contains(&channel_id) => self.channels.push(channel_id),
+            Some(_) => {},
+            None => self.guild = true,
+        }
+    }
+
+    // None resumes the whole guild, including channels paused on their own.
+    pub fn resume(&mut self, channel_id: Option<ChannelId>) {
+        match channel_id {
+            Some(channel_id) => self.channels.retain(|paused| *paused != channel_id),
+            None => *self = PauseState::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RoleFilter {
+    pub allowed: Vec<RoleId>,
+    pub denied: Vec<RoleId>,
+}
+
+impl RoleFilter {
+    // holding any denied role wins; an empty allowlist allows everyone else.
+    pub fn is_tracked(&self, roles: &[RoleId]) -> bool {
+        if roles.iter().any(|role_id| self.denied.contains(role_id)) {
+            return false
+        }
+        self.allowed.is_empty() || roles.iter().any(|role_id| self.allowed.contains(role_id))
+    }
+
+    pub fn allow(&mut self, id: RoleId) {
+        self.remove(id);
+        self.allowed.push(id);
+    }
+
+    pub fn deny(&mut self, id: RoleId) {
+        self.remove(id);
+        self.denied.push(id);
+    }
+
+    pub fn remove(&mut self, id: RoleId) {
+        self.allowed.retain(|allowed| *allowed != id);
+        self.denied.retain(|denied| *denied != id);
+    }
+}
@@ -1,9 +1,21 @@
 mod activity;
+mod guild_config;
 mod participant;
 mod room;
+mod room_event;
 mod room_manager;
+mod scheduled_event;
+mod snapshot;
+mod speaking;
+mod write_order;
 
 pub use activity::{Activity, VoiceStateFlags, ActivityError, ActivityResult};
-pub use room::{Room, RoomError, RoomStatus, RoomResult};
-pub use room_manager::RoomManager;
-pub use participant::Participant;
\ No newline at end of file
+pub use room::{ChannelInfo, Room, RoomError, RoomStatus, RoomResult};
+pub(crate) use room::DEFAULT_IDLE_TIMEOUT;
+pub use room_event::RoomEvent;
+pub use room_manager::{ConnectingMember, RoomManager};
+pub use participant::Participant;
+pub use scheduled_event::ScheduledEventTag;
+pub use snapshot::{ActivitySnapshot, ParticipantSnapshot, RoomSnapshot};
+pub use speaking::SpeakingSpan;
+pub use guild_config::{ChannelFilter, ContinuationMode, DigestConfig, DigestFrequency, EmbedTemplate, GuildConfig, PauseState, ReportPolicy, ReportRoute, RoleFilter};
\ No newline at end of file
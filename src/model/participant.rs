@@ -2,13 +2,20 @@ use std::time::Duration;
 use serenity::all::UserId;
 use tokio::time::Instant;
 use crate::model::activity::{Activity, ActivityError, ActivityResult, VoiceStateFlags};
+use crate::model::speaking::SpeakingSpan;
 
 #[derive(Debug, Clone)]
 pub struct Participant{
     user_id: UserId,
     name: String,
     face: String,
-    history: Vec<Activity>
+    history: Vec<Activity>,
+    // only filled when voice receive is enabled; not persisted.
+    speaking: Vec<SpeakingSpan>,
+    // (dropped, rejoined) pairs of merged rejoins; not persisted either.
+    reconnects: Vec<(Instant, Instant)>,
+    // left the guild, so the face can no longer be fetched.
+    departed: bool,
 }
 
 impl Participant {
@@ -18,6 +25,21 @@ impl Participant {
             name,
             face,
             history: Vec::new(),
+            speaking: Vec::new(),
+            reconnects: Vec::new(),
+            departed: false,
+        }
+    }
+
+    pub fn restore(user_id: UserId, name: String, face: String, history: Vec<Activity>) -> Self {
+        Participant{
+            user_id,
+            name,
+            face,
+            history,
+            speaking: Vec::new(),
+            reconnects: Vec::new(),
+            departed: false,
         }
     }
 
@@ -33,29 +55,87 @@ impl Participant {
         &self.face
     }
 
+    // returns the previous face.
+    pub fn set_face(&mut self, face: String) -> String {
+        std::mem::replace(&mut self.face, face)
+    }
+
+    pub fn is_departed(&self) -> bool {
+        self.departed
+    }
+
+    pub fn set_departed(&mut self, departed: bool) {
+        self.departed = departed;
+    }
+
     pub fn history(&self) -> &Vec<Activity> {
         &self.history
     }
 
+    pub fn speaking(&self) -> &Vec<SpeakingSpan> {
+        &self.speaking
+    }
+
+    pub fn reconnects(&self) -> &Vec<(Instant, Instant)> {
+        &self.reconnects
+    }
+
+    pub fn is_speaking(&self) -> bool {
+        self.speaking.last().is_some_and(|span| span.is_ongoing())
+    }
+
     pub fn is_connected(&self) -> bool {
         self.history.last().map_or(false, |a| a.is_ongoing())
     }
 
-    pub fn connect(&mut self, now: Instant, flags: VoiceStateFlags) -> ActivityResult<()> {
+    // number of times the user joined; merged rejoins still count as their own session.
+    pub fn sessions(&self) -> usize {
+        let joins = self.history.iter()
+            .enumerate()
+            .filter(|(i, activity)| *i == 0 || !activity.is_following(&self.history[i - 1]))
+            .count();
+        joins + self.reconnects.len()
+    }
+
+    // returns whether the connection was merged into the previous activity.
+    pub fn connect(&mut self, now: Instant, flags: VoiceStateFlags, rejoin_window: Duration) -> ActivityResult<bool> {
         if self.is_connected() {
             return Err(ActivityError::AlreadyStarted)
         }
+
+        // a rejoin within the window continues the previous activity instead of fragmenting it.
+        let dropped_at = self.history.last().and_then(|last| last.end());
+        if let Some(dropped_at) = dropped_at.filter(|&end| !rejoin_window.is_zero() && now.duration_since(end) <= rejoin_window) {
+            self.history.last_mut().expect("dropped_at comes from the last activity").reopen()?;
+            self.reconnects.push((dropped_at, now));
+            self.update(now, flags)?;
+            return Ok(true)
+        }
+
         let activity = Activity::start_at(now, flags);
         self.history.push(activity);
-        Ok(())
+        Ok(false)
     }
 
     pub fn disconnect(&mut self, now: Instant) -> ActivityResult<()> {
         let last = self.history.last_mut().ok_or(ActivityError::NoActiveActivity)?;
         last.end_at(now)?;
+        self.set_speaking(now, false);
         Ok(())
     }
 
+    pub fn set_speaking(&mut self, now: Instant, speaking: bool) {
+        match (self.is_speaking(), speaking) {
+            (false, true) => self.speaking.push(SpeakingSpan::start_at(now)),
+            (true, false) => {
+                if let Some(span) = self.speaking.last_mut() {
+                    span.end_at(now);
+                }
+            },
+            _ => {},
+        }
+    }
+
     pub fn update(&mut self, now: Instant, flags: VoiceStateFlags) -> Result<(), ActivityError> {
         if !self.is_connected() {
             return Err(ActivityError::NoActiveActivity)
@@ -79,4 +159,25 @@ impl Participant {
         }
         duration
     }
+
+    // appends what the same user did in another room that was merged into this one.
+    pub fn absorb(&mut self, other: &Participant) {
+        self.name = other.name.clone();
+        self.face = other.face.clone();
+        self.departed = other.departed;
+        self.history.extend(other.history.iter().cloned());
+        self.speaking.extend(other.speaking.iter().cloned());
+        self.reconnects.extend(other.reconnects.iter().copied());
+    }
+
+    // time spent in the call within [start, end).
+    pub fn calculate_presence(&self, now: Instant, start: Instant, end: Instant) -> Duration {
+        let mut duration = Duration::ZERO;
+        for activity in &self.history {
+            let activity_start = activity.start().max(start);
+            let activity_end = activity.end().unwrap_or(now).min(end);
+            duration += activity_end.saturating_duration_since(activity_start);
+        }
+        duration
+    }
 }
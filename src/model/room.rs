@@ -1,11 +1,15 @@
-use serenity::all::{ChannelId, GuildId, Timestamp, UserId};
+use serenity::all::{ChannelId, GuildChannel, GuildId, ScheduledEventId, Timestamp, UserId};
+use std::time::Duration;
 use thiserror::Error;
 use tokio::time::Instant;
 use tracing::debug;
 use crate::model::activity::{ActivityError, VoiceStateFlags};
 use crate::model::participant::Participant;
+use crate::model::scheduled_event::ScheduledEventTag;
 
-const IDLE_TIMEOUT_SECS: u64 = 60;
+// used until the guild's configuration says otherwise.
+pub(crate) const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+pub(crate) const DEFAULT_REJOIN_WINDOW: Duration = Duration::from_secs(10);
 
 #[derive(Debug, Error)]
 pub enum RoomError {
@@ -25,30 +29,100 @@ pub enum RoomStatus {
     Idle,
 }
 
+// what a room knows of its voice channel, taken from the cache on connects and from channel updates.
+#[derive(Debug, Clone, Default)]
+pub struct ChannelInfo {
+    pub category_id: Option<ChannelId>,
+    pub name: Option<String>,
+    // None when unlimited.
+    pub user_limit: Option<u32>,
+}
+
+impl From<&GuildChannel> for ChannelInfo {
+    fn from(channel: &GuildChannel) -> Self {
+        ChannelInfo {
+            category_id: channel.parent_id,
+            name: Some(channel.name.clone()),
+            // discord reports an unlimited channel as a limit of 0.
+            user_limit: channel.user_limit.filter(|&limit| limit > 0),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Room {
     guild_id: GuildId,
     channel_id: ChannelId,
+    // the channel's category as of the latest connect; unknown for rooms restored from storage until then.
+    category_id: Option<ChannelId>,
+    // the channel's user limit, kept up to date like the category; None when unlimited or unknown.
+    user_limit: Option<u32>,
+    // the channel's name, so reports still name it after a rename or deletion.
+    channel_name: Option<String>,
     timestamp: Timestamp,
     created_at: Instant,
     participants: Vec<Participant>, // retains all participant since a room was created.
     expires_at: Option<Instant>,
+    idle_timeout: Duration,
+    scheduled_event: Option<ScheduledEventTag>,
+    // most participants connected at the same time so far.
+    peak_participants: usize,
+    // spans tracking was paused in; the last one is open while paused.
+    paused_spans: Vec<(Instant, Option<Instant>)>,
+    // bumped by every change, so reports of an unchanged room are not edited again.
+    revision: u64,
+    // 1 unless the call continues calls that ended shortly before in the same channel.
+    session: u32,
 }
 
 pub type RoomResult<T> = Result<T, RoomError>;
 
 impl Room {
-    pub fn new(guild_id: GuildId, channel_id: ChannelId, created_at: Instant, timestamp: Timestamp) -> Self {
+    pub fn new(guild_id: GuildId, channel_id: ChannelId, created_at: Instant, timestamp: Timestamp, idle_timeout: Duration) -> Self {
         Room {
             guild_id,
             channel_id,
+            category_id: None,
+            user_limit: None,
+            channel_name: None,
             timestamp,
             created_at,
             participants: Vec::new(),
             expires_at: None,
+            idle_timeout,
+            scheduled_event: None,
+            peak_participants: 0,
+            paused_spans: Vec::new(),
+            revision: 0,
+            session: 1,
         }
     }
 
+    pub fn restore(guild_id: GuildId, channel_id: ChannelId, created_at: Instant, timestamp: Timestamp, participants: Vec<Participant>, now: Instant) -> Self {
+        let mut room = Room {
+            guild_id,
+            channel_id,
+            category_id: None,
+            user_limit: None,
+            channel_name: None,
+            timestamp,
+            created_at,
+            participants,
+            expires_at: None,
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
+            scheduled_event: None,
+            peak_participants: 0,
+            paused_spans: Vec::new(),
+            revision: 0,
+            session: 1,
+        };
+        room.peak_participants = calculate_peak(&room.participants, now);
+        if room.get_status() == RoomStatus::Idle {
+            room.expires_at = Some(now + room.idle_timeout);
+        }
+        room
+    }
+
     pub fn guild_id(&self) -> GuildId {
         self.guild_id
     }
@@ -57,6 +131,76 @@ impl Room {
         self.channel_id
     }
 
+    pub fn category_id(&self) -> Option<ChannelId> {
+        self.category_id
+    }
+
+    pub fn user_limit(&self) -> Option<u32> {
+        self.user_limit
+    }
+
+    pub fn channel_name(&self) -> Option<&str> {
+        self.channel_name.as_deref()
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    fn touch(&mut self) {
+        self.revision += 1;
+    }
+
+    pub fn session(&self) -> u32 {
+        self.session
+    }
+
+    pub fn set_session(&mut self, session: u32) {
+        self.session = session;
+        self.touch();
+    }
+
+    // takes an ended room back, e.g. when its call continues; it expires again unless someone connects.
+    pub fn reopen(&mut self, now: Instant) {
+        self.expires_at = Some(now + self.idle_timeout);
+        self.touch();
+    }
+
+    // an unknown name keeps the last known one; returns whether anything shown in reports changed.
+    pub fn set_channel_info(&mut self, info: ChannelInfo) -> bool {
+        let name = info.name.or_else(|| self.channel_name.clone());
+        let changed = self.user_limit != info.user_limit || self.channel_name != name;
+        self.category_id = info.category_id;
+        self.user_limit = info.user_limit;
+        self.channel_name = name;
+        if changed {
+            self.touch();
+        }
+        changed
+    }
+
+    pub fn paused_spans(&self) -> &Vec<(Instant, Option<Instant>)> {
+        &self.paused_spans
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_spans.last().is_some_and(|(_, end)| end.is_none())
+    }
+
+    // opens or closes a paused span; setting the current state again changes nothing.
+    pub fn set_paused(&mut self, now: Instant, paused: bool) {
+        match (self.is_paused(), paused) {
+            (false, true) => self.paused_spans.push((now, None)),
+            (true, false) => {
+                if let Some((_, end)) = self.paused_spans.last_mut() {
+                    *end = Some(now);
+                }
+            },
+            _ => return,
+        }
+        self.touch();
+    }
+
     pub fn timestamp(&self) -> Timestamp {
         self.timestamp
     }
@@ -65,6 +209,50 @@ impl Room {
         self.created_at
     }
 
+    // when an idle room is closed; none while someone is connected.
+    pub fn expires_at(&self) -> Option<Instant> {
+        self.expires_at
+    }
+
+    pub fn idle_timeout(&self) -> Duration {
+        self.idle_timeout
+    }
+
+    // an idle room keeps the time it became idle, so only its expiry moves.
+    pub fn set_idle_timeout(&mut self, idle_timeout: Duration) {
+        if let Some(expires_at) = self.expires_at {
+            self.expires_at = expires_at.checked_sub(self.idle_timeout).map(|idle_since| idle_since + idle_timeout);
+        }
+        self.idle_timeout = idle_timeout;
+        self.touch();
+    }
+
+    pub fn scheduled_event(&self) -> Option<&ScheduledEventTag> {
+        self.scheduled_event.as_ref()
+    }
+
+    // a later event in the same call replaces the earlier one.
+    pub fn set_scheduled_event(&mut self, event: ScheduledEventTag) {
+        self.scheduled_event = Some(event);
+        self.touch();
+    }
+
+    pub fn end_scheduled_event(&mut self, now: Instant, event_id: ScheduledEventId) {
+        if let Some(event) = self.scheduled_event.as_mut().filter(|event| event.id == event_id) {
+            event.ended_at.get_or_insert(now);
+            self.touch();
+        }
+    }
+
+    pub fn peak_participants(&self) -> usize {
+        self.peak_participants
+    }
+
+    fn update_peak(&mut self) {
+        let connected = self.participants.iter().filter(|part| part.is_connected()).count();
+        self.peak_participants = self.peak_participants.max(connected);
+    }
+
     pub fn participants(&self) -> &Vec<Participant> {
         self.participants.as_ref()
     }
@@ -73,28 +261,47 @@ impl Room {
         self.participants.iter().find(|part| part.user_id() == user_id)
     }
 
+    pub fn is_connected(&self, user_id: UserId) -> bool {
+        self.find_participant(user_id).is_some_and(|part| part.is_connected())
+    }
+
+    // flags of the ongoing activity, if the user is connected.
+    pub fn current_flags(&self, user_id: UserId) -> Option<VoiceStateFlags> {
+        self.find_participant(user_id)
+            .and_then(|part| part.history().last())
+            .filter(|activity| activity.is_ongoing())
+            .map(|activity| activity.flags())
+    }
+
     fn find_participant_mut(&mut self, user_id: UserId) -> Option<&mut Participant> {
         self.participants.iter_mut().find(|part| part.user_id() == user_id)
     }
 
-    pub fn handle_connect(&mut self, now: Instant, user_id: UserId, name: String, face: String, flags: VoiceStateFlags) -> RoomResult<()> {
+    // returns whether the connection was merged into the participant's previous activity.
+    pub fn handle_connect(&mut self, now: Instant, user_id: UserId, name: String, face: String, flags: VoiceStateFlags, rejoin_window: Duration) -> RoomResult<bool> {
         debug!("handle connect");
         if let Some(participant) = self.find_participant_mut(user_id) {
             debug!("participant already exists");
-            participant.connect(now, flags)?;
+            // joining again means the user is back in the guild.
+            participant.set_departed(false);
+            let merged = participant.connect(now, flags, rejoin_window)?;
             self.expires_at = None;
-            return Ok(())
+            self.update_peak();
+            self.touch();
+            return Ok(merged)
         }
 
         debug!("newly connected, create participant");
         let mut participant = Participant::new(user_id, name, face);
-        participant.connect(now, flags)?;
+        participant.connect(now, flags, rejoin_window)?;
         self.participants.push(participant);
         self.expires_at = None;
-        Ok(())
+        self.update_peak();
+        self.touch();
+        Ok(false)
     }
 
-    fn get_status(&self) -> RoomStatus {
+    pub fn get_status(&self) -> RoomStatus {
         if self.participants.iter().any(|part| part.is_connected()) {
             RoomStatus::Occupied
         } else {
@@ -106,10 +313,12 @@ impl Room {
         debug!("handle disconnect");
         let participant = self.find_participant_mut(user_id).ok_or(RoomError::ParticipantNotFound)?;
         participant.disconnect(now)?;
+        self.update_peak();
+        self.touch();
         let status = self.get_status();
         if status == RoomStatus::Idle {
             debug!("no one is in room");
-            self.expires_at = Some(now + std::time::Duration::from_secs(IDLE_TIMEOUT_SECS));
+            self.expires_at = Some(now + self.idle_timeout);
         }
         debug!("finish handle disconnect");
         Ok(status)
@@ -119,11 +328,104 @@ impl Room {
         debug!("handle update");
         let participant = self.find_participant_mut(user_id).ok_or(RoomError::ParticipantNotFound)?;
         participant.update(now, flags)?;
+        self.touch();
         debug!("finish handle update");
         Ok(())
     }
 
+    pub fn handle_speaking(&mut self, now: Instant, user_id: UserId, speaking: bool) -> RoomResult<()> {
+        let participant = self.find_participant_mut(user_id).ok_or(RoomError::ParticipantNotFound)?;
+        if speaking && !participant.is_connected() {
+            return Ok(())
+        }
+        participant.set_speaking(now, speaking);
+        self.touch();
+        Ok(())
+    }
+
+    // returns the previous face if the user is in the room and the face changed.
+    pub fn update_face(&mut self, user_id: UserId, face: &str) -> Option<String> {
+        let participant = self.find_participant_mut(user_id)?;
+        if participant.face() == face {
+            return None
+        }
+        let previous = participant.set_face(face.to_string());
+        self.touch();
+        Some(previous)
+    }
+
+    // returns whether the user was a participant of the room.
+    pub fn mark_departed(&mut self, user_id: UserId) -> bool {
+        match self.find_participant_mut(user_id) {
+            Some(participant) => {
+                participant.set_departed(true);
+                self.touch();
+                true
+            },
+            None => false,
+        }
+    }
+
+    // drops every trace of the user from the room, e.g. after opting out.
+    pub fn forget(&mut self, now: Instant, user_id: UserId) {
+        self.participants.retain(|part| part.user_id() != user_id);
+        self.touch();
+        if self.expires_at.is_none() && self.get_status() == RoomStatus::Idle {
+            self.expires_at = Some(now + self.idle_timeout);
+        }
+    }
+
+    // continues this room in the channel of `other`, taking over its participants.
+    pub fn absorb(&mut self, other: &Room) {
+        self.channel_id = other.channel_id;
+        self.category_id = other.category_id;
+        self.user_limit = other.user_limit;
+        self.channel_name = other.channel_name.clone();
+        for participant in other.participants() {
+            match self.find_participant_mut(participant.user_id()) {
+                Some(existing) => existing.absorb(participant),
+                None => self.participants.push(participant.clone()),
+            }
+        }
+        if self.get_status() == RoomStatus::Occupied {
+            self.expires_at = None;
+        }
+        self.peak_participants = self.peak_participants.max(other.peak_participants);
+        self.update_peak();
+        // past both revisions, so a report rendered for either room counts as stale.
+        self.revision = self.revision.max(other.revision) + 1;
+    }
+
+    // disconnects everyone still connected and expires the room immediately.
+    pub fn close(&mut self, now: Instant) {
+        for participant in self.participants.iter_mut().filter(|part| part.is_connected()) {
+            if let Err(err) = participant.disconnect(now) {
+                debug!("failed to disconnect participant on close: {}", err);
+            }
+        }
+        self.expires_at = Some(now);
+        self.touch();
+    }
+
     pub fn has_expired(&self, now: Instant) -> bool {
         self.expires_at.map_or(false, |expires_at| now > expires_at)
     }
 }
+
+// replays the activities, for rooms whose peak was not recorded live.
+fn calculate_peak(participants: &[Participant], now: Instant) -> usize {
+    let mut changes: Vec<(Instant, i32)> = participants.iter()
+        .flat_map(|part| part.history().iter())
+        .flat_map(|activity| [(activity.start(), 1), (activity.end().unwrap_or(now), -1)])
+        .collect();
+    // an activity ending when the next one starts is the same connection, so ends go first.
+    changes.sort_by_key(|&(at, delta)| (at, delta));
+
+    let mut connected = 0;
+    let mut peak = 0;
+    for (_, delta) in changes {
+        connected += delta;
+        peak = peak.max(connected);
+    }
+    peak.max(0) as usize
+}
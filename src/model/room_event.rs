@@ -0,0 +1,54 @@
+use crate::model::VoiceStateFlags;
+use serde::Serialize;
+use serenity::all::{ChannelId, GuildId, Timestamp, UserId};
+
+// Changes of rooms published by `RoomManager`, for subsystems that should not be wired into the handlers.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RoomEvent {
+    RoomCreated {
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        timestamp: Timestamp,
+    },
+    ParticipantJoined {
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        user_id: UserId,
+        name: String,
+    },
+    ParticipantLeft {
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        user_id: UserId,
+    },
+    FlagsChanged {
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        user_id: UserId,
+        flags: VoiceStateFlags,
+    },
+    RoomClosed {
+        guild_id: GuildId,
+        channel_id: ChannelId,
+    },
+    // the call moved to another channel as a group and continues there.
+    RoomMoved {
+        guild_id: GuildId,
+        from: ChannelId,
+        to: ChannelId,
+    },
+}
+
+impl RoomEvent {
+    pub fn guild_id(&self) -> GuildId {
+        match self {
+            RoomEvent::RoomCreated { guild_id, .. }
+            | RoomEvent::ParticipantJoined { guild_id, .. }
+            | RoomEvent::ParticipantLeft { guild_id, .. }
+            | RoomEvent::FlagsChanged { guild_id, .. }
+            | RoomEvent::RoomClosed { guild_id, .. }
+            | RoomEvent::RoomMoved { guild_id, .. } => *guild_id,
+        }
+    }
+}
@@ -1,18 +1,70 @@
-use crate::model::{Room, RoomError, VoiceStateFlags};
-use serenity::all::{ChannelId, GuildId, UserId};
-use std::collections::HashMap;
+use crate::model::{ChannelInfo, ContinuationMode, PauseState, Room, RoomError, RoomEvent, RoomStatus, ScheduledEventTag, VoiceStateFlags};
+use crate::model::room::{DEFAULT_IDLE_TIMEOUT, DEFAULT_REJOIN_WINDOW};
+use crate::model::write_order::{WriteOrder, WriteTurn};
+use serenity::all::{ChannelId, GuildId, ScheduledEventId, UserId};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::time::Duration;
 use serenity::model::Timestamp;
 use thiserror::Error;
-use tokio::sync::{Mutex};
+use tokio::sync::{broadcast, Mutex};
 use tokio::time::Instant;
-use tracing::debug;
+use tracing::{debug, error, info, instrument, warn};
+use crate::service::config::ConfigService;
+use crate::service::lease::LeaseService;
+use crate::service::privacy::PrivacyService;
+use crate::storage::{EventJournal, JournalEvent, OpenRoom, SqliteStorage};
 
 pub struct RoomManager{
     shards: Vec<Arc<Mutex<HashMap<ChannelId, Arc<Mutex<Room>>>>>>,
-    num_shards: usize
+    num_shards: usize,
+    storage: Option<Arc<SqliteStorage>>,
+    journal: Option<Arc<EventJournal>>,
+    config_service: Option<Arc<ConfigService>>,
+    privacy_service: Option<Arc<PrivacyService>>,
+    // with other instances sharing the database, only the guild's lease holder writes to it.
+    lease_service: Option<Arc<LeaseService>>,
+    events: broadcast::Sender<RoomEvent>,
+    // scheduled events currently held in a voice channel; rooms created meanwhile are tagged too.
+    active_events: Mutex<HashMap<ChannelId, ScheduledEventTag>>,
+    // recent channel switches, to notice a group moving together.
+    recent_moves: Mutex<Vec<ChannelMove>>,
+    // the channel each user is connected to, so a user is never in two rooms of a guild at once.
+    presence: Mutex<HashMap<GuildId, HashMap<UserId, ChannelId>>>,
+    // rooms that ended lately, so a call starting again in their channel can continue them.
+    recently_ended: Mutex<HashMap<ChannelId, EndedRoom>>,
+    // storage writes happen after the locks are released, but still in the order of the changes.
+    write_order: WriteOrder,
 }
 
+struct EndedRoom {
+    until: Instant,
+    room: Arc<Mutex<Room>>,
+    // merged calls keep their report message, so the final report waits until the window passes.
+    held: bool,
+}
+
+struct ChannelMove {
+    at: Instant,
+    from: ChannelId,
+    to: ChannelId,
+    user_id: UserId,
+}
+
+// the member a connect event is about.
+pub struct ConnectingMember {
+    pub user_id: UserId,
+    pub name: String,
+    pub face: String,
+    pub flags: VoiceStateFlags,
+}
+
+// switches further apart are not considered one group moving.
+const MIGRATION_WINDOW: Duration = Duration::from_secs(10);
+
+// slow subscribers skip events older than this many.
+const EVENT_CAPACITY: usize = 256;
+
 #[derive(Debug, Error)]
 pub enum RoomManagerError{
     #[error(transparent)]
@@ -35,7 +87,197 @@ impl RoomManager {
 
         RoomManager{
             shards,
-            num_shards
+            num_shards,
+            storage: None,
+            journal: None,
+            config_service: None,
+            privacy_service: None,
+            lease_service: None,
+            events: broadcast::channel(EVENT_CAPACITY).0,
+            active_events: Mutex::new(HashMap::new()),
+            recent_moves: Mutex::new(Vec::new()),
+            presence: Mutex::new(HashMap::new()),
+            recently_ended: Mutex::new(HashMap::new()),
+            write_order: WriteOrder::default(),
+        }
+    }
+
+    pub fn with_storage(mut self, storage: Arc<SqliteStorage>) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    pub fn with_journal(mut self, journal: Arc<EventJournal>) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    pub fn with_config(mut self, config_service: Arc<ConfigService>) -> Self {
+        self.config_service = Some(config_service);
+        self
+    }
+
+    pub fn with_privacy(mut self, privacy_service: Arc<PrivacyService>) -> Self {
+        self.privacy_service = Some(privacy_service);
+        self
+    }
+
+    pub fn with_lease(mut self, lease_service: Arc<LeaseService>) -> Self {
+        self.lease_service = Some(lease_service);
+        self
+    }
+
+    // the storage to persist the guild's rooms to; None while another instance holds the lease.
+    async fn writer(&self, guild_id: GuildId) -> Option<&Arc<SqliteStorage>> {
+        let storage = self.storage.as_ref()?;
+        match &self.lease_service {
+            Some(lease_service) if !lease_service.is_active(guild_id).await => None,
+            _ => Some(storage),
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<RoomEvent> {
+        self.events.subscribe()
+    }
+
+    fn publish(&self, event: RoomEvent) {
+        // sending only fails without subscribers.
+        let _ = self.events.send(event);
+    }
+
+    // restores rooms that were still open when the bot stopped.
+    pub async fn restore(&self, now: Instant) -> usize {
+        let storage = match &self.storage {
+            Some(storage) => storage,
+            None => return 0,
+        };
+
+        let rooms = match storage.restore_rooms(now).await {
+            Ok(rooms) => rooms,
+            Err(err) => {
+                error!("Failed to restore rooms from storage: {}", err);
+                return 0;
+            }
+        };
+
+        let restored = rooms.len();
+        for mut room in rooms {
+            room.set_idle_timeout(self.idle_timeout(room.guild_id()).await);
+            let channel_id = room.channel_id();
+            for participant in room.participants().iter().filter(|participant| participant.is_connected()) {
+                self.set_presence(room.guild_id(), participant.user_id(), channel_id).await;
+            }
+            let mut rooms_guard = self.get_shard(channel_id).lock().await;
+            rooms_guard.insert(channel_id, Arc::new(Mutex::new(room)));
+        }
+        debug!("{} rooms were restored from storage.", restored);
+        restored
+    }
+
+    // rebuilds rooms from the journal that storage did not restore, then compacts the journal.
+    // group moves are not journaled, so a moved room comes back as a room of its new channel.
+    pub async fn replay(&self, now: Instant) -> usize {
+        let journal = match &self.journal {
+            Some(journal) => journal,
+            None => return 0,
+        };
+
+        let entries = match journal.read() {
+            Ok(entries) => entries,
+            Err(err) => {
+                error!("Failed to read the event journal: {}", err);
+                return 0;
+            }
+        };
+
+        let now_millis = Self::to_unix_millis(now);
+        let mut rooms: HashMap<ChannelId, Room> = HashMap::new();
+        for entry in entries {
+            let ago = Duration::from_millis((now_millis - entry.at).max(0) as u64);
+            let at = now.checked_sub(ago).unwrap_or(now);
+            let result = match entry.event {
+                JournalEvent::Connect { guild_id, channel_id, user_id, name, face, flags } => {
+                    let rejoin_window = self.rejoin_window(guild_id).await;
+                    let room = rooms.entry(channel_id).or_insert_with(|| {
+                        let start = Timestamp::from_unix_timestamp(entry.at / 1000).unwrap_or_else(|_| Timestamp::now());
+                        Room::new(guild_id, channel_id, at, start, DEFAULT_IDLE_TIMEOUT)
+                    });
+                    room.handle_connect(at, user_id, name, face, flags, rejoin_window).map(|_| ())
+                },
+                JournalEvent::Disconnect { channel_id, user_id } => match rooms.get_mut(&channel_id) {
+                    Some(room) => room.handle_disconnect(at, user_id).map(|_| ()),
+                    None => Ok(()),
+                },
+                JournalEvent::Update { channel_id, user_id, flags } => match rooms.get_mut(&channel_id) {
+                    Some(room) => room.handle_update(at, user_id, flags),
+                    None => Ok(()),
+                },
+                JournalEvent::Forget { user_id } => {
+                    rooms.values_mut().for_each(|room| room.forget(at, user_id));
+                    Ok(())
+                },
+                JournalEvent::RoomClosed { channel_id } => {
+                    rooms.remove(&channel_id);
+                    Ok(())
+                },
+                JournalEvent::Moved { from, to } => {
+                    if let (Some(mut room), Some(to_room)) = (rooms.remove(&from), rooms.remove(&to)) {
+                        room.absorb(&to_room);
+                        rooms.insert(to, room);
+                    }
+                    Ok(())
+                },
+            };
+            if let Err(err) = result {
+                warn!("Skipping journal entry that does not apply: {}", err);
+            }
+        }
+
+        let mut replayed = 0;
+        for (channel_id, mut room) in rooms {
+            let mut rooms_guard = self.get_shard(channel_id).lock().await;
+            if rooms_guard.contains_key(&channel_id) {
+                continue;
+            }
+            room.set_idle_timeout(self.idle_timeout(room.guild_id()).await);
+            for participant in room.participants().iter().filter(|participant| participant.is_connected()) {
+                self.set_presence(room.guild_id(), participant.user_id(), channel_id).await;
+            }
+            rooms_guard.insert(channel_id, Arc::new(Mutex::new(room)));
+            replayed += 1;
+        }
+        debug!("{} rooms were replayed from the journal.", replayed);
+        self.compact_journal().await;
+        replayed
+    }
+
+    // wall-clock time of an instant, for journal entries.
+    fn to_unix_millis(instant: Instant) -> i64 {
+        let now = Instant::now();
+        let millis = chrono::Utc::now().timestamp_millis();
+        match now.checked_duration_since(instant) {
+            Some(ago) => millis - ago.as_millis() as i64,
+            None => millis + instant.duration_since(now).as_millis() as i64,
+        }
+    }
+
+    fn journal(&self, now: Instant, event: JournalEvent) {
+        let journal = match &self.journal {
+            Some(journal) => journal,
+            None => return,
+        };
+        if let Err(err) = journal.append(Self::to_unix_millis(now), event) {
+            error!("Failed to journal voice event: {}", err);
+        }
+    }
+
+    async fn compact_journal(&self) {
+        let journal = match &self.journal {
+            Some(journal) => journal,
+            None => return,
+        };
+        if let Err(err) = journal.compact() {
+            error!("Failed to compact the event journal: {}", err);
         }
     }
 
@@ -52,6 +294,61 @@ impl RoomManager {
         all_rooms
     }
 
+    pub async fn open_channel_ids(&self) -> HashSet<ChannelId> {
+        let mut channel_ids = HashSet::new();
+        for shard_mutex in self.shards.iter() {
+            channel_ids.extend(shard_mutex.lock().await.keys().copied());
+        }
+        channel_ids
+    }
+
+    pub async fn get_guild_rooms(&self, guild_id: GuildId) -> Vec<Arc<Mutex<Room>>> {
+        let mut guild_rooms = Vec::new();
+        for room in self.get_all_rooms().await {
+            if room.lock().await.guild_id() == guild_id {
+                guild_rooms.push(room);
+            }
+        }
+        guild_rooms
+    }
+
+    pub async fn get_room(&self, channel_id: ChannelId) -> Option<Arc<Mutex<Room>>> {
+        let rooms_guard = self.get_shard(channel_id).lock().await;
+        rooms_guard.get(&channel_id).cloned()
+    }
+
+    // finds the channel where the user is currently connected, for events lacking the old voice state.
+    pub async fn find_connected_channel(&self, guild_id: GuildId, user_id: UserId) -> Option<ChannelId> {
+        for room in self.get_all_rooms().await {
+            let room = room.lock().await;
+            if room.guild_id() == guild_id && room.is_connected(user_id) {
+                return Some(room.channel_id());
+            }
+        }
+        None
+    }
+
+    async fn presence_of(&self, guild_id: GuildId, user_id: UserId) -> Option<ChannelId> {
+        self.presence.lock().await.get(&guild_id).and_then(|users| users.get(&user_id)).copied()
+    }
+
+    async fn set_presence(&self, guild_id: GuildId, user_id: UserId, channel_id: ChannelId) {
+        self.presence.lock().await.entry(guild_id).or_default().insert(user_id, channel_id);
+    }
+
+    // removes the user's entry only while it still points at the channel.
+    async fn clear_presence(&self, guild_id: GuildId, user_id: UserId, channel_id: ChannelId) {
+        let mut presence = self.presence.lock().await;
+        if let Some(users) = presence.get_mut(&guild_id) {
+            if users.get(&user_id) == Some(&channel_id) {
+                users.remove(&user_id);
+            }
+            if users.is_empty() {
+                presence.remove(&guild_id);
+            }
+        }
+    }
+
     fn calculate_shard_index(channel_id: ChannelId, num_shards: usize) -> usize{
         (channel_id.get() % num_shards as u64) as usize
     }
@@ -60,20 +357,354 @@ impl RoomManager {
         self.shards.get(Self::calculate_shard_index(channel_id, self.num_shards)).unwrap()
     }
 
-    pub async fn handle_connect_event(&self, now: Instant, start: Timestamp, channel_id: ChannelId, guild_id: GuildId, user_id: UserId, name: String, face: String, flags: VoiceStateFlags) -> RoomManagerResult<Arc<Mutex<Room>>> {
+    // returns None when the guild excluded the channel or the user opted out of tracking.
+    #[instrument(skip_all, fields(%guild_id, %channel_id, user_id = %member.user_id))]
+    pub async fn handle_connect_event(&self, now: Instant, start: Timestamp, channel_id: ChannelId, channel_info: ChannelInfo, guild_id: GuildId, member: ConnectingMember) -> RoomManagerResult<Option<Arc<Mutex<Room>>>> {
+        let ConnectingMember { user_id, name, face, flags } = member;
         debug!("handle connect event");
+        // racing events of a fast channel hop can connect before the previous channel is left.
+        if let Some(previous) = self.presence_of(guild_id, user_id).await.filter(|previous| *previous != channel_id) {
+            debug!("user is still connected to channel {}, closing the activity there", previous);
+            if let Err(err) = self.handle_disconnect_event(now, previous, user_id).await {
+                warn!("Failed to close the activity in channel {}: {}", previous, err);
+            }
+        }
+
+        let mut idle_timeout = DEFAULT_IDLE_TIMEOUT;
+        let mut rejoin_window = DEFAULT_REJOIN_WINDOW;
+        if let Some(config_service) = &self.config_service {
+            let config = config_service.get(guild_id).await;
+            if !config.channel_filter.is_tracked(channel_id, channel_info.category_id) {
+                debug!("channel {} is excluded from tracking", channel_id);
+                return Ok(None)
+            }
+            idle_timeout = config.idle_timeout();
+            rejoin_window = config.rejoin_window();
+        }
+        if self.is_opted_out(user_id).await {
+            debug!("user {} opted out of tracking", user_id);
+            return Ok(None)
+        }
+
         let mut rooms_guard = self.get_shard(channel_id).lock().await;
-        let room_guard = rooms_guard.entry(channel_id).or_insert_with(|| {
-            debug!("no room found, create new room");
-            Arc::new(Mutex::new(Room::new(guild_id, channel_id, now, start)))
-        });
+        let mut created = false;
+        let mut reopened = false;
+        let room_guard = match rooms_guard.get(&channel_id) {
+            Some(room) => room.clone(),
+            None => {
+                debug!("no room found, create new room");
+                created = true;
+                let room = match self.take_ended(now, channel_id).await {
+                    Some(ended) if ended.held => {
+                        info!("call continues the room that ended before");
+                        ended.room.lock().await.reopen(now);
+                        reopened = true;
+                        ended.room
+                    },
+                    Some(ended) => {
+                        let session = ended.room.lock().await.session() + 1;
+                        let mut room = Room::new(guild_id, channel_id, now, start, idle_timeout);
+                        room.set_session(session);
+                        Arc::new(Mutex::new(room))
+                    },
+                    None => Arc::new(Mutex::new(Room::new(guild_id, channel_id, now, start, idle_timeout))),
+                };
+                rooms_guard.insert(channel_id, room.clone());
+                room
+            },
+        };
 
         let mut room = room_guard.lock().await;
-        room.handle_connect(now, user_id, name, face, flags)?;
-        Ok(room_guard.clone())
+        if created {
+            info!("room opened");
+            self.publish(RoomEvent::RoomCreated { guild_id, channel_id, timestamp: room.timestamp() });
+        }
+        room.set_channel_info(channel_info);
+        let merged = room.handle_connect(now, user_id, name.clone(), face.clone(), flags, rejoin_window)?;
+        let (created_at, session) = (room.created_at(), room.session());
+        self.set_presence(guild_id, user_id, channel_id).await;
+        self.publish(RoomEvent::ParticipantJoined { guild_id, channel_id, user_id, name: name.clone() });
+        self.journal(now, JournalEvent::Connect { guild_id, channel_id, user_id, name: name.clone(), face: face.clone(), flags });
+        // disk io waits until the locks are released, so other voice events of the shard are not held up.
+        let mut turn = self.write_order.take(channel_id);
+        drop(room);
+        drop(rooms_guard);
+
+        turn.wait().await;
+        if let Some(storage) = self.writer(guild_id).await {
+            let reopening = match reopened {
+                true => storage.reopen_room(channel_id).await,
+                false => Ok(()),
+            };
+            if let Err(err) = reopening {
+                error!("Failed to persist room reopening: {}", err);
+            }
+            let result = if merged {
+                storage.record_reconnect(channel_id, now, user_id, flags).await
+            } else {
+                storage.record_connect(OpenRoom { guild_id, channel_id, created_at, session }, now, user_id, &name, &face, flags).await
+            };
+            if let Err(err) = result {
+                error!("Failed to persist connect event: {}", err);
+            }
+        }
+        drop(turn);
+        // the room is persisted by its first connect, so it is tagged afterwards.
+        if created
+            && let Some(event) = self.active_events.lock().await.get(&channel_id).cloned()
+        {
+            self.tag_scheduled_event(&room_guard, event).await;
+        }
+        Ok(Some(room_guard))
+    }
+
+    // tags the channel's room, and rooms opened there until the event ends.
+    pub async fn handle_event_started(&self, now: Instant, channel_id: ChannelId, event_id: ScheduledEventId, name: String) {
+        let event = ScheduledEventTag::new(event_id, name, now);
+        self.active_events.lock().await.insert(channel_id, event.clone());
+        if let Some(room) = self.get_room(channel_id).await {
+            self.tag_scheduled_event(&room, event).await;
+        }
+    }
+
+    pub async fn handle_event_ended(&self, now: Instant, channel_id: ChannelId, event_id: ScheduledEventId) {
+        {
+            let mut active_events = self.active_events.lock().await;
+            if active_events.get(&channel_id).is_some_and(|event| event.id == event_id) {
+                active_events.remove(&channel_id);
+            }
+        }
+        if let Some(room) = self.get_room(channel_id).await {
+            room.lock().await.end_scheduled_event(now, event_id);
+        }
+    }
+
+    async fn tag_scheduled_event(&self, room: &Arc<Mutex<Room>>, event: ScheduledEventTag) {
+        let (guild_id, channel_id, created_at) = {
+            let mut room = room.lock().await;
+            room.set_scheduled_event(event.clone());
+            (room.guild_id(), room.channel_id(), room.created_at())
+        };
+        debug!("room of channel {} is tagged with scheduled event {}", channel_id, event.id);
+        if let Some(storage) = self.writer(guild_id).await {
+            let started_at = storage.to_unix_millis(created_at);
+            if let Err(err) = storage.save_scheduled_event(channel_id, started_at, event.id).await {
+                error!("Failed to persist scheduled event of channel {}: {}", channel_id, err);
+            }
+        }
+    }
+
+    // called after the user switched channels; returns whether the room of `from` moved along to `to`.
+    #[instrument(skip_all, fields(%guild_id, %from, %to, %user_id))]
+    pub async fn handle_move_event(&self, now: Instant, guild_id: GuildId, from: ChannelId, to: ChannelId, user_id: UserId) -> bool {
+        let min_members = match &self.config_service {
+            Some(config_service) => config_service.get(guild_id).await.migration_min_members,
+            None => 0,
+        };
+        if min_members == 0 {
+            return false
+        }
+
+        let movers: Vec<UserId> = {
+            let mut recent_moves = self.recent_moves.lock().await;
+            recent_moves.retain(|channel_move| channel_move.at + MIGRATION_WINDOW >= now);
+            recent_moves.push(ChannelMove { at: now, from, to, user_id });
+            recent_moves.iter()
+                .filter(|channel_move| channel_move.from == from && channel_move.to == to)
+                .map(|channel_move| channel_move.user_id)
+                .collect()
+        };
+        if movers.len() < min_members {
+            return false
+        }
+
+        // both shards stay locked from the lookup until the room replaced the one of `to`,
+        // so a connect cannot land in the room that is discarded. they are locked in index order,
+        // so the reverse move cannot deadlock.
+        let (from_index, to_index) = (Self::calculate_shard_index(from, self.num_shards), Self::calculate_shard_index(to, self.num_shards));
+        let (mut from_shard, mut to_shard) = match from_index.cmp(&to_index) {
+            std::cmp::Ordering::Equal => (None, self.shards[to_index].lock().await),
+            std::cmp::Ordering::Less => {
+                let from_shard = self.shards[from_index].lock().await;
+                (Some(from_shard), self.shards[to_index].lock().await)
+            },
+            std::cmp::Ordering::Greater => {
+                let to_shard = self.shards[to_index].lock().await;
+                (Some(self.shards[from_index].lock().await), to_shard)
+            },
+        };
+        let from_room = from_shard.as_deref().unwrap_or(&*to_shard).get(&from).cloned();
+        let (from_room, to_room) = match (from_room, to_shard.get(&to).cloned()) {
+            (Some(from_room), Some(to_room)) => (from_room, to_room),
+            _ => return false,
+        };
+
+        // the old channel must be empty and the new room made up of the group alone.
+        // the new room cannot be idle at the same time, so the reverse move never locks the other way around.
+        {
+            let mut from_guard = from_room.lock().await;
+            if from_guard.get_status() != RoomStatus::Idle {
+                return false
+            }
+            let to_guard = to_room.lock().await;
+            if !to_guard.participants().iter().all(|participant| movers.contains(&participant.user_id())) {
+                return false
+            }
+            from_guard.absorb(&to_guard);
+        }
+        to_shard.insert(to, from_room);
+        from_shard.as_deref_mut().unwrap_or(&mut *to_shard).remove(&from);
+        let mut turns = (self.write_order.take(from), self.write_order.take(to));
+        drop(from_shard);
+        drop(to_shard);
+        // the room of `from` is gone, so nobody may be left pointing at it.
+        if let Some(users) = self.presence.lock().await.get_mut(&guild_id) {
+            users.values_mut()
+                .filter(|channel_id| **channel_id == from)
+                .for_each(|channel_id| *channel_id = to);
+        }
+
+        self.recent_moves.lock().await.retain(|channel_move| !(channel_move.from == from && channel_move.to == to));
+        debug!("{} members moved from channel {} to {} together, the room moves along", movers.len(), from, to);
+        self.publish(RoomEvent::RoomMoved { guild_id, from, to });
+        self.journal(now, JournalEvent::Moved { from, to });
+        turns.0.wait().await;
+        turns.1.wait().await;
+        if let Some(storage) = self.writer(guild_id).await
+            && let Err(err) = storage.migrate_room(from, to).await
+        {
+            error!("Failed to persist room migration: {}", err);
+        }
+        true
+    }
+
+    // keeps the ended room for the guild's continuation window; returns whether its final report is held back.
+    // only rooms whose final report is not sent yet may be held, so a merged call never gets a second one.
+    async fn remember_ended(&self, now: Instant, guild_id: GuildId, channel_id: ChannelId, room: Arc<Mutex<Room>>, holdable: bool) -> bool {
+        let (window, mode) = match &self.config_service {
+            Some(config_service) => {
+                let config = config_service.get(guild_id).await;
+                (config.continuation_window(), config.continuation_mode)
+            },
+            None => return false,
+        };
+        if window.is_zero() {
+            return false
+        }
+        let held = holdable && mode == ContinuationMode::Merge;
+        self.recently_ended.lock().await.insert(channel_id, EndedRoom { until: now + window, room, held });
+        held
+    }
+
+    // a held room past its window still continues until cleanup sent its final report,
+    // as its report message stays tracked until then.
+    async fn take_ended(&self, now: Instant, channel_id: ChannelId) -> Option<EndedRoom> {
+        let mut recently_ended = self.recently_ended.lock().await;
+        match recently_ended.get(&channel_id) {
+            Some(ended) if ended.held || ended.until >= now => recently_ended.remove(&channel_id),
+            _ => None,
+        }
+    }
+
+    // forgets rooms past their continuation window; returns the held ones, whose final report is due now.
+    async fn expire_ended(&self, now: Instant) -> Vec<Arc<Mutex<Room>>> {
+        let mut due = Vec::new();
+        self.recently_ended.lock().await.retain(|_, ended| {
+            let expired = ended.until < now;
+            if expired && ended.held {
+                due.push(ended.room.clone());
+            }
+            !expired
+        });
+        due
+    }
+
+    async fn idle_timeout(&self, guild_id: GuildId) -> Duration {
+        match &self.config_service {
+            Some(config_service) => config_service.get(guild_id).await.idle_timeout(),
+            None => DEFAULT_IDLE_TIMEOUT,
+        }
+    }
+
+    async fn rejoin_window(&self, guild_id: GuildId) -> Duration {
+        match &self.config_service {
+            Some(config_service) => config_service.get(guild_id).await.rejoin_window(),
+            None => DEFAULT_REJOIN_WINDOW,
+        }
+    }
+
+    // applies a changed idle timeout to the guild's existing rooms as well.
+    pub async fn set_idle_timeout(&self, guild_id: GuildId, idle_timeout: Duration) {
+        for room in self.get_all_rooms().await {
+            let mut room = room.lock().await;
+            if room.guild_id() == guild_id {
+                room.set_idle_timeout(idle_timeout);
+            }
+        }
+    }
+
+    // returns the channel's room when its name or user limit changed, so the report can be refreshed.
+    pub async fn update_channel(&self, channel_id: ChannelId, channel_info: ChannelInfo) -> Option<Arc<Mutex<Room>>> {
+        let room = self.get_room(channel_id).await?;
+        let changed = room.lock().await.set_channel_info(channel_info);
+        changed.then_some(room)
+    }
+
+    // opens or closes paused spans of the guild's rooms to match the pause state.
+    pub async fn apply_pause(&self, now: Instant, guild_id: GuildId, pause: &PauseState) {
+        for room in self.get_guild_rooms(guild_id).await {
+            let mut room = room.lock().await;
+            let paused = pause.is_paused(room.channel_id());
+            room.set_paused(now, paused);
+        }
+    }
+
+    // keeps avatars of participants up to date; returns the faces that were replaced.
+    pub async fn update_face(&self, guild_id: GuildId, user_id: UserId, face: &str) -> Vec<String> {
+        let mut replaced = Vec::new();
+        for room in self.get_all_rooms().await {
+            let mut room = room.lock().await;
+            if room.guild_id() == guild_id {
+                replaced.extend(room.update_face(user_id, face));
+            }
+        }
+        replaced
+    }
+
+    // called when the user left the guild; their rows are rendered without fetching the face.
+    pub async fn mark_departed(&self, guild_id: GuildId, user_id: UserId) {
+        for room in self.get_all_rooms().await {
+            let mut room = room.lock().await;
+            if room.guild_id() == guild_id && room.mark_departed(user_id) {
+                debug!("user {} left guild {} during a call", user_id, guild_id);
+            }
+        }
     }
 
+    // removes the user from every room, so they are no longer rendered either.
+    pub async fn forget_user(&self, now: Instant, user_id: UserId) {
+        for room in self.get_all_rooms().await {
+            room.lock().await.forget(now, user_id);
+        }
+        let mut presence = self.presence.lock().await;
+        presence.values_mut().for_each(|users| { users.remove(&user_id); });
+        presence.retain(|_, users| !users.is_empty());
+        drop(presence);
+        self.journal(now, JournalEvent::Forget { user_id });
+    }
+
+    async fn is_opted_out(&self, user_id: UserId) -> bool {
+        match &self.privacy_service {
+            Some(privacy_service) => privacy_service.is_opted_out(user_id).await,
+            None => false,
+        }
+    }
+
+    #[instrument(skip_all, fields(%channel_id, %user_id))]
     pub async fn handle_disconnect_event(&self, now: Instant, channel_id: ChannelId, user_id: UserId) -> RoomManagerResult<()> {
+        if self.is_opted_out(user_id).await {
+            return Ok(())
+        }
         let rooms_guard = self.get_shard(channel_id).lock().await;
         let room_guard = rooms_guard.get(&channel_id).cloned();
         match room_guard {
@@ -83,13 +714,35 @@ impl RoomManager {
             },
             Some(room) => {
                 let mut room = room.lock().await;
+                let guild_id = room.guild_id();
+                // the late disconnect of a channel hop whose activity a connect elsewhere already closed.
+                if !room.is_connected(user_id) && self.presence_of(guild_id, user_id).await.is_some_and(|current| current != channel_id) {
+                    debug!("user already moved on to another channel");
+                    return Ok(())
+                }
                 room.handle_disconnect(now, user_id)?;
+                self.clear_presence(guild_id, user_id, channel_id).await;
+                self.publish(RoomEvent::ParticipantLeft { guild_id, channel_id, user_id });
+                self.journal(now, JournalEvent::Disconnect { channel_id, user_id });
+                let mut turn = self.write_order.take(channel_id);
+                drop(room);
+                drop(rooms_guard);
+                turn.wait().await;
+                if let Some(storage) = self.writer(guild_id).await
+                    && let Err(err) = storage.record_disconnect(channel_id, now, user_id).await
+                {
+                    error!("Failed to persist disconnect event: {}", err);
+                }
                 Ok(())
             }
         }
     }
 
+    #[instrument(skip_all, fields(%channel_id, %user_id))]
     pub async fn handle_update_event(&self, now: Instant, channel_id: ChannelId, user_id: UserId, flags: VoiceStateFlags) -> RoomManagerResult<()> {
+        if self.is_opted_out(user_id).await {
+            return Ok(())
+        }
         let rooms_guard = self.get_shard(channel_id).lock().await;
         let room_guard = rooms_guard.get(&channel_id).cloned();
         match room_guard {
@@ -99,29 +752,139 @@ impl RoomManager {
             },
             Some(room) => {
                 let mut room = room.lock().await;
+                let guild_id = room.guild_id();
+                let changed = room.current_flags(user_id) != Some(flags);
                 room.handle_update(now, user_id, flags)?;
+                self.journal(now, JournalEvent::Update { channel_id, user_id, flags });
+                if changed {
+                    self.publish(RoomEvent::FlagsChanged { guild_id, channel_id, user_id, flags });
+                }
+                let mut turn = self.write_order.take(channel_id);
+                drop(room);
+                drop(rooms_guard);
+                turn.wait().await;
+                if let Some(storage) = self.writer(guild_id).await
+                    && let Err(err) = storage.record_update(channel_id, now, user_id, flags).await
+                {
+                    error!("Failed to persist update event: {}", err);
+                }
                 Ok(())
             }
         }
     }
 
+    pub async fn handle_speaking_event(&self, now: Instant, channel_id: ChannelId, user_id: UserId, speaking: bool) -> RoomManagerResult<()> {
+        let room = match self.get_room(channel_id).await {
+            Some(room) => room,
+            None => {
+                debug!("no room to record speaking");
+                return Ok(())
+            }
+        };
+        room.lock().await.handle_speaking(now, user_id, speaking)?;
+        Ok(())
+    }
+
     pub async fn cleanup(&self, now: Instant) -> RoomManagerResult<Vec<Arc<Mutex<Room>>>> {
         let mut before_cleanup = 0;
         let mut after_cleanup = 0;
         let mut removed = Vec::new();
+        let mut removed_channel_ids = Vec::new();
         for rooms in self.shards.iter() {
             let mut rooms = rooms.lock().await;
             before_cleanup += rooms.iter().count();
-            rooms.retain(|_, room| {
-                let has_expired = room.try_lock().map_or(false, |room| { room.has_expired(now) });
-                if has_expired {
-                    removed.push(room.clone());
+            rooms.retain(|channel_id, room| {
+                let expired_guild_id = room.try_lock().ok().filter(|room| room.has_expired(now)).map(|room| room.guild_id());
+                let has_expired = expired_guild_id.is_some();
+                if let Some(guild_id) = expired_guild_id {
+                    info!(%guild_id, %channel_id, "room closed");
+                    removed.push((room.clone(), self.write_order.take(*channel_id)));
+                    removed_channel_ids.push(*channel_id);
+                    self.publish(RoomEvent::RoomClosed { guild_id, channel_id: *channel_id });
                 }
                 !has_expired
             });
             after_cleanup += rooms.iter().count();
         }
         debug!("{}/{} rooms was cleaned up.", before_cleanup - after_cleanup, before_cleanup);
-        Ok(removed)
+
+        for channel_id in removed_channel_ids.iter().copied() {
+            self.journal(now, JournalEvent::RoomClosed { channel_id });
+        }
+        // rooms whose final report was held back come first, as their channel may have ended again.
+        let mut due = self.expire_ended(now).await;
+        let compact = !removed.is_empty();
+        for (room, mut turn) in removed {
+            let (guild_id, channel_id) = {
+                let room = room.lock().await;
+                (room.guild_id(), room.channel_id())
+            };
+            if !self.remember_ended(now, guild_id, channel_id, room.clone(), true).await {
+                due.push(room);
+            }
+            turn.wait().await;
+            if let Some(storage) = self.writer(guild_id).await
+                && let Err(err) = storage.close_room(channel_id, now).await
+            {
+                error!("Failed to persist room removal: {}", err);
+            }
+        }
+        if compact {
+            self.compact_journal().await;
+        }
+        Ok(due)
     }
-}
\ No newline at end of file
+
+    // removes every room after closing it, e.g. on shutdown.
+    pub async fn close_all(&self, now: Instant) -> Vec<Arc<Mutex<Room>>> {
+        let mut drained = Vec::new();
+        for rooms in self.shards.iter() {
+            drained.extend(rooms.lock().await.drain().map(|(channel_id, room)| (channel_id, room, self.write_order.take(channel_id))));
+        }
+        let mut closed = Vec::new();
+        for (channel_id, room, turn) in drained {
+            self.close(now, channel_id, &room, turn).await;
+            closed.push(room);
+        }
+        // held rooms cannot continue after a shutdown, so their final reports are due as well.
+        closed.extend(self.recently_ended.lock().await.drain()
+            .filter(|(_, ended)| ended.held)
+            .map(|(_, ended)| ended.room));
+        closed
+    }
+
+    // removes the room of the channel even though members are still connected.
+    pub async fn close_room(&self, now: Instant, channel_id: ChannelId) -> Option<Arc<Mutex<Room>>> {
+        let (room, turn) = {
+            let mut rooms_guard = self.get_shard(channel_id).lock().await;
+            let room = rooms_guard.remove(&channel_id)?;
+            (room, self.write_order.take(channel_id))
+        };
+        self.close(now, channel_id, &room, turn).await;
+        Some(room)
+    }
+
+    // a room closed on purpose is not remembered for continuation, not even as its next session.
+    async fn close(&self, now: Instant, channel_id: ChannelId, room: &Arc<Mutex<Room>>, mut turn: WriteTurn) {
+        let guild_id = {
+            let mut room = room.lock().await;
+            room.close(now);
+            room.guild_id()
+        };
+        info!(%guild_id, %channel_id, "room closed");
+        {
+            let mut presence = self.presence.lock().await;
+            if let Some(users) = presence.get_mut(&guild_id) {
+                users.retain(|_, connected| *connected != channel_id);
+            }
+        }
+        self.publish(RoomEvent::RoomClosed { guild_id, channel_id });
+        self.journal(now, JournalEvent::RoomClosed { channel_id });
+        turn.wait().await;
+        if let Some(storage) = self.writer(guild_id).await
+            && let Err(err) = storage.close_room(channel_id, now).await
+        {
+            error!("Failed to persist room removal: {}", err);
+        }
+    }
+}
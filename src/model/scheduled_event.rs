@@ -0,0 +1,27 @@
+use serenity::all::ScheduledEventId;
+use std::time::Duration;
+use tokio::time::Instant;
+
+// a Discord scheduled event held in the channel while the room was open.
+#[derive(Debug, Clone)]
+pub struct ScheduledEventTag {
+    pub id: ScheduledEventId,
+    pub name: String,
+    pub started_at: Instant,
+    // None while the event is still active.
+    pub ended_at: Option<Instant>,
+}
+
+impl ScheduledEventTag {
+    pub fn new(id: ScheduledEventId, name: String, started_at: Instant) -> Self {
+        ScheduledEventTag { id, name, started_at, ended_at: None }
+    }
+
+    pub fn end(&self, now: Instant) -> Instant {
+        self.ended_at.unwrap_or(now)
+    }
+
+    pub fn duration(&self, now: Instant) -> Duration {
+        self.end(now).saturating_duration_since(self.started_at)
+    }
+}
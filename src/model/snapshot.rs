@@ -0,0 +1,96 @@
+use crate::model::activity::{Activity, VoiceStateFlags};
+use crate::model::participant::Participant;
+use crate::model::room::Room;
+use serde::{Deserialize, Serialize};
+use serenity::all::{ChannelId, GuildId, Timestamp, UserId};
+use std::time::Duration;
+use tokio::time::Instant;
+
+// Serializable state of a room. `Instant` cannot leave the process,
+// so every time is kept as milliseconds since the room was created.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomSnapshot {
+    pub guild_id: GuildId,
+    pub channel_id: ChannelId,
+    pub timestamp: Timestamp,
+    pub idle_timeout_secs: u64,
+    // 0 in snapshots taken before sessions were numbered, read as the first session.
+    #[serde(default)]
+    pub session: u32,
+    pub participants: Vec<ParticipantSnapshot>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParticipantSnapshot {
+    pub user_id: UserId,
+    pub name: String,
+    pub face: String,
+    #[serde(default)]
+    pub departed: bool,
+    pub activities: Vec<ActivitySnapshot>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivitySnapshot {
+    pub start_ms: u64,
+    pub end_ms: Option<u64>,
+    #[serde(flatten)]
+    pub flags: VoiceStateFlags,
+}
+
+impl RoomSnapshot {
+    pub fn to_json(&self) -> serde_json::Result<Vec<u8>> {
+        serde_json::to_vec_pretty(self)
+    }
+}
+
+impl Room {
+    pub fn snapshot(&self) -> RoomSnapshot {
+        let created_at = self.created_at();
+        let offset = |instant: Instant| instant.saturating_duration_since(created_at).as_millis() as u64;
+
+        RoomSnapshot {
+            guild_id: self.guild_id(),
+            channel_id: self.channel_id(),
+            timestamp: self.timestamp(),
+            idle_timeout_secs: self.idle_timeout().as_secs(),
+            session: self.session(),
+            participants: self.participants().iter()
+                .map(|participant| ParticipantSnapshot {
+                    user_id: participant.user_id(),
+                    name: participant.name().to_string(),
+                    face: participant.face().to_string(),
+                    departed: participant.is_departed(),
+                    activities: participant.history().iter()
+                        .map(|activity| ActivitySnapshot {
+                            start_ms: offset(activity.start()),
+                            end_ms: activity.end().map(offset),
+                            flags: activity.flags(),
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+
+    // `created_at` anchors the offsets of the snapshot; `now` decides whether the room is already idle.
+    pub fn from_snapshot(snapshot: RoomSnapshot, created_at: Instant, now: Instant) -> Self {
+        let instant = |millis: u64| created_at + Duration::from_millis(millis);
+
+        let participants = snapshot.participants.into_iter()
+            .map(|participant| {
+                let history = participant.activities.iter()
+                    .map(|activity| Activity::restore(instant(activity.start_ms), activity.end_ms.map(instant), activity.flags))
+                    .collect();
+                let mut restored = Participant::restore(participant.user_id, participant.name, participant.face, history);
+                restored.set_departed(participant.departed);
+                restored
+            })
+            .collect();
+
+        let mut room = Room::restore(snapshot.guild_id, snapshot.channel_id, created_at, snapshot.timestamp, participants, now);
+        room.set_idle_timeout(Duration::from_secs(snapshot.idle_timeout_secs));
+        room.set_session(snapshot.session.max(1));
+        room
+    }
+}
@@ -0,0 +1,35 @@
+use tokio::time::Instant;
+
+// a period in which voice receive saw the user talking.
+#[derive(Debug, Clone, Copy)]
+pub struct SpeakingSpan {
+    start: Instant,
+    end: Option<Instant>,
+}
+
+impl SpeakingSpan {
+    pub fn start_at(start: Instant) -> Self {
+        SpeakingSpan {
+            start,
+            end: None,
+        }
+    }
+
+    pub fn start(&self) -> Instant {
+        self.start
+    }
+
+    pub fn end(&self) -> Option<Instant> {
+        self.end
+    }
+
+    pub fn is_ongoing(&self) -> bool {
+        self.end.is_none()
+    }
+
+    pub fn end_at(&mut self, now: Instant) {
+        if self.end.is_none() {
+            self.end = Some(now);
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use serenity::all::ChannelId;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::watch;
+
+// hands out turns for the storage writes of each channel.
+// a turn is taken while the room is still locked and waited for after releasing it,
+// so writes reach the database in the order the room was changed.
+#[derive(Default)]
+pub(crate) struct WriteOrder {
+    channels: std::sync::Mutex<HashMap<ChannelId, Arc<WriteQueue>>>,
+}
+
+struct WriteQueue {
+    next: AtomicU64,
+    serving: watch::Sender<u64>,
+}
+
+pub(crate) struct WriteTurn {
+    queue: Arc<WriteQueue>,
+    ticket: u64,
+    served: bool,
+}
+
+impl WriteOrder {
+    pub(crate) fn take(&self, channel_id: ChannelId) -> WriteTurn {
+        let queue = self.channels.lock().unwrap()
+            .entry(channel_id)
+            .or_insert_with(|| Arc::new(WriteQueue { next: AtomicU64::new(0), serving: watch::Sender::new(0) }))
+            .clone();
+        let ticket = queue.next.fetch_add(1, Ordering::Relaxed);
+        WriteTurn { queue, ticket, served: false }
+    }
+}
+
+impl WriteTurn {
+    // waits until every write of the channel with an earlier turn is done.
+    pub(crate) async fn wait(&mut self) {
+        let ticket = self.ticket;
+        let _ = self.queue.serving.subscribe().wait_for(|serving| *serving == ticket).await;
+        self.served = true;
+    }
+}
+
+impl Drop for WriteTurn {
+    fn drop(&mut self) {
+        if self.served {
+            self.queue.serving.send_replace(self.ticket + 1);
+            return
+        }
+        // a turn given up before it came must still be passed on, or later writes would wait forever.
+        let (queue, ticket) = (self.queue.clone(), self.ticket);
+        tokio::spawn(async move {
+            let _ = queue.serving.subscribe().wait_for(|serving| *serving == ticket).await;
+            queue.serving.send_replace(ticket + 1);
+        });
+    }
+}
@@ -1,15 +1,40 @@
 use std::error::Error;
 use image::imageops::FilterType;
-use image::{imageops, ImageFormat, ImageReader};
+use image::codecs::gif::GifDecoder;
+use image::{imageops, AnimationDecoder, DynamicImage, ImageFormat, ImageReader};
 use kmeans_colors::{get_kmeans, Kmeans, Sort};
 use moka::future::Cache;
 use palette::cast::from_component_slice;
-use palette::{FromColor, IntoColor, Lab, Srgba};
-use serenity::all::{GuildId, UserId};
+use palette::{FromColor, IntoColor, Lab, Lch, Srgba};
+use serenity::all::{GuildId, ImageHash, User, UserId};
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::io::{BufReader, Cursor};
 use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
-use tiny_skia::{Color, Pixmap};
+use tiny_skia::{Color, FillRule, Paint, PathBuilder, Pixmap, Rect, Transform};
+use tracing::{debug, error, warn};
+use crate::service::renderer::draw::{TextAnchor, TextRenderer};
+use crate::storage::{SqliteStorage, StorageError, StorageResult};
+
+const FETCH_ATTEMPTS: u32 = 3;
+
+pub const DEFAULT_KMEANS_RUNS: u64 = 5;
+pub const DEFAULT_KMEANS_ITERATIONS: usize = 30;
+// small sources carry little detail, so their colors come from a single run over a thumbnail.
+const FAST_PATH_MAX_SOURCE_SIZE: u32 = 128;
+const FAST_PATH_THUMBNAIL_SIZE: u32 = 16;
+const COLOR_CACHE_CAPACITY: u64 = 4096;
+const FETCH_INITIAL_BACKOFF: Duration = Duration::from_millis(250);
+
+// bars need at least this contrast ratio against the background, the WCAG minimum for graphics.
+const MIN_CONTRAST: f32 = 3.0;
+// lightness is moved in steps of this many Lab units until the contrast is reached.
+const CONTRAST_STEP: f32 = 2.0;
+
+// gray levels of the silhouette shown for departed members.
+const DEPARTED_BACKGROUND: u8 = 200;
+const DEPARTED_FOREGROUND: u8 = 140;
 
 #[derive(Clone)]
 pub struct MemberVisual {
@@ -33,101 +58,405 @@ pub enum AssetError{
     #[error("Failed to decode image: {0}")]
     PngDecoding(Box<dyn Error + Send + Sync + 'static>),
 
+    #[error("Animated image has no frames")]
+    EmptyAnimation,
+
     #[error("Async task join error: {0}")]
     Join(#[from] tokio::task::JoinError),
 }
 
+impl MemberVisual {
+    // derives the other colors from the active one, the same way as for computed colors.
+    pub fn with_active_color(mut self, active_color: Color) -> Self {
+        self.active_color = active_color;
+        self.inactive_color = Color::from_rgba(active_color.red(), active_color.green(), active_color.blue(), active_color.alpha()*0.35).unwrap();
+        self.streaming_color = {
+            let mut lab_color: Lab = Srgba::new(active_color.red(), active_color.green(), active_color.blue(), active_color.alpha()).into_color();
+            lab_color.l *= 0.4;
+            let rgba_color = Srgba::from_color(lab_color);
+            Color::from_rgba(rgba_color.red, rgba_color.green, rgba_color.blue, rgba_color.alpha).unwrap()
+        };
+        self
+    }
+
+    // darkens the active color on light backgrounds and lightens it on dark ones until the bars stand out.
+    pub fn with_contrast(self, background: Color) -> Self {
+        let active_color = self.active_color;
+        if contrast_ratio(active_color, background) >= MIN_CONTRAST {
+            return self
+        }
+
+        let mut lab: Lab = Srgba::new(active_color.red(), active_color.green(), active_color.blue(), 1.0).into_color();
+        let step = if relative_luminance(background) > 0.5 { -CONTRAST_STEP } else { CONTRAST_STEP };
+        let mut adjusted = active_color;
+        while (0.0..=100.0).contains(&lab.l) && contrast_ratio(adjusted, background) < MIN_CONTRAST {
+            lab.l += step;
+            let rgba = Srgba::from_color(lab);
+            adjusted = Color::from_rgba(
+                rgba.red.clamp(0.0, 1.0),
+                rgba.green.clamp(0.0, 1.0),
+                rgba.blue.clamp(0.0, 1.0),
+                active_color.alpha(),
+            ).unwrap_or(active_color);
+        }
+        self.with_active_color(adjusted)
+    }
+}
+
+// cheap to clone: the client and caches share their state between clones.
+#[derive(Clone)]
 pub struct AssetService {
     client: reqwest::Client,
-    cache: Cache<(GuildId, UserId), MemberVisual>,
+    // keyed by the avatar url, so a changed avatar is fetched again on its own.
+    cache: Cache<u64, MemberVisual>,
     avatar_size: u32,
+    storage: Option<Arc<SqliteStorage>>,
+    custom_colors: Cache<UserId, Option<Color>>,
+    // draws initials on placeholder avatars; without it they are plain circles.
+    text_renderer: Option<Arc<TextRenderer>>,
+    // dominant colors keyed by the avatar hash, shared by every url of the same image.
+    colors: Cache<String, Color>,
+    kmeans: KmeansConfig,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct KmeansConfig {
+    // the best of this many runs with different seeds is taken.
+    pub runs: u64,
+    pub iterations: usize,
+}
+
+impl Default for KmeansConfig {
+    fn default() -> Self {
+        KmeansConfig {
+            runs: DEFAULT_KMEANS_RUNS,
+            iterations: DEFAULT_KMEANS_ITERATIONS,
+        }
+    }
 }
 
 impl AssetService {
-    pub fn new(client: reqwest::Client) -> Self {
+    pub fn new(client: reqwest::Client, cache_capacity: u64, cache_ttl: Duration) -> Self {
         Self{
             client,
-            cache: Cache::new(128),
+            cache: Cache::builder()
+                .max_capacity(cache_capacity)
+                .time_to_live(cache_ttl)
+                .build(),
             avatar_size: 64,
+            storage: None,
+            custom_colors: Cache::new(1024),
+            text_renderer: None,
+            colors: Cache::new(COLOR_CACHE_CAPACITY),
+            kmeans: KmeansConfig::default(),
         }
     }
 
-    pub async fn get_members_visual(&self, guild_id: GuildId, user_id: UserId, avatar_url: &str) -> Result<MemberVisual, Arc<AssetError>> {
-        let entry = self.cache.entry((guild_id, user_id)).or_try_insert_with::<_, AssetError>(async {
-            let request = self.client.get(avatar_url).build()?;
-
-            let response = self.client.execute(request).await?;
-
-            let avatar_bytes = response.bytes().await?;
-
-            let avatar_size = self.avatar_size;
-
-            let task = tokio::task::spawn_blocking(move || {
-                let image_reader = ImageReader::new(BufReader::new(Cursor::new(avatar_bytes))).with_guessed_format()?;
-                let avatar_image = image_reader.decode()?;
-                let avatar_image = imageops::resize(&avatar_image, avatar_size, avatar_size, FilterType::Lanczos3);
-
-                let active_color = {
-                    let lab: Vec<Lab> = from_component_slice::<Srgba<u8>>(&avatar_image.to_vec())
-                        .iter()
-                        .map(|x| x.color.into_linear().into_color())
-                        .filter(|x: &Lab| 20.0 < x.l && x.l < 90.0)
-                        .collect();
-
-                    let mut result = Kmeans::new();
-                    for i in 0..5 {
-                        let run_result = get_kmeans(
-                            3,
-                            30,
-                            1.0,
-                            false,
-                            &lab,
-                            i,
-                        );
-                        if run_result.score < result.score {
-                            result = run_result;
-                        }
-                    }
+    pub fn with_kmeans(mut self, kmeans: KmeansConfig) -> Self {
+        self.kmeans = kmeans;
+        self
+    }
 
-                    let res = Lab::sort_indexed_colors(&result.centroids, &result.indices);
+    pub fn with_text_renderer(mut self, text_renderer: Arc<TextRenderer>) -> Self {
+        self.text_renderer = Some(text_renderer);
+        self
+    }
 
-                    let dominant_color = Lab::get_dominant_color(&res);
+    pub fn with_storage(mut self, storage: Arc<SqliteStorage>) -> Self {
+        self.storage = Some(storage);
+        self
+    }
 
-                    match dominant_color {
-                        Some(color) => {
-                            let color = Srgba::from_color(color);
-                            Color::from_rgba(color.red, color.green, color.blue, color.alpha).unwrap()
-                        },
-                        None => Color::BLACK,
-                    }
-                };
+    pub async fn custom_color(&self, user_id: UserId) -> Option<Color> {
+        let storage = self.storage.as_ref()?;
+        let result = self.custom_colors.try_get_with(user_id, async {
+            let color = storage.load_user_color(user_id).await?;
+            Ok::<_, StorageError>(color.map(color_from_rgb))
+        }).await;
 
-                let mut bytes: Vec<u8> = Vec::new();
-                avatar_image.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)?;
+        match result {
+            Ok(color) => color,
+            Err(err) => {
+                error!("Failed to load custom color for user {}: {}", user_id, err);
+                None
+            }
+        }
+    }
 
-                let inactive_color = Color::from_rgba(active_color.red(), active_color.green(), active_color.blue(), active_color.alpha()*0.35).unwrap();
-                let streaming_color = {
-                    let mut lab_color: Lab = Srgba::new(active_color.red(), active_color.green(), active_color.blue(), active_color.alpha()).into_color();
-                    lab_color.l = lab_color.l * 0.4;
-                    let rgba_color = Srgba::from_color(lab_color);
-                    Color::from_rgba(rgba_color.red, rgba_color.green, rgba_color.blue, rgba_color.alpha).unwrap()
-                };
+    // None goes back to the color computed from the avatar.
+    pub async fn set_custom_color(&self, user_id: UserId, rgb: Option<u32>) -> StorageResult<()> {
+        if let Some(storage) = &self.storage {
+            storage.save_user_color(user_id, rgb).await?;
+        }
+        self.custom_colors.invalidate(&user_id).await;
+        Ok(())
+    }
+
+    // drops the visual of an avatar that is no longer used, e.g. after a member update.
+    pub async fn invalidate(&self, avatar_url: &str) {
+        self.cache.invalidate(&cache_key(avatar_url)).await;
+    }
 
-                let pixmap = Pixmap::decode_png(&bytes).map_err(|e| AssetError::PngDecoding(Box::new(e)))?;
+    // a custom color of the user wins over the one computed from the avatar; either is kept readable on `background`.
+    // never fails: an avatar that cannot be fetched is replaced by a placeholder, which is not cached.
+    pub async fn get_members_visual(&self, user_id: UserId, name: &str, avatar_url: &str, background: Color) -> MemberVisual {
+        let visual = match self.get_computed_visual(avatar_url).await {
+            Ok(visual) => visual,
+            Err(err) => {
+                warn!("Failed to load avatar of user {}, using a placeholder: {}", user_id, err);
+                self.placeholder_visual(user_id, name)
+            }
+        };
+        let visual = match self.custom_color(user_id).await {
+            Some(color) => visual.with_active_color(color),
+            None => visual,
+        };
+        visual.with_contrast(background)
+    }
+
+    // retries network errors, rate limits and server errors with exponential backoff.
+    async fn fetch_avatar(&self, avatar_url: &str) -> Result<Vec<u8>, AssetError> {
+        let mut backoff = FETCH_INITIAL_BACKOFF;
+        let mut attempt = 1;
+
+        loop {
+            let result = match self.client.get(avatar_url).send().await {
+                Ok(response) => response.error_for_status(),
+                Err(err) => Err(err),
+            };
+            let err = match result {
+                Ok(response) => return Ok(response.bytes().await?.to_vec()),
+                Err(err) => err,
+            };
+
+            let retryable = err.status().is_none_or(|status| status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS);
+            if !retryable || attempt >= FETCH_ATTEMPTS {
+                return Err(err.into())
+            }
+            debug!("avatar fetch attempt {} failed: {}, retry in {:?}", attempt, err, backoff);
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+            attempt += 1;
+        }
+    }
 
-                Ok(MemberVisual {
-                    avatar: pixmap,
-                    active_color,
-                    inactive_color,
-                    streaming_color,
-                })
-            });
+    // a gray silhouette for members who left the guild.
+    pub fn departed_visual(&self) -> MemberVisual {
+        let background = Color::from_rgba8(DEPARTED_BACKGROUND, DEPARTED_BACKGROUND, DEPARTED_BACKGROUND, 255);
+        let foreground = Color::from_rgba8(DEPARTED_FOREGROUND, DEPARTED_FOREGROUND, DEPARTED_FOREGROUND, 255);
+
+        let size = self.avatar_size as f32;
+        let mut avatar = Pixmap::new(self.avatar_size, self.avatar_size).expect("avatar size must not be zero");
+        avatar.fill(background);
+
+        let path = {
+            let mut path_builder = PathBuilder::new();
+            path_builder.push_circle(size * 0.5, size * 0.38, size * 0.18);
+            if let Some(shoulders) = Rect::from_xywh(size * 0.2, size * 0.62, size * 0.6, size * 0.5) {
+                path_builder.push_oval(shoulders);
+            }
+            path_builder.finish()
+        };
+        if let Some(path) = path {
+            let mut paint = Paint { anti_alias: true, ..Paint::default() };
+            paint.set_color(foreground);
+            avatar.fill_path(&path, &paint, FillRule::Winding, Transform::identity(), None);
+        }
+
+        MemberVisual {
+            avatar,
+            active_color: foreground,
+            inactive_color: foreground,
+            streaming_color: foreground,
+        }.with_active_color(foreground)
+    }
 
-            let pixmap = task.await?;
+    // a circle with the initial of the name, colored from the user id so it stays stable.
+    fn placeholder_visual(&self, user_id: UserId, name: &str) -> MemberVisual {
+        let hue = (user_id.get() % 360) as f32;
+        let color = Srgba::from_color(Lch::new(60.0, 50.0, hue));
+        let color = Color::from_rgba(color.red, color.green, color.blue, color.alpha).unwrap_or(Color::BLACK);
 
-            pixmap
+        let size = self.avatar_size;
+        let mut avatar = Pixmap::new(size, size).expect("avatar size must not be zero");
+        avatar.fill(color);
+
+        if let (Some(text_renderer), Some(initial)) = (&self.text_renderer, name.chars().next()) {
+            let initial: String = initial.to_uppercase().collect();
+            let font_size = size as f32 * 0.5;
+            // draw_text takes the baseline, so shift it down to center the glyph.
+            text_renderer.draw_text(&mut avatar, &initial, font_size, (size as f32 / 2.0, size as f32 / 2.0 + font_size * 0.35), Color::WHITE, TextAnchor::Middle);
+        }
+
+        MemberVisual {
+            avatar,
+            active_color: color,
+            inactive_color: color,
+            streaming_color: color,
+        }.with_active_color(color)
+    }
+
+    async fn get_computed_visual(&self, avatar_url: &str) -> Result<MemberVisual, Arc<AssetError>> {
+        let entry = self.cache.entry(cache_key(avatar_url)).or_try_insert_with::<_, AssetError>(async {
+            // animated or webp avatars the decoder chokes on are fetched again as png.
+            let color_key = avatar_hash(avatar_url);
+            let known_color = self.colors.get(color_key).await;
+            let candidates = avatar_candidates(avatar_url);
+            let mut last_err = None;
+            for candidate in &candidates {
+                let result = match self.fetch_avatar(candidate).await {
+                    Ok(avatar_bytes) => {
+                        let (avatar_size, kmeans) = (self.avatar_size, self.kmeans);
+                        tokio::task::spawn_blocking(move || compute_visual(avatar_bytes, avatar_size, kmeans, known_color)).await?
+                    },
+                    Err(err) => Err(err),
+                };
+                match result {
+                    Ok(visual) => {
+                        if known_color.is_none() {
+                            self.colors.insert(color_key.to_string(), visual.active_color).await;
+                        }
+                        return Ok(visual)
+                    },
+                    Err(err) => {
+                        debug!("failed to load avatar from {}: {}", candidate, err);
+                        last_err = Some(err);
+                    },
+                }
+            }
+            Err(last_err.expect("there is always at least one candidate"))
         }).await;
 
         Ok(entry?.into_value())
     }
-}
\ No newline at end of file
+}
+
+fn dominant_color(rgba: &[u8], kmeans: KmeansConfig) -> Color {
+    let lab: Vec<Lab> = from_component_slice::<Srgba<u8>>(rgba)
+        .iter()
+        .map(|x| x.color.into_linear().into_color())
+        .filter(|x: &Lab| 20.0 < x.l && x.l < 90.0)
+        .collect();
+
+    let mut result = Kmeans::new();
+    for i in 0..kmeans.runs.max(1) {
+        let run_result = get_kmeans(
+            3,
+            kmeans.iterations,
+            1.0,
+            false,
+            &lab,
+            i,
+        );
+        if run_result.score < result.score {
+            result = run_result;
+        }
+    }
+
+    let res = Lab::sort_indexed_colors(&result.centroids, &result.indices);
+
+    let dominant_color = Lab::get_dominant_color(&res);
+
+    match dominant_color {
+        Some(color) => {
+            let color = Srgba::from_color(color);
+            Color::from_rgba(color.red, color.green, color.blue, color.alpha).unwrap()
+        },
+        None => Color::BLACK,
+    }
+}
+
+// the image hash is the file name of the url; sizes and formats of the same image share it.
+fn avatar_hash(avatar_url: &str) -> &str {
+    let path = avatar_url.split('?').next().unwrap_or(avatar_url);
+    let file = path.rsplit('/').next().unwrap_or(path);
+    file.split('.').next().unwrap_or(file)
+}
+
+// the guild avatar of a member when set, their own avatar otherwise.
+pub fn member_face(guild_id: GuildId, user: &User, guild_avatar: Option<&ImageHash>) -> String {
+    match guild_avatar {
+        Some(hash) => {
+            let ext = if hash.is_animated() { "gif" } else { "webp" };
+            format!("https://cdn.discordapp.com/guilds/{}/users/{}/avatars/{}.{}?size=1024", guild_id, user.id, hash, ext)
+        },
+        None => user.face(),
+    }
+}
+
+// the url itself, then the same image as png, which the CDN serves for every avatar.
+fn avatar_candidates(avatar_url: &str) -> Vec<String> {
+    let (path, query) = match avatar_url.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (avatar_url, None),
+    };
+    let mut candidates = vec![avatar_url.to_string()];
+    if let Some(stem) = [".gif", ".webp"].iter().find_map(|ext| path.strip_suffix(ext)) {
+        candidates.push(match query {
+            Some(query) => format!("{}.png?{}", stem, query),
+            None => format!("{}.png", stem),
+        });
+    }
+    candidates
+}
+
+// only the first frame of animated avatars is used.
+fn decode_avatar(avatar_bytes: Vec<u8>) -> Result<DynamicImage, AssetError> {
+    let image_reader = ImageReader::new(BufReader::new(Cursor::new(avatar_bytes))).with_guessed_format()?;
+    if image_reader.format() == Some(ImageFormat::Gif) {
+        let decoder = GifDecoder::new(image_reader.into_inner())?;
+        return match decoder.into_frames().next() {
+            Some(frame) => Ok(DynamicImage::ImageRgba8(frame?.into_buffer())),
+            None => Err(AssetError::EmptyAnimation),
+        }
+    }
+    Ok(image_reader.decode()?)
+}
+
+// the color is only extracted when it is not known from another url of the same avatar.
+fn compute_visual(avatar_bytes: Vec<u8>, avatar_size: u32, kmeans: KmeansConfig, known_color: Option<Color>) -> Result<MemberVisual, AssetError> {
+    let source = decode_avatar(avatar_bytes)?;
+    let avatar_image = imageops::resize(&source, avatar_size, avatar_size, FilterType::Lanczos3);
+
+    let active_color = match known_color {
+        Some(color) => color,
+        None if source.width().max(source.height()) <= FAST_PATH_MAX_SOURCE_SIZE => {
+            let thumbnail = imageops::resize(&source, FAST_PATH_THUMBNAIL_SIZE, FAST_PATH_THUMBNAIL_SIZE, FilterType::Triangle);
+            dominant_color(&thumbnail, KmeansConfig { runs: 1, ..kmeans })
+        },
+        None => dominant_color(&avatar_image, kmeans),
+    };
+
+    let mut bytes: Vec<u8> = Vec::new();
+    avatar_image.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)?;
+
+    let pixmap = Pixmap::decode_png(&bytes).map_err(|e| AssetError::PngDecoding(Box::new(e)))?;
+
+    Ok(MemberVisual {
+        avatar: pixmap,
+        active_color,
+        inactive_color: active_color,
+        streaming_color: active_color,
+    }.with_active_color(active_color))
+}
+
+// WCAG relative luminance of the opaque color.
+fn relative_luminance(color: Color) -> f32 {
+    let linear = Srgba::new(color.red(), color.green(), color.blue(), 1.0).into_linear::<f32, f32>();
+    0.2126 * linear.red + 0.7152 * linear.green + 0.0722 * linear.blue
+}
+
+fn contrast_ratio(a: Color, b: Color) -> f32 {
+    let (a, b) = (relative_luminance(a), relative_luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+fn color_from_rgb(rgb: u32) -> Color {
+    Color::from_rgba8((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8, 255)
+}
+
+fn cache_key(avatar_url: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    avatar_url.hash(&mut hasher);
+    hasher.finish()
+}
@@ -0,0 +1,179 @@
+use crate::model::{RoomEvent, VoiceStateFlags};
+use crate::service::config::ConfigService;
+use crate::service::i18n::{Language, TextKey};
+use crate::service::lease::LeaseService;
+use serenity::all::{
+    ChannelId, CreateAllowedMentions, CreateMessage, FormattedTimestamp, FormattedTimestampStyle, Http, Mentionable, Timestamp, UserId,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, watch, Mutex};
+use tokio::task::JoinHandle;
+use tokio::time::{self, MissedTickBehavior};
+use tracing::{debug, error, warn};
+
+// lines are collected for this long, so a burst of joins becomes one message instead of hitting rate limits.
+const AUDIT_FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+const MESSAGE_LIMIT: usize = 2000;
+
+// posts a compact line for every join, leave and stream to the guild's audit channel.
+pub struct AuditService {
+    config_service: Arc<ConfigService>,
+    // the last known flags per member, to tell which of them changed.
+    flags: Mutex<HashMap<(ChannelId, UserId), VoiceStateFlags>>,
+    pending: Mutex<HashMap<ChannelId, Vec<String>>>,
+    // without it this instance posts for every guild.
+    lease_service: Option<Arc<LeaseService>>,
+}
+
+impl AuditService {
+    pub fn new(config_service: Arc<ConfigService>) -> Self {
+        AuditService {
+            config_service,
+            flags: Mutex::new(HashMap::new()),
+            pending: Mutex::new(HashMap::new()),
+            lease_service: None,
+        }
+    }
+
+    pub fn with_lease(mut self, lease_service: Arc<LeaseService>) -> Self {
+        self.lease_service = Some(lease_service);
+        self
+    }
+
+    // records room events and posts them every few seconds until `shutdown` changes or its sender is dropped.
+    pub fn spawn(self: Arc<Self>, http: Arc<Http>, mut events: broadcast::Receiver<RoomEvent>, mut shutdown: watch::Receiver<bool>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = time::interval(AUDIT_FLUSH_INTERVAL);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                tokio::select! {
+                    event = events.recv() => match event {
+                        Ok(event) => self.record(&event).await,
+                        Err(RecvError::Lagged(skipped)) => warn!("audit service lagged behind, {} room events skipped", skipped),
+                        Err(RecvError::Closed) => break,
+                    },
+                    _ = interval.tick() => self.flush(&http).await,
+                    _ = shutdown.changed() => break,
+                }
+            }
+            self.flush(&http).await;
+            debug!("audit loop stopped");
+        })
+    }
+
+    async fn record(&self, event: &RoomEvent) {
+        if let Some(lease_service) = &self.lease_service
+            && !lease_service.is_active(event.guild_id()).await
+        {
+            return
+        }
+        let config = self.config_service.get(event.guild_id()).await;
+        let language = Language::from_locale(&config.locale);
+        let texts = self.describe(event, language).await;
+
+        let audit_channel_id = match config.audit_channel_id {
+            Some(channel_id) => channel_id,
+            None => return,
+        };
+        if texts.is_empty() {
+            return
+        }
+
+        let time = FormattedTimestamp::new(Timestamp::now(), Some(FormattedTimestampStyle::LongTime));
+        let mut pending = self.pending.lock().await;
+        let lines = pending.entry(audit_channel_id).or_default();
+        lines.extend(texts.into_iter().map(|text| format!("{} {}", time, text)));
+    }
+
+    // also keeps the known flags up to date, so it runs for guilds without an audit channel as well.
+    async fn describe(&self, event: &RoomEvent, language: Language) -> Vec<String> {
+        let text = |key: TextKey, user_id: Option<UserId>, channel_id: ChannelId| {
+            let text = language.text(key).replace("{channel}", &channel_id.mention().to_string());
+            match user_id {
+                Some(user_id) => text.replace("{user}", &user_id.mention().to_string()),
+                None => text,
+            }
+        };
+
+        let mut flags = self.flags.lock().await;
+        match event {
+            RoomEvent::RoomCreated { channel_id, .. } => vec![text(TextKey::AuditCallStarted, None, *channel_id)],
+            RoomEvent::ParticipantJoined { channel_id, user_id, .. } => {
+                flags.insert((*channel_id, *user_id), VoiceStateFlags::default());
+                vec![text(TextKey::AuditJoined, Some(*user_id), *channel_id)]
+            },
+            RoomEvent::ParticipantLeft { channel_id, user_id, .. } => {
+                flags.remove(&(*channel_id, *user_id));
+                vec![text(TextKey::AuditLeft, Some(*user_id), *channel_id)]
+            },
+            RoomEvent::FlagsChanged { channel_id, user_id, flags: new, .. } => {
+                let old = flags.insert((*channel_id, *user_id), *new).unwrap_or_default();
+                let mut texts = Vec::new();
+                match (old.is_sharing_screen, new.is_sharing_screen) {
+                    (false, true) => texts.push(text(TextKey::AuditStartedStreaming, Some(*user_id), *channel_id)),
+                    (true, false) => texts.push(text(TextKey::AuditStoppedStreaming, Some(*user_id), *channel_id)),
+                    _ => {},
+                }
+                match (old.is_video, new.is_video) {
+                    (false, true) => texts.push(text(TextKey::AuditStartedVideo, Some(*user_id), *channel_id)),
+                    (true, false) => texts.push(text(TextKey::AuditStoppedVideo, Some(*user_id), *channel_id)),
+                    _ => {},
+                }
+                texts
+            },
+            RoomEvent::RoomClosed { channel_id, .. } => {
+                flags.retain(|(channel, _), _| channel != channel_id);
+                vec![text(TextKey::AuditCallEnded, None, *channel_id)]
+            },
+            RoomEvent::RoomMoved { from, to, .. } => {
+                let moved: Vec<_> = flags.keys().filter(|(channel, _)| channel == from).copied().collect();
+                for key in moved {
+                    if let Some(known) = flags.remove(&key) {
+                        flags.insert((*to, key.1), known);
+                    }
+                }
+                vec![text(TextKey::AuditCallMoved, None, *from).replace("{to}", &to.mention().to_string())]
+            },
+        }
+    }
+
+    async fn flush(&self, http: &Http) {
+        let pending = std::mem::take(&mut *self.pending.lock().await);
+        for (channel_id, lines) in pending {
+            for content in chunk_lines(&lines) {
+                // mentions only name members and channels; nobody is pinged.
+                let message = CreateMessage::new()
+                    .content(content)
+                    .allowed_mentions(CreateAllowedMentions::new());
+                if let Err(err) = channel_id.send_message(http, message).await {
+                    error!("Failed to post audit log to channel {}: {}", channel_id, err);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+// packs lines into as few messages as the length limit allows; overlong lines are cut.
+fn chunk_lines(lines: &[String]) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    for line in lines {
+        let line: String = line.chars().take(MESSAGE_LIMIT).collect();
+        if !current.is_empty() && current.chars().count() + 1 + line.chars().count() > MESSAGE_LIMIT {
+            chunks.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(&line);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
@@ -0,0 +1,70 @@
+use crate::config::DefaultsConfig;
+use crate::model::GuildConfig;
+use crate::storage::{SqliteStorage, StorageError, StorageResult};
+use moka::future::Cache;
+use serenity::all::GuildId;
+use std::sync::{Arc, RwLock};
+use tokio::sync::Mutex;
+use tracing::error;
+
+pub struct ConfigService {
+    storage: Arc<SqliteStorage>,
+    cache: Cache<GuildId, GuildConfig>,
+    // fallback for guilds that have not configured themselves yet; replaced on reloads.
+    defaults: RwLock<DefaultsConfig>,
+    update_lock: Mutex<()>,
+}
+
+impl ConfigService {
+    pub fn new(storage: Arc<SqliteStorage>, defaults: DefaultsConfig) -> Self {
+        ConfigService {
+            storage,
+            cache: Cache::new(1024),
+            defaults: RwLock::new(defaults),
+            update_lock: Mutex::new(()),
+        }
+    }
+
+    fn default_config(&self) -> GuildConfig {
+        let defaults = self.defaults.read().unwrap();
+        GuildConfig {
+            report_channel_id: defaults.report_channel_id,
+            theme: defaults.theme,
+            ..GuildConfig::default()
+        }
+    }
+
+    // cached configs of unconfigured guilds were built from the old defaults, so the cache starts over.
+    pub fn set_defaults(&self, defaults: DefaultsConfig) {
+        *self.defaults.write().unwrap() = defaults;
+        self.cache.invalidate_all();
+    }
+
+    pub async fn get(&self, guild_id: GuildId) -> GuildConfig {
+        let result = self.cache.try_get_with(guild_id, async {
+            let config = self.storage.load_guild_config(guild_id).await?;
+            Ok::<_, StorageError>(config.unwrap_or_else(|| self.default_config()))
+        }).await;
+
+        match result {
+            Ok(config) => config,
+            Err(err) => {
+                error!("Failed to load config for guild {}: {}", guild_id, err);
+                self.default_config()
+            }
+        }
+    }
+
+    pub async fn update<F>(&self, guild_id: GuildId, f: F) -> StorageResult<GuildConfig>
+    where
+        F: FnOnce(&mut GuildConfig),
+    {
+        let _guard = self.update_lock.lock().await;
+
+        let mut config = self.get(guild_id).await;
+        f(&mut config);
+        self.storage.save_guild_config(guild_id, &config).await?;
+        self.cache.insert(guild_id, config.clone()).await;
+        Ok(config)
+    }
+}
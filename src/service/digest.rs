@@ -0,0 +1,211 @@
+use crate::model::{DigestFrequency, GuildConfig};
+use crate::service::config::ConfigService;
+use crate::service::i18n::{Language, TextKey};
+use crate::service::report::{ReportService, ReportServiceError};
+use crate::service::stats::{window, LeaderboardEntry, StatsService};
+use crate::storage::{SqliteStorage, StorageError};
+use chrono::{DateTime, Datelike, Local, TimeZone, Utc, Weekday};
+use serenity::all::{ChannelId, CreateEmbed, CreateMessage, GuildId, Http, Mentionable, MessageFlags};
+use serenity::prelude::SerenityError;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::{self, MissedTickBehavior};
+use tracing::{debug, error};
+
+const DIGEST_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+const DIGEST_TOP_MEMBERS: usize = 5;
+
+#[derive(Debug, Error)]
+pub enum DigestError {
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+
+    #[error(transparent)]
+    Report(#[from] ReportServiceError),
+
+    #[error("Serenity error")]
+    Serenity(#[from] SerenityError),
+}
+
+pub type DigestResult<T> = Result<T, DigestError>;
+
+// aggregated calls of a guild over a digest period.
+#[derive(Debug, Clone)]
+pub struct Digest {
+    pub calls: usize,
+    pub voice_time: Duration,
+    pub busiest_channel: Option<ChannelId>,
+    pub top_members: Vec<LeaderboardEntry>,
+}
+
+pub struct DigestService {
+    storage: Arc<SqliteStorage>,
+    config_service: Arc<ConfigService>,
+    stats_service: Arc<StatsService>,
+    report_service: Arc<ReportService>,
+}
+
+impl DigestService {
+    pub fn new(storage: Arc<SqliteStorage>, config_service: Arc<ConfigService>, stats_service: Arc<StatsService>, report_service: Arc<ReportService>) -> Self {
+        DigestService {
+            storage,
+            config_service,
+            stats_service,
+            report_service,
+        }
+    }
+
+    // checks for due digests every minute until `shutdown` changes or its sender is dropped.
+    pub fn spawn(self: Arc<Self>, http: Arc<Http>, mut shutdown: watch::Receiver<bool>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = time::interval(DIGEST_CHECK_INTERVAL);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => self.run_once(&http).await,
+                    _ = shutdown.changed() => break,
+                }
+            }
+            debug!("digest loop stopped");
+        })
+    }
+
+    pub async fn run_once(&self, http: &Http) {
+        let guilds = match self.storage.load_configured_guilds().await {
+            Ok(guilds) => guilds,
+            Err(err) => {
+                error!("Failed to load configured guilds: {}", err);
+                return
+            }
+        };
+
+        for guild_id in guilds {
+            if !self.report_service.is_active_reporter(guild_id).await {
+                continue;
+            }
+            let config = self.config_service.get(guild_id).await;
+            if let Err(err) = self.send_if_due(http, guild_id, &config).await {
+                error!("Failed to send digest for guild {}: {}", guild_id, err);
+            }
+        }
+    }
+
+    async fn send_if_due(&self, http: &Http, guild_id: GuildId, config: &GuildConfig) -> DigestResult<()> {
+        let digest = &config.digest;
+        let period = match digest.frequency.period() {
+            Some(period) => period,
+            None => return Ok(()),
+        };
+
+        let now = Utc::now();
+        let scheduled = match config.timezone {
+            Some(timezone) => last_occurrence(now.with_timezone(&timezone), digest.hour, digest.frequency),
+            None => last_occurrence(now.with_timezone(&Local), digest.hour, digest.frequency),
+        };
+        let scheduled = match scheduled {
+            Some(scheduled) => scheduled,
+            None => return Ok(()),
+        };
+
+        // a freshly enabled digest starts with the next occurrence instead of catching up.
+        let sent_at = match self.storage.load_digest_sent_at(guild_id).await? {
+            Some(sent_at) => sent_at,
+            None => {
+                self.storage.save_digest_sent_at(guild_id, now.timestamp_millis()).await?;
+                return Ok(())
+            }
+        };
+        if sent_at >= scheduled.timestamp_millis() {
+            return Ok(())
+        }
+
+        // recorded before posting, so a failing channel is not retried every minute.
+        self.storage.save_digest_sent_at(guild_id, now.timestamp_millis()).await?;
+
+        let channel_id = match digest.channel_id.or(config.report_channel_id) {
+            Some(channel_id) => channel_id,
+            None => {
+                debug!("guild {} has no channel for digests, skip digest", guild_id);
+                return Ok(())
+            }
+        };
+
+        let language = Language::from_locale(&config.locale);
+        let title = match digest.frequency {
+            DigestFrequency::Weekly => language.text(TextKey::WeeklyDigest),
+            _ => language.text(TextKey::DailyDigest),
+        };
+
+        let summary = self.build_digest(guild_id, period).await?;
+        let mut embed = CreateEmbed::new().title(title);
+        let mut message = CreateMessage::new().flags(MessageFlags::SUPPRESS_NOTIFICATIONS);
+        if summary.calls == 0 {
+            embed = embed.description(language.text(TextKey::NoActivity));
+        } else {
+            embed = embed
+                .field(language.text(TextKey::Calls), summary.calls.to_string(), true)
+                .field(language.text(TextKey::VoiceHours), format!("{:.1}h", summary.voice_time.as_secs_f64() / 3600.0), true)
+                .field(
+                    language.text(TextKey::BusiestChannel),
+                    summary.busiest_channel.map_or(String::from("-"), |channel_id| channel_id.mention().to_string()),
+                    true,
+                );
+            if !summary.top_members.is_empty() {
+                let attachment = self.report_service.render_leaderboard(guild_id, title, &summary.top_members).await?;
+                embed = embed.image(format!("attachment://{}", attachment.filename));
+                message = message.add_file(attachment);
+            }
+        }
+
+        channel_id.send_message(http, message.embed(embed)).await?;
+
+        Ok(())
+    }
+
+    // aggregates the calls of the guild within the past `period`.
+    pub async fn build_digest(&self, guild_id: GuildId, period: Duration) -> DigestResult<Digest> {
+        let (since, until) = window(period);
+        let rooms = self.storage.load_rooms(guild_id, since, until).await?;
+
+        let mut channel_time: HashMap<ChannelId, i64> = HashMap::new();
+        for room in &rooms {
+            let duration = room.ended_at.unwrap_or(until).min(until) - room.started_at;
+            *channel_time.entry(room.channel_id).or_default() += duration.max(0);
+        }
+        let busiest_channel = channel_time.into_iter()
+            .max_by_key(|(_, duration)| *duration)
+            .map(|(channel_id, _)| channel_id);
+
+        let mut members = self.stats_service.leaderboard(guild_id, period, usize::MAX).await?;
+        let voice_time = members.iter().map(|entry| entry.stats.voice_time).sum();
+        members.truncate(DIGEST_TOP_MEMBERS);
+
+        Ok(Digest {
+            calls: rooms.len(),
+            voice_time,
+            busiest_channel,
+            top_members: members,
+        })
+    }
+}
+
+// the latest scheduled time at or before `now`; weekly digests fall on mondays.
+fn last_occurrence<T: TimeZone>(now: DateTime<T>, hour: u32, frequency: DigestFrequency) -> Option<DateTime<Utc>> {
+    let mut date = now.date_naive();
+    // two weeks are plenty even when a daylight saving gap swallows the hour.
+    for _ in 0..14 {
+        let on_schedule = frequency != DigestFrequency::Weekly || date.weekday() == Weekday::Mon;
+        let at = date.and_hms_opt(hour, 0, 0)
+            .and_then(|naive| now.timezone().from_local_datetime(&naive).earliest());
+        if on_schedule && let Some(at) = at && at <= now {
+            return Some(at.with_timezone(&Utc))
+        }
+        date = date.pred_opt()?;
+    }
+    None
+}
@@ -0,0 +1,189 @@
+use crate::model::{Activity, Participant, VoiceStateFlags};
+use crate::service::report::RoomDTO;
+use crate::storage::{StoredActivity, StoredRoom};
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::Serialize;
+use serenity::all::{ChannelId, GuildId, UserId};
+use std::collections::HashMap;
+use std::fmt::Write;
+use tokio::time::Instant;
+
+// Machine-readable form of a room; times are RFC 3339 plus seconds since the room was created.
+#[derive(Debug, Serialize)]
+pub struct RoomExport {
+    pub guild_id: GuildId,
+    pub channel_id: ChannelId,
+    pub started_at: String,
+    pub exported_at: String,
+    pub participants: Vec<ParticipantExport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ParticipantExport {
+    pub user_id: UserId,
+    pub name: String,
+    pub total_secs: f64,
+    pub segments: Vec<SegmentExport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SegmentExport {
+    pub start: String,
+    pub end: Option<String>,
+    pub start_offset_secs: f64,
+    pub end_offset_secs: Option<f64>,
+    #[serde(flatten)]
+    pub flags: VoiceStateFlags,
+}
+
+impl RoomExport {
+    pub fn from_room(now: Instant, room: &RoomDTO) -> Self {
+        let started_at = *room.timestamp;
+        let to_datetime = |instant: Instant| started_at + TimeDelta::from_std(instant - room.created_at).unwrap_or(TimeDelta::zero());
+
+        RoomExport {
+            guild_id: room.guild_id,
+            channel_id: room.channel_id,
+            started_at: started_at.to_rfc3339(),
+            exported_at: to_datetime(now).to_rfc3339(),
+            participants: room.participants.iter()
+                .map(|participant| ParticipantExport::from_participant(now, room.created_at, participant, &to_datetime))
+                .collect(),
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<Vec<u8>> {
+        serde_json::to_vec_pretty(self)
+    }
+}
+
+impl ParticipantExport {
+    fn from_participant(now: Instant, created_at: Instant, participant: &Participant, to_datetime: &impl Fn(Instant) -> DateTime<Utc>) -> Self {
+        ParticipantExport {
+            user_id: participant.user_id(),
+            name: participant.name().to_string(),
+            total_secs: participant.calculate_duration(now).as_secs_f64(),
+            segments: participant.history().iter()
+                .map(|activity| SegmentExport::from_activity(created_at, activity, to_datetime))
+                .collect(),
+        }
+    }
+}
+
+impl SegmentExport {
+    fn from_activity(created_at: Instant, activity: &Activity, to_datetime: &impl Fn(Instant) -> DateTime<Utc>) -> Self {
+        SegmentExport {
+            start: to_datetime(activity.start()).to_rfc3339(),
+            end: activity.end().map(|end| to_datetime(end).to_rfc3339()),
+            start_offset_secs: (activity.start() - created_at).as_secs_f64(),
+            end_offset_secs: activity.end().map(|end| (end - created_at).as_secs_f64()),
+            flags: activity.flags(),
+        }
+    }
+}
+
+const CSV_HEADER: &str = "channel_id,user_id,name,start,end,duration_secs,muted,deafened,sharing_screen,video,audience";
+
+// one row per activity segment, for attendance sheets; open segments have an empty end.
+pub fn activities_to_csv(activities: &[StoredActivity]) -> Vec<u8> {
+    let mut csv = String::from(CSV_HEADER);
+    csv.push_str("\r\n");
+
+    let rfc3339 = |millis: i64| DateTime::<Utc>::from_timestamp_millis(millis).map_or(String::new(), |time| time.to_rfc3339());
+    for activity in activities {
+        let flags = activity.flags;
+        let _ = write!(
+            csv,
+            "{},{},{},{},{},{},{},{},{},{},{}\r\n",
+            activity.channel_id,
+            activity.user_id,
+            csv_field(&activity.name),
+            rfc3339(activity.started_at),
+            activity.ended_at.map_or(String::new(), rfc3339),
+            activity.ended_at.map_or(String::new(), |ended_at| ((ended_at - activity.started_at).max(0) / 1000).to_string()),
+            flags.is_muted,
+            flags.is_deafened,
+            flags.is_sharing_screen,
+            flags.is_video,
+            flags.is_audience,
+        );
+    }
+    csv.into_bytes()
+}
+
+// quotes fields containing separators, as RFC 4180 describes.
+// names that spreadsheets would read as a formula are prefixed with a quote to be kept as text.
+fn csv_field(value: &str) -> String {
+    let value = match value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        true => format!("'{}", value),
+        false => value.to_string(),
+    };
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+// iCalendar lines should not exceed this many octets.
+const ICS_LINE_LIMIT: usize = 75;
+
+// one VEVENT per room with its participants as attendees; ongoing rooms end at `now_millis`.
+pub fn rooms_to_ics(guild_id: GuildId, rooms: &[StoredRoom], activities: &[StoredActivity], now_millis: i64) -> Vec<u8> {
+    let mut attendees: HashMap<i64, Vec<(UserId, &str)>> = HashMap::new();
+    for activity in activities {
+        let room_attendees = attendees.entry(activity.room_id).or_default();
+        if !room_attendees.iter().any(|(user_id, _)| *user_id == activity.user_id) {
+            room_attendees.push((activity.user_id, &activity.name));
+        }
+    }
+
+    let mut lines = vec![
+        String::from("BEGIN:VCALENDAR"),
+        String::from("VERSION:2.0"),
+        String::from("PRODID:-//ringring-rs//calls//EN"),
+    ];
+    for room in rooms {
+        lines.push(String::from("BEGIN:VEVENT"));
+        lines.push(format!("UID:room-{}@ringring-rs", room.id));
+        lines.push(format!("DTSTAMP:{}", ics_time(now_millis)));
+        lines.push(format!("DTSTART:{}", ics_time(room.started_at)));
+        lines.push(format!("DTEND:{}", ics_time(room.ended_at.unwrap_or(now_millis))));
+        lines.push(String::from("SUMMARY:Voice call"));
+        lines.push(format!("URL:https://discord.com/channels/{}/{}", guild_id, room.channel_id));
+        for (user_id, name) in attendees.get(&room.id).into_iter().flatten() {
+            lines.push(format!("ATTENDEE;CN=\"{}\":https://discord.com/users/{}", ics_param(name), user_id));
+        }
+        lines.push(String::from("END:VEVENT"));
+    }
+    lines.push(String::from("END:VCALENDAR"));
+
+    let mut ics = String::new();
+    for line in lines {
+        fold_line(&mut ics, &line);
+    }
+    ics.into_bytes()
+}
+
+fn ics_time(millis: i64) -> String {
+    DateTime::<Utc>::from_timestamp_millis(millis).map_or(String::new(), |time| time.format("%Y%m%dT%H%M%SZ").to_string())
+}
+
+// quoted parameter values cannot contain quotes or line breaks at all.
+fn ics_param(value: &str) -> String {
+    value.chars().filter(|c| !matches!(c, '"' | '\r' | '\n')).collect()
+}
+
+// continues long lines on the next one, starting with a space, without splitting a character.
+fn fold_line(ics: &mut String, line: &str) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > ICS_LINE_LIMIT {
+            ics.push_str("\r\n ");
+            width = 1;
+        }
+        ics.push(c);
+        width += c.len_utf8();
+    }
+    ics.push_str("\r\n");
+}
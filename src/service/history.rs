@@ -0,0 +1,69 @@
+use crate::service::export::{activities_to_csv, rooms_to_ics};
+use crate::service::report::{RenderedReport, ReportService, ReportServiceError, RoomDTO};
+use crate::storage::{SqliteStorage, StorageError, StoredRoom};
+use chrono::Utc;
+use serenity::all::{ChannelId, GuildId};
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::time::Instant;
+
+#[derive(Debug, Error)]
+pub enum HistoryError {
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+
+    #[error(transparent)]
+    Report(#[from] ReportServiceError),
+}
+
+pub type HistoryResult<T> = Result<T, HistoryError>;
+
+// past calls as recorded in the storage.
+pub struct HistoryService {
+    storage: Arc<SqliteStorage>,
+    report_service: Arc<ReportService>,
+}
+
+impl HistoryService {
+    pub fn new(storage: Arc<SqliteStorage>, report_service: Arc<ReportService>) -> Self {
+        HistoryService { storage, report_service }
+    }
+
+    // calls of the guild started in [since, until), newest first; optionally limited to one channel.
+    pub async fn list_calls(&self, guild_id: GuildId, channel_id: Option<ChannelId>, since: i64, until: i64) -> HistoryResult<Vec<StoredRoom>> {
+        let mut rooms = self.storage.load_rooms(guild_id, since, until).await?;
+        if let Some(channel_id) = channel_id {
+            rooms.retain(|room| room.channel_id == channel_id);
+        }
+        Ok(rooms)
+    }
+
+    // activity segments of calls overlapping [since, until) as CSV; optionally limited to one channel.
+    pub async fn export_csv(&self, guild_id: GuildId, channel_id: Option<ChannelId>, since: i64, until: i64) -> HistoryResult<Vec<u8>> {
+        let mut activities = self.storage.load_activities(guild_id, None, since, until).await?;
+        if let Some(channel_id) = channel_id {
+            activities.retain(|activity| activity.channel_id == channel_id);
+        }
+        Ok(activities_to_csv(&activities))
+    }
+
+    // calls started in [since, until) as iCalendar events; optionally limited to one channel.
+    pub async fn export_ics(&self, guild_id: GuildId, channel_id: Option<ChannelId>, since: i64, until: i64) -> HistoryResult<Vec<u8>> {
+        let rooms = self.list_calls(guild_id, channel_id, since, until).await?;
+        // calls may last beyond `until`, so every later activity is needed for their attendees.
+        let activities = self.storage.load_activities(guild_id, None, since, i64::MAX).await?;
+        Ok(rooms_to_ics(guild_id, &rooms, &activities, Utc::now().timestamp_millis()))
+    }
+
+    // renders the final report of a past call again; None if the guild has no such call.
+    pub async fn render_call(&self, guild_id: GuildId, room_id: i64) -> HistoryResult<Option<RenderedReport>> {
+        let (room, ended_at) = match self.storage.load_room(guild_id, room_id, Instant::now()).await? {
+            Some(loaded) => loaded,
+            None => return Ok(None),
+        };
+
+        let room_dto = RoomDTO::from_room(&room);
+        let report = self.report_service.render_room_report(ended_at, &room_dto, false, None).await?;
+        Ok(Some(report))
+    }
+}
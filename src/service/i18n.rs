@@ -0,0 +1,211 @@
+// a key/value catalog for user facing texts; keys missing in a catalog fall back to English.
+
+use chrono::Weekday;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    English,
+    Japanese,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextKey {
+    OnCall,
+    // takes `{number}`
+    Session,
+    // takes `{channel}`
+    RoomActiveOn,
+    Start,
+    Elapsed,
+    History,
+    LeftServer,
+    DailyDigest,
+    WeeklyDigest,
+    Calls,
+    VoiceHours,
+    BusiestChannel,
+    NoActivity,
+    Active,
+    Muted,
+    Deafened,
+    Streaming,
+    // takes `{count}`
+    MoreParticipants,
+    ConnectedNow,
+    PeakParticipants,
+    Capacity,
+    PersonHours,
+    AverageConcurrency,
+    // takes `{event}`
+    EventAttendance,
+    // takes `{percent}`
+    NoAttendance,
+    Refresh,
+    Details,
+    StopTracking,
+    // takes `{count}`
+    Sessions,
+    TrackingPaused,
+    // takes `{duration}` and `{count}`
+    AltTimeline,
+    // audit lines take `{channel}`, and `{user}` when about a member.
+    AuditCallStarted,
+    AuditCallEnded,
+    // takes `{channel}` and `{to}`
+    AuditCallMoved,
+    AuditJoined,
+    AuditLeft,
+    AuditStartedStreaming,
+    AuditStoppedStreaming,
+    AuditStartedVideo,
+    AuditStoppedVideo,
+}
+
+const ENGLISH: &[(TextKey, &str)] = &[
+    (TextKey::OnCall, "On call"),
+    (TextKey::Session, "session #{number}"),
+    (TextKey::RoomActiveOn, "Room is active on {channel}"),
+    (TextKey::Start, "start"),
+    (TextKey::Elapsed, "elapsed"),
+    (TextKey::History, "history"),
+    (TextKey::LeftServer, "left the server"),
+    (TextKey::DailyDigest, "Daily digest"),
+    (TextKey::WeeklyDigest, "Weekly digest"),
+    (TextKey::Calls, "calls"),
+    (TextKey::VoiceHours, "voice hours"),
+    (TextKey::BusiestChannel, "busiest channel"),
+    (TextKey::NoActivity, "No voice activity was recorded in this period."),
+    (TextKey::Active, "active"),
+    (TextKey::Muted, "muted"),
+    (TextKey::Deafened, "deafened"),
+    (TextKey::Streaming, "streaming"),
+    (TextKey::MoreParticipants, "+{count} more"),
+    (TextKey::ConnectedNow, "in the call now"),
+    (TextKey::PeakParticipants, "peak"),
+    (TextKey::Capacity, "capacity"),
+    (TextKey::PersonHours, "person-hours"),
+    (TextKey::AverageConcurrency, "avg. concurrency"),
+    (TextKey::EventAttendance, "attended {event}"),
+    (TextKey::NoAttendance, "No one was present for {percent}% of the event."),
+    (TextKey::Refresh, "Refresh"),
+    (TextKey::Details, "Details"),
+    (TextKey::StopTracking, "Stop tracking"),
+    (TextKey::Sessions, "{count} sessions"),
+    (TextKey::TrackingPaused, "tracking paused"),
+    (TextKey::AltTimeline, "Timeline of a {duration} voice call with {count} participants."),
+    (TextKey::AuditCallStarted, "📞 a call started in {channel}"),
+    (TextKey::AuditCallEnded, "📴 the call in {channel} ended"),
+    (TextKey::AuditCallMoved, "🔀 the call moved from {channel} to {to}"),
+    (TextKey::AuditJoined, "➡️ {user} joined {channel}"),
+    (TextKey::AuditLeft, "⬅️ {user} left {channel}"),
+    (TextKey::AuditStartedStreaming, "🖥️ {user} started streaming in {channel}"),
+    (TextKey::AuditStoppedStreaming, "🖥️ {user} stopped streaming in {channel}"),
+    (TextKey::AuditStartedVideo, "📷 {user} turned on their camera in {channel}"),
+    (TextKey::AuditStoppedVideo, "📷 {user} turned off their camera in {channel}"),
+];
+
+const JAPANESE: &[(TextKey, &str)] = &[
+    (TextKey::OnCall, "通話中"),
+    (TextKey::Session, "第{number}部"),
+    (TextKey::RoomActiveOn, "{channel} で通話しています"),
+    (TextKey::Start, "開始"),
+    (TextKey::Elapsed, "経過時間"),
+    (TextKey::History, "参加履歴"),
+    (TextKey::LeftServer, "サーバー退出済み"),
+    (TextKey::DailyDigest, "日次まとめ"),
+    (TextKey::WeeklyDigest, "週次まとめ"),
+    (TextKey::Calls, "通話数"),
+    (TextKey::VoiceHours, "通話時間"),
+    (TextKey::BusiestChannel, "最も賑わったチャンネル"),
+    (TextKey::NoActivity, "この期間の通話記録はありません。"),
+    (TextKey::Active, "通話中"),
+    (TextKey::Muted, "ミュート"),
+    (TextKey::Deafened, "スピーカーミュート"),
+    (TextKey::Streaming, "画面共有"),
+    (TextKey::MoreParticipants, "他{count}人"),
+    (TextKey::ConnectedNow, "参加中のメンバー"),
+    (TextKey::PeakParticipants, "最大同時接続"),
+    (TextKey::Capacity, "定員"),
+    (TextKey::PersonHours, "延べ時間"),
+    (TextKey::AverageConcurrency, "平均同時接続"),
+    (TextKey::EventAttendance, "{event} の出席者"),
+    (TextKey::NoAttendance, "イベントの{percent}%以上参加したメンバーはいません。"),
+    (TextKey::Refresh, "更新"),
+    (TextKey::Details, "詳細"),
+    (TextKey::StopTracking, "記録を終了"),
+    (TextKey::Sessions, "{count}回参加"),
+    (TextKey::TrackingPaused, "記録停止中"),
+    (TextKey::AltTimeline, "{duration}の通話のタイムライン、参加者{count}人。"),
+    (TextKey::AuditCallStarted, "📞 {channel} で通話が始まりました"),
+    (TextKey::AuditCallEnded, "📴 {channel} の通話が終わりました"),
+    (TextKey::AuditCallMoved, "🔀 通話が {channel} から {to} に移動しました"),
+    (TextKey::AuditJoined, "➡️ {user} が {channel} に参加しました"),
+    (TextKey::AuditLeft, "⬅️ {user} が {channel} から退出しました"),
+    (TextKey::AuditStartedStreaming, "🖥️ {user} が {channel} で画面共有を始めました"),
+    (TextKey::AuditStoppedStreaming, "🖥️ {user} が {channel} で画面共有を終えました"),
+    (TextKey::AuditStartedVideo, "📷 {user} が {channel} でカメラをオンにしました"),
+    (TextKey::AuditStoppedVideo, "📷 {user} が {channel} でカメラをオフにしました"),
+];
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::English, Language::Japanese];
+
+    // accepts locale tags such as "ja" or "ja-JP"; unknown ones fall back to English.
+    pub fn from_locale(locale: &str) -> Language {
+        let primary = locale.split(['-', '_']).next().unwrap_or_default();
+        Self::ALL
+            .into_iter()
+            .find(|language| language.code().eq_ignore_ascii_case(primary))
+            .unwrap_or(Language::English)
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::Japanese => "ja",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::English => "English",
+            Language::Japanese => "日本語",
+        }
+    }
+
+    // short name of the day, as labels of day rows.
+    pub fn weekday(&self, weekday: Weekday) -> &'static str {
+        match (self, weekday) {
+            (Language::English, Weekday::Mon) => "Mon",
+            (Language::English, Weekday::Tue) => "Tue",
+            (Language::English, Weekday::Wed) => "Wed",
+            (Language::English, Weekday::Thu) => "Thu",
+            (Language::English, Weekday::Fri) => "Fri",
+            (Language::English, Weekday::Sat) => "Sat",
+            (Language::English, Weekday::Sun) => "Sun",
+            (Language::Japanese, Weekday::Mon) => "月",
+            (Language::Japanese, Weekday::Tue) => "火",
+            (Language::Japanese, Weekday::Wed) => "水",
+            (Language::Japanese, Weekday::Thu) => "木",
+            (Language::Japanese, Weekday::Fri) => "金",
+            (Language::Japanese, Weekday::Sat) => "土",
+            (Language::Japanese, Weekday::Sun) => "日",
+        }
+    }
+
+    fn catalog(&self) -> &'static [(TextKey, &'static str)] {
+        match self {
+            Language::English => ENGLISH,
+            Language::Japanese => JAPANESE,
+        }
+    }
+
+    pub fn text(&self, key: TextKey) -> &'static str {
+        let lookup = |catalog: &'static [(TextKey, &'static str)]| {
+            catalog.iter().find(|(k, _)| *k == key).map(|(_, text)| *text)
+        };
+        lookup(self.catalog())
+            .or_else(|| lookup(ENGLISH))
+            .expect("every key must have an English text")
+    }
+}
@@ -0,0 +1,61 @@
+use crate::storage::SqliteStorage;
+use chrono::Utc;
+use serenity::all::GuildId;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use tracing::{error, info};
+
+// lets instances sharing a database take turns: only the holder of a guild's lease posts for it, others stand by.
+pub struct LeaseService {
+    storage: Arc<SqliteStorage>,
+    holder: String,
+    ttl: Duration,
+    // recent answers, so the lease is renewed a few times per ttl instead of on every post.
+    checked: Mutex<HashMap<GuildId, (bool, Instant)>>,
+}
+
+impl LeaseService {
+    pub fn new(storage: Arc<SqliteStorage>, holder: String, ttl: Duration) -> Self {
+        LeaseService {
+            storage,
+            holder,
+            ttl,
+            checked: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // takes over the lease once the previous holder let it expire; a storage error means standing by.
+    pub async fn is_active(&self, guild_id: GuildId) -> bool {
+        let now = Instant::now();
+        let previous = self.checked.lock().await.get(&guild_id).copied();
+        if let Some((active, _)) = previous.filter(|(_, checked_at)| now < *checked_at + self.ttl / 3) {
+            return active
+        }
+
+        let active = match self.storage.try_acquire_lease(guild_id, &self.holder, Utc::now().timestamp_millis(), self.ttl).await {
+            Ok(active) => active,
+            Err(err) => {
+                error!("Failed to renew the report lease of guild {}: {}", guild_id, err);
+                false
+            }
+        };
+        match (previous.map(|(active, _)| active), active) {
+            (Some(false) | None, true) => info!(%guild_id, "became the active reporter"),
+            (Some(true), false) => info!(%guild_id, "lost the report lease, standing by"),
+            _ => {},
+        }
+        self.checked.lock().await.insert(guild_id, (active, now));
+        active
+    }
+
+    // hands every lease over right away, e.g. on shutdown.
+    pub async fn release_all(&self) {
+        self.checked.lock().await.clear();
+        if let Err(err) = self.storage.release_leases(&self.holder).await {
+            error!("Failed to release report leases: {}", err);
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use crate::model::{Room, RoomManager};
+use crate::service::report::{ReportService, RoomDTO};
+use serenity::all::{Cache, Http, UserId};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{watch, Mutex};
+use tokio::task::JoinHandle;
+use tokio::time::{self, Instant, MissedTickBehavior};
+use tracing::{debug, error, info, warn};
+
+pub struct MaintenanceService {
+    room_manager: Arc<RoomManager>,
+    report_service: Arc<ReportService>,
+    cleanup_interval: Duration,
+    // voice states to check rooms against; without it ghosts are left alone.
+    cache: Option<Arc<Cache>>,
+}
+
+impl MaintenanceService {
+    pub fn new(room_manager: Arc<RoomManager>, report_service: Arc<ReportService>, cleanup_interval: Duration) -> Self {
+        MaintenanceService {
+            room_manager,
+            report_service,
+            cleanup_interval,
+            cache: None,
+        }
+    }
+
+    pub fn with_cache(mut self, cache: Arc<Cache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    // runs cleanup periodically until `shutdown` changes or its sender is dropped.
+    pub fn spawn(self: Arc<Self>, http: Arc<Http>, mut shutdown: watch::Receiver<bool>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = time::interval(self.cleanup_interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            interval.tick().await;
+
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => self.cleanup(&http).await,
+                    _ = shutdown.changed() => break,
+                }
+            }
+            debug!("cleanup loop stopped");
+        })
+    }
+
+    pub async fn cleanup(&self, http: &Http) {
+        self.disconnect_ghosts().await;
+
+        let now = Instant::now();
+        let removed = match self.room_manager.cleanup(now).await {
+            Ok(removed) => removed,
+            Err(err) => {
+                error!("Error during room cleanup: {:?}", err);
+                Vec::new()
+            },
+        };
+
+        let mut removed_channel_ids = Vec::with_capacity(removed.len());
+        for room in &removed {
+            removed_channel_ids.push(room.lock().await.channel_id());
+        }
+        self.send_final_reports(http, now, removed).await;
+
+        let open_channel_ids = self.room_manager.open_channel_ids().await;
+        // a final report that failed would otherwise leave its track behind for good.
+        self.report_service.prune_tracks(now, &removed_channel_ids, &open_channel_ids).await;
+    }
+
+    // disconnects participants without a matching voice state, e.g. after a missed disconnect event.
+    pub async fn disconnect_ghosts(&self) {
+        let cache = match &self.cache {
+            Some(cache) => cache,
+            None => return,
+        };
+
+        let now = Instant::now();
+        for room in self.room_manager.get_all_rooms().await {
+            let (guild_id, channel_id, connected) = {
+                let room = room.lock().await;
+                let connected: Vec<UserId> = room.participants().iter()
+                    .filter(|participant| participant.is_connected())
+                    .map(|participant| participant.user_id())
+                    .collect();
+                (room.guild_id(), room.channel_id(), connected)
+            };
+
+            let ghosts: Vec<UserId> = match cache.guild(guild_id) {
+                Some(guild) => connected.into_iter()
+                    .filter(|user_id| guild.voice_states.get(user_id).and_then(|state| state.channel_id) != Some(channel_id))
+                    .collect(),
+                // an unavailable guild says nothing about its voice states.
+                None => continue,
+            };
+
+            for user_id in ghosts {
+                warn!("user {} has no voice state in channel {}, disconnecting the ghost", user_id, channel_id);
+                if let Err(err) = self.room_manager.handle_disconnect_event(now, channel_id, user_id).await {
+                    error!("Failed to disconnect ghost participant: {:?}", err);
+                }
+            }
+        }
+    }
+
+    // closes every ongoing room and sends its final report; used on shutdown.
+    pub async fn finalize_all(&self, http: &Http) {
+        let now = Instant::now();
+        let closed = self.room_manager.close_all(now).await;
+        info!("finalizing {} ongoing rooms", closed.len());
+        self.send_final_reports(http, now, closed).await;
+    }
+
+    async fn send_final_reports(&self, http: &Http, now: Instant, rooms: Vec<Arc<Mutex<Room>>>) {
+        for room in rooms {
+            let room_dto = {
+                let room = room.lock().await;
+                RoomDTO::from_room(&room)
+            };
+
+            if let Err(err) = self.report_service.send_room_report(http, now, &room_dto, false).await {
+                // log error and just ignore
+                // it may be better if there is retry behavior.
+                error!("Failed to send final report: {}", err);
+            }
+        }
+    }
+}
@@ -2,4 +2,20 @@ pub mod renderer;
 pub mod report;
 pub mod tracker;
 pub mod asset;
-
+pub mod config;
+pub mod digest;
+pub mod maintenance;
+pub mod scheduler;
+pub mod stats;
+pub mod export;
+pub mod history;
+pub mod i18n;
+pub mod privacy;
+pub mod nickname;
+pub mod lease;
+pub mod room_sync;
+pub mod webhook;
+pub mod audit;
+pub mod template;
+#[cfg(feature = "voice-receive")]
+pub mod speaking;
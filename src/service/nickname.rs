@@ -0,0 +1,57 @@
+use crate::storage::{SqliteStorage, StorageResult};
+use moka::future::Cache;
+use serenity::all::{GuildId, UserId};
+use std::sync::Arc;
+use tracing::error;
+
+pub const MAX_NICKNAME_LENGTH: usize = 32;
+
+// labels members chose for their reports, preferred over their display names.
+pub struct NicknameService {
+    storage: Arc<SqliteStorage>,
+    cache: Cache<(GuildId, UserId), Option<String>>,
+}
+
+impl NicknameService {
+    pub fn new(storage: Arc<SqliteStorage>) -> Self {
+        NicknameService {
+            storage,
+            cache: Cache::new(4096),
+        }
+    }
+
+    pub async fn nickname(&self, guild_id: GuildId, user_id: UserId) -> Option<String> {
+        let result = self.cache.try_get_with((guild_id, user_id), async {
+            self.storage.load_nickname(guild_id, user_id).await
+        }).await;
+
+        match result {
+            Ok(nickname) => nickname,
+            Err(err) => {
+                error!("Failed to load nickname of user {}: {}", user_id, err);
+                None
+            }
+        }
+    }
+
+    // the nickname if the member chose one, else the given display name.
+    pub async fn resolve(&self, guild_id: GuildId, user_id: UserId, display_name: String) -> String {
+        self.nickname(guild_id, user_id).await.unwrap_or(display_name)
+    }
+
+    // None goes back to the display name.
+    pub async fn set_nickname(&self, guild_id: GuildId, user_id: UserId, nickname: Option<String>) -> StorageResult<()> {
+        self.storage.save_nickname(guild_id, user_id, nickname.as_deref()).await?;
+        self.cache.insert((guild_id, user_id), nickname).await;
+        Ok(())
+    }
+}
+
+// trims the name and rejects empty or overlong names and control characters.
+pub fn sanitize_nickname(name: &str) -> Option<String> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NICKNAME_LENGTH || name.chars().any(char::is_control) {
+        return None
+    }
+    Some(name.to_string())
+}
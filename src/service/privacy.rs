@@ -0,0 +1,39 @@
+use crate::storage::{SqliteStorage, StorageResult};
+use moka::future::Cache;
+use serenity::all::UserId;
+use std::sync::Arc;
+use tracing::error;
+
+pub struct PrivacyService {
+    storage: Arc<SqliteStorage>,
+    cache: Cache<UserId, bool>,
+}
+
+impl PrivacyService {
+    pub fn new(storage: Arc<SqliteStorage>) -> Self {
+        PrivacyService {
+            storage,
+            cache: Cache::new(4096),
+        }
+    }
+
+    pub async fn is_opted_out(&self, user_id: UserId) -> bool {
+        let result = self.cache.try_get_with(user_id, async {
+            self.storage.is_opted_out(user_id).await
+        }).await;
+
+        match result {
+            Ok(opted_out) => opted_out,
+            Err(err) => {
+                error!("Failed to load opt-out of user {}: {}", user_id, err);
+                false
+            }
+        }
+    }
+
+    pub async fn set_opted_out(&self, user_id: UserId, opted_out: bool) -> StorageResult<()> {
+        self.storage.set_opted_out(user_id, opted_out).await?;
+        self.cache.insert(user_id, opted_out).await;
+        Ok(())
+    }
+}
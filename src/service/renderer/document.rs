@@ -0,0 +1,324 @@
+use crate::service::i18n::Language;
+use crate::service::renderer::draw::{draw_avatar, TextAnchor, TextRenderer};
+use crate::service::renderer::encoder::encode_jpeg;
+use crate::service::renderer::leaderboard::LeaderboardRow;
+use crate::service::renderer::theme::Theme;
+use crate::service::renderer::timeline::{TimelineRenderer, TimelineRendererResult};
+use crate::service::stats::MonthlySummary;
+use chrono::{Datelike, Days, NaiveDate, TimeDelta, Weekday};
+use std::sync::Arc;
+use std::time::Duration;
+use tiny_skia::{Color, Paint, Pixmap, Rect, Transform};
+
+// pages are drawn at 150 dpi on a4, which is 595.28 by 841.89 points in the pdf.
+const PAGE_WIDTH: u32 = 1240;
+const PAGE_HEIGHT: u32 = 1754;
+const PAGE_WIDTH_PT: &str = "595.28";
+const PAGE_HEIGHT_PT: &str = "841.89";
+const MARGIN: f32 = 90.0;
+const HEADER_HEIGHT: f32 = 160.0;
+const TITLE_FONT_SIZE: f32 = 56.0;
+const HEADING_FONT_SIZE: f32 = 40.0;
+const FONT_SIZE: f32 = 28.0;
+const STAT_FONT_SIZE: f32 = 52.0;
+const FOOTER_FONT_SIZE: f32 = 22.0;
+const ACCENT_HEIGHT: f32 = 4.0;
+const STAT_HEIGHT: f32 = 150.0;
+const ROW_HEIGHT: f32 = 90.0;
+const ROWS_PER_PAGE: usize = 15;
+const RANK_WIDTH: f32 = 70.0;
+const AVATAR_SIZE: f32 = 72.0;
+const NAME_WIDTH: f32 = 330.0;
+const DURATION_WIDTH: f32 = 130.0;
+const BAR_HEIGHT_RATIO: f32 = 0.4;
+const MAX_NAME_CHARS: usize = 18;
+const WEEKS_PER_PAGE: usize = 3;
+const WEEK_HEADING_HEIGHT: f32 = 70.0;
+const DAY_LABEL_HEIGHT: f32 = 90.0;
+// share of a day's column left empty between bars.
+const BAR_GAP_RATIO: f32 = 0.3;
+
+const WEEKDAYS: [Weekday; 7] = [Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri, Weekday::Sat, Weekday::Sun];
+
+// draws a month of a guild as a multi-page pdf: cover stats, the leaderboard and one chart per week.
+pub struct DocumentRenderer {
+    text_renderer: Arc<TextRenderer>,
+}
+
+impl DocumentRenderer {
+    pub fn new(text_renderer: Arc<TextRenderer>) -> DocumentRenderer {
+        DocumentRenderer { text_renderer }
+    }
+
+    // `rows` are the leaderboard of the summary with avatars and colors resolved.
+    pub fn generate_pdf(&self, title: &str, summary: &MonthlySummary, rows: &[LeaderboardRow], language: Language, theme: &Theme, quality: u8) -> TimelineRendererResult<Vec<u8>> {
+        let mut pages = vec![self.draw_cover(title, summary, theme)];
+
+        let longest = rows.iter().map(|row| row.duration).max().unwrap_or(Duration::ZERO);
+        for (i, chunk) in rows.chunks(ROWS_PER_PAGE).enumerate() {
+            pages.push(self.draw_leaderboard(title, chunk, i * ROWS_PER_PAGE, longest, theme));
+        }
+
+        let weeks = weeks_of(summary);
+        let busiest = summary.days.iter().copied().max().unwrap_or(Duration::ZERO);
+        for chunk in weeks.chunks(WEEKS_PER_PAGE) {
+            pages.push(self.draw_weeks(title, chunk, busiest, language, theme));
+        }
+
+        let count = pages.len();
+        let mut encoded = Vec::with_capacity(count);
+        for (i, mut page) in pages.into_iter().enumerate() {
+            let footer = format!("{} / {}", i + 1, count);
+            self.text_renderer.draw_text(&mut page, &footer, FOOTER_FONT_SIZE, (PAGE_WIDTH as f32 - MARGIN, PAGE_HEIGHT as f32 - MARGIN / 2.0), theme.grid, TextAnchor::End);
+            encoded.push(EncodedPage {
+                width: page.width(),
+                height: page.height(),
+                jpeg: encode_jpeg(&page, quality)?,
+            });
+        }
+
+        Ok(write_pdf(&encoded))
+    }
+
+    fn new_page(&self, title: &str, heading: &str, theme: &Theme) -> Pixmap {
+        let mut page = Pixmap::new(PAGE_WIDTH, PAGE_HEIGHT).expect("invalid pixmap size");
+        page.fill(opaque(theme.background));
+
+        self.text_renderer.draw_text(&mut page, title, TITLE_FONT_SIZE, (MARGIN, MARGIN + TITLE_FONT_SIZE), theme.text, TextAnchor::Start);
+        self.text_renderer.draw_text(&mut page, heading, HEADING_FONT_SIZE, (MARGIN, MARGIN + TITLE_FONT_SIZE + HEADING_FONT_SIZE * 1.5), theme.text, TextAnchor::Start);
+        fill_rect(&mut page, MARGIN, MARGIN + HEADER_HEIGHT - ACCENT_HEIGHT * 4.0, PAGE_WIDTH as f32 - MARGIN * 2.0, ACCENT_HEIGHT, accent());
+        page
+    }
+
+    fn draw_cover(&self, title: &str, summary: &MonthlySummary, theme: &Theme) -> Pixmap {
+        let mut page = self.new_page(title, &summary.month.format("%B %Y").to_string(), theme);
+
+        let average = summary.voice_time / summary.days.len().max(1) as u32;
+        let busiest = summary.busiest_day()
+            .map_or(String::from("-"), |(date, duration)| format!("{} ({})", date.format("%b %-d"), format_duration(duration)));
+        let stats = [
+            ("Voice time", format_duration(summary.voice_time)),
+            ("Calls", summary.calls.to_string()),
+            ("Sessions", summary.sessions.to_string()),
+            ("Active members", summary.members.to_string()),
+            ("Busiest day", busiest),
+            ("Daily average", format_duration(average)),
+        ];
+
+        for (i, (label, value)) in stats.iter().enumerate() {
+            let top = MARGIN + HEADER_HEIGHT + i as f32 * STAT_HEIGHT;
+            self.text_renderer.draw_text(&mut page, label, FONT_SIZE, (MARGIN, top + FONT_SIZE * 2.0), theme.grid, TextAnchor::Start);
+            self.text_renderer.draw_text(&mut page, value, STAT_FONT_SIZE, (MARGIN, top + FONT_SIZE * 2.0 + STAT_FONT_SIZE * 1.4), theme.text, TextAnchor::Start);
+        }
+        page
+    }
+
+    // `first_rank` counts the rows on earlier pages.
+    fn draw_leaderboard(&self, title: &str, rows: &[LeaderboardRow], first_rank: usize, longest: Duration, theme: &Theme) -> Pixmap {
+        let mut page = self.new_page(title, "Leaderboard", theme);
+
+        let longest = longest.as_secs_f32().max(1.0);
+        let name_left = MARGIN + RANK_WIDTH + ROW_HEIGHT;
+        let bar_left = name_left + NAME_WIDTH;
+        let bar_max_width = PAGE_WIDTH as f32 - MARGIN - DURATION_WIDTH - bar_left;
+
+        for (i, row) in rows.iter().enumerate() {
+            let top = MARGIN + HEADER_HEIGHT + i as f32 * ROW_HEIGHT;
+            let center_y = top + ROW_HEIGHT / 2.0;
+            let baseline = center_y + FONT_SIZE / 3.0;
+
+            self.text_renderer.draw_text(&mut page, &format!("#{}", first_rank + i + 1), FONT_SIZE, (MARGIN + RANK_WIDTH / 2.0, baseline), theme.text, TextAnchor::Middle);
+            draw_avatar(&mut page, &row.avatar, MARGIN + RANK_WIDTH + ROW_HEIGHT / 2.0, center_y, AVATAR_SIZE);
+
+            let name: String = row.name.chars().take(MAX_NAME_CHARS).collect();
+            self.text_renderer.draw_text(&mut page, &name, FONT_SIZE, (name_left, baseline), theme.text, TextAnchor::Start);
+
+            let bar_width = bar_max_width * row.duration.as_secs_f32() / longest;
+            let bar_height = ROW_HEIGHT * BAR_HEIGHT_RATIO;
+            fill_rect(&mut page, bar_left, center_y - bar_height / 2.0, bar_width, bar_height, row.color);
+
+            self.text_renderer.draw_text(&mut page, &format_duration(row.duration), FONT_SIZE, (PAGE_WIDTH as f32 - MARGIN, baseline), theme.text, TextAnchor::End);
+        }
+        page
+    }
+
+    // bars share the scale of the busiest day, so weeks compare across pages.
+    fn draw_weeks(&self, title: &str, weeks: &[Week], busiest: Duration, language: Language, theme: &Theme) -> Pixmap {
+        let mut page = self.new_page(title, "Weekly activity", theme);
+
+        let busiest = busiest.as_secs_f32().max(1.0);
+        let block_height = (PAGE_HEIGHT as f32 - MARGIN * 2.0 - HEADER_HEIGHT) / WEEKS_PER_PAGE as f32;
+        let column_width = (PAGE_WIDTH as f32 - MARGIN * 2.0) / 7.0;
+        let bar_width = column_width * (1.0 - BAR_GAP_RATIO);
+
+        for (slot, week) in weeks.iter().enumerate() {
+            let top = MARGIN + HEADER_HEIGHT + slot as f32 * block_height;
+            let heading = match (week.first(), week.last()) {
+                (Some(first), Some(last)) => format!("{} - {}", first.format("%b %-d"), last.format("%b %-d")),
+                _ => continue,
+            };
+            self.text_renderer.draw_text(&mut page, &heading, FONT_SIZE, (MARGIN, top + FONT_SIZE * 1.5), theme.text, TextAnchor::Start);
+            self.text_renderer.draw_text(&mut page, &format_duration(week.total()), FONT_SIZE, (PAGE_WIDTH as f32 - MARGIN, top + FONT_SIZE * 1.5), theme.text, TextAnchor::End);
+
+            let chart_top = top + WEEK_HEADING_HEIGHT + FONT_SIZE * 1.5;
+            let chart_bottom = top + block_height - DAY_LABEL_HEIGHT;
+            fill_rect(&mut page, MARGIN, chart_bottom, PAGE_WIDTH as f32 - MARGIN * 2.0, 2.0, theme.grid);
+
+            for (column, (weekday, day)) in WEEKDAYS.iter().zip(week.days.iter()).enumerate() {
+                let center_x = MARGIN + column_width * (column as f32 + 0.5);
+                self.text_renderer.draw_text(&mut page, language.weekday(*weekday), FONT_SIZE, (center_x, chart_bottom + FONT_SIZE * 1.5), theme.text, TextAnchor::Middle);
+
+                let (date, duration) = match day {
+                    Some(day) => *day,
+                    None => continue,
+                };
+                self.text_renderer.draw_text(&mut page, &date.day().to_string(), FONT_SIZE, (center_x, chart_bottom + FONT_SIZE * 2.8), theme.grid, TextAnchor::Middle);
+                if duration.is_zero() {
+                    continue;
+                }
+
+                let bar_height = (chart_bottom - chart_top) * duration.as_secs_f32() / busiest;
+                fill_rect(&mut page, center_x - bar_width / 2.0, chart_bottom - bar_height, bar_width, bar_height, accent());
+                self.text_renderer.draw_text(&mut page, &format_duration(duration), FONT_SIZE * 0.8, (center_x, chart_bottom - bar_height - FONT_SIZE * 0.4), theme.text, TextAnchor::Middle);
+            }
+        }
+        page
+    }
+}
+
+// monday to sunday; days outside the month are None.
+struct Week {
+    days: [Option<(NaiveDate, Duration)>; 7],
+}
+
+impl Week {
+    fn first(&self) -> Option<NaiveDate> {
+        self.days.iter().flatten().next().map(|(date, _)| *date)
+    }
+
+    fn last(&self) -> Option<NaiveDate> {
+        self.days.iter().flatten().last().map(|(date, _)| *date)
+    }
+
+    fn total(&self) -> Duration {
+        self.days.iter().flatten().map(|(_, duration)| *duration).sum()
+    }
+}
+
+fn weeks_of(summary: &MonthlySummary) -> Vec<Week> {
+    let offset = summary.month.weekday().num_days_from_monday() as usize;
+    let cells = offset + summary.days.len();
+
+    (0..cells.div_ceil(7))
+        .map(|week| {
+            let mut days = [None; 7];
+            for (column, day) in days.iter_mut().enumerate() {
+                *day = (week * 7 + column).checked_sub(offset)
+                    .and_then(|index| Some((summary.month.checked_add_days(Days::new(index as u64))?, *summary.days.get(index)?)));
+            }
+            Week { days }
+        })
+        .collect()
+}
+
+fn format_duration(duration: Duration) -> String {
+    TimelineRenderer::format_time_delta(TimeDelta::from_std(duration).unwrap_or(TimeDelta::zero()))
+}
+
+// discord's blurple, readable on both themes.
+fn accent() -> Color {
+    Color::from_rgba8(88, 101, 242, 255)
+}
+
+// jpeg has no alpha, so a translucent theme background would turn out darker than intended.
+fn opaque(mut color: Color) -> Color {
+    color.set_alpha(1.0);
+    color
+}
+
+fn fill_rect(pixmap: &mut Pixmap, x: f32, y: f32, width: f32, height: f32, color: Color) {
+    if let Some(rect) = Rect::from_xywh(x, y, width, height) {
+        let mut paint = Paint::default();
+        paint.set_color(color);
+        pixmap.fill_rect(rect, &paint, Transform::identity(), None);
+    }
+}
+
+struct EncodedPage {
+    width: u32,
+    height: u32,
+    jpeg: Vec<u8>,
+}
+
+// a minimal pdf with one full-page image per page; DCTDecode takes the jpeg as it is.
+fn write_pdf(pages: &[EncodedPage]) -> Vec<u8> {
+    let mut pdf = PdfWriter::new();
+
+    // 1 is the catalog and 2 the page tree; each page then takes three objects: page, contents and image.
+    let kids: Vec<String> = (0..pages.len()).map(|i| format!("{} 0 R", 3 + i * 3)).collect();
+    pdf.object(b"<< /Type /Catalog /Pages 2 0 R >>");
+    pdf.object(format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages.len()).as_bytes());
+
+    for (i, page) in pages.iter().enumerate() {
+        let (contents_id, image_id) = (4 + i * 3, 5 + i * 3);
+        pdf.object(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /XObject << /Page {} 0 R >> >> /Contents {} 0 R >>",
+            PAGE_WIDTH_PT, PAGE_HEIGHT_PT, image_id, contents_id,
+        ).as_bytes());
+
+        let contents = format!("q {} 0 0 {} 0 0 cm /Page Do Q", PAGE_WIDTH_PT, PAGE_HEIGHT_PT);
+        pdf.stream("", contents.as_bytes());
+
+        let image = format!(
+            "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /DCTDecode",
+            page.width, page.height,
+        );
+        pdf.stream(&image, &page.jpeg);
+    }
+
+    pdf.finish()
+}
+
+struct PdfWriter {
+    buffer: Vec<u8>,
+    // byte offset of every object, for the cross-reference table.
+    offsets: Vec<usize>,
+}
+
+impl PdfWriter {
+    fn new() -> Self {
+        let mut buffer = Vec::new();
+        // the binary comment tells tools the file is not plain text.
+        buffer.extend_from_slice(b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n");
+        PdfWriter { buffer, offsets: Vec::new() }
+    }
+
+    // objects are numbered in the order they are written, from 1.
+    fn object(&mut self, body: &[u8]) {
+        self.offsets.push(self.buffer.len());
+        self.buffer.extend_from_slice(format!("{} 0 obj\n", self.offsets.len()).as_bytes());
+        self.buffer.extend_from_slice(body);
+        self.buffer.extend_from_slice(b"\nendobj\n");
+    }
+
+    // `entries` go into the stream dictionary next to its length.
+    fn stream(&mut self, entries: &str, data: &[u8]) {
+        let mut body = format!("<< {} /Length {} >>\nstream\n", entries, data.len()).into_bytes();
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\nendstream");
+        self.object(&body);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        let xref = self.buffer.len();
+        self.buffer.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", self.offsets.len() + 1).as_bytes());
+        for offset in &self.offsets {
+            self.buffer.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+        }
+        self.buffer.extend_from_slice(format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            self.offsets.len() + 1, xref,
+        ).as_bytes());
+        self.buffer
+    }
+}
@@ -0,0 +1,225 @@
+use cosmic_text::{fontdb, Attrs, Buffer, FontSystem, Metrics, Shaping, SwashCache, SwashContent};
+use std::path::Path;
+use std::sync::Mutex;
+use tiny_skia::{Color, FillRule, FilterQuality, IntSize, Mask, Paint, PathBuilder, Pixmap, PixmapPaint, PixmapRef, Rect, Transform};
+use tracing::{debug, info};
+
+const DEFAULT_LOCALE: &str = "en-US";
+
+// horizontal alignment relative to the x passed to `draw_text`, named after SVG's text-anchor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextAnchor {
+    Start,
+    Middle,
+    End,
+}
+
+// Shared font state, so that every renderer reuses the loaded fonts and glyph cache.
+pub struct TextRenderer {
+    font_system: Mutex<FontSystem>,
+    swash_cache: Mutex<SwashCache>,
+}
+
+impl Default for TextRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TextRenderer {
+    pub fn new() -> TextRenderer {
+        TextRenderer {
+            font_system: Mutex::new(FontSystem::new()),
+            swash_cache: Mutex::new(SwashCache::new()),
+        }
+    }
+
+    // loads fonts from `font_dir` on top of the system fonts; `family` replaces the default sans-serif.
+    // glyphs missing from the primary font (CJK, emoji) fall back to any other loaded font covering the script.
+    pub fn with_fonts(font_dir: Option<&Path>, family: Option<&str>) -> TextRenderer {
+        let mut db = fontdb::Database::new();
+        db.load_system_fonts();
+        if let Some(font_dir) = font_dir {
+            db.load_fonts_dir(font_dir);
+            info!("loaded fonts from {}, {} faces available", font_dir.display(), db.len());
+        }
+        if let Some(family) = family {
+            db.set_sans_serif_family(family);
+        }
+
+        TextRenderer {
+            font_system: Mutex::new(FontSystem::new_with_locale_and_db(String::from(DEFAULT_LOCALE), db)),
+            swash_cache: Mutex::new(SwashCache::new()),
+        }
+    }
+
+    pub fn draw_text(
+        &self,
+        pixmap: &mut Pixmap,
+        text: &str,
+        font_size: f32,
+        (x, y): (f32, f32),
+        color: Color,
+        anchor: TextAnchor,
+    ) {
+        if let Some(image) = self.rasterize(text, font_size, color, anchor) {
+            image.draw(pixmap, x, y);
+        }
+    }
+
+    // renders the text into a pixmap just large enough for its glyphs; None when nothing is visible.
+    pub fn rasterize(&self, text: &str, font_size: f32, color: Color, anchor: TextAnchor) -> Option<TextImage> {
+        let mut font_system = self.font_system.lock().unwrap();
+        let mut swash_cache = self.swash_cache.lock().unwrap();
+        let font_system = &mut *font_system;
+
+        let metrics = Metrics::new(font_size, font_size * 1.2);
+        let mut buffer = Buffer::new(font_system, metrics);
+
+        let attrs = Attrs::new();
+        buffer.set_text(font_system, text, &attrs, Shaping::Advanced, None);
+        buffer.shape_until_scroll(font_system, true);
+
+        // the bounds of all glyphs relative to the anchor point, measured before anything is drawn.
+        let (mut left, mut top, mut right, mut bottom) = (i32::MAX, i32::MAX, i32::MIN, i32::MIN);
+        for run in buffer.layout_runs() {
+            let offset = anchor_offset(anchor, run.line_w);
+            for glyph in run.glyphs {
+                let physical_glyph = glyph.physical((offset, 0.0), 1.0);
+                if let Some(image) = swash_cache.get_image(font_system, physical_glyph.cache_key) {
+                    let placement = image.placement;
+                    if placement.width == 0 || placement.height == 0 {
+                        continue;
+                    }
+                    let glyph_left = placement.left + physical_glyph.x;
+                    let glyph_top = physical_glyph.y - placement.top;
+                    left = left.min(glyph_left);
+                    top = top.min(glyph_top);
+                    right = right.max(glyph_left + placement.width as i32);
+                    bottom = bottom.max(glyph_top + placement.height as i32);
+                }
+            }
+        }
+        if left >= right || top >= bottom {
+            return None
+        }
+
+        let size = IntSize::from_wh((right - left) as u32, (bottom - top) as u32)?;
+        let mut pixmap = Pixmap::new(size.width(), size.height())?;
+        let mut text_mask_data = vec![0; size.width() as usize * size.height() as usize];
+
+        for run in buffer.layout_runs() {
+            let offset = anchor_offset(anchor, run.line_w);
+            for glyph in run.glyphs {
+                debug!("now drawing: {:?}", glyph);
+                let physical_glyph = glyph.physical((offset, 0.0), 1.0);
+
+                if let Some(image) = swash_cache.get_image(font_system, physical_glyph.cache_key) {
+                    let glyph_left = image.placement.left + physical_glyph.x - left;
+                    let glyph_top = physical_glyph.y - image.placement.top - top;
+                    let width = image.placement.width;
+                    let height = image.placement.height;
+
+                    if width == 0 || height == 0 {
+                        continue;
+                    }
+
+                    match image.content {
+                        SwashContent::Mask => { // character
+                            for (i, &a) in image.data.iter().enumerate() {
+                                let x = i as i32 % width as i32 + glyph_left;
+                                let y = i as i32 / width as i32 + glyph_top;
+                                let idx = (x + y * size.width() as i32) as usize;
+                                text_mask_data[idx] = a;
+                            }
+                        },
+
+                        SwashContent::Color => { // emoji
+                            if let Some(glyph_pixmap) = PixmapRef::from_bytes(&image.data, width, height) {
+                                pixmap.draw_pixmap(
+                                    glyph_left,
+                                    glyph_top,
+                                    glyph_pixmap,
+                                    &PixmapPaint::default(),
+                                    Transform::identity(),
+                                    None,
+                                );
+                            }
+                        },
+
+                        SwashContent::SubpixelMask => {
+                            // skips
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut paint = Paint::default();
+        paint.set_color(color);
+
+        if let Some(mask) = Mask::from_vec(text_mask_data, size) {
+            pixmap.fill_rect(
+                Rect::from_xywh(0.0, 0.0, size.width() as f32, size.height() as f32)?,
+                &paint,
+                Transform::identity(),
+                Some(&mask),
+            );
+        }
+
+        Some(TextImage { pixmap, left, top })
+    }
+}
+
+// rasterized text, positioned relative to the anchor point on its baseline.
+pub struct TextImage {
+    pixmap: Pixmap,
+    left: i32,
+    top: i32,
+}
+
+impl TextImage {
+    pub fn draw(&self, pixmap: &mut Pixmap, x: f32, y: f32) {
+        pixmap.draw_pixmap(
+            x as i32 + self.left,
+            y as i32 + self.top,
+            self.pixmap.as_ref(),
+            &PixmapPaint::default(),
+            Transform::identity(),
+            None,
+        );
+    }
+
+    pub fn byte_size(&self) -> usize {
+        self.pixmap.data().len()
+    }
+}
+
+fn anchor_offset(anchor: TextAnchor, line_width: f32) -> f32 {
+    match anchor {
+        TextAnchor::Start => 0.0,
+        TextAnchor::Middle => -line_width / 2.0,
+        TextAnchor::End => -line_width,
+    }
+}
+
+// draws the avatar clipped to a circle centered at (cx, cy).
+pub fn draw_avatar(pixmap: &mut Pixmap, avatar: &Pixmap, cx: f32, cy: f32, size: f32) {
+    let path = {
+        let mut path_builder = PathBuilder::new();
+        path_builder.push_circle(size/2.0, size/2.0, size/2.0);
+        path_builder.push_circle(size/2.0, size/2.0, 0.0);
+        path_builder.finish().unwrap()
+    };
+
+    let transform = Transform::from_translate(cx - size/2.0, cy - size/2.0);
+
+    let mut avatar_mask = Mask::new(pixmap.width(), pixmap.height()).unwrap();
+    avatar_mask.fill_path(&path, FillRule::EvenOdd, true, transform);
+
+    let paint = PixmapPaint { quality: FilterQuality::Bicubic, ..PixmapPaint::default() };
+
+    let avatar_transform = transform.pre_scale(size/avatar.width() as f32, size/avatar.height() as f32);
+
+    pixmap.draw_pixmap(0, 0, avatar.as_ref(), &paint, avatar_transform, Some(&avatar_mask));
+}
@@ -0,0 +1,151 @@
+use crate::service::renderer::timeline::{TimelineRendererError, TimelineRendererResult};
+use image::codecs::avif::AvifEncoder;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::{ExtendedColorType, ImageEncoder};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tiny_skia::{IntRect, Pixmap};
+
+// 1 (slowest, smallest) to 10 (fastest); reports favour latency over the last few bytes.
+const AVIF_SPEED: u8 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageFormat {
+    #[default]
+    Png,
+    // lossless; quality is ignored.
+    Webp,
+    Avif,
+    // animated png of the call growing; images without a timeline are still pngs.
+    Apng,
+}
+
+impl ImageFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Webp => "webp",
+            ImageFormat::Avif => "avif",
+            ImageFormat::Apng => "apng",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Apng => "png",
+            _ => self.name(),
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "image/png",
+            ImageFormat::Webp => "image/webp",
+            ImageFormat::Avif => "image/avif",
+            ImageFormat::Apng => "image/apng",
+        }
+    }
+}
+
+// encodes the pixmap, with quality from 1 to 100 for lossy formats.
+pub fn encode(pixmap: &Pixmap, format: ImageFormat, quality: u8) -> TimelineRendererResult<Vec<u8>> {
+    let (width, height) = (pixmap.width(), pixmap.height());
+
+    let mut buffer = Vec::new();
+    let result = match format {
+        ImageFormat::Png | ImageFormat::Apng => {
+            return pixmap.encode_png().map_err(|e| TimelineRendererError::PngEncoding(Box::new(e)));
+        },
+        ImageFormat::Webp => WebPEncoder::new_lossless(&mut buffer)
+            .write_image(&straight_rgba(pixmap), width, height, ExtendedColorType::Rgba8),
+        ImageFormat::Avif => AvifEncoder::new_with_speed_quality(&mut buffer, AVIF_SPEED, quality.clamp(1, 100))
+            .write_image(&straight_rgba(pixmap), width, height, ExtendedColorType::Rgba8),
+    };
+    result.map_err(|e| TimelineRendererError::Encoding(Box::new(e)))?;
+
+    Ok(buffer)
+}
+
+// drops alpha, so the pixmap should be opaque; documents embed jpeg pages as they are.
+pub fn encode_jpeg(pixmap: &Pixmap, quality: u8) -> TimelineRendererResult<Vec<u8>> {
+    let rgb: Vec<u8> = pixmap.pixels().iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue()]
+        })
+        .collect();
+
+    let mut buffer = Vec::new();
+    JpegEncoder::new_with_quality(&mut buffer, quality.clamp(1, 100))
+        .write_image(&rgb, pixmap.width(), pixmap.height(), ExtendedColorType::Rgb8)
+        .map_err(|e| TimelineRendererError::Encoding(Box::new(e)))?;
+    Ok(buffer)
+}
+
+// plays the frames once and stays on the last one; `still` is shown by viewers without animation support.
+pub fn encode_apng(still: &Pixmap, frames: &[Pixmap], delay: Duration) -> TimelineRendererResult<Vec<u8>> {
+    let to_error = |e: png::EncodingError| TimelineRendererError::PngEncoding(Box::new(e));
+    let mut buffer = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut buffer, still.width(), still.height());
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(frames.len().max(1) as u32, 1).map_err(to_error)?;
+        encoder.set_sep_def_img(!frames.is_empty()).map_err(to_error)?;
+        encoder.set_frame_delay(delay.as_millis().min(u16::MAX as u128) as u16, 1000).map_err(to_error)?;
+
+        let mut writer = encoder.write_header().map_err(to_error)?;
+        writer.write_image_data(&straight_rgba(still)).map_err(to_error)?;
+        let mut previous: Option<&Pixmap> = None;
+        for frame in frames {
+            // frames after the first only carry the region that changed since the previous one;
+            // it is drawn over the previous frame, which is kept.
+            let region = match previous {
+                Some(previous) => changed_region(previous, frame).unwrap_or(IntRect::from_xywh(0, 0, 1, 1).unwrap()),
+                None => IntRect::from_xywh(0, 0, frame.width(), frame.height()).unwrap(),
+            };
+            let data = match frame.clone_rect(region) {
+                Some(cropped) => straight_rgba(&cropped),
+                None => straight_rgba(frame),
+            };
+            writer.reset_frame_position().map_err(to_error)?;
+            writer.set_frame_dimension(region.width(), region.height()).map_err(to_error)?;
+            writer.set_frame_position(region.x() as u32, region.y() as u32).map_err(to_error)?;
+            writer.write_image_data(&data).map_err(to_error)?;
+            previous = Some(frame);
+        }
+        writer.finish().map_err(to_error)?;
+    }
+    Ok(buffer)
+}
+
+// the bounding box of the pixels that differ between two frames of the same size; None when they are equal.
+fn changed_region(previous: &Pixmap, frame: &Pixmap) -> Option<IntRect> {
+    let width = frame.width() as usize;
+    let (mut left, mut top, mut right, mut bottom) = (usize::MAX, usize::MAX, 0, 0);
+    for (i, (before, after)) in previous.pixels().iter().zip(frame.pixels()).enumerate() {
+        if before != after {
+            let (x, y) = (i % width, i / width);
+            left = left.min(x);
+            top = top.min(y);
+            right = right.max(x);
+            bottom = bottom.max(y);
+        }
+    }
+    if left > right {
+        return None
+    }
+    IntRect::from_xywh(left as i32, top as i32, (right - left + 1) as u32, (bottom - top + 1) as u32)
+}
+
+// tiny-skia keeps premultiplied alpha while the other encoders expect straight alpha.
+fn straight_rgba(pixmap: &Pixmap) -> Vec<u8> {
+    pixmap.pixels().iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect()
+}
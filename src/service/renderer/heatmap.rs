@@ -0,0 +1,79 @@
+use crate::service::i18n::Language;
+use crate::service::renderer::draw::{TextAnchor, TextRenderer};
+use crate::service::renderer::encoder::{encode, ImageFormat};
+use crate::service::renderer::theme::Theme;
+use crate::service::renderer::timeline::TimelineRendererResult;
+use crate::service::stats::Heatmap;
+use chrono::Weekday;
+use std::sync::Arc;
+use tiny_skia::{Color, Paint, Pixmap, Rect, Transform};
+
+const MARGIN: f32 = 20.0;
+const TITLE_HEIGHT: f32 = 50.0;
+const TITLE_FONT_SIZE: f32 = 28.0;
+const FONT_SIZE: f32 = 18.0;
+const DAY_LABEL_WIDTH: f32 = 70.0;
+const HOUR_LABEL_HEIGHT: f32 = 30.0;
+const CELL_SIZE: f32 = 32.0;
+const CELL_GAP: f32 = 3.0;
+// hours are labeled every this many columns.
+const HOUR_LABEL_STEP: usize = 3;
+// even the quietest hour with any activity stays visible against empty ones.
+const MIN_ACTIVE_ALPHA: f32 = 0.12;
+const EMPTY_CELL_ALPHA: f32 = 0.08;
+
+const WEEKDAYS: [Weekday; 7] = [Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri, Weekday::Sat, Weekday::Sun];
+
+// draws voice time of a guild as a grid of days by hours.
+pub struct HeatmapRenderer {
+    text_renderer: Arc<TextRenderer>,
+}
+
+impl HeatmapRenderer {
+    pub fn new(text_renderer: Arc<TextRenderer>) -> HeatmapRenderer {
+        HeatmapRenderer { text_renderer }
+    }
+
+    pub fn generate_image(&self, title: &str, heatmap: &Heatmap, language: Language, theme: &Theme, format: ImageFormat, quality: u8) -> TimelineRendererResult<Vec<u8>> {
+        let grid_left = MARGIN + DAY_LABEL_WIDTH;
+        let grid_top = MARGIN + TITLE_HEIGHT + HOUR_LABEL_HEIGHT;
+        let width = grid_left + 24.0 * CELL_SIZE + MARGIN;
+        let height = grid_top + 7.0 * CELL_SIZE + MARGIN;
+
+        let mut pixmap = Pixmap::new(width as u32, height as u32).expect("invalid pixmap size");
+        pixmap.fill(theme.background);
+
+        self.text_renderer.draw_text(&mut pixmap, title, TITLE_FONT_SIZE, (MARGIN, MARGIN + TITLE_FONT_SIZE), theme.text, TextAnchor::Start);
+
+        for hour in (0..24).step_by(HOUR_LABEL_STEP) {
+            let x = grid_left + hour as f32 * CELL_SIZE;
+            self.text_renderer.draw_text(&mut pixmap, &format!("{:02}", hour), FONT_SIZE, (x, grid_top - FONT_SIZE / 2.0), theme.text, TextAnchor::Start);
+        }
+
+        // discord's blurple, readable on both themes.
+        let color = Color::from_rgba8(88, 101, 242, 255);
+        let max = heatmap.max().as_secs_f32().max(1.0);
+        for (day, weekday) in WEEKDAYS.iter().enumerate() {
+            let top = grid_top + day as f32 * CELL_SIZE;
+            self.text_renderer.draw_text(&mut pixmap, language.weekday(*weekday), FONT_SIZE, (MARGIN, top + CELL_SIZE / 2.0 + FONT_SIZE / 3.0), theme.text, TextAnchor::Start);
+
+            for hour in 0..24 {
+                let value = heatmap.cells[day][hour].as_secs_f32();
+                let mut cell_color = if value > 0.0 { color } else { theme.grid };
+                cell_color.set_alpha(match value > 0.0 {
+                    true => MIN_ACTIVE_ALPHA + (1.0 - MIN_ACTIVE_ALPHA) * value / max,
+                    false => EMPTY_CELL_ALPHA,
+                });
+
+                let left = grid_left + hour as f32 * CELL_SIZE;
+                if let Some(rect) = Rect::from_xywh(left + CELL_GAP / 2.0, top + CELL_GAP / 2.0, CELL_SIZE - CELL_GAP, CELL_SIZE - CELL_GAP) {
+                    let mut paint = Paint::default();
+                    paint.set_color(cell_color);
+                    pixmap.fill_rect(rect, &paint, Transform::identity(), None);
+                }
+            }
+        }
+
+        encode(&pixmap, format, quality)
+    }
+}
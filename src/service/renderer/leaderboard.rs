@@ -0,0 +1,77 @@
+use crate::service::renderer::draw::{draw_avatar, TextAnchor, TextRenderer};
+use crate::service::renderer::encoder::{encode, ImageFormat};
+use crate::service::renderer::theme::Theme;
+use crate::service::renderer::timeline::{TimelineRenderer, TimelineRendererResult};
+use chrono::TimeDelta;
+use std::sync::Arc;
+use std::time::Duration;
+use tiny_skia::{Color, Paint, Pixmap, Rect, Transform};
+
+const WIDTH: f32 = 800.0;
+const MARGIN: f32 = 20.0;
+const TITLE_HEIGHT: f32 = 50.0;
+const TITLE_FONT_SIZE: f32 = 28.0;
+const ROW_HEIGHT: f32 = 70.0;
+const FONT_SIZE: f32 = 22.0;
+const RANK_WIDTH: f32 = 60.0;
+const AVATAR_SIZE: f32 = 56.0;
+const NAME_WIDTH: f32 = 220.0;
+const DURATION_WIDTH: f32 = 90.0;
+const BAR_HEIGHT_RATIO: f32 = 0.4;
+const MAX_NAME_CHARS: usize = 16;
+
+pub struct LeaderboardRow {
+    pub avatar: Pixmap,
+    pub name: String,
+    pub duration: Duration,
+    pub color: Color,
+}
+
+pub struct LeaderboardRenderer {
+    text_renderer: Arc<TextRenderer>,
+}
+
+impl LeaderboardRenderer {
+    pub fn new(text_renderer: Arc<TextRenderer>) -> LeaderboardRenderer {
+        LeaderboardRenderer { text_renderer }
+    }
+
+    pub fn generate_image(&self, title: &str, rows: &[LeaderboardRow], theme: &Theme, format: ImageFormat, quality: u8) -> TimelineRendererResult<Vec<u8>> {
+        let height = MARGIN * 2.0 + TITLE_HEIGHT + ROW_HEIGHT * rows.len().max(1) as f32;
+        let mut pixmap = Pixmap::new(WIDTH as u32, height as u32).expect("invalid pixmap size");
+        pixmap.fill(theme.background);
+
+        self.text_renderer.draw_text(&mut pixmap, title, TITLE_FONT_SIZE, (MARGIN, MARGIN + TITLE_FONT_SIZE), theme.text, TextAnchor::Start);
+
+        let longest = rows.iter().map(|row| row.duration).max().unwrap_or(Duration::ZERO).as_secs_f32().max(1.0);
+        let name_left = MARGIN + RANK_WIDTH + ROW_HEIGHT;
+        let bar_left = name_left + NAME_WIDTH;
+        let bar_max_width = WIDTH - MARGIN - DURATION_WIDTH - bar_left;
+
+        for (i, row) in rows.iter().enumerate() {
+            let top = MARGIN + TITLE_HEIGHT + i as f32 * ROW_HEIGHT;
+            let center_y = top + ROW_HEIGHT / 2.0;
+            let baseline = center_y + FONT_SIZE / 3.0;
+
+            self.text_renderer.draw_text(&mut pixmap, &format!("#{}", i + 1), FONT_SIZE, (MARGIN + RANK_WIDTH / 2.0, baseline), theme.text, TextAnchor::Middle);
+
+            draw_avatar(&mut pixmap, &row.avatar, MARGIN + RANK_WIDTH + ROW_HEIGHT / 2.0, center_y, AVATAR_SIZE);
+
+            let name: String = row.name.chars().take(MAX_NAME_CHARS).collect();
+            self.text_renderer.draw_text(&mut pixmap, &name, FONT_SIZE, (name_left, baseline), theme.text, TextAnchor::Start);
+
+            let bar_width = bar_max_width * row.duration.as_secs_f32() / longest;
+            let bar_height = ROW_HEIGHT * BAR_HEIGHT_RATIO;
+            if let Some(rect) = Rect::from_xywh(bar_left, center_y - bar_height / 2.0, bar_width, bar_height) {
+                let mut paint = Paint { anti_alias: true, ..Paint::default() };
+                paint.set_color(row.color);
+                pixmap.fill_rect(rect, &paint, Transform::identity(), None);
+            }
+
+            let duration = TimelineRenderer::format_time_delta(TimeDelta::from_std(row.duration).unwrap_or(TimeDelta::zero()));
+            self.text_renderer.draw_text(&mut pixmap, &duration, FONT_SIZE, (WIDTH - MARGIN, baseline), theme.text, TextAnchor::End);
+        }
+
+        encode(&pixmap, format, quality)
+    }
+}
@@ -1,3 +1,13 @@
 pub mod view;
 pub mod timeline;
-pub mod transformer;
\ No newline at end of file
+pub mod transformer;
+pub mod svg;
+pub mod theme;
+pub mod draw;
+pub mod leaderboard;
+pub mod heatmap;
+pub mod user_timeline;
+pub mod overview;
+pub mod document;
+pub mod encoder;
+pub mod pool;
@@ -0,0 +1,65 @@
+use crate::service::renderer::draw::{TextAnchor, TextRenderer};
+use crate::service::renderer::encoder::{encode, ImageFormat};
+use crate::service::renderer::theme::Theme;
+use crate::service::renderer::timeline::{TimelineRenderer, TimelineRendererResult};
+use crate::service::renderer::view::Timeline;
+use std::sync::Arc;
+use tiny_skia::{Paint, Pixmap, PixmapPaint, Rect, Transform};
+
+const MARGIN: f32 = 20.0;
+const HEADER_HEIGHT: f32 = 48.0;
+const HEADER_FONT_SIZE: f32 = 24.0;
+const SEPARATOR_WIDTH: f32 = 2.0;
+
+// one room of the overview, headed by its channel name.
+pub struct OverviewSection {
+    pub title: String,
+    pub timeline: Timeline,
+}
+
+// Stacks the timelines of every active room into one image.
+pub struct OverviewRenderer {
+    timeline_renderer: Arc<TimelineRenderer>,
+    text_renderer: Arc<TextRenderer>,
+}
+
+impl OverviewRenderer {
+    pub fn new(timeline_renderer: Arc<TimelineRenderer>, text_renderer: Arc<TextRenderer>) -> OverviewRenderer {
+        OverviewRenderer { timeline_renderer, text_renderer }
+    }
+
+    pub fn generate_image(&self, sections: &[OverviewSection], theme: &Theme, format: ImageFormat, quality: u8) -> TimelineRendererResult<Vec<u8>> {
+        let timelines: Vec<Pixmap> = sections.iter()
+            .map(|section| self.timeline_renderer.render_pixmap(&section.timeline, theme))
+            .collect();
+        // headers follow the scale of the timelines below them.
+        let scale = self.timeline_renderer.scale();
+        let header_height = HEADER_HEIGHT * scale;
+        let font_size = HEADER_FONT_SIZE * scale;
+
+        let width = timelines.iter().map(|timeline| timeline.width()).max().unwrap_or(1);
+        let height = timelines.iter().map(|timeline| timeline.height() + header_height as u32).sum::<u32>().max(1);
+        let mut pixmap = Pixmap::new(width, height).expect("invalid pixmap size");
+        pixmap.fill(theme.background);
+
+        let mut top = 0.0;
+        for (i, (section, timeline)) in sections.iter().zip(&timelines).enumerate() {
+            if i > 0
+                && let Some(rect) = Rect::from_xywh(0.0, top, width as f32, SEPARATOR_WIDTH * scale)
+            {
+                let mut paint = Paint::default();
+                paint.set_color(theme.grid);
+                pixmap.fill_rect(rect, &paint, Transform::identity(), None);
+            }
+
+            let baseline = top + (header_height + font_size) / 2.0;
+            self.text_renderer.draw_text(&mut pixmap, &section.title, font_size, (MARGIN * scale, baseline), theme.text, TextAnchor::Start);
+            top += header_height;
+
+            pixmap.draw_pixmap(0, top as i32, timeline.as_ref(), &PixmapPaint::default(), Transform::identity(), None);
+            top += timeline.height() as f32;
+        }
+
+        encode(&pixmap, format, quality)
+    }
+}
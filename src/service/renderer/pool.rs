@@ -0,0 +1,115 @@
+use crate::memory::MemoryMonitor;
+use serenity::all::ChannelId;
+use std::collections::HashMap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, error};
+
+#[derive(Debug, Error)]
+pub enum RenderPoolError {
+    #[error("render pool is shut down")]
+    Closed,
+
+    // a newer render of the same room was queued before this one started.
+    #[error("render was superseded by a newer one")]
+    Superseded,
+
+    #[error("render panicked")]
+    Panicked,
+}
+
+pub type RenderPoolResult<T> = Result<T, RenderPoolError>;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+// runs renders on a fixed set of threads, so a burst of calls waits in a bounded queue instead of
+// spreading over the blocking pool.
+pub struct RenderPool {
+    sender: mpsc::Sender<Job>,
+    // the latest queued render per room; older ones are skipped once a worker picks them up.
+    latest: Arc<std::sync::Mutex<HashMap<ChannelId, u64>>>,
+    generation: AtomicU64,
+    memory: Option<Arc<MemoryMonitor>>,
+}
+
+impl RenderPool {
+    pub fn new(workers: usize, queue_size: usize) -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>(queue_size.max(1));
+        let receiver = Arc::new(std::sync::Mutex::new(receiver));
+
+        for i in 0..workers.max(1) {
+            let receiver = receiver.clone();
+            std::thread::Builder::new()
+                .name(format!("render-{}", i))
+                .spawn(move || loop {
+                    // the lock is released before running the job, so workers only contend while idle.
+                    let job = receiver.lock().unwrap().blocking_recv();
+                    match job {
+                        Some(job) => {
+                            if catch_unwind(AssertUnwindSafe(job)).is_err() {
+                                error!("render job panicked");
+                            }
+                        },
+                        None => break,
+                    }
+                })
+                .expect("Failed to spawn render worker");
+        }
+
+        RenderPool {
+            sender,
+            latest: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            generation: AtomicU64::new(0),
+            memory: None,
+        }
+    }
+
+    pub fn with_memory(mut self, memory: Arc<MemoryMonitor>) -> Self {
+        self.memory = Some(memory);
+        self
+    }
+
+    // renders keyed by a room replace any render of the same room still waiting in the queue;
+    // unkeyed renders run unless the returned future is dropped before they start.
+    pub async fn run<T, F>(&self, key: Option<ChannelId>, render: F) -> RenderPoolResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let generation = self.generation.fetch_add(1, Ordering::Relaxed);
+        if let Some(key) = key {
+            self.latest.lock().unwrap().insert(key, generation);
+        }
+
+        let (tx, rx) = oneshot::channel();
+        let latest = self.latest.clone();
+        let job: Job = Box::new(move || {
+            if let Some(key) = key {
+                let mut latest = latest.lock().unwrap();
+                if latest.get(&key) != Some(&generation) {
+                    debug!("render of channel {} was superseded, skip it", key);
+                    let _ = tx.send(Err(RenderPoolError::Superseded));
+                    return
+                }
+                latest.remove(&key);
+            }
+            // the caller gave up waiting, e.g. on a timeout, before a worker picked the render up.
+            if tx.is_closed() {
+                debug!("render was cancelled before it started, skip it");
+                return
+            }
+            let _ = tx.send(Ok(render()));
+        });
+
+        self.sender.send(job).await.map_err(|_| RenderPoolError::Closed)?;
+        // the sender is only dropped without a result when the render panicked.
+        let result = rx.await.map_err(|_| RenderPoolError::Panicked)?;
+        if let (Ok(_), Some(memory)) = (&result, &self.memory) {
+            memory.observe("render");
+        }
+        result
+    }
+}
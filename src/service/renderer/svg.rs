@@ -0,0 +1,400 @@
+use crate::service::renderer::theme::Theme;
+use crate::service::renderer::timeline::layout::LayoutConfig;
+use crate::service::renderer::timeline::{
+    capacity_fill, capacity_label_position, legend_items, legend_slot, paused_label_position, stats_lines, streaming_label, SectionShape, TimelineRendererError, TimelineRendererResult, BADGE_BORDER_WIDTH, BADGE_OFFSET_RATIO, BADGE_SIZE_RATIO, CAPACITY_ALPHA, CAPACITY_FONT_SIZE, DEPARTED_AVATAR_ALPHA, GAP_DASH, GAP_WIDTH, HATCH_LINE_WIDTH, HATCH_SIZE, INDICATOR_DASH,
+    LEGEND_FONT_SIZE, STATS_FONT_SIZE, STATS_PADDING, LEGEND_INACTIVE_ALPHA, MUTED_ALPHA, ONGOING_FADE_WIDTH, PAUSED_ALPHA, PAUSED_FONT_SIZE, RECONNECT_DASH, SEPARATOR_DASH, SEPARATOR_WIDTH, SPEAKING_BAR_BOTTOM_RATIO, SPEAKING_BAR_TOP_RATIO,
+    STREAMING_LABEL_FONT_SIZE, STREAMING_STROKE_WIDTH, STROKE_WIDTH, TIMELINE_BAR_BOTTOM_RATIO, TIMELINE_BAR_TOP_RATIO,
+    VIDEO_BAR_BOTTOM_RATIO, VIDEO_BAR_TOP_RATIO,
+};
+use crate::service::i18n::TextKey;
+use crate::service::renderer::view::{FillStyle, Timeline};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use std::fmt::Write;
+use tiny_skia::{Color, Rect};
+
+const FONT_SIZE: f32 = 20.0;
+
+// Vector counterpart of `TimelineRenderer`, sharing its layout so both outputs line up.
+pub struct SvgRenderer {
+    layout_config: LayoutConfig,
+}
+
+impl Default for SvgRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SvgRenderer {
+    pub fn new() -> SvgRenderer {
+        SvgRenderer {
+            layout_config: LayoutConfig::default(),
+        }
+    }
+
+    pub fn generate_svg(&self, timeline: &Timeline, theme: &Theme) -> TimelineRendererResult<String> {
+        let (background, _) = svg_color(theme.background);
+        let (grid, _) = svg_color(theme.grid);
+        let (text, _) = svg_color(theme.text);
+        let (border, _) = svg_color(theme.stroke);
+
+        let layout = LayoutConfig {
+            aspect_ratio_policy: timeline.aspect_ratio,
+            ..self.layout_config
+        }.calculate(timeline.entries.len());
+        let font_size = FONT_SIZE * layout.scale();
+        let full_timeline_bb = layout.full_timeline_bb();
+
+        let mut svg = String::new();
+        let _ = write!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+            w = layout.total_width(),
+            h = layout.total_height(),
+        );
+        let _ = write!(svg, r#"<rect width="100%" height="100%" fill="{background}"/>"#);
+
+        // Render ticks first.
+        for (ratio, label) in timeline.tick_positions() {
+            let x = full_timeline_bb.left() + ratio * full_timeline_bb.width();
+            let _ = write!(
+                svg,
+                r#"<line x1="{x}" y1="{top}" x2="{x}" y2="{bottom}" stroke="{grid}" stroke-width="1"/>"#,
+                top = full_timeline_bb.top(),
+                bottom = full_timeline_bb.bottom(),
+            );
+
+            // labels grow upward so that the last line sits right above the timeline.
+            let lines: Vec<&str> = label.lines().collect();
+            let _ = write!(svg, r#"<text x="{x}" font-size="{font_size}" text-anchor="middle" fill="{text}">"#);
+            for (i, line) in lines.iter().enumerate() {
+                let y = full_timeline_bb.top() - (lines.len() - 1 - i) as f32 * font_size * 1.2;
+                let _ = write!(svg, r#"<tspan x="{x}" y="{y}">{}</tspan>"#, escape(line));
+            }
+            svg.push_str("</text>");
+        }
+
+        // Then, Render fills.
+        for (i, entry) in timeline.entries.iter().enumerate() {
+            let headline_bb = layout.headline_bb_for_entry(i);
+            let avatar_size = layout.avatar_size();
+            let cx = (headline_bb.left() + headline_bb.right()) / 2.0;
+            let cy = (headline_bb.top() + headline_bb.bottom()) / 2.0;
+
+            let avatar = entry.avatar.encode_png().map_err(|e| TimelineRendererError::PngEncoding(Box::new(e)))?;
+            let _ = write!(
+                svg,
+                r#"<clipPath id="avatar-{i}"><circle cx="{cx}" cy="{cy}" r="{r}"/></clipPath>"#,
+                r = avatar_size / 2.0,
+            );
+            let _ = write!(
+                svg,
+                r#"<image x="{x}" y="{y}" width="{avatar_size}" height="{avatar_size}" clip-path="url(#avatar-{i})" opacity="{opacity}" href="data:image/png;base64,{}"/>"#,
+                STANDARD.encode(avatar),
+                opacity = if entry.is_departed { 1.0 - DEPARTED_AVATAR_ALPHA } else { 1.0 },
+                x = cx - avatar_size / 2.0,
+                y = cy - avatar_size / 2.0,
+            );
+
+            let radius = avatar_size * BADGE_SIZE_RATIO / 2.0;
+            let offset = avatar_size * BADGE_OFFSET_RATIO;
+            if entry.sessions > 1 {
+                let (x, y) = (cx + offset, cy - offset);
+                let _ = write!(
+                    svg,
+                    r#"<circle cx="{x}" cy="{y}" r="{radius}" fill="{border}" stroke="{background}" stroke-width="{BADGE_BORDER_WIDTH}"/><text x="{x}" y="{ty}" font-size="{fs}" text-anchor="middle" fill="{background}">{}</text>"#,
+                    entry.sessions.min(99),
+                    ty = y + radius * 0.5,
+                    fs = radius * 1.4,
+                );
+            }
+            if entry.is_connected {
+                let (online, _) = svg_color(theme.online);
+                let _ = write!(
+                    svg,
+                    r#"<circle cx="{x}" cy="{y}" r="{r}" fill="{online}" stroke="{background}" stroke-width="{BADGE_BORDER_WIDTH}"/>"#,
+                    x = cx + offset,
+                    y = cy + offset,
+                    r = radius * 0.8,
+                );
+            }
+
+            let (active, active_opacity) = svg_color(entry.active_color);
+            let (inactive, inactive_opacity) = svg_color(entry.inactive_color);
+            let (streaming, streaming_opacity) = svg_color(entry.streaming_color);
+
+            let _ = write!(
+                svg,
+                r#"<pattern id="muted-{i}" width="{HATCH_SIZE}" height="{HATCH_SIZE}" patternUnits="userSpaceOnUse"><rect width="{HATCH_SIZE}" height="{HATCH_SIZE}" fill="{inactive}" fill-opacity="{inactive_opacity}"/><path d="M{o},{so} L{so},{o} M{o},{u} L{u},{o} M{s},{so} L{so},{s}" stroke="{active}" stroke-opacity="{MUTED_ALPHA}" stroke-width="{HATCH_LINE_WIDTH}"/></pattern>"#,
+                o = -HATCH_LINE_WIDTH,
+                u = HATCH_LINE_WIDTH,
+                s = HATCH_SIZE as f32 - HATCH_LINE_WIDTH,
+                so = HATCH_SIZE as f32 + HATCH_LINE_WIDTH,
+            );
+
+            let timeline_bb = layout.timeline_bb_for_entry(i);
+
+            if let Some(stats_bb) = layout.stats_bb_for_entry(i) {
+                let stats_font_size = STATS_FONT_SIZE * layout.scale();
+                let x = stats_bb.left() + STATS_PADDING * layout.scale();
+                for (line, y) in stats_lines(stats_bb, &entry.stats, timeline.language) {
+                    let _ = write!(svg, r#"<text x="{x}" y="{y}" font-size="{stats_font_size}" fill="{text}">{}</text>"#, escape(&line));
+                }
+            }
+
+            // gaps first, so a rounded section end overlaps them.
+            for section in &entry.gap_sections {
+                let middle = timeline_bb.top() + (TIMELINE_BAR_TOP_RATIO + TIMELINE_BAR_BOTTOM_RATIO) / 2.0 * timeline_bb.height();
+                let _ = write!(
+                    svg,
+                    r#"<line x1="{x1}" y1="{middle}" x2="{x2}" y2="{middle}" stroke="{grid}" stroke-width="{GAP_WIDTH}" stroke-dasharray="{} {}"/>"#,
+                    GAP_DASH[0],
+                    GAP_DASH[1],
+                    x1 = timeline_bb.left() + section.start_ratio * timeline_bb.width(),
+                    x2 = timeline_bb.left() + section.end_ratio * timeline_bb.width(),
+                );
+            }
+
+            let corner_radius = layout.section_corner_radius();
+            for section in &entry.voice_sections {
+                let fill = match section.fill_style {
+                    FillStyle::Active => format!(r#"fill="{active}" fill-opacity="{active_opacity}""#),
+                    FillStyle::Muted => format!(r#"fill="url(#muted-{i})""#),
+                    FillStyle::Deafened => format!(r#"fill="{inactive}" fill-opacity="{inactive_opacity}""#),
+                };
+                let shape = SectionShape::for_voice(timeline_bb, section, corner_radius);
+                if section.is_open {
+                    // open sections are left without their right edge.
+                    let _ = write!(svg, r#"<path d="{}" {fill}/>"#, shape_path_data(&shape, false));
+                    let _ = write!(
+                        svg,
+                        r#"<path d="{}" fill="none" stroke="{active}" stroke-opacity="{active_opacity}" stroke-width="{STROKE_WIDTH}"/>"#,
+                        shape_path_data(&shape, true),
+                    );
+                } else {
+                    let _ = write!(
+                        svg,
+                        r#"<path d="{}" {fill} stroke="{active}" stroke-opacity="{active_opacity}" stroke-width="{STROKE_WIDTH}"/>"#,
+                        shape_path_data(&shape, false),
+                    );
+                }
+            }
+
+            // finally, streaming strokes
+            for section in &entry.streaming_sections {
+                let shape = SectionShape::new(timeline_bb, section.start_ratio, section.end_ratio, corner_radius, corner_radius);
+                let _ = write!(
+                    svg,
+                    r#"<path d="{}" fill="none" stroke="{streaming}" stroke-opacity="{streaming_opacity}" stroke-width="{STREAMING_STROKE_WIDTH}" stroke-linejoin="round"/>"#,
+                    shape_path_data(&shape, false),
+                );
+            }
+
+            let label_font_size = STREAMING_LABEL_FONT_SIZE * layout.scale();
+            for section in &entry.streaming_sections {
+                if let Some((x, y, label)) = streaming_label(timeline_bb, section, label_font_size) {
+                    let _ = write!(
+                        svg,
+                        r#"<text x="{x}" y="{y}" font-size="{label_font_size}" text-anchor="middle" fill="{text}">{label}</text>"#,
+                    );
+                }
+            }
+
+            for (j, section) in entry.voice_sections.iter().enumerate().filter(|(_, section)| section.is_open) {
+                let start = timeline_bb.left() + section.start_ratio * timeline_bb.width();
+                let end = timeline_bb.left() + section.end_ratio * timeline_bb.width();
+                let fade_start = (end - ONGOING_FADE_WIDTH).max(start);
+                let top = timeline_bb.top() + TIMELINE_BAR_TOP_RATIO * timeline_bb.height() - STROKE_WIDTH;
+                let bottom = timeline_bb.top() + TIMELINE_BAR_BOTTOM_RATIO * timeline_bb.height() + STROKE_WIDTH;
+                let _ = write!(
+                    svg,
+                    r#"<linearGradient id="fade-{i}-{j}" x1="{fade_start}" x2="{end}" gradientUnits="userSpaceOnUse"><stop offset="0" stop-color="{background}" stop-opacity="0"/><stop offset="1" stop-color="{background}" stop-opacity="1"/></linearGradient>"#,
+                );
+                let _ = write!(
+                    svg,
+                    r#"<rect x="{fade_start}" y="{top}" width="{w}" height="{h}" fill="url(#fade-{i}-{j})"/>"#,
+                    w = end + STROKE_WIDTH - fade_start,
+                    h = bottom - top,
+                );
+            }
+
+            for section in &entry.reconnect_sections {
+                let start = timeline_bb.left() + section.start_ratio * timeline_bb.width();
+                let end = timeline_bb.left() + section.end_ratio * timeline_bb.width();
+                let top = timeline_bb.top() + TIMELINE_BAR_TOP_RATIO * timeline_bb.height() - STROKE_WIDTH;
+                let bottom = timeline_bb.top() + TIMELINE_BAR_BOTTOM_RATIO * timeline_bb.height() + STROKE_WIDTH;
+                let middle = (top + bottom) / 2.0;
+                let _ = write!(
+                    svg,
+                    r#"<rect x="{start}" y="{top}" width="{w}" height="{h}" fill="{background}"/>"#,
+                    w = end - start,
+                    h = bottom - top,
+                );
+                let _ = write!(
+                    svg,
+                    r#"<line x1="{start}" y1="{middle}" x2="{end}" y2="{middle}" stroke="{active}" stroke-opacity="{active_opacity}" stroke-width="{STROKE_WIDTH}" stroke-dasharray="{} {}"/>"#,
+                    RECONNECT_DASH[0],
+                    RECONNECT_DASH[1],
+                );
+            }
+
+            for section in &entry.speaking_sections {
+                let _ = write!(
+                    svg,
+                    r#"<rect x="{x}" y="{y}" width="{w}" height="{h}" fill="{active}" fill-opacity="{active_opacity}"/>"#,
+                    x = timeline_bb.left() + section.start_ratio * timeline_bb.width(),
+                    y = timeline_bb.top() + SPEAKING_BAR_TOP_RATIO * timeline_bb.height(),
+                    w = (section.end_ratio - section.start_ratio) * timeline_bb.width(),
+                    h = (SPEAKING_BAR_BOTTOM_RATIO - SPEAKING_BAR_TOP_RATIO) * timeline_bb.height(),
+                );
+            }
+
+            for section in &entry.video_sections {
+                let _ = write!(
+                    svg,
+                    r#"<rect x="{x}" y="{y}" width="{w}" height="{h}" fill="{streaming}" fill-opacity="{streaming_opacity}"/>"#,
+                    x = timeline_bb.left() + section.start_ratio * timeline_bb.width(),
+                    y = timeline_bb.top() + VIDEO_BAR_TOP_RATIO * timeline_bb.height(),
+                    w = (section.end_ratio - section.start_ratio) * timeline_bb.width(),
+                    h = (VIDEO_BAR_BOTTOM_RATIO - VIDEO_BAR_TOP_RATIO) * timeline_bb.height(),
+                );
+            }
+        }
+
+        let paused_font_size = PAUSED_FONT_SIZE * layout.scale();
+        for section in &timeline.paused_sections {
+            let band = Rect::from_ltrb(
+                full_timeline_bb.left() + section.start_ratio * full_timeline_bb.width(),
+                full_timeline_bb.top(),
+                full_timeline_bb.left() + section.end_ratio.min(1.0) * full_timeline_bb.width(),
+                full_timeline_bb.bottom(),
+            );
+            let band = match band {
+                Some(band) => band,
+                None => continue,
+            };
+            let _ = write!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{grid}" fill-opacity="{PAUSED_ALPHA}"/>"#,
+                band.left(), band.top(), band.width(), band.height(),
+            );
+            if let Some((x, y)) = paused_label_position(band, timeline.language, paused_font_size) {
+                let _ = write!(
+                    svg,
+                    r#"<text x="{x}" y="{y}" font-size="{paused_font_size}" text-anchor="middle" fill="{text}">{}</text>"#,
+                    escape(timeline.language.text(TextKey::TrackingPaused)),
+                );
+            }
+        }
+
+        if let (Some(capacity), Some(header_bb)) = (timeline.capacity, layout.header_bb()) {
+            if let Some(rect) = capacity_fill(header_bb, capacity) {
+                let _ = write!(
+                    svg,
+                    r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{border}" fill-opacity="{CAPACITY_ALPHA}"/>"#,
+                    rect.left(), rect.top(), rect.width(), rect.height(),
+                );
+            }
+            let font_size = CAPACITY_FONT_SIZE * layout.scale();
+            let (x, y) = capacity_label_position(header_bb, font_size);
+            let _ = write!(
+                svg,
+                r#"<text x="{x}" y="{y}" font-size="{font_size}" text-anchor="middle" fill="{text}">{}</text>"#,
+                capacity.label(),
+            );
+        }
+
+        for &i in &timeline.separators {
+            let y = layout.headline_bb_for_entry(i).top();
+            let _ = write!(
+                svg,
+                r#"<line x1="{x1}" y1="{y}" x2="{x2}" y2="{y}" stroke="{grid}" stroke-width="{w}" stroke-dasharray="{} {}"/>"#,
+                SEPARATOR_DASH[0],
+                SEPARATOR_DASH[1],
+                x1 = layout.headline_bb_for_entry(i).left(),
+                x2 = layout.timeline_bb_for_entry(i).right(),
+                w = SEPARATOR_WIDTH * layout.scale(),
+            );
+        }
+
+        if let Some(ratio) = timeline.indicator_ratio() {
+            let x = full_timeline_bb.left() + ratio * full_timeline_bb.width();
+            let _ = write!(
+                svg,
+                r#"<line x1="{x}" y1="{t}" x2="{x}" y2="{b}" stroke="{border}" stroke-width="{STROKE_WIDTH}" stroke-dasharray="{} {}"/>"#,
+                INDICATOR_DASH[0],
+                INDICATOR_DASH[1],
+                t = full_timeline_bb.top(),
+                b = full_timeline_bb.bottom(),
+            );
+        }
+
+        if let Some(legend_bb) = layout.legend_bb() {
+            let _ = write!(
+                svg,
+                r#"<pattern id="muted-legend" width="{HATCH_SIZE}" height="{HATCH_SIZE}" patternUnits="userSpaceOnUse"><rect width="{HATCH_SIZE}" height="{HATCH_SIZE}" fill="{grid}" fill-opacity="{LEGEND_INACTIVE_ALPHA}"/><path d="M{o},{so} L{so},{o} M{o},{u} L{u},{o} M{s},{so} L{so},{s}" stroke="{grid}" stroke-opacity="{MUTED_ALPHA}" stroke-width="{HATCH_LINE_WIDTH}"/></pattern>"#,
+                o = -HATCH_LINE_WIDTH,
+                u = HATCH_LINE_WIDTH,
+                s = HATCH_SIZE as f32 - HATCH_LINE_WIDTH,
+                so = HATCH_SIZE as f32 + HATCH_LINE_WIDTH,
+            );
+
+            let legend_font_size = LEGEND_FONT_SIZE * layout.scale();
+            for (i, (style, label)) in legend_items(timeline.language).into_iter().enumerate() {
+                let (swatch, label_x, label_y) = legend_slot(legend_bb, i, layout.scale());
+                let paint = match style {
+                    Some(FillStyle::Active) => format!(r#"fill="{grid}" stroke="{grid}" stroke-width="{STROKE_WIDTH}""#),
+                    Some(FillStyle::Muted) => format!(r#"fill="url(#muted-legend)" stroke="{grid}" stroke-width="{STROKE_WIDTH}""#),
+                    Some(FillStyle::Deafened) => format!(r#"fill="{grid}" fill-opacity="{LEGEND_INACTIVE_ALPHA}" stroke="{grid}" stroke-width="{STROKE_WIDTH}""#),
+                    // streaming is only drawn as a thick outline.
+                    None => format!(r#"fill="none" stroke="{border}" stroke-width="{STREAMING_STROKE_WIDTH}" stroke-linejoin="round""#),
+                };
+                let _ = write!(
+                    svg,
+                    r#"<rect x="{}" y="{}" width="{}" height="{}" {paint}/><text x="{label_x}" y="{label_y}" font-size="{legend_font_size}" fill="{text}">{}</text>"#,
+                    swatch.left(),
+                    swatch.top(),
+                    swatch.width(),
+                    swatch.height(),
+                    escape(label),
+                );
+            }
+        }
+
+        // draw start and end
+        let _ = write!(
+            svg,
+            r#"<path d="M{l},{t} L{l},{b} M{r},{t} L{r},{b}" stroke="{border}" stroke-width="{STREAMING_STROKE_WIDTH}"/>"#,
+            l = full_timeline_bb.left(),
+            r = full_timeline_bb.right(),
+            t = full_timeline_bb.top(),
+            b = full_timeline_bb.bottom(),
+        );
+
+        svg.push_str("</svg>");
+        Ok(svg)
+    }
+}
+
+// mirrors `SectionShape::path`.
+fn shape_path_data(shape: &SectionShape, open: bool) -> String {
+    let SectionShape { left: l, top: t, right: r, bottom: b, start_radius: rs, end_radius: re } = *shape;
+    if open {
+        format!("M{r},{t} L{},{t} Q{l},{t} {l},{} L{l},{} Q{l},{b} {},{b} L{r},{b}", l + rs, t + rs, b - rs, l + rs)
+    } else {
+        format!(
+            "M{},{t} L{},{t} Q{r},{t} {r},{} L{r},{} Q{r},{b} {},{b} L{},{b} Q{l},{b} {l},{} L{l},{} Q{l},{t} {},{t} Z",
+            l + rs, r - re, t + re, b - re, r - re, l + rs, b - rs, t + rs, l + rs,
+        )
+    }
+}
+
+fn svg_color(color: Color) -> (String, f32) {
+    let color = color.to_color_u8();
+    (format!("rgb({},{},{})", color.red(), color.green(), color.blue()), color.alpha() as f32 / 255.0)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+use tiny_skia::Color;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemeKind {
+    #[default]
+    Light,
+    Dark,
+}
+
+impl ThemeKind {
+    pub fn theme(&self) -> Theme {
+        match self {
+            ThemeKind::Light => Theme::light(),
+            ThemeKind::Dark => Theme::dark(),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ThemeKind::Light => "light",
+            ThemeKind::Dark => "dark",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Theme {
+    pub background: Color,
+    pub grid: Color,
+    pub text: Color,
+    pub stroke: Color,
+    // marks members still in the call.
+    pub online: Color,
+}
+
+impl Theme {
+    pub fn light() -> Theme {
+        Theme {
+            background: Color::WHITE,
+            grid: Color::from_rgba(0.4, 0.4, 0.4, 1.0).unwrap(),
+            text: Color::BLACK,
+            stroke: Color::from_rgba(0.2, 0.2, 0.2, 1.0).unwrap(),
+            online: Color::from_rgba8(35, 165, 90, 255),
+        }
+    }
+
+    // matches the background of Discord's dark mode.
+    pub fn dark() -> Theme {
+        Theme {
+            background: Color::from_rgba8(49, 51, 56, 255),
+            grid: Color::from_rgba8(128, 132, 142, 255),
+            text: Color::from_rgba8(219, 222, 225, 255),
+            stroke: Color::from_rgba8(181, 186, 193, 255),
+            online: Color::from_rgba8(35, 165, 90, 255),
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use crate::service::renderer::timeline::policy::AspectRatioPolicy;
-use tiny_skia::NonZeroRect;
+use tiny_skia::{IntRect, NonZeroRect};
 
 #[derive(Copy, Clone)]
 pub struct Margin {
@@ -17,40 +17,104 @@ impl Margin {
     pub fn vertical(&self) -> f32{
         self.top + self.bottom
     }
+
+    fn scaled(&self, scale: f32) -> Margin {
+        Margin {
+            left: self.left * scale,
+            top: self.top * scale,
+            right: self.right * scale,
+            bottom: self.bottom * scale,
+        }
+    }
 }
 
+// Discord refuses to preview images larger than this on either side.
+const DISCORD_MAX_DIMENSION: f32 = 4096.0;
+
+#[derive(Clone, Copy)]
 pub struct LayoutConfig {
     pub margin: Margin,
     pub label_area_height: f32,
     pub avatar_column_width: f32,
+    // totals of each participant right of the timeline.
+    pub show_stats: bool,
+    pub stats_column_width: f32,
     pub min_timeline_width: f32,
     pub aspect_ratio_policy: AspectRatioPolicy,
     pub entry_height: f32,
     pub avatar_size: f32,
+    // rounds the ends of section bars; 0 keeps them square.
+    pub section_corner_radius: f32,
+    // explains the fill styles below the timeline.
+    pub show_legend: bool,
+    pub legend_area_height: f32,
+    // larger layouts are scaled down as a whole to fit.
+    pub max_dimension: f32,
+    // multiplies every size, e.g. 2.0 for high-DPI screens; still limited by the maximum dimension.
+    pub scale: f32,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        LayoutConfig{
+            margin: Margin{
+                left: 10.0,
+                top: 10.0,
+                right: 10.0,
+                bottom: 10.0,
+            },
+            label_area_height: 20.0,
+            avatar_column_width: 100.0,
+            show_stats: true,
+            stats_column_width: 150.0,
+            min_timeline_width: 900.0,
+            entry_height: 70.0,
+            avatar_size: 64.0,
+            section_corner_radius: 6.0,
+            show_legend: true,
+            legend_area_height: 30.0,
+            aspect_ratio_policy: AspectRatioPolicy::discord_thumbnail_4_3(),
+            max_dimension: DISCORD_MAX_DIMENSION,
+            scale: 1.0,
+        }
+    }
 }
 
 impl LayoutConfig {
     pub fn calculate(&self, n_entries: usize) -> Layout {
         let total_entry_height = self.entry_height * n_entries as f32;
-        let total_height = self.label_area_height + total_entry_height + self.margin.vertical();
+        let legend_area_height = if self.show_legend { self.legend_area_height } else { 0.0 };
+        let total_height = self.label_area_height + total_entry_height + legend_area_height + self.margin.vertical();
         let timeline_width = self.aspect_ratio_policy.calculate_timeline_width(total_height, self.fixed_content_width(), self.min_timeline_width);
         let total_width = timeline_width + self.fixed_content_width();
 
+        let scale = (self.max_dimension / total_width)
+            .min(self.max_dimension / total_height)
+            .min(self.scale);
+
         Layout {
-            total_width,
-            total_height,
-            avatar_column_width: self.avatar_column_width,
-            timeline_width,
-            margin: self.margin,
-            label_area_height: self.label_area_height,
-            entry_height: self.entry_height,
-            total_entry_height,
-            avatar_size: self.avatar_size,
+            total_width: total_width * scale,
+            total_height: total_height * scale,
+            avatar_column_width: self.avatar_column_width * scale,
+            stats_column_width: self.stats_column_width() * scale,
+            timeline_width: timeline_width * scale,
+            margin: self.margin.scaled(scale),
+            label_area_height: self.label_area_height * scale,
+            entry_height: self.entry_height * scale,
+            total_entry_height: total_entry_height * scale,
+            legend_area_height: legend_area_height * scale,
+            avatar_size: self.avatar_size * scale,
+            section_corner_radius: self.section_corner_radius * scale,
+            scale,
         }
     }
 
     fn fixed_content_width(&self) -> f32{
-        self.avatar_column_width + self.margin.horizontal()
+        self.avatar_column_width + self.stats_column_width() + self.margin.horizontal()
+    }
+
+    fn stats_column_width(&self) -> f32 {
+        if self.show_stats { self.stats_column_width } else { 0.0 }
     }
 }
 
@@ -62,9 +126,16 @@ pub struct Layout {
     label_area_height: f32,
     entry_height: f32,
     total_entry_height: f32,
+    // zero when the legend is hidden.
+    legend_area_height: f32,
     avatar_column_width: f32,
+    // zero when the stats column is hidden.
+    stats_column_width: f32,
     timeline_width: f32,
     avatar_size: f32,
+    section_corner_radius: f32,
+    // the configured scale unless the layout was scaled down to fit the maximum dimension.
+    scale: f32,
 }
 
 
@@ -81,6 +152,14 @@ impl Layout {
         self.avatar_size
     }
 
+    pub fn section_corner_radius(&self) -> f32 {
+        self.section_corner_radius
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
     pub fn full_timeline_bb(&self) -> NonZeroRect {
         NonZeroRect::from_xywh(
             self.margin.left + self.avatar_column_width,
@@ -90,6 +169,26 @@ impl Layout {
         ).unwrap()
     }
 
+    // returns the corner above the avatar column, left of the tick labels.
+    pub fn header_bb(&self) -> Option<NonZeroRect> {
+        NonZeroRect::from_xywh(
+            self.margin.left,
+            self.margin.top,
+            self.avatar_column_width,
+            self.label_area_height,
+        )
+    }
+
+    // returns the legend area below the timeline, if the legend is shown.
+    pub fn legend_bb(&self) -> Option<NonZeroRect> {
+        NonZeroRect::from_xywh(
+            self.margin.left + self.avatar_column_width,
+            self.margin.top + self.label_area_height + self.total_entry_height,
+            self.timeline_width,
+            self.legend_area_height,
+        )
+    }
+
     // returns timeline bounding-box for i-th entry.
     pub fn timeline_bb_for_entry(&self, i: usize) -> NonZeroRect {
         NonZeroRect::from_xywh(
@@ -109,4 +208,24 @@ impl Layout {
             self.entry_height,
         ).unwrap()
     }
+
+    // returns stats bounding-box for i-th entry, if the column is shown.
+    pub fn stats_bb_for_entry(&self, i: usize) -> Option<NonZeroRect> {
+        NonZeroRect::from_xywh(
+            self.margin.left + self.avatar_column_width + self.timeline_width,
+            self.margin.top + self.label_area_height + i as f32 * self.entry_height,
+            self.stats_column_width,
+            self.entry_height,
+        )
+    }
+
+    // returns the full-width pixel area of the i-th entry.
+    pub fn row_rect_for_entry(&self, i: usize) -> IntRect {
+        IntRect::from_xywh(
+            0,
+            (self.margin.top + self.label_area_height + i as f32 * self.entry_height) as i32,
+            self.total_width as u32,
+            self.entry_height as u32,
+        ).unwrap()
+    }
 }
@@ -1,73 +1,155 @@
-mod policy;
-mod layout;
+pub mod policy;
+pub mod layout;
 
 use std::error::Error;
-use crate::model::Participant;
-use crate::service::renderer::timeline::layout::{LayoutConfig, Margin};
-use crate::service::renderer::timeline::policy::AspectRatioPolicy;
-use crate::service::renderer::view::{FillStyle, Timeline};
+use crate::model::{Participant, ScheduledEventTag};
+use crate::service::i18n::{Language, TextKey};
+use crate::service::renderer::draw::{draw_avatar, TextAnchor, TextImage, TextRenderer};
+use crate::service::renderer::encoder::{encode, encode_apng, ImageFormat};
+use crate::service::renderer::theme::Theme;
+use crate::service::renderer::timeline::layout::{Layout, LayoutConfig};
+use crate::service::renderer::view::{format_duration, Capacity, EntryStats, FillStyle, PausedSection, StreamingSection, Timeline, TimelineEntry, VoiceSection};
 use crate::service::report::RoomDTO;
-use chrono::{DurationRound, TimeDelta};
-use cosmic_text::{Attrs, Buffer, FontSystem, Metrics, Shaping, SwashCache, SwashContent};
+use chrono::TimeDelta;
 use serenity::all::{
     CreateEmbed, CreateEmbedAuthor, CreateEmbedFooter, FormattedTimestamp,
     FormattedTimestampStyle, Mentionable, Timestamp,
 };
-use std::sync::{Arc, Mutex};
+use moka::sync::Cache;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
-use tiny_skia::{Color, FillRule, FilterQuality, IntSize, LineCap, Mask, NonZeroRect, Paint, PathBuilder, Pattern, Pixmap, PixmapPaint, PixmapRef, Rect, Shader, SpreadMode, Stroke, Transform};
+use tiny_skia::{BlendMode, Color, FillRule, FilterQuality, GradientStop, LineCap, LineJoin, LinearGradient, NonZeroRect, Paint, PathBuilder, Pattern, Pixmap, PixmapPaint, Point, Rect, Shader, SpreadMode, Stroke, StrokeDash, Transform};
 use tokio::time::Instant;
-use tracing::debug;
+use tracing::{debug, warn};
 
-const TIMELINE_BAR_HEIGHT_RATIO: f32 = 4.0 / 7.0;
-const TIMELINE_BAR_TOP_RATIO: f32 = 3.0 / 14.0;
+// renders slower than this are logged, to notice large rooms getting expensive.
+const RENDER_BUDGET: Duration = Duration::from_millis(500);
 
-const TIMELINE_BAR_BOTTOM_RATIO: f32 = TIMELINE_BAR_TOP_RATIO + TIMELINE_BAR_HEIGHT_RATIO;
+// Discord rejects attachments larger than this; scaled images are rendered smaller to fit.
+const DISCORD_UPLOAD_LIMIT: usize = 10 * 1024 * 1024;
+// encoded sizes only roughly follow the pixel count, so a retry aims a bit below the limit.
+const UPLOAD_RETRY_MARGIN: f32 = 0.9;
 
-const STROKE_WIDTH: f32 = 2.0;
-const STREAMING_STROKE_WIDTH: f32 = 5.0;
+// each frame of an animated timeline is shown this long.
+const ANIMATION_FRAME_DELAY: Duration = Duration::from_millis(400);
 
-const HATCH_SIZE: u32 = 10;
-const HATCH_LINE_WIDTH: f32 = 3.0;
-const MUTED_ALPHA: f32 = 0.8;
+const TIMELINE_BAR_HEIGHT_RATIO: f32 = 4.0 / 7.0;
+pub(crate) const TIMELINE_BAR_TOP_RATIO: f32 = 3.0 / 14.0;
+
+pub(crate) const TIMELINE_BAR_BOTTOM_RATIO: f32 = TIMELINE_BAR_TOP_RATIO + TIMELINE_BAR_HEIGHT_RATIO;
+
+// speaking sections are a thin bar right below the voice bar.
+pub(crate) const SPEAKING_BAR_TOP_RATIO: f32 = TIMELINE_BAR_BOTTOM_RATIO + 1.0 / 28.0;
+pub(crate) const SPEAKING_BAR_BOTTOM_RATIO: f32 = SPEAKING_BAR_TOP_RATIO + 1.0 / 14.0;
+
+// camera usage mirrors it right above the voice bar.
+pub(crate) const VIDEO_BAR_BOTTOM_RATIO: f32 = TIMELINE_BAR_TOP_RATIO - 1.0 / 28.0;
+pub(crate) const VIDEO_BAR_TOP_RATIO: f32 = VIDEO_BAR_BOTTOM_RATIO - 1.0 / 14.0;
+
+pub(crate) const STROKE_WIDTH: f32 = 2.0;
+// a section of a few seconds in a long call is widened to this many pixels, so it never vanishes.
+pub(crate) const MIN_SECTION_WIDTH: f32 = 2.0;
+const TICK_FONT_SIZE: f32 = 20.0;
+pub(crate) const STREAMING_STROKE_WIDTH: f32 = 5.0;
+pub(crate) const STREAMING_LABEL_FONT_SIZE: f32 = 12.0;
+// rough advance of a digit relative to the font size, to skip labels wider than their stream.
+pub(crate) const STREAMING_LABEL_CHAR_WIDTH: f32 = 0.6;
+
+pub(crate) const HATCH_SIZE: u32 = 10;
+pub(crate) const HATCH_LINE_WIDTH: f32 = 3.0;
+pub(crate) const MUTED_ALPHA: f32 = 0.8;
+pub(crate) const DEPARTED_AVATAR_ALPHA: f32 = 0.5;
+
+// open sections fade out over this many pixels instead of ending at a hard edge.
+pub(crate) const ONGOING_FADE_WIDTH: f32 = 24.0;
+pub(crate) const INDICATOR_DASH: [f32; 2] = [6.0, 4.0];
+pub(crate) const RECONNECT_DASH: [f32; 2] = [3.0, 3.0];
+pub(crate) const GAP_DASH: [f32; 2] = [2.0, 4.0];
+pub(crate) const GAP_WIDTH: f32 = 1.0;
+pub(crate) const SEPARATOR_DASH: [f32; 2] = [8.0, 6.0];
+pub(crate) const SEPARATOR_WIDTH: f32 = 2.0;
+
+// badges sit on the rim of the avatar, sized relative to it.
+pub(crate) const BADGE_SIZE_RATIO: f32 = 0.36;
+pub(crate) const BADGE_OFFSET_RATIO: f32 = 0.36;
+pub(crate) const BADGE_BORDER_WIDTH: f32 = 2.0;
+
+pub(crate) const STATS_FONT_SIZE: f32 = 14.0;
+pub(crate) const STATS_PADDING: f32 = 12.0;
+
+pub(crate) const LEGEND_FONT_SIZE: f32 = 14.0;
+
+// paused spans shade every row, faint enough to keep the bars beneath readable.
+pub(crate) const PAUSED_ALPHA: f32 = 0.25;
+pub(crate) const PAUSED_FONT_SIZE: f32 = 14.0;
+pub(crate) const CAPACITY_ALPHA: f32 = 0.3;
+pub(crate) const CAPACITY_FONT_SIZE: f32 = 14.0;
+pub(crate) const LEGEND_SWATCH_WIDTH: f32 = 32.0;
+pub(crate) const LEGEND_LABEL_GAP: f32 = 6.0;
+pub(crate) const LEGEND_INACTIVE_ALPHA: f32 = 0.35;
+
+// Discord rejects embed fields longer than this.
+const EMBED_FIELD_LIMIT: usize = 1024;
+
+// rasterized rows are kept by their fingerprint; a row takes roughly 300KB.
+const ROW_CACHE_CAPACITY_BYTES: u64 = 64 * 1024 * 1024;
+const ROW_CACHE_IDLE_SECS: u64 = 60 * 60;
+// tick and legend labels repeat across renders; each takes a few KB.
+const LABEL_CACHE_CAPACITY_BYTES: u64 = 4 * 1024 * 1024;
 
 pub struct TimelineRenderer{
     layout_config: LayoutConfig,
-    font_system: Arc<Mutex<FontSystem>>,
-    swash_cache: Arc<Mutex<SwashCache>>,
+    text_renderer: Arc<TextRenderer>,
+    row_cache: Cache<u64, Arc<Pixmap>>,
+    label_cache: Cache<u64, Arc<TextImage>>,
 }
 
 #[derive(Error, Debug)]
 pub enum TimelineRendererError {
     #[error("failed to encode png: {0}")]
     PngEncoding(Box<dyn Error + Send + Sync + 'static>),
+
+    #[error("failed to encode image: {0}")]
+    Encoding(Box<dyn Error + Send + Sync + 'static>),
 }
 
 pub type TimelineRendererResult<T> = Result<T, TimelineRendererError>;
 
 impl TimelineRenderer {
-    pub fn new() -> TimelineRenderer {
+    pub fn new(text_renderer: Arc<TextRenderer>) -> TimelineRenderer {
         TimelineRenderer {
-            layout_config: LayoutConfig{
-                margin: Margin{
-                    left: 10.0,
-                    top: 10.0,
-                    right: 10.0,
-                    bottom: 10.0,
-                },
-                label_area_height: 20.0,
-                avatar_column_width: 100.0,
-                min_timeline_width: 900.0,
-                entry_height: 70.0,
-                avatar_size: 64.0,
-                aspect_ratio_policy: AspectRatioPolicy::discord_thumbnail_4_3(),
-            },
-            font_system: Arc::new(Mutex::new(FontSystem::new())),
-            swash_cache: Arc::new(Mutex::new(SwashCache::new())),
+            layout_config: LayoutConfig::default(),
+            text_renderer,
+            row_cache: Cache::builder()
+                .weigher(|_, row: &Arc<Pixmap>| row.data().len() as u32)
+                .max_capacity(ROW_CACHE_CAPACITY_BYTES)
+                .time_to_idle(Duration::from_secs(ROW_CACHE_IDLE_SECS))
+                .build(),
+            label_cache: Cache::builder()
+                .weigher(|_, label: &Arc<TextImage>| label.byte_size() as u32)
+                .max_capacity(LABEL_CACHE_CAPACITY_BYTES)
+                .build(),
         }
     }
 
-    fn format_time_delta(delta: TimeDelta) -> String {
+
+    // renders every image this many times larger, e.g. 2.0 for crisp display on high-DPI screens.
+    pub fn with_scale(mut self, scale: f32) -> Self {
+        self.layout_config.scale = scale.max(1.0);
+        self
+    }
+
+    pub(crate) fn scale(&self) -> f32 {
+        self.layout_config.scale
+    }
+
+    pub(crate) fn text_renderer(&self) -> &Arc<TextRenderer> {
+        &self.text_renderer
+    }
+
+    pub fn format_time_delta(delta: TimeDelta) -> String {
         let total_seconds = delta.num_minutes();
         let hours = total_seconds / 60;
         let minutes = total_seconds % 60;
@@ -75,130 +157,172 @@ impl TimelineRenderer {
         format!("{:01}:{:02}", hours, minutes)
     }
 
-    fn format_history(now: Instant, participants: &Vec<Participant>) -> String {
-        participants
+    // falls back to the longest participants when the list exceeds the field limit.
+    fn format_history(now: Instant, participants: &[Participant], language: Language) -> String {
+        let mut lines: Vec<(Duration, String)> = participants
             .iter()
             .map(|participant| {
-                format!(
-                    "{} ({})",
-                    participant.name(),
-                    Self::format_time_delta(
-                        TimeDelta::from_std(participant.calculate_duration(now)).unwrap()
-                    )
-                )
+                let name = match participant.is_departed() {
+                    true => format!("*{}* - {}", participant.name(), language.text(TextKey::LeftServer)),
+                    false => participant.name().to_string(),
+                };
+                let duration = participant.calculate_duration(now);
+                (duration, format!("{} ({})", name, Self::format_time_delta(TimeDelta::from_std(duration).unwrap())))
             })
-            .collect::<Vec<String>>()
-            .join("\n")
-    }
-
-    pub fn generate_png_image(&self, timeline: &Timeline) -> TimelineRendererResult<Vec<u8>> {
-        let n_entries = timeline.entries.len();
-        let layout = self.layout_config.calculate(n_entries);
+            .collect();
 
-        let path = {
-            let mut path_builder = PathBuilder::new();
-            path_builder.push_circle(layout.avatar_size()/2.0, layout.avatar_size()/2.0, layout.avatar_size()/2.0);
-            path_builder.push_circle(layout.avatar_size()/2.0, layout.avatar_size()/2.0, 0.0);
-            path_builder.finish().unwrap()
-        };
+        if field_length(lines.iter().map(|(_, line)| line)) > EMBED_FIELD_LIMIT {
+            lines.sort_by_key(|(duration, _)| std::cmp::Reverse(*duration));
+        }
+        fit_field(lines.into_iter().map(|(_, line)| line).collect(), language)
+    }
 
-        let avatar_mask = |transform| {
-            let mut avatar_mask = Mask::new(layout.total_width() as u32, layout.total_height() as u32).unwrap();
-            avatar_mask.fill_path(&path, FillRule::EvenOdd, true, transform);
-            avatar_mask
-        };
+    // connected participants with the state of their microphone and whether they stream.
+    fn format_connected(participants: &[Participant], language: Language) -> Option<String> {
+        let lines: Vec<String> = participants
+            .iter()
+            .filter_map(|participant| {
+                let activity = participant.history().last().filter(|activity| activity.is_ongoing())?;
+                let flags = activity.flags();
+                let mic = match FillStyle::from_flags(flags) {
+                    FillStyle::Active => "🎙",
+                    FillStyle::Muted | FillStyle::Deafened => "🔇",
+                };
+                let stream = if flags.is_sharing_screen { " 🖥" } else { "" };
+                Some(format!("{}{} {}", mic, stream, participant.name()))
+            })
+            .collect();
 
-        let mut pixmap = Pixmap::new(layout.total_width() as u32, layout.total_height() as u32).expect("invalid pixmap size");
-        pixmap.fill(Color::WHITE);
+        match lines.is_empty() {
+            true => None,
+            false => Some(fit_field(lines, language)),
+        }
+    }
 
-        // Render ticks first.
-        {
-            let mut font_system = self.font_system.lock().unwrap();
-            let mut swash_cache = self.swash_cache.lock().unwrap();
-            Self::render_ticks(&mut pixmap, timeline, layout.full_timeline_bb(), &mut font_system, &mut swash_cache);
+    // members present for at least `percent` of the event, longest first.
+    pub fn format_attendance(now: Instant, participants: &[Participant], event: &ScheduledEventTag, percent: u8, language: Language) -> String {
+        let duration = event.duration(now);
+        let mut attendees: Vec<(Duration, &str)> = participants
+            .iter()
+            .map(|participant| (participant.calculate_presence(now, event.started_at, event.end(now)), participant.name()))
+            .filter(|(presence, _)| !duration.is_zero() && presence.as_secs_f32() * 100.0 >= duration.as_secs_f32() * percent as f32)
+            .collect();
 
+        if attendees.is_empty() {
+            return language.text(TextKey::NoAttendance).replace("{percent}", &percent.to_string())
         }
 
-        let mut paint = PixmapPaint::default();
-        paint.quality = FilterQuality::Bicubic;
+        attendees.sort_by_key(|(presence, _)| std::cmp::Reverse(*presence));
+        let lines = attendees.into_iter()
+            .map(|(presence, name)| format!("✅ {} ({:.0}%)", name, (presence.as_secs_f32() / duration.as_secs_f32() * 100.0).min(100.0)))
+            .collect();
+        fit_field(lines, language)
+    }
 
-        // Then, Render fills.
-        for (i, entry) in timeline.entries.iter().enumerate() {
-            let headline_bb = layout.headline_bb_for_entry(i);
+    pub fn generate_image(&self, timeline: &Timeline, theme: &Theme, format: ImageFormat, quality: u8) -> TimelineRendererResult<Vec<u8>> {
+        let started = std::time::Instant::now();
+        let mut scale = self.layout_config.scale;
+        let image = loop {
+            let image = encode(&self.render_pixmap_at(timeline, theme, scale), format, quality)?;
+            if image.len() <= DISCORD_UPLOAD_LIMIT || scale <= 1.0 {
+                break image;
+            }
+            // the encoded size grows with the area, so the scale shrinks by the square root.
+            let next = (scale * (DISCORD_UPLOAD_LIMIT as f32 / image.len() as f32).sqrt() * UPLOAD_RETRY_MARGIN).max(1.0);
+            debug!("{} bytes at scale {} exceed the upload limit, rendering at scale {}", image.len(), scale, next);
+            scale = next;
+        };
 
-            let avatar = entry.avatar.as_ref();
+        let elapsed = started.elapsed();
+        if elapsed > RENDER_BUDGET {
+            warn!("rendering {} entries as {} took {} ms, over the {} ms budget", timeline.entries.len(), format.extension(), elapsed.as_millis(), RENDER_BUDGET.as_millis());
+        }
+        Ok(image)
+    }
 
-            let center = ((headline_bb.left() + headline_bb.right()) / 2.0, (headline_bb.top() + headline_bb.bottom()) / 2.0);
-            let transform = Transform::from_translate(center.0 - layout.avatar_size()/2.0, center.1 - layout.avatar_size()/2.0);
+    // an apng of the timeline growing through the given instants up to the whole timeline.
+    // an animation over the upload limit falls back to a still png, which is sized down to fit.
+    pub fn generate_animation(&self, timeline: &Timeline, theme: &Theme, instants: &[Instant]) -> TimelineRendererResult<Vec<u8>> {
+        let started = std::time::Instant::now();
+        let still = self.render_pixmap(timeline, theme);
+        let mut frames: Vec<Pixmap> = instants.iter()
+            .map(|at| self.render_pixmap(&timeline.until(*at), theme))
+            .collect();
+        frames.push(still.clone());
+        let animation = encode_apng(&still, &frames, ANIMATION_FRAME_DELAY)?;
+
+        let elapsed = started.elapsed();
+        if elapsed > RENDER_BUDGET {
+            warn!("rendering {} entries as {} frames took {} ms, over the {} ms budget", timeline.entries.len(), frames.len(), elapsed.as_millis(), RENDER_BUDGET.as_millis());
+        }
+        if animation.len() <= DISCORD_UPLOAD_LIMIT {
+            return Ok(animation)
+        }
+        debug!("animation of {} bytes exceeds the upload limit, rendering a still image", animation.len());
+        self.generate_image(timeline, theme, ImageFormat::Png, 0)
+    }
 
-            let avatar_transform = transform.pre_scale(layout.avatar_size()/avatar.width() as f32, layout.avatar_size()/avatar.height() as f32);
+    // draws the timeline without encoding it, so it can be composed into larger images.
+    pub(crate) fn render_pixmap(&self, timeline: &Timeline, theme: &Theme) -> Pixmap {
+        self.render_pixmap_at(timeline, theme, self.layout_config.scale)
+    }
 
-            pixmap.draw_pixmap(0, 0, avatar, &paint, avatar_transform, Some(&avatar_mask(transform)));
+    fn render_pixmap_at(&self, timeline: &Timeline, theme: &Theme, scale: f32) -> Pixmap {
+        let n_entries = timeline.entries.len();
+        let layout = LayoutConfig {
+            aspect_ratio_policy: timeline.aspect_ratio,
+            scale,
+            ..self.layout_config
+        }.calculate(n_entries);
 
-            let timeline_bb = layout.timeline_bb_for_entry(i);
-            let transformer = Transform::from_bbox(timeline_bb);
+        let mut pixmap = Pixmap::new(layout.total_width() as u32, layout.total_height() as u32).expect("invalid pixmap size");
+        pixmap.fill(theme.background);
 
-            let muted_pixmap = create_hatching_pattern(entry.active_color, entry.inactive_color);
-            let muted_shader = Pattern::new(muted_pixmap.as_ref(), SpreadMode::Repeat, FilterQuality::Bicubic, 1.0, Transform::identity());
-            let active_shader = Shader::SolidColor(entry.active_color);
-            let deafened_shader = Shader::SolidColor(entry.inactive_color);
+        // Render ticks first.
+        self.render_ticks(&mut pixmap, timeline, theme, layout.full_timeline_bb(), layout.scale());
 
-            for section in &entry.voice_sections {
-                let mut paint = Paint::default();
-                paint.anti_alias = true;
-                paint.shader = match section.fill_style {
-                    FillStyle::Active => active_shader.clone(),
-                    FillStyle::Muted => muted_shader.clone(),
-                    FillStyle::Deafened => deafened_shader.clone(),
-                };
+        // Then, Render fills, reusing rows that did not change since they were last rendered.
+        for (i, entry) in timeline.entries.iter().enumerate() {
+            let row_rect = layout.row_rect_for_entry(i);
+            let row_key = entry.fingerprint.map(|fingerprint| row_key(fingerprint, pixmap.width(), layout.scale(), timeline, theme));
 
-                let path = {
-                    let mut path_builder = PathBuilder::new();
-                    path_builder.push_rect(Rect::from_ltrb(
-                        section.start_ratio,
-                        TIMELINE_BAR_TOP_RATIO,
-                        section.end_ratio,
-                        TIMELINE_BAR_BOTTOM_RATIO,
-                    ).unwrap().transform(transformer).unwrap());
-                    path_builder.finish().unwrap()
+            if let Some(row) = row_key.and_then(|key| self.row_cache.get(&key)) {
+                let paint = PixmapPaint {
+                    blend_mode: BlendMode::Source,
+                    ..PixmapPaint::default()
                 };
-
-                pixmap.fill_path(&path, &paint, FillRule::Winding, Transform::identity(), None);
+                pixmap.draw_pixmap(row_rect.x(), row_rect.y(), (*row).as_ref(), &paint, Transform::identity(), None);
+                continue;
             }
 
+            self.render_entry(&mut pixmap, &layout, i, entry, theme, timeline.language);
 
-            let mut stroke = Stroke::default();
-            stroke.line_cap = LineCap::Round;
-            stroke.width = STROKE_WIDTH;
-
-            let mut paint = Paint::default();
-            paint.anti_alias = true;
-            paint.set_color(entry.active_color);
-
-            let path_creator = |start_ratio, end_ratio| {
-                let mut path_builder = PathBuilder::new();
-                path_builder.push_rect(Rect::from_ltrb(start_ratio, TIMELINE_BAR_TOP_RATIO, end_ratio, TIMELINE_BAR_BOTTOM_RATIO).unwrap());
+            if let Some(key) = row_key
+                && let Some(row) = pixmap.clone_rect(row_rect)
+            {
+                self.row_cache.insert(key, Arc::new(row));
+            }
+        }
 
-                path_builder.finish().unwrap().transform(transformer).unwrap()
-            };
+        // drawn over the rows, so cached rows stay valid while a pause goes on.
+        for section in &timeline.paused_sections {
+            self.render_paused_section(&mut pixmap, layout.full_timeline_bb(), section, timeline.language, theme, layout.scale());
+        }
 
-            // normal strokes later: they may overlap the previous rendered fills.
-            for section in &entry.voice_sections {
-                pixmap.stroke_path(&path_creator(section.start_ratio, section.end_ratio), &paint, &stroke, Transform::identity(), None);
-            }
+        for &i in &timeline.separators {
+            render_separator(&mut pixmap, &layout, i, theme.grid);
+        }
 
-            let mut stroke = Stroke::default();
-            stroke.line_cap = LineCap::Round;
-            stroke.width = STREAMING_STROKE_WIDTH;
+        if let Some(ratio) = timeline.indicator_ratio() {
+            render_indicator(&mut pixmap, layout.full_timeline_bb(), ratio, theme.stroke, layout.scale());
+        }
 
-            let mut paint = Paint::default();
-            paint.anti_alias = true;
-            paint.set_color(entry.streaming_color);
+        if let (Some(capacity), Some(header_bb)) = (timeline.capacity, layout.header_bb()) {
+            self.render_capacity(&mut pixmap, header_bb, capacity, theme, layout.scale());
+        }
 
-            // finally, streaming strokes
-            for section in &entry.streaming_sections {
-                pixmap.stroke_path(&path_creator(section.start_ratio, section.end_ratio), &paint, &stroke, Transform::identity(), None);
-            }
+        if let Some(legend_bb) = layout.legend_bb() {
+            self.render_legend(&mut pixmap, legend_bb, timeline.language, theme, layout.scale());
         }
 
         // draw start and end
@@ -212,16 +336,125 @@ impl TimelineRenderer {
             path_builder.finish().unwrap().transform(Transform::from_bbox(layout.full_timeline_bb())).unwrap()
         };
         let mut paint = Paint::default();
-        paint.set_color(Color::from_rgba(0.2, 0.2, 0.2, 1.0).unwrap());
+        paint.set_color(theme.stroke);
 
-        let mut stroke = Stroke::default();
-        stroke.width = STREAMING_STROKE_WIDTH;
+        let stroke = Stroke { width: STREAMING_STROKE_WIDTH * layout.scale(), ..Stroke::default() };
 
         pixmap.stroke_path(&path, &paint, &stroke, Transform::identity(), None);
 
-        let image = pixmap.encode_png().map_err(|e| TimelineRendererError::PngEncoding(Box::new(e)))?;
+        pixmap
+    }
+
+    fn render_entry(&self, pixmap: &mut Pixmap, layout: &Layout, i: usize, entry: &TimelineEntry, theme: &Theme, language: Language) {
+        let headline_bb = layout.headline_bb_for_entry(i);
 
-        Ok(image)
+        let center = ((headline_bb.left() + headline_bb.right()) / 2.0, (headline_bb.top() + headline_bb.bottom()) / 2.0);
+        draw_avatar(pixmap, &entry.avatar, center.0, center.1, layout.avatar_size());
+        if entry.is_departed {
+            wash_out_avatar(pixmap, center.0, center.1, layout.avatar_size(), theme.background);
+        }
+        self.render_badges(pixmap, center, layout.avatar_size(), entry, theme, layout.scale());
+
+        if let Some(stats_bb) = layout.stats_bb_for_entry(i) {
+            let font_size = STATS_FONT_SIZE * layout.scale();
+            for (line, y) in stats_lines(stats_bb, &entry.stats, language) {
+                self.text_renderer.draw_text(pixmap, &line, font_size, (stats_bb.left() + STATS_PADDING * layout.scale(), y), theme.text, TextAnchor::Start);
+            }
+        }
+
+        let timeline_bb = layout.timeline_bb_for_entry(i);
+        let transformer = Transform::from_bbox(timeline_bb);
+        let corner_radius = layout.section_corner_radius();
+        let scale = layout.scale();
+
+        // gaps first, so a rounded section end overlaps them.
+        for section in &entry.gap_sections {
+            render_gap(pixmap, timeline_bb, section.start_ratio, section.end_ratio, theme.grid, scale);
+        }
+
+        let muted_pixmap = create_hatching_pattern(entry.active_color, entry.inactive_color);
+        let muted_shader = Pattern::new(muted_pixmap.as_ref(), SpreadMode::Repeat, FilterQuality::Bicubic, 1.0, Transform::from_scale(scale, scale));
+        let active_shader = Shader::SolidColor(entry.active_color);
+        let deafened_shader = Shader::SolidColor(entry.inactive_color);
+
+        for section in &entry.voice_sections {
+            let mut paint = Paint { anti_alias: true, ..Paint::default() };
+            paint.shader = match section.fill_style {
+                FillStyle::Active => active_shader.clone(),
+                FillStyle::Muted => muted_shader.clone(),
+                FillStyle::Deafened => deafened_shader.clone(),
+            };
+
+            let shape = SectionShape::for_voice(timeline_bb, section, corner_radius);
+            if let Some(path) = shape.path(false) {
+                pixmap.fill_path(&path, &paint, FillRule::Winding, Transform::identity(), None);
+            }
+        }
+
+
+        let stroke = Stroke { line_cap: LineCap::Round, width: STROKE_WIDTH * scale, ..Stroke::default() };
+
+        let mut paint = Paint { anti_alias: true, ..Paint::default() };
+        paint.set_color(entry.active_color);
+
+        // normal strokes later: they may overlap the previous rendered fills.
+        // open sections are left without their right edge.
+        for section in &entry.voice_sections {
+            let shape = SectionShape::for_voice(timeline_bb, section, corner_radius);
+            if let Some(path) = shape.path(section.is_open) {
+                pixmap.stroke_path(&path, &paint, &stroke, Transform::identity(), None);
+            }
+        }
+
+        let stroke = Stroke { line_cap: LineCap::Round, width: STREAMING_STROKE_WIDTH * scale, ..Stroke::default() };
+
+        let mut paint = Paint { anti_alias: true, ..Paint::default() };
+        paint.set_color(entry.streaming_color);
+
+        // finally, streaming strokes
+        for section in &entry.streaming_sections {
+            let shape = SectionShape::new(timeline_bb, section.start_ratio, section.end_ratio, corner_radius, corner_radius);
+            if let Some(path) = shape.path(false) {
+                pixmap.stroke_path(&path, &paint, &stroke, Transform::identity(), None);
+            }
+        }
+
+        let font_size = STREAMING_LABEL_FONT_SIZE * layout.scale();
+        for section in &entry.streaming_sections {
+            if let Some((x, y, label)) = streaming_label(timeline_bb, section, font_size) {
+                self.text_renderer.draw_text(pixmap, &label, font_size, (x, y), theme.text, TextAnchor::Middle);
+            }
+        }
+
+        for section in entry.voice_sections.iter().filter(|section| section.is_open) {
+            render_fade_out(pixmap, timeline_bb, section.start_ratio, section.end_ratio, theme.background, scale);
+        }
+
+        for section in &entry.reconnect_sections {
+            render_reconnect(pixmap, timeline_bb, section.start_ratio, section.end_ratio, theme.background, entry.active_color, scale);
+        }
+
+        let mut paint = Paint { anti_alias: true, ..Paint::default() };
+        paint.set_color(entry.active_color);
+
+        for section in &entry.speaking_sections {
+            let rect = Rect::from_ltrb(section.start_ratio, SPEAKING_BAR_TOP_RATIO, section.end_ratio, SPEAKING_BAR_BOTTOM_RATIO)
+                .and_then(|rect| rect.transform(transformer));
+            if let Some(rect) = rect {
+                pixmap.fill_rect(rect, &paint, Transform::identity(), None);
+            }
+        }
+
+        let mut paint = Paint { anti_alias: true, ..Paint::default() };
+        paint.set_color(entry.streaming_color);
+
+        for section in &entry.video_sections {
+            let rect = Rect::from_ltrb(section.start_ratio, VIDEO_BAR_TOP_RATIO, section.end_ratio, VIDEO_BAR_BOTTOM_RATIO)
+                .and_then(|rect| rect.transform(transformer));
+            if let Some(rect) = rect {
+                pixmap.fill_rect(rect, &paint, Transform::identity(), None);
+            }
+        }
     }
 
     pub fn generate_ongoing_embed(
@@ -229,15 +462,22 @@ impl TimelineRenderer {
         now: Instant,
         timestamp: Timestamp,
         room: &RoomDTO,
+        image_name: &str,
+        language: Language,
     ) -> CreateEmbed {
         let elapsed = TimeDelta::from_std(now - room.created_at).unwrap();
+        let title = match room.session {
+            0 | 1 => language.text(TextKey::OnCall).to_string(),
+            session => format!("{} · {}", language.text(TextKey::OnCall), language.text(TextKey::Session).replace("{number}", &session.to_string())),
+        };
 
         let builder = CreateEmbed::new()
-            .author(CreateEmbedAuthor::new("ringring-rs"))
-            .title("On call")
-            .description(format!("Room is active on {}", room.channel_id.mention()))
+            // the name still tells the channel apart once its mention no longer resolves.
+            .author(CreateEmbedAuthor::new(room.channel_name.as_deref().map_or(String::from("ringring-rs"), |name| format!("🔊 {}", name))))
+            .title(title)
+            .description(language.text(TextKey::RoomActiveOn).replace("{channel}", &room.channel_id.mention().to_string()))
             .field(
-                "start",
+                language.text(TextKey::Start),
                 format!(
                     "{}",
                     FormattedTimestamp::new(
@@ -248,59 +488,521 @@ impl TimelineRenderer {
                 true,
             )
             .field(
-                "elapsed",
+                language.text(TextKey::Elapsed),
                 format!("{}", Self::format_time_delta(elapsed)),
                 true,
             )
             .field(
-                "history",
-                Self::format_history(now, &room.participants),
+                language.text(TextKey::PeakParticipants),
+                room.peak_participants.to_string(),
+                true,
+            );
+        let builder = match room.user_limit {
+            Some(limit) => {
+                let connected = room.participants.iter().filter(|participant| participant.is_connected()).count();
+                builder.field(language.text(TextKey::Capacity), Capacity { connected, limit }.label(), true)
+            },
+            None => builder,
+        };
+        let builder = builder
+            .field(
+                language.text(TextKey::History),
+                Self::format_history(now, &room.participants, language),
                 false,
-            )
-            .image("attachment://thumbnail.png")
-            .timestamp(timestamp)
-            .footer(CreateEmbedFooter::new("ringring-rs v25.11.10"));
+            );
+        let builder = match Self::format_connected(&room.participants, language) {
+            Some(connected) => builder.field(language.text(TextKey::ConnectedNow), connected, false),
+            None => builder,
+        };
 
         builder
+            .image(format!("attachment://{}", image_name))
+            .timestamp(timestamp)
+            .footer(CreateEmbedFooter::new("ringring-rs v25.11.10"))
+    }
+
+    // the number of sessions at the top right and a dot at the bottom right while connected.
+    fn render_badges(&self, pixmap: &mut Pixmap, center: (f32, f32), avatar_size: f32, entry: &TimelineEntry, theme: &Theme, scale: f32) {
+        let radius = avatar_size * BADGE_SIZE_RATIO / 2.0;
+        let offset = avatar_size * BADGE_OFFSET_RATIO;
+
+        if entry.sessions > 1 {
+            let (x, y) = (center.0 + offset, center.1 - offset);
+            fill_badge(pixmap, x, y, radius, theme.stroke, theme.background, scale);
+            let label = entry.sessions.min(99).to_string();
+            self.text_renderer.draw_text(pixmap, &label, radius * 1.4, (x, y + radius * 0.5), theme.background, TextAnchor::Middle);
+        }
+
+        if entry.is_connected {
+            fill_badge(pixmap, center.0 + offset, center.1 + offset, radius * 0.8, theme.online, theme.background, scale);
+        }
+    }
+
+    // one swatch per fill style, spread evenly over the width of the timeline.
+    fn render_legend(&self, pixmap: &mut Pixmap, legend_bb: NonZeroRect, language: Language, theme: &Theme, scale: f32) {
+        let mut inactive = theme.grid;
+        inactive.set_alpha(LEGEND_INACTIVE_ALPHA);
+        let muted_pixmap = create_hatching_pattern(theme.grid, inactive);
+
+        let outline = Stroke { width: STROKE_WIDTH * scale, ..Stroke::default() };
+        let mut outline_paint = Paint { anti_alias: true, ..Paint::default() };
+        outline_paint.set_color(theme.grid);
+
+        for (i, (style, label)) in legend_items(language).into_iter().enumerate() {
+            let (swatch, label_x, label_y) = legend_slot(legend_bb, i, scale);
+            let path = PathBuilder::from_rect(swatch.to_rect());
+
+            let mut paint = Paint { anti_alias: true, ..Paint::default() };
+            match style {
+                Some(style) => {
+                    match style {
+                        FillStyle::Active => paint.set_color(theme.grid),
+                        FillStyle::Muted => paint.shader = Pattern::new(muted_pixmap.as_ref(), SpreadMode::Repeat, FilterQuality::Bicubic, 1.0, Transform::from_scale(scale, scale)),
+                        FillStyle::Deafened => paint.set_color(inactive),
+                    }
+                    pixmap.fill_path(&path, &paint, FillRule::Winding, Transform::identity(), None);
+                    pixmap.stroke_path(&path, &outline_paint, &outline, Transform::identity(), None);
+                },
+                // streaming is only drawn as a thick outline.
+                None => {
+                    let stroke = Stroke { width: STREAMING_STROKE_WIDTH * scale, line_join: LineJoin::Round, ..Stroke::default() };
+                    paint.set_color(theme.stroke);
+                    pixmap.stroke_path(&path, &paint, &stroke, Transform::identity(), None);
+                },
+            }
+
+            self.draw_label(pixmap, label, LEGEND_FONT_SIZE * scale, (label_x, label_y), theme.text, TextAnchor::Start);
+        }
+    }
+
+    // a shaded band across all rows, labeled when it is wide enough.
+    fn render_paused_section(&self, pixmap: &mut Pixmap, full_timeline_bb: NonZeroRect, section: &PausedSection, language: Language, theme: &Theme, scale: f32) {
+        let rect = Rect::from_ltrb(
+            full_timeline_bb.left() + section.start_ratio * full_timeline_bb.width(),
+            full_timeline_bb.top(),
+            full_timeline_bb.left() + section.end_ratio.min(1.0) * full_timeline_bb.width(),
+            full_timeline_bb.bottom(),
+        );
+        let rect = match rect {
+            Some(rect) => rect,
+            None => return,
+        };
+
+        let mut color = theme.grid;
+        color.set_alpha(PAUSED_ALPHA);
+        let mut paint = Paint::default();
+        paint.set_color(color);
+        pixmap.fill_rect(rect, &paint, Transform::identity(), None);
+
+        let font_size = PAUSED_FONT_SIZE * scale;
+        if let Some((x, y)) = paused_label_position(rect, language, font_size) {
+            self.draw_label(pixmap, language.text(TextKey::TrackingPaused), font_size, (x, y), theme.text, TextAnchor::Middle);
+        }
     }
 
-    fn render_ticks(pixmap: &mut Pixmap, timeline: &Timeline, full_timeline_bb: NonZeroRect, font_system: &mut FontSystem, swash_cache: &mut SwashCache) {
-        let interval = TimeDelta::from_std(timeline.tick.interval).unwrap();
-        let base_timestamp = timeline.created_timestamp.duration_trunc(interval).unwrap();
+    // "7/10" over a bar filled as far as the channel is full.
+    fn render_capacity(&self, pixmap: &mut Pixmap, header_bb: NonZeroRect, capacity: Capacity, theme: &Theme, scale: f32) {
+        let filled = capacity_fill(header_bb, capacity);
+        if let Some(rect) = filled {
+            let mut color = theme.stroke;
+            color.set_alpha(CAPACITY_ALPHA);
+            let mut paint = Paint::default();
+            paint.set_color(color);
+            pixmap.fill_rect(rect, &paint, Transform::identity(), None);
+        }
+
+        let (x, y) = capacity_label_position(header_bb, CAPACITY_FONT_SIZE * scale);
+        self.draw_label(pixmap, &capacity.label(), CAPACITY_FONT_SIZE * scale, (x, y), theme.text, TextAnchor::Middle);
+    }
 
-        let mut delta = base_timestamp - timeline.created_timestamp;
-        if delta < TimeDelta::zero() {
-            delta += interval;
+    // like `draw_text`, but keeps the rasterized text so a repeated label is only blitted.
+    fn draw_label(&self, pixmap: &mut Pixmap, text: &str, font_size: f32, (x, y): (f32, f32), color: Color, anchor: TextAnchor) {
+        let key = label_key(text, font_size, color, anchor);
+        let image = self.label_cache.optionally_get_with(key, || {
+            self.text_renderer.rasterize(text, font_size, color, anchor).map(Arc::new)
+        });
+        if let Some(image) = image {
+            image.draw(pixmap, x, y);
         }
-        let elapsed = TimeDelta::from_std(timeline.terminated_at - timeline.created_at).unwrap();
+    }
 
+    fn render_ticks(&self, pixmap: &mut Pixmap, timeline: &Timeline, theme: &Theme, full_timeline_bb: NonZeroRect, scale: f32) {
         let transform = Transform::from_bbox(full_timeline_bb);
 
         let path = {
             let mut builder = PathBuilder::new();
 
-            while delta < elapsed {
-                let ratio = delta.as_seconds_f32()/elapsed.as_seconds_f32();
+            for (ratio, label) in timeline.tick_positions() {
                 let mut position = (ratio, 0.0f32).into();
                 transform.map_point(&mut position);
-                draw_text(pixmap, font_system, swash_cache, timeline.tick.format(timeline.created_timestamp + delta).as_str(), 20.0, position.x, position.y, Color::BLACK);
+                self.draw_label(pixmap, label.as_str(), TICK_FONT_SIZE * scale, (position.x, position.y), theme.text, TextAnchor::Middle);
                 builder.move_to(ratio, 0.0);
                 builder.line_to(ratio, 1.0);
-                delta += interval;
             }
 
             builder.finish().unwrap().transform(transform).unwrap()
         };
 
         let mut paint = Paint::default();
-        paint.set_color(Color::from_rgba(0.4, 0.4, 0.4, 1.0).unwrap());
+        paint.set_color(theme.grid);
         let mut stroke = Stroke::default();
         stroke.width = 1.0;
         pixmap.stroke_path(&path, &paint, &stroke, Transform::identity(), None);
     }
 }
 
-fn create_hatching_pattern(active: Color, inactive: Color) -> Pixmap {
+// the pixel outline of a section bar, built in pixel space so corners stay circular.
+#[derive(Clone, Copy)]
+pub(crate) struct SectionShape {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub start_radius: f32,
+    pub end_radius: f32,
+}
+
+impl SectionShape {
+    pub fn new(timeline_bb: NonZeroRect, start_ratio: f32, end_ratio: f32, start_radius: f32, end_radius: f32) -> SectionShape {
+        // edges snap to whole pixels, so touching sections meet without a blended seam.
+        let mut left = (timeline_bb.left() + start_ratio * timeline_bb.width()).round();
+        let mut right = (timeline_bb.left() + end_ratio * timeline_bb.width()).round().max(left + MIN_SECTION_WIDTH);
+        if right > timeline_bb.right() {
+            left = (left - (right - timeline_bb.right())).max(timeline_bb.left());
+            right = timeline_bb.right();
+        }
+        let top = (timeline_bb.top() + TIMELINE_BAR_TOP_RATIO * timeline_bb.height()).round();
+        let bottom = (timeline_bb.top() + TIMELINE_BAR_BOTTOM_RATIO * timeline_bb.height()).round();
+
+        // short sections get smaller corners instead of overlapping arcs.
+        let max_radius = ((right - left) / 2.0).min((bottom - top) / 2.0).max(0.0);
+        SectionShape {
+            left,
+            top,
+            right,
+            bottom,
+            start_radius: start_radius.min(max_radius),
+            end_radius: end_radius.min(max_radius),
+        }
+    }
+
+    // touching sections and the open end of an ongoing one keep square corners.
+    pub fn for_voice(timeline_bb: NonZeroRect, section: &VoiceSection, corner_radius: f32) -> SectionShape {
+        SectionShape::new(
+            timeline_bb,
+            section.start_ratio,
+            section.end_ratio,
+            if section.continues_previous { 0.0 } else { corner_radius },
+            if section.continues_next || section.is_open { 0.0 } else { corner_radius },
+        )
+    }
+
+    // an open shape has no right edge.
+    pub fn path(&self, open: bool) -> Option<tiny_skia::Path> {
+        let SectionShape { left, top, right, bottom, start_radius: rs, end_radius: re } = *self;
+        let mut builder = PathBuilder::new();
+        if open {
+            builder.move_to(right, top);
+            builder.line_to(left + rs, top);
+            builder.quad_to(left, top, left, top + rs);
+            builder.line_to(left, bottom - rs);
+            builder.quad_to(left, bottom, left + rs, bottom);
+            builder.line_to(right, bottom);
+        } else {
+            builder.move_to(left + rs, top);
+            builder.line_to(right - re, top);
+            builder.quad_to(right, top, right, top + re);
+            builder.line_to(right, bottom - re);
+            builder.quad_to(right, bottom, right - re, bottom);
+            builder.line_to(left + rs, bottom);
+            builder.quad_to(left, bottom, left, bottom - rs);
+            builder.line_to(left, top + rs);
+            builder.quad_to(left, top, left + rs, top);
+            builder.close();
+        }
+        builder.finish()
+    }
+}
+
+// total duration, mute percentage and stream time, each with the baseline it is drawn at.
+pub(crate) fn stats_lines(stats_bb: NonZeroRect, stats: &EntryStats, language: Language) -> [(String, f32); 3] {
+    let baseline = |ratio: f32| stats_bb.top() + ratio * stats_bb.height();
+    [
+        (format_duration(stats.total), baseline(0.35)),
+        (format!("{} {}%", language.text(TextKey::Muted), stats.muted_percentage()), baseline(0.6)),
+        (format!("{} {}", language.text(TextKey::Streaming), format_duration(stats.streaming)), baseline(0.85)),
+    ]
+}
+
+// fill styles explained by the legend; None stands for the streaming outline.
+pub(crate) fn legend_items(language: Language) -> [(Option<FillStyle>, &'static str); 4] {
+    [
+        (Some(FillStyle::Active), language.text(TextKey::Active)),
+        (Some(FillStyle::Muted), language.text(TextKey::Muted)),
+        (Some(FillStyle::Deafened), language.text(TextKey::Deafened)),
+        (None, language.text(TextKey::Streaming)),
+    ]
+}
+
+// returns the swatch of the i-th legend item and the baseline its label starts at.
+pub(crate) fn legend_slot(legend_bb: NonZeroRect, i: usize, scale: f32) -> (NonZeroRect, f32, f32) {
+    let slot_width = legend_bb.width() / 4.0;
+    let height = legend_bb.height() / 2.0;
+    let left = legend_bb.left() + i as f32 * slot_width;
+    let top = legend_bb.top() + (legend_bb.height() - height) / 2.0;
+    let swatch = NonZeroRect::from_xywh(left, top, LEGEND_SWATCH_WIDTH * scale, height).unwrap();
+
+    let label_x = swatch.right() + LEGEND_LABEL_GAP * scale;
+    let label_y = top + height / 2.0 + LEGEND_FONT_SIZE * scale / 3.0;
+    (swatch, label_x, label_y)
+}
+
+// the part of the header filled by the connected members.
+pub(crate) fn capacity_fill(header_bb: NonZeroRect, capacity: Capacity) -> Option<Rect> {
+    Rect::from_xywh(header_bb.left(), header_bb.top(), header_bb.width() * capacity.ratio(), header_bb.height())
+}
+
+pub(crate) fn capacity_label_position(header_bb: NonZeroRect, font_size: f32) -> (f32, f32) {
+    ((header_bb.left() + header_bb.right()) / 2.0, (header_bb.top() + header_bb.bottom()) / 2.0 + font_size / 3.0)
+}
+
+// the centered baseline at the top of the band, or None when the band is too narrow to be labeled.
+pub(crate) fn paused_label_position(band: Rect, language: Language, font_size: f32) -> Option<(f32, f32)> {
+    let label = language.text(TextKey::TrackingPaused);
+    // wide glyphs such as kana take about twice the advance of latin ones.
+    let width = label.chars().map(|c| if c.is_ascii() { 1.0 } else { 2.0 }).sum::<f32>() * font_size * STREAMING_LABEL_CHAR_WIDTH;
+    if band.width() < width {
+        return None
+    }
+    Some((band.left() + band.width() / 2.0, band.top() + font_size * 1.5))
+}
+
+// the centered baseline right above the streaming stroke, or None when the stream is too short to be labeled.
+pub(crate) fn streaming_label(timeline_bb: NonZeroRect, section: &StreamingSection, font_size: f32) -> Option<(f32, f32, String)> {
+    let label = section.duration_label();
+    let width = (section.end_ratio - section.start_ratio) * timeline_bb.width();
+    if width < label.len() as f32 * font_size * STREAMING_LABEL_CHAR_WIDTH {
+        return None
+    }
+
+    let x = timeline_bb.left() + (section.start_ratio + section.end_ratio) / 2.0 * timeline_bb.width();
+    let y = timeline_bb.top() + TIMELINE_BAR_TOP_RATIO * timeline_bb.height() - STREAMING_STROKE_WIDTH;
+    Some((x, y, label))
+}
+
+// joins the lines, replacing those beyond the embed field limit with a "+K more" line.
+fn fit_field(lines: Vec<String>, language: Language) -> String {
+    if field_length(&lines) <= EMBED_FIELD_LIMIT {
+        return lines.join("\n")
+    }
+
+    let more = |count: usize| language.text(TextKey::MoreParticipants).replace("{count}", &count.to_string());
+    // reserve room for the widest possible "+K more" line.
+    let budget = EMBED_FIELD_LIMIT - more(lines.len()).chars().count() - 1;
+
+    let mut used = 0;
+    let shown = lines.iter()
+        .take_while(|line| {
+            used += line.chars().count() + 1;
+            used <= budget
+        })
+        .count();
+
+    format!("{}\n{}", lines[..shown].join("\n"), more(lines.len() - shown))
+}
+
+// characters of the lines joined by newlines.
+fn field_length<'a>(lines: impl IntoIterator<Item = &'a String>) -> usize {
+    lines.into_iter().map(|line| line.chars().count() + 1).sum::<usize>().saturating_sub(1)
+}
+
+// a filled circle with a ring in the background color, so it stands out from the avatar.
+fn fill_badge(pixmap: &mut Pixmap, cx: f32, cy: f32, radius: f32, color: Color, border: Color, scale: f32) {
+    for (radius, color) in [(radius + BADGE_BORDER_WIDTH * scale, border), (radius, color)] {
+        if let Some(path) = PathBuilder::from_circle(cx, cy, radius) {
+            let mut paint = Paint { anti_alias: true, ..Paint::default() };
+            paint.set_color(color);
+            pixmap.fill_path(&path, &paint, FillRule::Winding, Transform::identity(), None);
+        }
+    }
+}
+
+// grays the avatar of a departed member into the background.
+fn wash_out_avatar(pixmap: &mut Pixmap, cx: f32, cy: f32, size: f32, background: Color) {
+    let path = match PathBuilder::from_circle(cx, cy, size / 2.0) {
+        Some(path) => path,
+        None => return,
+    };
+
+    let mut color = background;
+    color.set_alpha(DEPARTED_AVATAR_ALPHA);
+    let mut paint = Paint { anti_alias: true, ..Paint::default() };
+    paint.set_color(color);
+    pixmap.fill_path(&path, &paint, FillRule::Winding, Transform::identity(), None);
+}
+
+// blends the tail of an open section into the background, so it reads as still going on.
+fn render_fade_out(pixmap: &mut Pixmap, timeline_bb: NonZeroRect, start_ratio: f32, end_ratio: f32, background: Color, scale: f32) {
+    let start = timeline_bb.left() + start_ratio * timeline_bb.width();
+    let end = timeline_bb.left() + end_ratio * timeline_bb.width();
+    let fade_start = (end - ONGOING_FADE_WIDTH * scale).max(start);
+    if end - fade_start < 1.0 {
+        return;
+    }
+
+    let mut transparent = background;
+    transparent.set_alpha(0.0);
+    let shader = match LinearGradient::new(
+        Point::from_xy(fade_start, 0.0),
+        Point::from_xy(end, 0.0),
+        vec![GradientStop::new(0.0, transparent), GradientStop::new(1.0, background)],
+        SpreadMode::Pad,
+        Transform::identity(),
+    ) {
+        Some(shader) => shader,
+        None => return,
+    };
+
+    // covers the strokes on the top and bottom edges as well.
+    let stroke_width = STROKE_WIDTH * scale;
+    let top = timeline_bb.top() + TIMELINE_BAR_TOP_RATIO * timeline_bb.height() - stroke_width;
+    let bottom = timeline_bb.top() + TIMELINE_BAR_BOTTOM_RATIO * timeline_bb.height() + stroke_width;
+    let rect = match Rect::from_ltrb(fade_start, top, end + stroke_width, bottom) {
+        Some(rect) => rect,
+        None => return,
+    };
+
+    let paint = Paint { anti_alias: true, shader, ..Paint::default() };
+    pixmap.fill_rect(rect, &paint, Transform::identity(), None);
+}
+
+// a faint dashed line through the middle of the bar.
+fn render_gap(pixmap: &mut Pixmap, timeline_bb: NonZeroRect, start_ratio: f32, end_ratio: f32, color: Color, scale: f32) {
+    let start = timeline_bb.left() + start_ratio * timeline_bb.width();
+    let end = timeline_bb.left() + end_ratio * timeline_bb.width();
+    let middle = timeline_bb.top() + (TIMELINE_BAR_TOP_RATIO + TIMELINE_BAR_BOTTOM_RATIO) / 2.0 * timeline_bb.height();
+
+    let path = {
+        let mut path_builder = PathBuilder::new();
+        path_builder.move_to(start, middle);
+        path_builder.line_to(end, middle);
+        path_builder.finish()
+    };
+    let path = match path {
+        Some(path) => path,
+        None => return,
+    };
+
+    let mut paint = Paint { anti_alias: true, ..Paint::default() };
+    paint.set_color(color);
+
+    let stroke = Stroke { width: GAP_WIDTH * scale, dash: scaled_dash(GAP_DASH, scale), ..Stroke::default() };
+    pixmap.stroke_path(&path, &paint, &stroke, Transform::identity(), None);
+}
+
+// cuts the gap out of the bar and bridges it with a dashed line.
+fn render_reconnect(pixmap: &mut Pixmap, timeline_bb: NonZeroRect, start_ratio: f32, end_ratio: f32, background: Color, color: Color, scale: f32) {
+    let start = timeline_bb.left() + start_ratio * timeline_bb.width();
+    let end = timeline_bb.left() + end_ratio * timeline_bb.width();
+    let stroke_width = STROKE_WIDTH * scale;
+    let top = timeline_bb.top() + TIMELINE_BAR_TOP_RATIO * timeline_bb.height() - stroke_width;
+    let bottom = timeline_bb.top() + TIMELINE_BAR_BOTTOM_RATIO * timeline_bb.height() + stroke_width;
+    let rect = match Rect::from_ltrb(start, top, end, bottom) {
+        Some(rect) => rect,
+        None => return,
+    };
+
+    let mut paint = Paint::default();
+    paint.set_color(background);
+    pixmap.fill_rect(rect, &paint, Transform::identity(), None);
+
+    let middle = (top + bottom) / 2.0;
+    let path = {
+        let mut path_builder = PathBuilder::new();
+        path_builder.move_to(start, middle);
+        path_builder.line_to(end, middle);
+        path_builder.finish()
+    };
+    let path = match path {
+        Some(path) => path,
+        None => return,
+    };
+
+    let mut paint = Paint { anti_alias: true, ..Paint::default() };
+    paint.set_color(color);
+
+    let stroke = Stroke { width: stroke_width, dash: scaled_dash(RECONNECT_DASH, scale), ..Stroke::default() };
+
+    pixmap.stroke_path(&path, &paint, &stroke, Transform::identity(), None);
+}
+
+// a dashed vertical line marking the current time.
+fn render_indicator(pixmap: &mut Pixmap, full_timeline_bb: NonZeroRect, ratio: f32, color: Color, scale: f32) {
+    let path = {
+        let mut path_builder = PathBuilder::new();
+        path_builder.move_to(ratio, 0.0);
+        path_builder.line_to(ratio, 1.0);
+        path_builder.finish().unwrap().transform(Transform::from_bbox(full_timeline_bb)).unwrap()
+    };
+
+    let mut paint = Paint { anti_alias: true, ..Paint::default() };
+    paint.set_color(color);
+
+    let stroke = Stroke { width: STROKE_WIDTH * scale, dash: scaled_dash(INDICATOR_DASH, scale), ..Stroke::default() };
+
+    pixmap.stroke_path(&path, &paint, &stroke, Transform::identity(), None);
+}
+
+// a line above the i-th entry spanning the avatars and the timeline.
+fn render_separator(pixmap: &mut Pixmap, layout: &Layout, i: usize, color: Color) {
+    let y = layout.headline_bb_for_entry(i).top();
+    let path = {
+        let mut path_builder = PathBuilder::new();
+        path_builder.move_to(layout.headline_bb_for_entry(i).left(), y);
+        path_builder.line_to(layout.timeline_bb_for_entry(i).right(), y);
+        path_builder.finish().unwrap()
+    };
+
+    let mut paint = Paint { anti_alias: true, ..Paint::default() };
+    paint.set_color(color);
+
+    let stroke = Stroke { width: SEPARATOR_WIDTH * layout.scale(), dash: scaled_dash(SEPARATOR_DASH, layout.scale()), ..Stroke::default() };
+
+    pixmap.stroke_path(&path, &paint, &stroke, Transform::identity(), None);
+}
+
+fn scaled_dash(dash: [f32; 2], scale: f32) -> Option<StrokeDash> {
+    StrokeDash::new(dash.iter().map(|length| length * scale).collect(), 0.0)
+}
+
+// a cached row is only valid for the same image width, scale, background, labels and tick positions.
+fn row_key(fingerprint: u64, width: u32, scale: f32, timeline: &Timeline, theme: &Theme) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    fingerprint.hash(&mut hasher);
+    width.hash(&mut hasher);
+    scale.to_bits().hash(&mut hasher);
+    timeline.language.code().hash(&mut hasher);
+    timeline.timezone.hash(&mut hasher);
+    for color in [theme.background, theme.grid] {
+        let color = color.to_color_u8();
+        [color.red(), color.green(), color.blue(), color.alpha()].hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+fn label_key(text: &str, font_size: f32, color: Color, anchor: TextAnchor) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    font_size.to_bits().hash(&mut hasher);
+    let color = color.to_color_u8();
+    [color.red(), color.green(), color.blue(), color.alpha()].hash(&mut hasher);
+    anchor.hash(&mut hasher);
+    hasher.finish()
+}
+
+// public for the benchmarks only.
+#[doc(hidden)]
+pub fn create_hatching_pattern(active: Color, inactive: Color) -> Pixmap {
     let size = HATCH_SIZE;
     let mut pixmap = Pixmap::new(size, size).unwrap();
     pixmap.fill(inactive);
@@ -342,93 +1044,3 @@ fn create_hatching_pattern(active: Color, inactive: Color) -> Pixmap {
 
     pixmap
 }
-
-fn draw_text(
-    pixmap: &mut Pixmap,
-    font_system: &mut FontSystem,
-    swash_cache: &mut SwashCache,
-    text: &str,
-    font_size: f32,
-    x: f32,
-    y: f32,
-    color: Color,
-) {
-    let metrics = Metrics::new(font_size, font_size * 1.2);
-    let mut buffer = Buffer::new(font_system, metrics);
-
-    let attrs = Attrs::new();
-    buffer.set_text(font_system, text, &attrs, Shaping::Advanced, None);
-    buffer.shape_until_scroll(font_system, true);
-
-    let size = IntSize::from_wh(pixmap.width(), pixmap.height()).unwrap();
-    let mut text_mask_data = vec![0; size.width() as usize * size.height() as usize];
-
-    for run in buffer.layout_runs() {
-        let half_line_width = run.line_w / 2.0;
-
-        for glyph in run.glyphs {
-            debug!("now drawing: {:?}", glyph);
-            let physical_glyph = glyph.physical((-half_line_width, 0.0), 1.0);
-
-            if let Some(image) = swash_cache.get_image(font_system, physical_glyph.cache_key) {
-                debug!("placement: {:?}", image.placement);
-                let left = x as i32 + image.placement.left + physical_glyph.x;
-                let top = y as i32 - image.placement.top + physical_glyph.y;
-                let width = image.placement.width;
-                let height = image.placement.height;
-
-                if width == 0 || height == 0 {
-                    continue;
-                }
-
-                match image.content {
-                    SwashContent::Mask => { // character
-                        for (i, &a) in image.data.iter().enumerate() {
-                            let x = i as i32 % width as i32 + left;
-                            let y = i as i32 / width as i32 + top;
-                            if x < 0 || size.width() as i32 <= x {
-                                continue;
-                            }
-                            if y < 0 || size.height() as i32 <= y {
-                                continue;
-                            }
-                            let idx = (x + y * size.width() as i32) as usize;
-                            text_mask_data[idx] = a;
-                        }
-                    },
-
-                    SwashContent::Color => { // emoji
-                        if let Some(glyph_pixmap) = PixmapRef::from_bytes(&image.data, width, height) {
-                            pixmap.draw_pixmap(
-                                left,
-                                top,
-                                glyph_pixmap,
-                                &PixmapPaint::default(),
-                                Transform::identity(),
-                                None,
-                            );
-                        }
-                    },
-
-                    SwashContent::SubpixelMask => {
-                        // skips
-                    }
-                }
-            }
-        }
-
-    }
-
-    let mut paint = Paint::default();
-    paint.set_color(color);
-
-    if let Some(mask) = Mask::from_vec(text_mask_data, size) {
-        pixmap.fill_rect(
-            Rect::from_xywh(0.0, 0.0, size.width() as f32, size.height() as f32)
-                .unwrap_or(Rect::from_xywh(0.0, 0.0, 0.0, 0.0).unwrap()),
-            &paint,
-            Transform::identity(),
-            Some(&mask),
-        );
-    }
-}
\ No newline at end of file
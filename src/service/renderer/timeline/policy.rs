@@ -1,3 +1,33 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AspectRatioKind {
+    #[default]
+    Standard,
+    Wide,
+    Square,
+}
+
+impl AspectRatioKind {
+    pub fn policy(&self) -> AspectRatioPolicy {
+        match self {
+            AspectRatioKind::Standard => AspectRatioPolicy::discord_thumbnail_4_3(),
+            AspectRatioKind::Wide => AspectRatioPolicy::wide_16_9(),
+            AspectRatioKind::Square => AspectRatioPolicy::square(),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AspectRatioKind::Standard => "4:3",
+            AspectRatioKind::Wide => "16:9",
+            AspectRatioKind::Square => "1:1",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
 pub struct AspectRatioPolicy {
     pub target_width_ratio: f32,
     pub target_height_ratio: f32,
@@ -11,6 +41,20 @@ impl AspectRatioPolicy {
         }
     }
 
+    pub fn wide_16_9() -> AspectRatioPolicy {
+        AspectRatioPolicy {
+            target_width_ratio: 16.0,
+            target_height_ratio: 9.0,
+        }
+    }
+
+    pub fn square() -> AspectRatioPolicy {
+        AspectRatioPolicy {
+            target_width_ratio: 1.0,
+            target_height_ratio: 1.0,
+        }
+    }
+
     pub fn calculate_timeline_width(
         &self,
         total_height: f32,
@@ -26,4 +70,4 @@ impl AspectRatioPolicy {
             desired_timeline_width
         }
     }
-}
\ No newline at end of file
+}
@@ -1,44 +1,255 @@
-use crate::model::Activity;
+use crate::model::{Activity, GuildConfig, Participant, SpeakingSpan, VoiceStateFlags};
 use crate::service::asset::MemberVisual;
-use crate::service::renderer::view::{FillStyle, StreamingSection, Tick, Timeline, TimelineEntry, VoiceSection};
+use crate::service::i18n::{Language, TextKey};
+use crate::service::renderer::view::{format_duration, Capacity, DayStrip, EntryStats, FillStyle, GapSection, PausedSection, ReconnectSection, SpeakingSection, StreamingSection, Tick, Timeline, TimelineEntry, VideoSection, VoiceSection};
 use crate::service::report::RoomDTO;
-use chrono::Local;
+use crate::storage::StoredActivity;
+use chrono::{DateTime, Local, NaiveDate, TimeDelta, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
 use serenity::all::UserId;
 use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::ops::Add;
 use std::time::Duration;
 use tokio::time::Instant;
 
-pub fn transform(now: Instant, room: &RoomDTO, visuals: &HashMap<UserId, MemberVisual>, ongoing: bool) -> Timeline {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    #[default]
+    JoinTime,
+    Duration,
+    Alphabetical,
+    // separates those still in the call from those who left.
+    ConnectedFirst,
+}
+
+impl SortOrder {
+    pub const ALL: [SortOrder; 4] = [SortOrder::JoinTime, SortOrder::Duration, SortOrder::Alphabetical, SortOrder::ConnectedFirst];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SortOrder::JoinTime => "join-time",
+            SortOrder::Duration => "duration",
+            SortOrder::Alphabetical => "alphabetical",
+            SortOrder::ConnectedFirst => "connected-first",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<SortOrder> {
+        Self::ALL.into_iter().find(|order| order.name() == name)
+    }
+}
+
+// orders participants and returns the indices where a new group starts.
+fn sort_participants(now: Instant, participants: &[Participant], order: SortOrder) -> (Vec<&Participant>, Vec<usize>) {
+    let mut sorted: Vec<&Participant> = participants.iter().collect();
+    match order {
+        // participants are kept in join order already.
+        SortOrder::JoinTime => {},
+        SortOrder::Duration => sorted.sort_by_key(|p| std::cmp::Reverse(p.calculate_duration(now))),
+        SortOrder::Alphabetical => sorted.sort_by_cached_key(|p| p.name().to_lowercase()),
+        SortOrder::ConnectedFirst => sorted.sort_by_key(|p| !p.is_connected()),
+    }
+
+    let separators = match order {
+        SortOrder::ConnectedFirst => sorted.iter()
+            .position(|p| !p.is_connected())
+            .filter(|&i| i > 0)
+            .into_iter()
+            .collect(),
+        _ => Vec::new(),
+    };
+    (sorted, separators)
+}
+
+pub fn transform(now: Instant, room: &RoomDTO, visuals: &HashMap<UserId, MemberVisual>, ongoing: bool, config: &GuildConfig) -> Timeline {
+    let show_reconnects = config.show_reconnects;
+    let show_gaps = config.show_gaps;
     let terminated_at = if ongoing {
         calculate_auto_scale(room.created_at, now)
     } else {
         now
     };
 
-    let entries = room.participants.iter().map(|p| {
+    let elapsed = terminated_at - room.created_at;
+    let (participants, separators) = sort_participants(now, &room.participants, config.sort_order);
+    let entries = participants.into_iter().map(|p| {
         let visual = visuals.get(&p.user_id()).expect("visual must be pre-fetched before rendering.");
+        let history = compact_history(p.history(), (terminated_at - room.created_at).mul_f32(MIN_SECTION_RATIO));
+        let (voice_sections, gap_sections) = convert_to_voice_sections(room.created_at, now, terminated_at, &history, show_gaps);
 
         TimelineEntry{
             avatar: visual.avatar.clone(),
-            voice_sections: convert_to_voice_sections(room.created_at, now, terminated_at, p.history()),
-            streaming_sections: convert_to_streaming_sections(room.created_at, now, terminated_at, p.history()),
+            voice_sections,
+            streaming_sections: convert_to_flag_sections(room.created_at, now, terminated_at, &history, |flags| flags.is_sharing_screen)
+                .into_iter()
+                .map(|(start_ratio, end_ratio)| StreamingSection {
+                    start_ratio,
+                    end_ratio,
+                    duration: elapsed.mul_f32(end_ratio - start_ratio),
+                })
+                .collect(),
+            video_sections: convert_to_flag_sections(room.created_at, now, terminated_at, &history, |flags| flags.is_video)
+                .into_iter()
+                .map(|(start_ratio, end_ratio)| VideoSection { start_ratio, end_ratio })
+                .collect(),
+            speaking_sections: convert_to_speaking_sections(room.created_at, now, terminated_at, p.speaking()),
+            reconnect_sections: if show_reconnects {
+                convert_to_reconnect_sections(room.created_at, terminated_at, p.reconnects())
+            } else {
+                Vec::new()
+            },
+            gap_sections,
             active_color: visual.active_color,
             streaming_color: visual.streaming_color,
             inactive_color: visual.inactive_color,
+            is_departed: p.is_departed(),
+            sessions: p.sessions(),
+            is_connected: p.is_connected(),
+            stats: calculate_stats(now, p),
+            fingerprint: fingerprint(room, terminated_at, p, visual, show_reconnects, show_gaps),
         }
     }).collect();
 
     Timeline{
         created_at: room.created_at,
         terminated_at,
-        created_timestamp: room.timestamp.with_timezone(&Local),
+        created_timestamp: *room.timestamp,
+        timezone: config.timezone,
+        language: Language::from_locale(&config.locale),
+        aspect_ratio: config.aspect_ratio.policy(),
         indicator: if ongoing { Some(now) } else { None },
         entries,
-        tick: choose_suitable_tics(terminated_at - room.created_at),
+        separators,
+        paused_sections: convert_to_paused_sections(room.created_at, now, terminated_at, &room.paused_spans),
+        capacity: room.user_limit.filter(|_| ongoing).map(|limit| Capacity {
+            connected: room.participants.iter().filter(|participant| participant.is_connected()).count(),
+            limit,
+        }),
+        tick: choose_suitable_tics(elapsed),
+    }
+}
+
+// one strip per day of the timezone for the last `days` days up to today, oldest first.
+pub fn transform_days(activities: &[StoredActivity], now_ms: i64, days: u32, timezone: Option<Tz>) -> Vec<DayStrip> {
+    match timezone {
+        Some(timezone) => transform_days_in(&timezone, activities, now_ms, days),
+        None => transform_days_in(&Local, activities, now_ms, days),
+    }
+}
+
+fn transform_days_in<T: TimeZone>(timezone: &T, activities: &[StoredActivity], now_ms: i64, days: u32) -> Vec<DayStrip> {
+    let Some(today) = DateTime::<Utc>::from_timestamp_millis(now_ms).map(|now| now.with_timezone(timezone).date_naive()) else {
+        return Vec::new()
+    };
+    let start_of = |date: NaiveDate| date.and_hms_opt(0, 0, 0)
+        .and_then(|naive| timezone.from_local_datetime(&naive).earliest())
+        .map_or(now_ms, |time| time.timestamp_millis());
+    let first_day = today - TimeDelta::days(days.saturating_sub(1) as i64);
+
+    let mut sorted: Vec<&StoredActivity> = activities.iter().collect();
+    sorted.sort_by_key(|activity| activity.started_at);
+
+    // persisted times become instants after a common origin, so the section conversion applies as is.
+    let base = sorted.first().map_or(now_ms, |activity| activity.started_at).min(start_of(first_day));
+    let origin = Instant::now();
+    let at = |ms: i64| origin + Duration::from_millis((ms - base).max(0) as u64);
+    let history: Vec<Activity> = sorted.iter()
+        .map(|activity| Activity::restore(at(activity.started_at), activity.ended_at.map(at), activity.flags))
+        .collect();
+
+    (0..days).map(|i| {
+        let date = first_day + TimeDelta::days(i as i64);
+        let (since, until) = (start_of(date), start_of(date + TimeDelta::days(1)));
+        let (voice_sections, _) = convert_to_voice_sections(at(since), at(now_ms), at(until), &history, false);
+        let total = sorted.iter()
+            .map(|activity| activity.ended_at.unwrap_or(now_ms).min(until) - activity.started_at.max(since))
+            .filter(|&millis| millis > 0)
+            .map(|millis| Duration::from_millis(millis as u64))
+            .sum();
+
+        DayStrip { date, voice_sections, total }
+    }).collect()
+}
+
+// attachment descriptions take at most this many characters.
+const ALT_TEXT_LIMIT: usize = 1024;
+
+// alt text of the timeline image for screen readers, listing participants in the order of the rows.
+pub fn describe_timeline(now: Instant, room: &RoomDTO, config: &GuildConfig) -> String {
+    let language = Language::from_locale(&config.locale);
+    let (participants, _) = sort_participants(now, &room.participants, config.sort_order);
+
+    let mut text = language.text(TextKey::AltTimeline)
+        .replace("{duration}", &format_duration(room.wall_clock(now)))
+        .replace("{count}", &participants.len().to_string());
+    for (i, participant) in participants.iter().enumerate() {
+        let stats = calculate_stats(now, participant);
+        let mut line = format!(" {}: {}", participant.name(), format_duration(stats.total));
+        if !stats.muted.is_zero() {
+            line.push_str(&format!(", {} {}%", language.text(TextKey::Muted), stats.muted_percentage()));
+        }
+        if !stats.streaming.is_zero() {
+            line.push_str(&format!(", {} {}", language.text(TextKey::Streaming), format_duration(stats.streaming)));
+        }
+        if participant.is_connected() {
+            line.push_str(&format!(", {}", language.text(TextKey::ConnectedNow)));
+        }
+        line.push('.');
+
+        // room is kept for the note on those who did not fit.
+        let rest = participants.len() - i;
+        let more = format!(" {}", language.text(TextKey::MoreParticipants).replace("{count}", &rest.to_string()));
+        if text.chars().count() + line.chars().count() + more.chars().count() > ALT_TEXT_LIMIT {
+            text.push_str(&more);
+            break;
+        }
+        text.push_str(&line);
+    }
+    text
+}
+
+// rows of connected participants grow on every render, so only finished rows get a fingerprint.
+fn fingerprint(room: &RoomDTO, terminated_at: Instant, participant: &Participant, visual: &MemberVisual, show_reconnects: bool, show_gaps: bool) -> Option<u64> {
+    if participant.is_connected() {
+        return None;
     }
+
+    let mut hasher = DefaultHasher::new();
+    room.channel_id.hash(&mut hasher);
+    room.created_at.hash(&mut hasher);
+    terminated_at.hash(&mut hasher);
+    participant.user_id().hash(&mut hasher);
+    participant.face().hash(&mut hasher);
+    participant.is_departed().hash(&mut hasher);
+    participant.history().len().hash(&mut hasher);
+    participant.history().last().and_then(|activity| activity.end()).hash(&mut hasher);
+    (show_reconnects, participant.reconnects().len()).hash(&mut hasher);
+    show_gaps.hash(&mut hasher);
+    // custom colors may change while the row stays the same otherwise.
+    let color = visual.active_color.to_color_u8();
+    [color.red(), color.green(), color.blue(), color.alpha()].hash(&mut hasher);
+    Some(hasher.finish())
 }
 
+pub(crate) fn calculate_stats(now: Instant, participant: &Participant) -> EntryStats {
+    let mut stats = EntryStats {
+        total: participant.calculate_duration(now),
+        ..EntryStats::default()
+    };
+    for activity in participant.history() {
+        let duration = activity.calculate_duration(now);
+        if FillStyle::from_flags(activity.flags()) != FillStyle::Active {
+            stats.muted += duration;
+        }
+        if activity.flags().is_sharing_screen {
+            stats.streaming += duration;
+        }
+    }
+    stats
+}
 
 fn calculate_auto_scale(start: Instant, end: Instant) -> Instant {
     const FRAMES: [Duration; 12] = [
@@ -97,61 +308,118 @@ fn choose_suitable_tics(duration: Duration) -> Tick {
 }
 
 
-fn convert_to_voice_sections(start: Instant, now: Instant, end: Instant, history: &Vec<Activity>) -> Vec<VoiceSection> {
+// sections narrower than this share of the timeline stay below a pixel even on the largest image.
+const MIN_SECTION_RATIO: f32 = 1.0 / 4096.0;
+
+// merges touching activities with the same flags, and those shorter than `min_duration` into the one before,
+// so multi-day calls render as few sections as look the same. the time covered stays exactly the same,
+// and a folded activity leaves its flags on the one it folds into, so a short screen share still shows.
+fn compact_history(history: &[Activity], min_duration: Duration) -> Vec<Activity> {
+    let mut compacted: Vec<Activity> = Vec::with_capacity(history.len());
+    for activity in history {
+        let Some(last) = compacted.last_mut() else {
+            compacted.push(activity.clone());
+            continue
+        };
+        if !activity.is_following(last) {
+            compacted.push(activity.clone());
+            continue
+        }
+        if activity.flags() == last.flags() {
+            *last = Activity::restore(last.start(), activity.end(), last.flags());
+            continue
+        }
+        if activity.end().is_none_or(|end| end - activity.start() >= min_duration) {
+            compacted.push(activity.clone());
+            continue
+        }
+        *last = Activity::restore(last.start(), activity.end(), last.flags().union(activity.flags()));
+        // the folded flags may now match the activity before.
+        if let [.., before, last] = compacted.as_slice()
+            && last.is_following(before)
+            && last.flags() == before.flags()
+        {
+            let merged = Activity::restore(before.start(), last.end(), before.flags());
+            compacted.pop();
+            *compacted.last_mut().unwrap() = merged;
+        }
+    }
+    compacted
+}
+
+// also returns the gaps between sessions when `with_gaps` is set.
+// activities outside [start, end] are skipped and those crossing an edge are cut there, so any range of a history can be drawn.
+fn convert_to_voice_sections(start: Instant, now: Instant, end: Instant, history: &[Activity], with_gaps: bool) -> (Vec<VoiceSection>, Vec<GapSection>) {
     let duration_sec = (end - start).as_secs_f32();
+    let ratio = |at: Instant| (at.clamp(start, end) - start).as_secs_f32()/duration_sec;
     let mut render_sections = Vec::new();
+    let mut gap_sections = Vec::new();
 
     for i in 0..history.len() {
         let current = &history[i];
+        let current_end = current.end().unwrap_or(now);
+        if current_end < start || current.start() > end {
+            continue
+        }
         let fill_style = FillStyle::from_flags(current.flags());
 
-        let start_ratio = (current.start() - start).as_secs_f32()/duration_sec;
-        let end_ratio = (current.end().unwrap_or(now) - start).as_secs_f32()/duration_sec;
+        let start_ratio = ratio(current.start());
+        let end_ratio = ratio(current_end);
+
+        if with_gaps && i > 0 && !current.is_following(&history[i - 1])
+            && let Some(left_at) = history[i - 1].end()
+        {
+            gap_sections.push(GapSection {
+                start_ratio: ratio(left_at),
+                end_ratio: start_ratio,
+            });
+        }
 
         render_sections.push(VoiceSection {
             start_ratio,
             end_ratio,
             fill_style,
+            is_open: current.is_ongoing() && current_end <= end,
+            continues_previous: current.start() < start || (i > 0 && current.is_following(&history[i - 1])),
+            continues_next: current_end > end || history.get(i + 1).is_some_and(|next| next.is_following(current)),
         })
     }
 
-    render_sections
+    (render_sections, gap_sections)
 }
 
-fn convert_to_streaming_sections(start: Instant, now: Instant, end: Instant, history: &Vec<Activity>) -> Vec<StreamingSection> {
+// merges consecutive activities having the flag into (start_ratio, end_ratio) sections.
+fn convert_to_flag_sections(start: Instant, now: Instant, end: Instant, history: &[Activity], is_set: impl Fn(&VoiceStateFlags) -> bool) -> Vec<(f32, f32)> {
     let duration_sec = (end - start).as_secs_f32();
-    let mut streaming_sections = Vec::new();
+    let mut sections = Vec::new();
 
-    // Always keep streaming start activity.
-    let mut streaming_start_activity: Option<&Activity> = None;
+    // Always keep section start activity.
+    let mut section_start_activity: Option<&Activity> = None;
 
     for i in 0..history.len() {
         let current_activity = &history[i];
 
-        // update current streaming start
-        match streaming_start_activity {
-            Some(streaming_start) => {
-                if !current_activity.flags().is_sharing_screen {
-                    let start_ratio = (streaming_start.start() - start).as_secs_f32()/duration_sec;
+        // update current section start
+        match section_start_activity {
+            Some(section_start) => {
+                if !is_set(&current_activity.flags()) {
+                    let start_ratio = (section_start.start() - start).as_secs_f32()/duration_sec;
                     let end_ratio = (current_activity.start() - start).as_secs_f32()/duration_sec;
 
-                    streaming_sections.push(StreamingSection{
-                        start_ratio,
-                        end_ratio,
-                    });
+                    sections.push((start_ratio, end_ratio));
 
-                    streaming_start_activity = None;
+                    section_start_activity = None;
                 }
             },
             None => {
-                if current_activity.flags().is_sharing_screen {
-                    streaming_start_activity = Some(&history[i]);
+                if is_set(&current_activity.flags()) {
+                    section_start_activity = Some(&history[i]);
                 }
             }
         }
 
         // detect disconnection from voice channel
-        if let Some(streaming_start) = &streaming_start_activity {
+        if let Some(section_start) = &section_start_activity {
             let terminated = if i == history.len() - 1 {
                 true
             } else {
@@ -159,17 +427,157 @@ fn convert_to_streaming_sections(start: Instant, now: Instant, end: Instant, his
             };
 
             if terminated {
-                let start_ratio = (streaming_start.start() - start).as_secs_f32()/duration_sec;
+                let start_ratio = (section_start.start() - start).as_secs_f32()/duration_sec;
                 let end_ratio = (current_activity.end().unwrap_or(now) - start).as_secs_f32()/duration_sec;
 
-                streaming_sections.push(StreamingSection{
-                    start_ratio,
-                    end_ratio,
-                });
-                streaming_start_activity = None;
+                sections.push((start_ratio, end_ratio));
+                section_start_activity = None;
             }
         }
     }
 
-    streaming_sections
+    sections
+}
+
+fn convert_to_speaking_sections(start: Instant, now: Instant, end: Instant, speaking: &[SpeakingSpan]) -> Vec<SpeakingSection> {
+    let duration_sec = (end - start).as_secs_f32();
+
+    speaking.iter().map(|span| SpeakingSection {
+        start_ratio: (span.start() - start).as_secs_f32()/duration_sec,
+        end_ratio: (span.end().unwrap_or(now) - start).as_secs_f32()/duration_sec,
+    }).collect()
+}
+
+// a span still open when rendering lasts until now.
+fn convert_to_paused_sections(start: Instant, now: Instant, end: Instant, paused_spans: &[(Instant, Option<Instant>)]) -> Vec<PausedSection> {
+    let duration_sec = (end - start).as_secs_f32();
+
+    paused_spans.iter().map(|(paused_at, resumed_at)| PausedSection {
+        start_ratio: (*paused_at - start).as_secs_f32()/duration_sec,
+        end_ratio: (resumed_at.unwrap_or(now) - start).as_secs_f32()/duration_sec,
+    }).collect()
+}
+
+fn convert_to_reconnect_sections(start: Instant, end: Instant, reconnects: &[(Instant, Instant)]) -> Vec<ReconnectSection> {
+    let duration_sec = (end - start).as_secs_f32();
+
+    reconnects.iter().map(|(dropped_at, rejoined_at)| ReconnectSection {
+        start_ratio: (*dropped_at - start).as_secs_f32()/duration_sec,
+        end_ratio: (*rejoined_at - start).as_secs_f32()/duration_sec,
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flags(is_muted: bool) -> VoiceStateFlags {
+        VoiceStateFlags { is_muted, ..VoiceStateFlags::default() }
+    }
+
+    // touching activities of the given lengths in seconds, alternating unmuted and muted.
+    fn alternating(start: Instant, lengths: &[u64]) -> Vec<Activity> {
+        let mut at = start;
+        lengths.iter().enumerate().map(|(i, &secs)| {
+            let end = at + Duration::from_secs(secs);
+            let activity = Activity::restore(at, Some(end), flags(i % 2 == 1));
+            at = end;
+            activity
+        }).collect()
+    }
+
+    fn total(now: Instant, history: &[Activity]) -> Duration {
+        history.iter().map(|activity| activity.calculate_duration(now)).sum()
+    }
+
+    #[test]
+    fn merges_touching_activities_with_the_same_flags() {
+        let start = Instant::now();
+        let first = Activity::restore(start, Some(start + Duration::from_secs(10)), flags(false));
+        let second = Activity::restore(start + Duration::from_secs(10), Some(start + Duration::from_secs(25)), flags(false));
+        let third = Activity::restore(start + Duration::from_secs(25), None, flags(true));
+        let history = vec![first, second, third];
+        let now = start + Duration::from_secs(40);
+
+        let compacted = compact_history(&history, Duration::ZERO);
+
+        assert_eq!(compacted.len(), 2);
+        assert_eq!(compacted[0].end(), Some(start + Duration::from_secs(25)));
+        assert!(compacted[1].is_ongoing());
+        assert_eq!(total(now, &compacted), total(now, &history));
+    }
+
+    #[test]
+    fn keeps_gaps_between_sessions() {
+        let start = Instant::now();
+        let history = vec![
+            Activity::restore(start, Some(start + Duration::from_secs(10)), flags(false)),
+            Activity::restore(start + Duration::from_secs(20), Some(start + Duration::from_secs(30)), flags(false)),
+        ];
+
+        let compacted = compact_history(&history, Duration::from_secs(60));
+
+        assert_eq!(compacted.len(), 2);
+        assert_eq!(total(start, &compacted), Duration::from_secs(20));
+    }
+
+    #[test]
+    fn short_activities_fold_into_the_previous_one() {
+        let start = Instant::now();
+        let lengths: Vec<u64> = (0..1000).map(|i| if i % 2 == 0 { 300 } else { 1 }).collect();
+        let history = alternating(start, &lengths);
+
+        let compacted = compact_history(&history, Duration::from_secs(5));
+
+        assert_eq!(compacted.len(), 1);
+        assert_eq!(total(start, &compacted), total(start, &history));
+        assert_eq!(compacted[0].flags(), flags(true));
+    }
+
+    #[test]
+    fn long_enough_activities_are_kept() {
+        let start = Instant::now();
+        let history = alternating(start, &[60, 60, 60, 2, 60]);
+
+        let compacted = compact_history(&history, Duration::from_secs(5));
+
+        // the 2 second muted one folds into the unmuted one before, which then merges with the muted one before that.
+        assert_eq!(compacted.len(), 3);
+        assert_eq!(compacted[1].start(), start + Duration::from_secs(60));
+        assert_eq!(compacted[1].end(), Some(start + Duration::from_secs(182)));
+        assert_eq!(compacted[1].flags(), flags(true));
+        assert_eq!(compacted[2].flags(), flags(false));
+        assert_eq!(total(start, &compacted), total(start, &history));
+    }
+
+    #[test]
+    fn short_activities_keep_their_flags() {
+        let start = Instant::now();
+        let sharing = VoiceStateFlags { is_sharing_screen: true, ..VoiceStateFlags::default() };
+        let history = vec![
+            Activity::restore(start, Some(start + Duration::from_secs(60)), flags(false)),
+            Activity::restore(start + Duration::from_secs(60), Some(start + Duration::from_secs(62)), sharing),
+        ];
+
+        let compacted = compact_history(&history, Duration::from_secs(5));
+
+        assert_eq!(compacted.len(), 1);
+        assert!(compacted[0].flags().is_sharing_screen);
+        assert_eq!(total(start, &compacted), total(start, &history));
+    }
+
+    #[test]
+    fn ongoing_activity_stays_open() {
+        let start = Instant::now();
+        let mut history = alternating(start, &[60, 1]);
+        history.push(Activity::restore(start + Duration::from_secs(61), None, flags(false)));
+        let now = start + Duration::from_secs(120);
+
+        let compacted = compact_history(&history, Duration::from_secs(5));
+
+        // the short muted one leaves its flag on the one before, so the ongoing one stays apart.
+        assert_eq!(compacted.len(), 2);
+        assert!(compacted[1].is_ongoing());
+        assert_eq!(total(now, &compacted), Duration::from_secs(120));
+    }
 }
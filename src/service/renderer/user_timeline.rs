@@ -0,0 +1,103 @@
+use crate::service::asset::MemberVisual;
+use crate::service::i18n::Language;
+use crate::service::renderer::draw::{draw_avatar, TextAnchor, TextRenderer};
+use crate::service::renderer::encoder::{encode, ImageFormat};
+use crate::service::renderer::theme::Theme;
+use crate::service::renderer::timeline::{create_hatching_pattern, SectionShape, TimelineRenderer, TimelineRendererResult};
+use crate::service::renderer::view::{DayStrip, FillStyle};
+use chrono::{Datelike, TimeDelta};
+use std::sync::Arc;
+use tiny_skia::{FillRule, FilterQuality, NonZeroRect, Paint, PathBuilder, Pattern, Pixmap, Shader, SpreadMode, Stroke, Transform};
+
+const WIDTH: f32 = 1000.0;
+const MARGIN: f32 = 20.0;
+const TITLE_HEIGHT: f32 = 50.0;
+const TITLE_FONT_SIZE: f32 = 28.0;
+const AVATAR_SIZE: f32 = 40.0;
+const FONT_SIZE: f32 = 18.0;
+const DATE_WIDTH: f32 = 110.0;
+const TOTAL_WIDTH: f32 = 80.0;
+const HOUR_LABEL_HEIGHT: f32 = 28.0;
+const ROW_HEIGHT: f32 = 36.0;
+const CORNER_RADIUS: f32 = 6.0;
+// hours are labeled and lined every this many hours.
+const HOUR_LABEL_STEP: usize = 3;
+const GRID_WIDTH: f32 = 1.0;
+
+// draws one member's voice activity as a strip per day, midnight to midnight.
+pub struct UserTimelineRenderer {
+    text_renderer: Arc<TextRenderer>,
+}
+
+impl UserTimelineRenderer {
+    pub fn new(text_renderer: Arc<TextRenderer>) -> UserTimelineRenderer {
+        UserTimelineRenderer { text_renderer }
+    }
+
+    pub fn generate_image(&self, title: &str, visual: &MemberVisual, days: &[DayStrip], language: Language, theme: &Theme, (format, quality): (ImageFormat, u8)) -> TimelineRendererResult<Vec<u8>> {
+        let strip_left = MARGIN + DATE_WIDTH;
+        let strip_right = WIDTH - MARGIN - TOTAL_WIDTH;
+        let strips_top = MARGIN + TITLE_HEIGHT + HOUR_LABEL_HEIGHT;
+        let height = strips_top + ROW_HEIGHT * days.len().max(1) as f32 + MARGIN;
+
+        let mut pixmap = Pixmap::new(WIDTH as u32, height as u32).expect("invalid pixmap size");
+        pixmap.fill(theme.background);
+
+        draw_avatar(&mut pixmap, &visual.avatar, MARGIN + AVATAR_SIZE / 2.0, MARGIN + TITLE_HEIGHT / 2.0, AVATAR_SIZE);
+        self.text_renderer.draw_text(&mut pixmap, title, TITLE_FONT_SIZE, (MARGIN + AVATAR_SIZE + MARGIN / 2.0, MARGIN + TITLE_FONT_SIZE), theme.text, TextAnchor::Start);
+
+        let mut grid_paint = Paint::default();
+        grid_paint.set_color(theme.grid);
+        let grid_stroke = Stroke { width: GRID_WIDTH, ..Stroke::default() };
+        for hour in (0..=24).step_by(HOUR_LABEL_STEP) {
+            let x = strip_left + (strip_right - strip_left) * hour as f32 / 24.0;
+            if hour < 24 {
+                self.text_renderer.draw_text(&mut pixmap, &format!("{:02}", hour), FONT_SIZE, (x, strips_top - FONT_SIZE / 2.0), theme.text, TextAnchor::Middle);
+            }
+
+            let mut builder = PathBuilder::new();
+            builder.move_to(x, strips_top);
+            builder.line_to(x, height - MARGIN);
+            if let Some(path) = builder.finish() {
+                pixmap.stroke_path(&path, &grid_paint, &grid_stroke, Transform::identity(), None);
+            }
+        }
+
+        let muted_pixmap = create_hatching_pattern(visual.active_color, visual.inactive_color);
+        let muted_shader = Pattern::new(muted_pixmap.as_ref(), SpreadMode::Repeat, FilterQuality::Bicubic, 1.0, Transform::identity());
+        let active_shader = Shader::SolidColor(visual.active_color);
+        let deafened_shader = Shader::SolidColor(visual.inactive_color);
+
+        for (i, day) in days.iter().enumerate() {
+            let top = strips_top + i as f32 * ROW_HEIGHT;
+            let baseline = top + ROW_HEIGHT / 2.0 + FONT_SIZE / 3.0;
+
+            let date = format!("{} {}", day.date.format("%m/%d"), language.weekday(day.date.weekday()));
+            self.text_renderer.draw_text(&mut pixmap, &date, FONT_SIZE, (MARGIN, baseline), theme.text, TextAnchor::Start);
+
+            let Some(strip_bb) = NonZeroRect::from_ltrb(strip_left, top, strip_right, top + ROW_HEIGHT) else {
+                continue
+            };
+            for section in &day.voice_sections {
+                let mut paint = Paint { anti_alias: true, ..Paint::default() };
+                paint.shader = match section.fill_style {
+                    FillStyle::Active => active_shader.clone(),
+                    FillStyle::Muted => muted_shader.clone(),
+                    FillStyle::Deafened => deafened_shader.clone(),
+                };
+
+                let shape = SectionShape::for_voice(strip_bb, section, CORNER_RADIUS);
+                if let Some(path) = shape.path(false) {
+                    pixmap.fill_path(&path, &paint, FillRule::Winding, Transform::identity(), None);
+                }
+            }
+
+            if !day.total.is_zero() {
+                let total = TimelineRenderer::format_time_delta(TimeDelta::from_std(day.total).unwrap_or(TimeDelta::zero()));
+                self.text_renderer.draw_text(&mut pixmap, &total, FONT_SIZE, (WIDTH - MARGIN, baseline), theme.text, TextAnchor::End);
+            }
+        }
+
+        encode(&pixmap, format, quality)
+    }
+}
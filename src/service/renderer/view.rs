@@ -1,6 +1,9 @@
 use std::time::Duration;
-use chrono::{DateTime, Datelike, Local, TimeZone, Timelike};
+use chrono::{DateTime, Datelike, DurationRound, Local, NaiveDate, TimeDelta, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
 use crate::model::VoiceStateFlags;
+use crate::service::i18n::Language;
+use crate::service::renderer::timeline::policy::AspectRatioPolicy;
 use crate::service::renderer::view::FillStyle::{Active, Deafened, Muted};
 use tiny_skia::{Color, Pixmap};
 use tokio::time::Instant;
@@ -64,19 +67,205 @@ impl Tick {
 pub struct Timeline {
     pub created_at: Instant,
     pub terminated_at: Instant,
-    pub created_timestamp: DateTime<Local>,
+    pub created_timestamp: DateTime<Utc>,
+    // labels use the host's local time when the guild has not configured a timezone.
+    pub timezone: Option<Tz>,
+    pub language: Language,
+    pub aspect_ratio: AspectRatioPolicy,
     pub tick: Tick,
     pub indicator: Option<Instant>,
     pub entries: Vec<TimelineEntry>,
+    // a separator is drawn above each of these entries to group them.
+    pub separators: Vec<usize>,
+    // spans tracking was paused in, drawn as bands across all rows.
+    pub paused_sections: Vec<PausedSection>,
+    // connected members against the channel's user limit, while the call goes on.
+    pub capacity: Option<Capacity>,
+}
+
+impl Timeline {
+    // splits the entries into timelines of at most `rows_per_page` rows sharing the same time axis.
+    pub fn paginate(self, rows_per_page: usize) -> Vec<Timeline> {
+        if rows_per_page == 0 || self.entries.len() <= rows_per_page {
+            return vec![self]
+        }
+
+        let mut entries = self.entries.into_iter().peekable();
+        let mut pages = Vec::new();
+        while entries.peek().is_some() {
+            let offset = pages.len() * rows_per_page;
+            pages.push(Timeline {
+                created_at: self.created_at,
+                terminated_at: self.terminated_at,
+                created_timestamp: self.created_timestamp,
+                timezone: self.timezone,
+                language: self.language,
+                aspect_ratio: self.aspect_ratio,
+                tick: self.tick,
+                indicator: self.indicator,
+                entries: entries.by_ref().take(rows_per_page).collect(),
+                // a separator at the top of a page adds nothing.
+                separators: self.separators.iter()
+                    .filter(|&&i| i > offset && i < offset + rows_per_page)
+                    .map(|i| i - offset)
+                    .collect(),
+                paused_sections: self.paused_sections.clone(),
+                capacity: self.capacity,
+            });
+        }
+        pages
+    }
+
+    // returns the position ratio and the label of every tick inside the timeline.
+    pub fn tick_positions(&self) -> Vec<(f32, String)> {
+        match self.timezone {
+            Some(timezone) => self.tick_positions_in(&timezone),
+            None => self.tick_positions_in(&Local),
+        }
+    }
+
+    // ticks are aligned in the given timezone, so offsets like +05:30 still get round labels.
+    fn tick_positions_in<T: TimeZone>(&self, timezone: &T) -> Vec<(f32, String)> {
+        let created_timestamp = self.created_timestamp.with_timezone(timezone);
+        let interval = TimeDelta::from_std(self.tick.interval).unwrap();
+        let base_timestamp = created_timestamp.clone().duration_trunc(interval).unwrap();
+
+        let mut delta = base_timestamp - created_timestamp.clone();
+        if delta < TimeDelta::zero() {
+            delta += interval;
+        }
+        let elapsed = TimeDelta::from_std(self.terminated_at - self.created_at).unwrap();
+
+        let mut positions = Vec::new();
+        while delta < elapsed {
+            let ratio = delta.as_seconds_f32()/elapsed.as_seconds_f32();
+            positions.push((ratio, self.tick.format(created_timestamp.clone() + delta)));
+            delta += interval;
+        }
+        positions
+    }
+
+    // position ratio of the "now" indicator, if any.
+    pub fn indicator_ratio(&self) -> Option<f32> {
+        let elapsed = (self.terminated_at - self.created_at).as_secs_f32();
+        self.indicator.map(|indicator| (indicator - self.created_at).as_secs_f32() / elapsed)
+    }
+
+    // the timeline as it looked at `at`, on the same axis and rows, for frames of an animation.
+    pub fn until(&self, at: Instant) -> Timeline {
+        let elapsed = (self.terminated_at - self.created_at).as_secs_f32();
+        let ratio = (at.saturating_duration_since(self.created_at).as_secs_f32() / elapsed).min(1.0);
+        Timeline {
+            created_at: self.created_at,
+            terminated_at: self.terminated_at,
+            created_timestamp: self.created_timestamp,
+            timezone: self.timezone,
+            language: self.language,
+            aspect_ratio: self.aspect_ratio,
+            tick: self.tick,
+            indicator: Some(at),
+            entries: self.entries.iter().map(|entry| entry.until(ratio)).collect(),
+            separators: self.separators.clone(),
+            paused_sections: self.paused_sections.iter()
+                .filter_map(|section| clip(section.start_ratio, section.end_ratio, ratio))
+                .map(|(start_ratio, end_ratio)| PausedSection { start_ratio, end_ratio })
+                .collect(),
+            capacity: self.capacity,
+        }
+    }
+}
+
+// cuts a section off at the ratio; None when it only starts later.
+fn clip(start_ratio: f32, end_ratio: f32, ratio: f32) -> Option<(f32, f32)> {
+    (start_ratio < ratio).then(|| (start_ratio, end_ratio.min(ratio)))
 }
 
 pub struct TimelineEntry {
     pub avatar: Pixmap,
     pub voice_sections: Vec<VoiceSection>,
     pub streaming_sections: Vec<StreamingSection>,
+    pub video_sections: Vec<VideoSection>,
+    pub speaking_sections: Vec<SpeakingSection>,
+    pub reconnect_sections: Vec<ReconnectSection>,
+    pub gap_sections: Vec<GapSection>,
     pub active_color: Color,
     pub inactive_color: Color,
     pub streaming_color: Color,
+    // the member left the guild; the row is grayed out.
+    pub is_departed: bool,
+    pub sessions: usize,
+    pub is_connected: bool,
+    pub stats: EntryStats,
+    // identifies the rendered row; None while the row still changes on every render.
+    pub fingerprint: Option<u64>,
+}
+
+impl TimelineEntry {
+    // stats stay those of the whole call; the row is never cached.
+    fn until(&self, ratio: f32) -> TimelineEntry {
+        TimelineEntry {
+            avatar: self.avatar.clone(),
+            voice_sections: self.voice_sections.iter()
+                .filter_map(|section| clip(section.start_ratio, section.end_ratio, ratio).map(|(start_ratio, end_ratio)| VoiceSection {
+                    start_ratio,
+                    end_ratio,
+                    fill_style: section.fill_style,
+                    is_open: section.is_open || end_ratio < section.end_ratio,
+                    continues_previous: section.continues_previous,
+                    continues_next: section.continues_next && end_ratio == section.end_ratio,
+                }))
+                .collect(),
+            streaming_sections: self.streaming_sections.iter()
+                .filter_map(|section| clip(section.start_ratio, section.end_ratio, ratio).map(|(start_ratio, end_ratio)| StreamingSection {
+                    start_ratio,
+                    end_ratio,
+                    duration: section.duration.mul_f32((end_ratio - start_ratio) / (section.end_ratio - section.start_ratio).max(f32::EPSILON)),
+                }))
+                .collect(),
+            video_sections: self.video_sections.iter()
+                .filter_map(|section| clip(section.start_ratio, section.end_ratio, ratio))
+                .map(|(start_ratio, end_ratio)| VideoSection { start_ratio, end_ratio })
+                .collect(),
+            speaking_sections: self.speaking_sections.iter()
+                .filter_map(|section| clip(section.start_ratio, section.end_ratio, ratio))
+                .map(|(start_ratio, end_ratio)| SpeakingSection { start_ratio, end_ratio })
+                .collect(),
+            reconnect_sections: self.reconnect_sections.iter()
+                .filter_map(|section| clip(section.start_ratio, section.end_ratio, ratio))
+                .map(|(start_ratio, end_ratio)| ReconnectSection { start_ratio, end_ratio })
+                .collect(),
+            gap_sections: self.gap_sections.iter()
+                .filter_map(|section| clip(section.start_ratio, section.end_ratio, ratio))
+                .map(|(start_ratio, end_ratio)| GapSection { start_ratio, end_ratio })
+                .collect(),
+            active_color: self.active_color,
+            inactive_color: self.inactive_color,
+            streaming_color: self.streaming_color,
+            is_departed: self.is_departed,
+            sessions: self.sessions,
+            is_connected: self.is_connected,
+            stats: self.stats,
+            fingerprint: None,
+        }
+    }
+}
+
+// totals shown in the stats column.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EntryStats {
+    pub total: Duration,
+    // muted or deafened.
+    pub muted: Duration,
+    pub streaming: Duration,
+}
+
+impl EntryStats {
+    pub fn muted_percentage(&self) -> u32 {
+        if self.total.is_zero() {
+            return 0
+        }
+        (self.muted.as_secs_f32() / self.total.as_secs_f32() * 100.0).round() as u32
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -88,7 +277,7 @@ pub enum FillStyle {
 
 impl FillStyle {
     pub fn from_flags(flags: VoiceStateFlags) -> FillStyle {
-        match (flags.is_deafened, flags.is_muted) {
+        match (flags.is_deafened, flags.is_muted || flags.is_audience) {
             (true, _) => Deafened,
             (_, true) => Muted,
             (_, _) => Active,
@@ -100,9 +289,82 @@ pub struct VoiceSection {
     pub start_ratio: f32,
     pub end_ratio: f32,
     pub fill_style: FillStyle,
+    // the activity is still going on, so the section has no real end yet.
+    pub is_open: bool,
+    // touching sections keep square corners where they meet.
+    pub continues_previous: bool,
+    pub continues_next: bool,
 }
 
 pub struct StreamingSection {
     pub start_ratio: f32,
     pub end_ratio: f32,
+    pub duration: Duration,
+}
+
+impl StreamingSection {
+    pub fn duration_label(&self) -> String {
+        format_duration(self.duration)
+    }
+}
+
+// "0:42" or "1:02:03" for longer durations.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs / 3600 {
+        0 => format!("{}:{:02}", secs / 60, secs % 60),
+        hours => format!("{}:{:02}:{:02}", hours, secs / 60 % 60, secs % 60),
+    }
+}
+
+pub struct VideoSection {
+    pub start_ratio: f32,
+    pub end_ratio: f32,
+}
+
+pub struct SpeakingSection {
+    pub start_ratio: f32,
+    pub end_ratio: f32,
+}
+
+// the gap of a rejoin that was merged into the previous activity.
+pub struct ReconnectSection {
+    pub start_ratio: f32,
+    pub end_ratio: f32,
+}
+
+// the time between two sessions the member was out of the call.
+pub struct GapSection {
+    pub start_ratio: f32,
+    pub end_ratio: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Capacity {
+    pub connected: usize,
+    pub limit: u32,
+}
+
+impl Capacity {
+    // "7/10"
+    pub fn label(&self) -> String {
+        format!("{}/{}", self.connected, self.limit)
+    }
+
+    pub fn ratio(&self) -> f32 {
+        (self.connected as f32 / self.limit.max(1) as f32).min(1.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PausedSection {
+    pub start_ratio: f32,
+    pub end_ratio: f32,
+}
+
+// a day of one member's voice activity; sections span the day from midnight to midnight.
+pub struct DayStrip {
+    pub date: NaiveDate,
+    pub voice_sections: Vec<VoiceSection>,
+    pub total: Duration,
 }
@@ -1,18 +1,42 @@
-use crate::model::{Participant, Room};
+use crate::model::{EmbedTemplate, GuildConfig, Participant, Room, ScheduledEventTag};
 use crate::service::asset::{AssetError, AssetService};
-use crate::service::renderer::timeline::{TimelineRenderer, TimelineRendererError};
-use crate::service::renderer::transformer::transform;
+use crate::service::config::ConfigService;
+use crate::service::export::RoomExport;
+use crate::service::i18n::{Language, TextKey};
+use crate::service::lease::LeaseService;
+use crate::service::renderer::document::DocumentRenderer;
+use crate::service::renderer::draw::TextRenderer;
+use crate::service::renderer::encoder::ImageFormat;
+use crate::service::renderer::heatmap::HeatmapRenderer;
+use crate::service::renderer::leaderboard::{LeaderboardRenderer, LeaderboardRow};
+use crate::service::renderer::overview::{OverviewRenderer, OverviewSection};
+use crate::service::renderer::pool::{RenderPool, RenderPoolError};
+use crate::service::renderer::svg::SvgRenderer;
+use crate::service::renderer::theme::Theme;
+use crate::service::renderer::timeline::{TimelineRenderer, TimelineRendererError, TimelineRendererResult};
+use crate::service::renderer::transformer::{describe_timeline, transform, transform_days, SortOrder};
+use crate::service::renderer::user_timeline::UserTimelineRenderer;
 use crate::service::renderer::view::Timeline;
-use crate::service::tracker::Tracker;
-use serenity::all::{ChannelId, CreateAttachment, CreateMessage, EditAttachments, EditMessage, GuildId, Http, MessageFlags, Timestamp};
-use std::collections::HashMap;
+use crate::service::stats::{Heatmap, LeaderboardEntry, MonthlySummary};
+use crate::service::template::{Template, TemplateValues};
+use crate::service::tracker::{Track, TrackPolicy, Tracker};
+use crate::service::webhook::{RoomSummary, WebhookPayload, WebhookService};
+use crate::storage::{SqliteStorage, StoredActivity};
+use chrono::{TimeDelta, Utc};
+use serde::Serialize;
+use serenity::all::{ActionRowComponent, ButtonKind, ButtonStyle, ChannelId, CreateActionRow, CreateAttachment, CreateButton, CreateEmbed, CreateEmbedFooter, CreateMessage, EditAttachments, EditMessage, GetMessages, GuildId, Http, Mentionable, Message, MessageFlags, MessageId, Timestamp, User, UserId};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
+use serenity::http::HttpError;
 use serenity::prelude::SerenityError;
 use thiserror::Error;
-use tokio::sync::Mutex;
-use tokio::task::JoinError;
-use tokio::time::Instant;
+use tokio::sync::{Mutex, Semaphore};
+use tokio::task::{JoinError, JoinSet};
+use tokio::time::{self, Instant};
+use tracing::{debug, error, info, instrument, warn};
 
 #[derive(Debug, Error)]
 pub enum ReportServiceError{
@@ -25,19 +49,168 @@ pub enum ReportServiceError{
     #[error("")]
     Join(#[from] JoinError),
 
+    #[error(transparent)]
+    RenderPool(#[from] RenderPoolError),
+
+    // boxed, as serenity errors are large and this error is returned everywhere.
     #[error("Serenity error")]
-    Serenity(#[from] SerenityError),
+    Serenity(#[source] Box<SerenityError>),
+
+    #[error("Failed to serialize export: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("{} took longer than its budget", .0.name())]
+    Timeout(ReportStage),
+
+    // sending timed out, so the message may or may not have been posted.
+    #[error("sending the report timed out; it may have been posted")]
+    Unconfirmed,
 }
 
 pub type ReportServiceResult<T> = Result<T, ReportServiceError>;
 
+impl From<SerenityError> for ReportServiceError {
+    fn from(err: SerenityError) -> Self {
+        ReportServiceError::Serenity(Box::new(err))
+    }
+}
+
+// JSON error code Discord returns for a message that no longer exists.
+const UNKNOWN_MESSAGE_CODE: isize = 10008;
+
+// how many of the latest messages are searched for a report whose sending timed out.
+const UNCONFIRMED_LOOKUP_LIMIT: u8 = 10;
+
+// upper bound of avatars downloaded at once while building a timeline.
+const ASSET_PREFETCH_CONCURRENCY: usize = 8;
+
+// animated reports replay this many of the latest changes before showing the whole call.
+const ANIMATION_FRAMES: usize = 8;
+
+// budgets of the stages of a report, unless configured otherwise.
+pub const DEFAULT_ASSET_FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+pub const DEFAULT_RENDER_TIMEOUT: Duration = Duration::from_secs(20);
+pub const DEFAULT_SEND_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportStage {
+    AssetFetch,
+    Render,
+    Send,
+}
+
+impl ReportStage {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReportStage::AssetFetch => "asset fetch",
+            ReportStage::Render => "render",
+            ReportStage::Send => "send",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StageTimeouts {
+    pub asset_fetch: Duration,
+    pub render: Duration,
+    pub send: Duration,
+}
 
+impl StageTimeouts {
+    fn of(&self, stage: ReportStage) -> Duration {
+        match stage {
+            ReportStage::AssetFetch => self.asset_fetch,
+            ReportStage::Render => self.render,
+            ReportStage::Send => self.send,
+        }
+    }
+}
+
+impl Default for StageTimeouts {
+    fn default() -> Self {
+        StageTimeouts {
+            asset_fetch: DEFAULT_ASSET_FETCH_TIMEOUT,
+            render: DEFAULT_RENDER_TIMEOUT,
+            send: DEFAULT_SEND_TIMEOUT,
+        }
+    }
+}
+
+// how many times each stage ran out of time since startup.
+#[derive(Debug, Default)]
+struct TimeoutCounters {
+    asset_fetch: AtomicU64,
+    render: AtomicU64,
+    send: AtomicU64,
+}
+
+impl TimeoutCounters {
+    fn of(&self, stage: ReportStage) -> &AtomicU64 {
+        match stage {
+            ReportStage::AssetFetch => &self.asset_fetch,
+            ReportStage::Render => &self.render,
+            ReportStage::Send => &self.send,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct TimeoutCounts {
+    pub asset_fetch: u64,
+    pub render: u64,
+    pub send: u64,
+}
+
+// embed limits of discord for the templated parts.
+const EMBED_TITLE_LIMIT: usize = 256;
+const EMBED_DESCRIPTION_LIMIT: usize = 4096;
+const EMBED_FOOTER_LIMIT: usize = 2048;
+
+#[derive(Clone)]
+pub struct RenderedReport {
+    pub embed: CreateEmbed,
+    // the first page is shown in the embed; large rooms add further pages.
+    pub attachments: Vec<CreateAttachment>,
+}
+
+// where a report is posted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportTarget {
+    Channel(ChannelId),
+    // a direct message, e.g. to the member who started the call.
+    Direct(UserId),
+}
+
+impl ReportTarget {
+    async fn send(&self, http: &Http, message: CreateMessage) -> serenity::Result<Message> {
+        match self {
+            ReportTarget::Channel(channel_id) => channel_id.send_message(http, message).await,
+            ReportTarget::Direct(user_id) => user_id.direct_message(http, message).await,
+        }
+    }
+}
 
 pub struct ReportService {
     asset_service: AssetService,
     renderer: Arc<TimelineRenderer>,
-    report_channel_id: Option<ChannelId>,
+    svg_renderer: Arc<SvgRenderer>,
+    leaderboard_renderer: Arc<LeaderboardRenderer>,
+    heatmap_renderer: Arc<HeatmapRenderer>,
+    user_timeline_renderer: Arc<UserTimelineRenderer>,
+    overview_renderer: Arc<OverviewRenderer>,
+    document_renderer: Arc<DocumentRenderer>,
+    config_service: Arc<ConfigService>,
+    render_pool: Arc<RenderPool>,
     tracker: Arc<Mutex<Tracker>>,
+    // posts of the same room wait for each other, so the tracker only needs locking around its own updates.
+    posting: std::sync::Mutex<HashMap<ChannelId, Arc<Mutex<()>>>>,
+    webhook_service: Option<Arc<WebhookService>>,
+    // without it this instance reports for every guild.
+    lease_service: Option<Arc<LeaseService>>,
+    // keeps tracked messages across restarts so reports are edited instead of posted again.
+    storage: Option<Arc<SqliteStorage>>,
+    timeouts: StageTimeouts,
+    timeout_counters: TimeoutCounters,
 }
 
 #[derive(Debug, Clone)]
@@ -46,7 +219,16 @@ pub struct RoomDTO {
     pub timestamp: Timestamp,
     pub guild_id: GuildId,
     pub channel_id: ChannelId,
+    pub category_id: Option<ChannelId>,
+    pub user_limit: Option<u32>,
+    pub channel_name: Option<String>,
     pub participants: Vec<Participant>,
+    pub peak_participants: usize,
+    pub scheduled_event: Option<ScheduledEventTag>,
+    pub paused_spans: Vec<(Instant, Option<Instant>)>,
+    pub revision: u64,
+    pub session: u32,
+    pub expires_at: Option<Instant>,
 }
 
 impl RoomDTO {
@@ -60,106 +242,838 @@ impl RoomDTO {
             timestamp: room.timestamp(),
             guild_id: room.guild_id(),
             channel_id: room.channel_id(),
+            category_id: room.category_id(),
+            user_limit: room.user_limit(),
+            channel_name: room.channel_name().map(String::from),
             participants,
+            peak_participants: room.peak_participants(),
+            scheduled_event: room.scheduled_event().cloned(),
+            paused_spans: room.paused_spans().clone(),
+            revision: room.revision(),
+            session: room.session(),
+            expires_at: room.expires_at(),
         }
     }
+
+    pub fn wall_clock(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(self.created_at)
+    }
+
+    // the time of every participant added up.
+    pub fn person_time(&self, now: Instant) -> Duration {
+        self.participants.iter().map(|participant| participant.calculate_duration(now)).sum()
+    }
+
+    // how many were connected on average while the room was open.
+    pub fn average_concurrency(&self, now: Instant) -> f32 {
+        let wall_clock = self.wall_clock(now);
+        match wall_clock.is_zero() {
+            true => 0.0,
+            false => self.person_time(now).as_secs_f32() / wall_clock.as_secs_f32(),
+        }
+    }
+
+    // whoever joined first, even if they left before the call ended.
+    pub fn starter(&self) -> Option<UserId> {
+        self.participants.iter()
+            .filter_map(|participant| participant.history().first().map(|activity| (activity.start(), participant.user_id())))
+            .min_by_key(|(start, _)| *start)
+            .map(|(_, user_id)| user_id)
+    }
+
+    // the latest `count` joins, leaves or state changes before now, oldest first.
+    pub fn recent_changes(&self, now: Instant, count: usize) -> Vec<Instant> {
+        let mut changes: Vec<Instant> = self.participants.iter()
+            .flat_map(|participant| participant.history().iter())
+            .flat_map(|activity| [Some(activity.start()), activity.end()])
+            .flatten()
+            .filter(|at| *at > self.created_at && *at < now)
+            .collect();
+        changes.sort();
+        changes.dedup();
+        changes.split_off(changes.len().saturating_sub(count))
+    }
+
+    // the latest join, leave or state change of any participant.
+    pub fn last_changed_at(&self) -> Instant {
+        self.participants.iter()
+            .flat_map(|participant| participant.history().iter())
+            .flat_map(|activity| [Some(activity.start()), activity.end()])
+            .flatten()
+            .max()
+            .unwrap_or(self.created_at)
+    }
 }
 
 impl ReportService {
-    pub fn new(asset_service: AssetService, report_channel_id: Option<ChannelId>) -> Self {
+    pub fn new(asset_service: AssetService, config_service: Arc<ConfigService>, text_renderer: Arc<TextRenderer>, render_pool: Arc<RenderPool>) -> Self {
+        let renderer = Arc::new(TimelineRenderer::new(text_renderer.clone()));
         Self{
             asset_service,
-            renderer: Arc::new(TimelineRenderer::new()),
-            report_channel_id,
-            tracker: Arc::new(Mutex::new(Tracker::new()))
+            renderer: renderer.clone(),
+            svg_renderer: Arc::new(SvgRenderer::new()),
+            leaderboard_renderer: Arc::new(LeaderboardRenderer::new(text_renderer.clone())),
+            heatmap_renderer: Arc::new(HeatmapRenderer::new(text_renderer.clone())),
+            user_timeline_renderer: Arc::new(UserTimelineRenderer::new(text_renderer.clone())),
+            document_renderer: Arc::new(DocumentRenderer::new(text_renderer.clone())),
+            overview_renderer: Arc::new(OverviewRenderer::new(renderer, text_renderer)),
+            config_service,
+            render_pool,
+            tracker: Arc::new(Mutex::new(Tracker::new(TrackPolicy::default()))),
+            posting: std::sync::Mutex::new(HashMap::new()),
+            webhook_service: None,
+            lease_service: None,
+            storage: None,
+            timeouts: StageTimeouts::default(),
+            timeout_counters: TimeoutCounters::default(),
         }
     }
 
-    async fn create_timeline(&self, now: Instant, room: &RoomDTO, finalized: bool) -> ReportServiceResult<Timeline> {
+    pub fn asset_service(&self) -> &AssetService {
+        &self.asset_service
+    }
+
+    // the overview composes timelines, so it is rebuilt around the scaled renderer.
+    pub fn with_render_scale(mut self, scale: f32) -> Self {
+        let text_renderer = self.renderer.text_renderer().clone();
+        let renderer = Arc::new(TimelineRenderer::new(text_renderer.clone()).with_scale(scale));
+        self.overview_renderer = Arc::new(OverviewRenderer::new(renderer.clone(), text_renderer));
+        self.renderer = renderer;
+        self
+    }
+
+    pub fn with_webhooks(mut self, webhook_service: Arc<WebhookService>) -> Self {
+        self.webhook_service = Some(webhook_service);
+        self
+    }
+
+    pub fn with_storage(mut self, storage: Arc<SqliteStorage>) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    pub fn with_timeouts(mut self, timeouts: StageTimeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    pub fn with_lease(mut self, lease_service: Arc<LeaseService>) -> Self {
+        self.lease_service = Some(lease_service);
+        self
+    }
+
+    // whether this instance reports for the guild rather than standing by.
+    pub async fn is_active_reporter(&self, guild_id: GuildId) -> bool {
+        match &self.lease_service {
+            Some(lease_service) => lease_service.is_active(guild_id).await,
+            None => true,
+        }
+    }
+
+    pub fn with_track_policy(mut self, policy: TrackPolicy) -> Self {
+        self.tracker = Arc::new(Mutex::new(Tracker::new(policy)));
+        self
+    }
+
+    pub fn timeout_counts(&self) -> TimeoutCounts {
+        let count = |stage| self.timeout_counters.of(stage).load(Ordering::Relaxed);
+        TimeoutCounts {
+            asset_fetch: count(ReportStage::AssetFetch),
+            render: count(ReportStage::Render),
+            send: count(ReportStage::Send),
+        }
+    }
+
+    // fails the stage once its budget is spent, so the caller can move on instead of stalling.
+    async fn within<T>(&self, stage: ReportStage, future: impl Future<Output = T>) -> ReportServiceResult<T> {
+        match time::timeout(self.timeouts.of(stage), future).await {
+            Ok(output) => Ok(output),
+            Err(_) => {
+                self.timeout_counters.of(stage).fetch_add(1, Ordering::Relaxed);
+                warn!("{} of a report took longer than {} ms", stage.name(), self.timeouts.of(stage).as_millis());
+                Err(ReportServiceError::Timeout(stage))
+            },
+        }
+    }
+
+    // restores tracked messages of rooms that are still open, dropping those deleted meanwhile.
+    pub async fn restore_tracks(&self, http: &Http, open_channels: &[ChannelId]) -> usize {
+        let storage = match &self.storage {
+            Some(storage) => storage,
+            None => return 0,
+        };
+
+        let stored = match storage.load_report_messages().await {
+            Ok(stored) => stored,
+            Err(err) => {
+                error!("Failed to load report messages from storage: {}", err);
+                return 0
+            }
+        };
+
+        let mut tracker_guard = self.tracker.lock().await;
+        let mut restored = 0;
+        for report in stored {
+            let exists = open_channels.contains(&report.channel_id)
+                && http.get_message(report.report_channel_id, report.message_id).await.is_ok();
+            if exists {
+                if let Some(evicted) = tracker_guard.add_track(report.channel_id, report.message_id, None) {
+                    self.forget_track(evicted).await;
+                }
+                restored += 1;
+            } else {
+                debug!("report message {} of channel {} is gone, forget it", report.message_id, report.channel_id);
+                self.forget_track(report.channel_id).await;
+            }
+        }
+        info!("{} report messages were restored from storage.", restored);
+        restored
+    }
+
+    async fn persist_track(&self, channel_id: ChannelId, report_channel_id: ChannelId, message_id: MessageId) {
+        if let Some(storage) = &self.storage
+            && let Err(err) = storage.save_report_message(channel_id, report_channel_id, message_id).await
+        {
+            error!("Failed to persist report message: {}", err);
+        }
+    }
+
+    // remembers the final report, so /history can link to it.
+    async fn persist_summary(&self, room: &RoomDTO, report_channel_id: ChannelId, message_id: MessageId) {
+        if let Some(storage) = &self.storage {
+            let started_at = storage.to_unix_millis(room.created_at);
+            if let Err(err) = storage.save_summary_message(room.channel_id, started_at, report_channel_id, message_id).await {
+                error!("Failed to persist summary message of channel {}: {}", room.channel_id, err);
+            }
+        }
+    }
+
+    async fn forget_track(&self, channel_id: ChannelId) {
+        if let Some(storage) = &self.storage
+            && let Err(err) = storage.delete_report_message(channel_id).await
+        {
+            error!("Failed to delete report message: {}", err);
+        }
+    }
+
+    // `sort` overrides the guild's configured order for this timeline only.
+    async fn create_timeline(&self, now: Instant, room: &RoomDTO, finalized: bool, sort: Option<SortOrder>) -> ReportServiceResult<Timeline> {
+        let mut config = self.config_service.get(room.guild_id).await;
+        if let Some(sort) = sort {
+            config.sort_order = sort;
+        }
+        let background = config.theme.theme().background;
+
         let mut visuals = HashMap::new();
+        let permits = Arc::new(Semaphore::new(ASSET_PREFETCH_CONCURRENCY));
+        let mut tasks = JoinSet::new();
 
         for participant in &room.participants {
-            let visual = self.asset_service.get_members_visual(room.guild_id, participant.user_id(), participant.face()).await?;
+            if participant.is_departed() {
+                visuals.insert(participant.user_id(), self.asset_service.departed_visual());
+                continue;
+            }
 
-            visuals.insert(participant.user_id(), visual);
+            let asset_service = self.asset_service.clone();
+            let permits = permits.clone();
+            let (user_id, name, face) = (participant.user_id(), participant.name().to_string(), participant.face().to_string());
+            tasks.spawn(async move {
+                // the semaphore is never closed, so acquiring only waits for a free slot.
+                let _permit = permits.acquire_owned().await.ok();
+                (user_id, asset_service.get_members_visual(user_id, &name, &face, background).await)
+            });
         }
 
-        Ok(transform(now, room, &visuals, finalized))
+        // tasks still downloading are aborted along with the set when the budget runs out.
+        self.within(ReportStage::AssetFetch, async {
+            while let Some(res) = tasks.join_next().await {
+                let (user_id, visual) = res?;
+                visuals.insert(user_id, visual);
+            }
+            Ok::<_, ReportServiceError>(())
+        }).await??;
+
+        Ok(transform(now, room, &visuals, finalized, &config))
     }
 
-    pub async fn send_room_report(&self, http: &Http, now: Instant, room: &RoomDTO, ongoing: bool) -> ReportServiceResult<()> {
-        let timeline = self.create_timeline(now, room, ongoing).await?;
+    // renders the timeline image in the guild's configured format.
+    pub async fn render_room_image(&self, now: Instant, room: &RoomDTO, ongoing: bool) -> ReportServiceResult<(Vec<u8>, ImageFormat)> {
+        let timeline = self.create_timeline(now, room, ongoing, None).await?;
+        let config = self.config_service.get(room.guild_id).await;
+        let theme = config.theme.theme();
+        let (format, quality) = (config.image_format, config.image_quality);
 
         let renderer = self.renderer.clone();
 
-        let task = tokio::task::spawn_blocking(move || {
-            return renderer.generate_png_image(&timeline);
-        });
+        let image = self.within(ReportStage::Render, self.render_pool.run(None, move || {
+            renderer.generate_image(&timeline, &theme, format, quality)
+        })).await???;
 
-        let encoded_image = task.await??;
+        Ok((image, format))
+    }
 
+    // renders the timeline split into pages of the guild's configured number of rows.
+    pub async fn render_room_pages(&self, now: Instant, room: &RoomDTO, ongoing: bool, sort: Option<SortOrder>) -> ReportServiceResult<(Vec<Vec<u8>>, ImageFormat)> {
+        self.render_pages(now, room, ongoing, sort, None).await
+    }
 
-        let mut tracker_guard = self.tracker.lock().await;
+    // a render keyed by its channel is dropped when a newer one of the same room is queued meanwhile.
+    async fn render_pages(&self, now: Instant, room: &RoomDTO, ongoing: bool, sort: Option<SortOrder>, key: Option<ChannelId>) -> ReportServiceResult<(Vec<Vec<u8>>, ImageFormat)> {
+        let timeline = self.create_timeline(now, room, ongoing, sort).await?;
+        let config = self.config_service.get(room.guild_id).await;
+        let theme = config.theme.theme();
+        let (format, quality) = (config.image_format, config.image_quality);
+        let pages = timeline.paginate(config.rows_per_image);
+        let frames = match format {
+            ImageFormat::Apng => room.recent_changes(now, ANIMATION_FRAMES),
+            _ => Vec::new(),
+        };
+
+        let renderer = self.renderer.clone();
 
-        let report_channel_id = self.report_channel_id.unwrap_or(room.channel_id.clone());
+        let images = self.within(ReportStage::Render, self.render_pool.run(key, move || {
+            pages.iter()
+                .map(|page| match format {
+                    ImageFormat::Apng => renderer.generate_animation(page, &theme, &frames),
+                    _ => renderer.generate_image(page, &theme, format, quality),
+                })
+                .collect::<TimelineRendererResult<Vec<_>>>()
+        })).await???;
 
+        Ok((images, format))
+    }
+
+    // renders the report without posting it, e.g. to answer an interaction.
+    pub async fn render_room_report(&self, now: Instant, room: &RoomDTO, ongoing: bool, sort: Option<SortOrder>) -> ReportServiceResult<RenderedReport> {
+        self.build_room_report(now, room, ongoing, sort, None).await
+    }
+
+    async fn build_room_report(&self, now: Instant, room: &RoomDTO, ongoing: bool, sort: Option<SortOrder>, key: Option<ChannelId>) -> ReportServiceResult<RenderedReport> {
+        let (pages, format) = self.render_pages(now, room, ongoing, sort, key).await?;
+        let page_name = |i: usize| match i {
+            0 => format!("thumbnail.{}", format.extension()),
+            i => format!("thumbnail-{}.{}", i + 1, format.extension()),
+        };
+        let config = self.config_service.get(room.guild_id).await;
+        let language = Language::from_locale(&config.locale);
+
+        let mut embed = self.renderer.generate_ongoing_embed(now, Timestamp::now(), room, &page_name(0), language);
+        if !config.embed_template.is_default() {
+            let values = TemplateValues {
+                channel: room.channel_id.mention().to_string(),
+                channel_name: room.channel_name.clone().unwrap_or_else(|| room.channel_id.to_string()),
+                elapsed: TimelineRenderer::format_time_delta(TimeDelta::from_std(room.wall_clock(now)).unwrap_or_default()),
+                participants: room.participants.len().to_string(),
+            };
+            embed = apply_template(embed, &config.embed_template, &values);
+        }
+        if !ongoing {
+            embed = embed
+                .field(language.text(TextKey::PersonHours), format!("{:.2}", room.person_time(now).as_secs_f32() / 3600.0), true)
+                .field(language.text(TextKey::AverageConcurrency), format!("{:.1}", room.average_concurrency(now)), true);
+        }
+        // attendance is only settled once the call is over.
+        if let (false, Some(event)) = (ongoing, &room.scheduled_event) {
+            embed = embed.field(
+                language.text(TextKey::EventAttendance).replace("{event}", &event.name),
+                TimelineRenderer::format_attendance(now, &room.participants, event, config.event_attendance_percent, language),
+                false,
+            );
+        }
+
+        // every page shares the description of the whole call.
+        let description = {
+            let mut config = config.clone();
+            if let Some(sort) = sort {
+                config.sort_order = sort;
+            }
+            describe_timeline(now, room, &config)
+        };
+
+        Ok(RenderedReport {
+            embed,
+            attachments: pages.into_iter()
+                .enumerate()
+                .map(|(i, page)| CreateAttachment::bytes(page, page_name(i)).description(description.clone()))
+                .collect(),
+        })
+    }
+
+    pub async fn render_room_svg(&self, now: Instant, room: &RoomDTO, ongoing: bool, sort: Option<SortOrder>) -> ReportServiceResult<CreateAttachment> {
+        let timeline = self.create_timeline(now, room, ongoing, sort).await?;
+        let theme = self.config_service.get(room.guild_id).await.theme.theme();
+
+        let renderer = self.svg_renderer.clone();
+
+        let svg = self.render_pool.run(None, move || {
+            renderer.generate_svg(&timeline, &theme)
+        }).await??;
+
+        Ok(CreateAttachment::bytes(svg.into_bytes(), "timeline.svg"))
+    }
+
+    pub fn export_room_json(&self, now: Instant, room: &RoomDTO) -> ReportServiceResult<CreateAttachment> {
+        let json = RoomExport::from_room(now, room).to_json()?;
+        Ok(CreateAttachment::bytes(json, "timeline.json"))
+    }
+
+    pub async fn render_leaderboard(&self, guild_id: GuildId, title: &str, entries: &[LeaderboardEntry]) -> ReportServiceResult<CreateAttachment> {
+        let config = self.config_service.get(guild_id).await;
+        let theme = config.theme.theme();
+        let rows = self.leaderboard_rows(entries, &theme).await;
+        let (format, quality) = (config.image_format, config.image_quality);
+
+        let renderer = self.leaderboard_renderer.clone();
+        let title = title.to_string();
+
+        let encoded_image = self.render_pool.run(None, move || {
+            renderer.generate_image(&title, &rows, &theme, format, quality)
+        }).await??;
+
+        Ok(CreateAttachment::bytes(encoded_image, format!("leaderboard.{}", format.extension())))
+    }
+
+    async fn leaderboard_rows(&self, entries: &[LeaderboardEntry], theme: &Theme) -> Vec<LeaderboardRow> {
+        let mut rows = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let visual = self.asset_service.get_members_visual(entry.user_id, &entry.name, &entry.face, theme.background).await;
+            rows.push(LeaderboardRow {
+                avatar: visual.avatar,
+                name: entry.name.clone(),
+                duration: entry.stats.voice_time,
+                color: visual.active_color,
+            });
+        }
+        rows
+    }
+
+    // the month as a pdf; pages are jpeg images at the guild's image quality.
+    pub async fn render_monthly_document(&self, guild_id: GuildId, title: &str, summary: MonthlySummary) -> ReportServiceResult<CreateAttachment> {
+        let config = self.config_service.get(guild_id).await;
+        let theme = config.theme.theme();
+        let language = Language::from_locale(&config.locale);
+        let rows = self.leaderboard_rows(&summary.leaderboard, &theme).await;
+        let quality = config.image_quality;
+        let filename = format!("voice-report-{}.pdf", summary.month.format("%Y-%m"));
+
+        let renderer = self.document_renderer.clone();
+        let title = title.to_string();
+
+        let document = self.render_pool.run(None, move || {
+            renderer.generate_pdf(&title, &summary, &rows, language, &theme, quality)
+        }).await??;
+
+        Ok(CreateAttachment::bytes(document, filename))
+    }
+
+    pub async fn render_heatmap(&self, guild_id: GuildId, title: &str, heatmap: Heatmap) -> ReportServiceResult<CreateAttachment> {
+        let config = self.config_service.get(guild_id).await;
+        let theme = config.theme.theme();
+        let language = Language::from_locale(&config.locale);
+        let (format, quality) = (config.image_format, config.image_quality);
+
+        let renderer = self.heatmap_renderer.clone();
+        let title = title.to_string();
+
+        let encoded_image = self.render_pool.run(None, move || {
+            renderer.generate_image(&title, &heatmap, language, &theme, format, quality)
+        }).await??;
+
+        Ok(CreateAttachment::bytes(encoded_image, format!("heatmap.{}", format.extension())))
+    }
+
+    // one strip per day of the last `days` days, in the guild's timezone.
+    pub async fn render_user_timeline(&self, guild_id: GuildId, user: &User, name: &str, title: &str, activities: &[StoredActivity], days: u32) -> ReportServiceResult<CreateAttachment> {
+        let config = self.config_service.get(guild_id).await;
+        let theme = config.theme.theme();
+        let language = Language::from_locale(&config.locale);
+        let visual = self.asset_service.get_members_visual(user.id, name, &user.face(), theme.background).await;
+        let strips = transform_days(activities, Utc::now().timestamp_millis(), days, config.timezone);
+        let (format, quality) = (config.image_format, config.image_quality);
+
+        let renderer = self.user_timeline_renderer.clone();
+        let title = title.to_string();
+
+        let encoded_image = self.render_pool.run(None, move || {
+            renderer.generate_image(&title, &visual, &strips, language, &theme, (format, quality))
+        }).await??;
+
+        Ok(CreateAttachment::bytes(encoded_image, format!("usertimeline.{}", format.extension())))
+    }
+
+    // the report message tracked for the channel, if any.
+    pub async fn get_track(&self, channel_id: ChannelId) -> Option<Track> {
+        self.tracker.lock().await.get_track(&channel_id).cloned()
+    }
+
+    // whether the room changed since the last edit and the guild's update interval has passed.
+    pub async fn is_report_due(&self, now: Instant, room: &RoomDTO) -> bool {
+        let config = self.config_service.get(room.guild_id).await;
+
+        self.tracker.lock().await
+            .get_track(&room.channel_id)
+            .is_none_or(|track| {
+                track.revision != Some(room.revision) && track.last_updated_at + config.report_policy.update_interval() <= now
+            })
+    }
+
+    // renders every given room of the guild into one stacked image; rooms come with their header.
+    pub async fn render_overview_image(&self, now: Instant, guild_id: GuildId, rooms: &[(String, RoomDTO)]) -> ReportServiceResult<(Vec<u8>, ImageFormat)> {
+        let mut sections = Vec::with_capacity(rooms.len());
+        for (title, room) in rooms {
+            sections.push(OverviewSection {
+                title: title.clone(),
+                timeline: self.create_timeline(now, room, true, None).await?,
+            });
+        }
+        let config = self.config_service.get(guild_id).await;
+        let theme = config.theme.theme();
+        let (format, quality) = (config.image_format, config.image_quality);
+
+        let renderer = self.overview_renderer.clone();
+
+        let image = self.render_pool.run(None, move || {
+            renderer.generate_image(&sections, &theme, format, quality)
+        }).await??;
+
+        Ok((image, format))
+    }
+
+    pub async fn render_overview(&self, now: Instant, guild_id: GuildId, rooms: &[(String, RoomDTO)]) -> ReportServiceResult<CreateAttachment> {
+        let (encoded_image, format) = self.render_overview_image(now, guild_id, rooms).await?;
+        Ok(CreateAttachment::bytes(encoded_image, format!("overview.{}", format.extension())))
+    }
+
+    // sends an ongoing report once the guild's update interval has passed since the last edit.
+    // returns whether the report was posted.
+    pub async fn send_scheduled_report(&self, http: &Http, now: Instant, room: &RoomDTO) -> ReportServiceResult<bool> {
+        if !self.is_report_due(now, room).await {
+            return Ok(false)
+        }
+
+        self.send_room_report(http, now, room, true).await
+    }
+
+    async fn should_report(&self, now: Instant, room: &RoomDTO, config: &GuildConfig, ongoing: bool) -> bool {
+        let tracker_guard = self.tracker.lock().await;
         match tracker_guard.get_track(&room.channel_id) {
             Some(track) => {
-                if !ongoing && track.last_updated_at + Duration::from_secs(20) > now {
-                    return Ok(())
+                // final reports are never held back.
+                if !ongoing {
+                    return true
+                }
+
+                let policy = &config.report_policy;
+                if track.last_updated_at + policy.debounce() > now {
+                    debug!("report was updated recently, skip report");
+                    return false
+                }
+                if track.recent_edits(now) >= policy.max_edits_per_hour as usize {
+                    debug!("report reached the hourly edit limit, skip report");
+                    return false
+                }
+                true
+            },
+            None => {
+                // once reached, the first report covers the whole call since the room was created.
+                if !config.meets_report_threshold(room.participants.len(), now - room.created_at) {
+                    debug!("room has not reached the report threshold, skip report");
+                    return false
                 }
+                true
+            }
+        }
+    }
+
+    // returns whether the report was posted.
+    #[instrument(skip_all, fields(guild_id = %room.guild_id, channel_id = %room.channel_id, ongoing))]
+    pub async fn send_room_report(&self, http: &Http, now: Instant, room: &RoomDTO, ongoing: bool) -> ReportServiceResult<bool> {
+        if !self.is_active_reporter(room.guild_id).await {
+            debug!("another instance reports for this guild, skip report");
+            return Ok(false)
+        }
+        let config = self.config_service.get(room.guild_id).await;
+
+        if !self.should_report(now, room, &config, ongoing).await {
+            return Ok(false)
+        }
+
+        self.post_room_report(http, now, room, ongoing, &config).await?;
+        Ok(true)
+    }
+
+    // re-renders an ongoing report on request; only the hourly edit limit holds it back.
+    // returns whether the report was posted.
+    #[instrument(skip_all, fields(guild_id = %room.guild_id, channel_id = %room.channel_id))]
+    pub async fn refresh_room_report(&self, http: &Http, now: Instant, room: &RoomDTO) -> ReportServiceResult<bool> {
+        let config = self.config_service.get(room.guild_id).await;
+
+        let limited = self.tracker.lock().await
+            .get_track(&room.channel_id)
+            .is_some_and(|track| track.recent_edits(now) >= config.report_policy.max_edits_per_hour as usize);
+        if limited {
+            return Ok(false)
+        }
+
+        self.post_room_report(http, now, room, true, &config).await?;
+        Ok(true)
+    }
 
-                let report_channel_id = self.report_channel_id.unwrap_or(room.channel_id.clone());
+    async fn post_room_report(&self, http: &Http, now: Instant, room: &RoomDTO, ongoing: bool, config: &GuildConfig) -> ReportServiceResult<()> {
+        // a final report is never dropped for a newer render.
+        let key = ongoing.then_some(room.channel_id);
+        let report = match self.build_room_report(now, room, ongoing, None, key).await {
+            Ok(report) => report,
+            Err(ReportServiceError::RenderPool(RenderPoolError::Superseded)) => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        let export = if config.attach_json {
+            Some(self.export_room_json(now, room)?)
+        } else {
+            None
+        };
+        let direct_report = (!ongoing && config.dm_starter).then(|| report.clone());
+        // the buttons only make sense while the call goes on, so the final edit removes them.
+        let components = match ongoing {
+            true => report_buttons(room.channel_id, Language::from_locale(&config.locale)),
+            false => Vec::new(),
+        };
 
-                match report_channel_id
+        let posting = self.posting.lock().unwrap().entry(room.channel_id).or_default().clone();
+        let posting_guard = posting.lock().await;
+
+        let report_channel_id = config.report_channel_for(room.channel_id, room.category_id).unwrap_or(room.channel_id);
+
+        let tracked = self.tracker.lock().await.get_track(&room.channel_id).map(|track| track.message_id);
+        let edited = match tracked {
+            Some(message_id) => {
+                let edit = self.within(ReportStage::Send, report_channel_id
                     .edit_message(
                         http,
-                        track.message_id,
+                        message_id,
                         EditMessage::new()
-                            .embed(self.renderer.generate_ongoing_embed(
-                                now,
-                                Timestamp::now(),
-                                room,
-                            ))
+                            .embed(report.embed.clone())
+                            .components(components.clone())
                             .flags(MessageFlags::SUPPRESS_NOTIFICATIONS)
-                            .attachments(EditAttachments::new().add(CreateAttachment::bytes(encoded_image, "thumbnail.png"))),
-                    )
-                    .await {
-                    Ok(_) => {
-                        if ongoing {
-                            tracker_guard.update_track(room.channel_id);
-                        } else {
-                            tracker_guard.remove(room.channel_id);
-                        }
-                        Ok(())
+                            .attachments(report.attachments.iter().chain(&export).cloned().fold(
+                                EditAttachments::new(),
+                                |attachments, attachment| attachments.add(attachment),
+                            )),
+                    )).await?;
+                match edit {
+                    Ok(message) => Some(message),
+                    // someone deleted the report; start over with a fresh message.
+                    Err(err) if is_unknown_message(&err) => {
+                        debug!("report message {} was deleted, send a new one", message_id);
+                        self.tracker.lock().await.remove(room.channel_id);
+                        self.forget_track(room.channel_id).await;
+                        None
                     },
-                    Err(err) => Err(err.into()),
+                    Err(err) => return Err(err.into()),
                 }
             },
+            None => None,
+        };
+
+        let message = match edited {
+            Some(message) => {
+                if ongoing {
+                    self.tracker.lock().await.update_track(room.channel_id, room.revision);
+                } else {
+                    self.tracker.lock().await.remove(room.channel_id);
+                    self.forget_track(room.channel_id).await;
+                }
+                message
+            },
             None => {
-                match report_channel_id
-                    .send_message(
+                let sent = self.within(ReportStage::Send, ReportTarget::Channel(report_channel_id)
+                    .send(
                         http,
                         CreateMessage::new()
-                            .embed(self.renderer.generate_ongoing_embed(
-                                now,
-                                Timestamp::now(),
-                                room,
-                            ))
+                            .embed(report.embed)
+                            .components(components)
                             .flags(MessageFlags::SUPPRESS_NOTIFICATIONS)
-                            .add_file(CreateAttachment::bytes(encoded_image, "thumbnail.png")),
-                    )
-                    .await {
-                    Ok(message) => {
-                        if ongoing {
-                            tracker_guard.add_track(room.channel_id, message.id);
+                            .add_files(report.attachments)
+                            .add_files(export),
+                    )).await;
+                // posting again could duplicate the report, so an ongoing one is looked up by its buttons instead.
+                let message = match sent {
+                    Ok(sent) => sent?,
+                    Err(ReportServiceError::Timeout(_)) if ongoing => {
+                        match self.find_posted_report(http, report_channel_id, room.channel_id).await {
+                            Some(message) => message,
+                            None => return Err(ReportServiceError::Unconfirmed),
                         }
-                        Ok(())
                     },
-                    Err(err) => Err(err.into()),
+                    Err(ReportServiceError::Timeout(_)) => return Err(ReportServiceError::Unconfirmed),
+                    Err(err) => return Err(err),
+                };
+                if ongoing {
+                    let evicted = self.tracker.lock().await.add_track(room.channel_id, message.id, Some(room.revision));
+                    if let Some(evicted) = evicted {
+                        debug!("tracker is full, evicted the track of channel {}", evicted);
+                        self.forget_track(evicted).await;
+                    }
+                    self.persist_track(room.channel_id, report_channel_id, message.id).await;
                 }
+                message
+            }
+        };
+        drop(posting_guard);
+        // the final report is the last post of the room.
+        if !ongoing {
+            self.posting.lock().unwrap().remove(&room.channel_id);
+        }
+
+        if !ongoing {
+            self.persist_summary(room, report_channel_id, message.id).await;
+        }
+
+        // members may close their DMs; the channel already has the report, so a failure is only logged.
+        if let (Some(report), Some(starter)) = (direct_report, room.starter()) {
+            let message = CreateMessage::new()
+                .embed(report.embed)
+                .add_files(report.attachments);
+            if let Err(err) = ReportTarget::Direct(starter).send(http, message).await {
+                debug!("failed to send the final report to {} by DM: {}", starter, err);
             }
         }
 
+        if let (false, Some(webhook_service)) = (ongoing, &self.webhook_service) {
+            let image_url = message.attachments.iter()
+                .find(|attachment| attachment.filename.starts_with("thumbnail."))
+                .map(|attachment| attachment.url.clone());
+            let summary = RoomSummary::from_room(now, room, image_url);
+            // deliveries may back off for a while, so they must not hold up the report loop.
+            let (webhook_service, guild_id) = (webhook_service.clone(), room.guild_id);
+            tokio::spawn(async move {
+                webhook_service.notify(guild_id, &WebhookPayload::Summary(summary)).await;
+            });
+        }
+
+        Ok(())
+    }
+
+    // the ongoing report of the room among the latest messages of the report channel, found by its refresh button.
+    async fn find_posted_report(&self, http: &Http, report_channel_id: ChannelId, channel_id: ChannelId) -> Option<Message> {
+        let custom_id = format!("{}:{}", REFRESH_BUTTON, channel_id);
+        let messages = self.within(ReportStage::Send, report_channel_id.messages(http, GetMessages::new().limit(UNCONFIRMED_LOOKUP_LIMIT))).await;
+        let messages = match messages {
+            Ok(Ok(messages)) => messages,
+            Ok(Err(err)) => {
+                debug!("failed to look up the report of channel {}: {}", channel_id, err);
+                return None
+            },
+            Err(_) => return None,
+        };
+        messages.into_iter().find(|message| {
+            message.components.iter()
+                .flat_map(|row| &row.components)
+                .any(|component| matches!(component, ActionRowComponent::Button(button) if matches!(&button.data, ButtonKind::NonLink { custom_id: id, .. } if *id == custom_id)))
+        })
+    }
+
+    // after a room moved along with its members, its report keeps being edited.
+    // unless both channels report to the same channel, the old report stays where it is and the new channel keeps its own.
+    pub async fn move_track(&self, http: &Http, guild_id: GuildId, from: ChannelId, from_category: Option<ChannelId>, to: ChannelId, to_category: Option<ChannelId>) {
+        let config = self.config_service.get(guild_id).await;
+        let report_channel_id = match (config.report_channel_for(from, from_category), config.report_channel_for(to, to_category)) {
+            (Some(old), Some(new)) if old == new => new,
+            _ => {
+                self.tracker.lock().await.remove(from);
+                self.forget_track(from).await;
+                return
+            },
+        };
+
+        let (moved, replaced) = {
+            let mut tracker_guard = self.tracker.lock().await;
+            let replaced = tracker_guard.move_track(from, to);
+            (tracker_guard.get_track(&to).map(|track| track.message_id), replaced)
+        };
+        if let Some(message_id) = replaced.filter(|&replaced| Some(replaced) != moved)
+            && let Err(err) = report_channel_id.delete_message(http, message_id).await
+        {
+            debug!("failed to delete the report replaced by a moved room: {}", err);
+        }
+        self.forget_track(from).await;
+        if let Some(message_id) = moved {
+            self.persist_track(to, report_channel_id, message_id).await;
+        }
+    }
+
+    // forgets the reports of rooms removed by cleanup and of tracks left unedited past the ttl.
+    pub async fn prune_tracks(&self, now: Instant, removed: &[ChannelId], open: &HashSet<ChannelId>) {
+        let (pruned, remaining) = {
+            let mut tracker_guard = self.tracker.lock().await;
+            let pruned = tracker_guard.prune(now, removed, open);
+            (pruned, tracker_guard.count())
+        };
+        for channel_id in &pruned {
+            self.forget_track(*channel_id).await;
+        }
+        if !pruned.is_empty() {
+            debug!("{} tracks were pruned, {} remain", pruned.len(), remaining);
+        }
+    }
+
+    // stops tracking a report message that was deleted, so the next report posts a new one.
+    pub async fn handle_message_deleted(&self, message_id: MessageId) {
+        let channel_id = self.tracker.lock().await.remove_by_message(message_id);
+        if let Some(channel_id) = channel_id {
+            debug!("tracked report message {} of channel {} was deleted", message_id, channel_id);
+            self.forget_track(channel_id).await;
+        }
+    }
+}
+
+// custom ids carry the voice channel, e.g. `report-refresh:1234`.
+pub const REFRESH_BUTTON: &str = "report-refresh";
+pub const DETAILS_BUTTON: &str = "report-details";
+pub const STOP_BUTTON: &str = "report-stop";
+
+fn report_buttons(channel_id: ChannelId, language: Language) -> Vec<CreateActionRow> {
+    let button = |prefix: &str| CreateButton::new(format!("{}:{}", prefix, channel_id));
+    vec![CreateActionRow::Buttons(vec![
+        button(REFRESH_BUTTON).label(language.text(TextKey::Refresh)).style(ButtonStyle::Secondary),
+        button(DETAILS_BUTTON).label(language.text(TextKey::Details)).style(ButtonStyle::Secondary),
+        button(STOP_BUTTON).label(language.text(TextKey::StopTracking)).style(ButtonStyle::Danger),
+    ])]
+}
+
+fn is_unknown_message(err: &SerenityError) -> bool {
+    matches!(
+        err,
+        SerenityError::Http(HttpError::UnsuccessfulRequest(response)) if response.error.code == UNKNOWN_MESSAGE_CODE
+    )
+}
+
+// a part whose template fails to parse keeps its default text.
+// mentions only render in the description, so other parts get the channel name.
+fn apply_template(mut embed: CreateEmbed, template: &EmbedTemplate, values: &TemplateValues) -> CreateEmbed {
+    let plain = values.plain();
+    let render = |text: &Option<String>, values: &TemplateValues, limit: usize| {
+        let text = text.as_deref()?;
+        match Template::parse(text) {
+            Ok(template) => Some(template.render(values).chars().take(limit).collect::<String>()),
+            Err(err) => {
+                warn!("ignoring invalid embed template {:?}: {}", text, err);
+                None
+            },
+        }
+    };
+
+    if let Some(title) = render(&template.title, &plain, EMBED_TITLE_LIMIT) {
+        embed = embed.title(title);
+    }
+    if let Some(description) = render(&template.description, values, EMBED_DESCRIPTION_LIMIT) {
+        embed = embed.description(description);
+    }
+    if let Some(footer) = render(&template.footer, &plain, EMBED_FOOTER_LIMIT) {
+        embed = embed.footer(CreateEmbedFooter::new(footer));
+    }
+    if let Some(color) = template.color {
+        embed = embed.color(color);
     }
+    embed
 }
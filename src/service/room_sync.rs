@@ -0,0 +1,83 @@
+use crate::model::{RoomEvent, RoomManager};
+use crate::storage::RoomMirror;
+use serenity::all::{ChannelId, GuildId};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, warn};
+
+// pauses, channel info and speaking publish no events, and mirrored rooms expire unless saved again;
+// every room is saved again this often to catch up with both.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+// mirrors every room change into a shared store, so other processes can read the rooms of this one.
+pub struct RoomSyncService {
+    room_manager: Arc<RoomManager>,
+    mirror: Arc<dyn RoomMirror>,
+}
+
+impl RoomSyncService {
+    pub fn new(room_manager: Arc<RoomManager>, mirror: Arc<dyn RoomMirror>) -> Self {
+        RoomSyncService { room_manager, mirror }
+    }
+
+    // saves the current rooms, then follows their changes until the room manager is dropped.
+    pub fn spawn(self: Arc<Self>, mut events: broadcast::Receiver<RoomEvent>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut refresh = tokio::time::interval(REFRESH_INTERVAL);
+            loop {
+                let event = tokio::select! {
+                    // the first tick completes right away, saving the current rooms.
+                    _ = refresh.tick() => {
+                        self.save_all().await;
+                        continue;
+                    },
+                    event = events.recv() => event,
+                };
+                match event {
+                    Ok(RoomEvent::RoomClosed { guild_id, channel_id }) => self.remove(guild_id, channel_id).await,
+                    Ok(RoomEvent::RoomMoved { guild_id, from, to }) => {
+                        self.remove(guild_id, from).await;
+                        self.save(to).await;
+                    },
+                    Ok(RoomEvent::RoomCreated { channel_id, .. })
+                    | Ok(RoomEvent::ParticipantJoined { channel_id, .. })
+                    | Ok(RoomEvent::ParticipantLeft { channel_id, .. })
+                    | Ok(RoomEvent::FlagsChanged { channel_id, .. }) => self.save(channel_id).await,
+                    // the skipped changes are unknown, so every room is saved again.
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("room sync lagged behind, {} room events skipped", skipped);
+                        self.save_all().await;
+                    },
+                    Err(RecvError::Closed) => break,
+                }
+            }
+            debug!("room sync loop stopped");
+        })
+    }
+
+    async fn save(&self, channel_id: ChannelId) {
+        let snapshot = match self.room_manager.get_room(channel_id).await {
+            Some(room) => room.lock().await.snapshot(),
+            None => return,
+        };
+        if let Err(err) = self.mirror.save(&snapshot).await {
+            error!("Failed to save room of channel {} to the shared store: {}", channel_id, err);
+        }
+    }
+
+    async fn save_all(&self) {
+        for room in self.room_manager.get_all_rooms().await {
+            let channel_id = room.lock().await.channel_id();
+            self.save(channel_id).await;
+        }
+    }
+
+    async fn remove(&self, guild_id: GuildId, channel_id: ChannelId) {
+        if let Err(err) = self.mirror.remove(guild_id, channel_id).await {
+            error!("Failed to remove room of channel {} from the shared store: {}", channel_id, err);
+        }
+    }
+}
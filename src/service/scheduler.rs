@@ -0,0 +1,199 @@
+use crate::model::RoomManager;
+use crate::service::report::{ReportService, RoomDTO};
+use futures::stream::{self, StreamExt};
+use serenity::all::{ChannelId, GuildId, Http};
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{watch, Mutex};
+use tokio::task::JoinHandle;
+use tokio::time::{self, Instant, MissedTickBehavior};
+use tracing::{debug, error};
+
+// every channel may burst a few edits, then gets one edit per refill period.
+const BUCKET_CAPACITY: f64 = 3.0;
+const BUCKET_REFILL_PERIOD: Duration = Duration::from_secs(30);
+
+struct TokenBucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(now: Instant) -> Self {
+        TokenBucket { tokens: BUCKET_CAPACITY, refilled_at: now }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.refilled_at);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() / BUCKET_REFILL_PERIOD.as_secs_f64()).min(BUCKET_CAPACITY);
+        self.refilled_at = now;
+    }
+
+    fn has_token(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        self.tokens >= 1.0
+    }
+
+    fn try_take(&mut self, now: Instant) -> bool {
+        if !self.has_token(now) {
+            return false
+        }
+        self.tokens -= 1.0;
+        true
+    }
+
+    // gives back a token taken for an edit that was not sent after all.
+    fn refund(&mut self) {
+        self.tokens = (self.tokens + 1.0).min(BUCKET_CAPACITY);
+    }
+}
+
+// Spreads scheduled report edits over each tick so many simultaneous calls do not hit rate limits.
+pub struct ReportScheduler {
+    room_manager: Arc<RoomManager>,
+    report_service: Arc<ReportService>,
+    tick: Duration,
+    // guilds sent side by side; rooms of one guild still go one by one, as they usually share a report channel.
+    concurrency: usize,
+    buckets: Mutex<HashMap<ChannelId, TokenBucket>>,
+}
+
+impl ReportScheduler {
+    pub fn new(room_manager: Arc<RoomManager>, report_service: Arc<ReportService>, tick: Duration) -> Self {
+        ReportScheduler {
+            room_manager,
+            report_service,
+            tick,
+            concurrency: 1,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    // runs the schedule until `shutdown` changes or its sender is dropped.
+    pub fn spawn(self: Arc<Self>, http: Arc<Http>, mut shutdown: watch::Receiver<bool>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = time::interval(self.tick);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            interval.tick().await;
+
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {},
+                    _ = shutdown.changed() => break,
+                }
+
+                tokio::select! {
+                    _ = self.run_once(&http) => {},
+                    _ = shutdown.changed() => break,
+                }
+            }
+            debug!("report scheduler stopped");
+        })
+    }
+
+    pub async fn run_once(&self, http: &Http) {
+        let now = Instant::now();
+        let mut open_channels = Vec::new();
+        let mut due = Vec::new();
+        for room in self.room_manager.get_all_rooms().await {
+            let room_dto = {
+                let room = room.lock().await;
+                RoomDTO::from_room(&room)
+            };
+            open_channels.push(room_dto.channel_id);
+            if self.report_service.is_report_due(now, &room_dto).await {
+                due.push(room_dto);
+            }
+        }
+
+        // rooms that changed most recently go first.
+        due.sort_by_key(|room| std::cmp::Reverse(room.last_changed_at()));
+        let due = self.with_tokens(now, &open_channels, due).await;
+        if due.is_empty() {
+            return
+        }
+
+        // guilds keep the order of their most recent change, so busy guilds do not starve quiet ones.
+        let mut guilds: Vec<Vec<RoomDTO>> = Vec::new();
+        let mut index: HashMap<GuildId, usize> = HashMap::new();
+        for room in due {
+            let i = *index.entry(room.guild_id).or_insert_with(|| {
+                guilds.push(Vec::new());
+                guilds.len() - 1
+            });
+            guilds[i].push(room);
+        }
+
+        stream::iter(guilds)
+            .map(|rooms| self.send_guild_reports(http, rooms))
+            .buffer_unordered(self.concurrency)
+            .for_each(|_| async {})
+            .await;
+    }
+
+    async fn send_guild_reports(&self, http: &Http, rooms: Vec<RoomDTO>) {
+        // stagger the edits over the tick instead of sending them in one burst.
+        let slot = self.tick / (rooms.len() as u32 + 1);
+        for room in rooms {
+            time::sleep(jitter(slot)).await;
+
+            let now = Instant::now();
+            if !self.take_token(now, room.channel_id).await {
+                debug!("channel {} ran out of report tokens, postpone", room.channel_id);
+                continue
+            }
+            match self.report_service.send_scheduled_report(http, now, &room).await {
+                Ok(true) => {},
+                // the room was skipped, so the edit never happened.
+                Ok(false) => self.refund_token(room.channel_id).await,
+                Err(err) => error!("Error sending room report: {:?}", err),
+            }
+        }
+    }
+
+    // keeps the rooms whose channel still has a token, and forgets buckets of closed rooms.
+    // the token itself is only taken right before sending.
+    async fn with_tokens(&self, now: Instant, open_channels: &[ChannelId], rooms: Vec<RoomDTO>) -> Vec<RoomDTO> {
+        let mut buckets = self.buckets.lock().await;
+        buckets.retain(|channel_id, _| open_channels.contains(channel_id));
+
+        rooms.into_iter()
+            .filter(|room| {
+                let allowed = buckets.entry(room.channel_id)
+                    .or_insert_with(|| TokenBucket::new(now))
+                    .has_token(now);
+                if !allowed {
+                    debug!("channel {} ran out of report tokens, postpone", room.channel_id);
+                }
+                allowed
+            })
+            .collect()
+    }
+
+    async fn take_token(&self, now: Instant, channel_id: ChannelId) -> bool {
+        self.buckets.lock().await
+            .entry(channel_id)
+            .or_insert_with(|| TokenBucket::new(now))
+            .try_take(now)
+    }
+
+    async fn refund_token(&self, channel_id: ChannelId) {
+        if let Some(bucket) = self.buckets.lock().await.get_mut(&channel_id) {
+            bucket.refund();
+        }
+    }
+}
+
+// somewhere between half and the whole slot.
+fn jitter(slot: Duration) -> Duration {
+    let random = RandomState::new().hash_one(0u8) % 1000;
+    slot / 2 + slot.mul_f64(random as f64 / 2000.0)
+}
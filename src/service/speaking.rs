@@ -0,0 +1,134 @@
+use crate::model::{RoomManager, RoomStatus};
+use crate::service::config::ConfigService;
+use serenity::all::{ChannelId, GuildId, UserId};
+use serenity::async_trait;
+use songbird::events::context_data::VoiceTick;
+use songbird::{CoreEvent, Event, EventContext, EventHandler, Songbird};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use tracing::{debug, error};
+
+// Joins voice channels to learn who is actually talking, instead of guessing from mute flags.
+pub struct SpeakingService {
+    songbird: Arc<Songbird>,
+    room_manager: Arc<RoomManager>,
+    config_service: Arc<ConfigService>,
+}
+
+impl SpeakingService {
+    pub fn new(songbird: Arc<Songbird>, room_manager: Arc<RoomManager>, config_service: Arc<ConfigService>) -> Self {
+        SpeakingService { songbird, room_manager, config_service }
+    }
+
+    // follows the room after a voice state change: joins an occupied room when idle, leaves an empty one.
+    pub async fn sync(&self, guild_id: GuildId, channel_id: ChannelId) {
+        let occupied = match self.room_manager.get_room(channel_id).await {
+            Some(room) => room.lock().await.get_status() == RoomStatus::Occupied,
+            None => false,
+        };
+
+        match self.current_channel(guild_id).await {
+            Some(current) if current == channel_id && !occupied => self.leave(guild_id).await,
+            None if occupied && self.config_service.get(guild_id).await.track_speaking => {
+                self.join(guild_id, channel_id).await
+            },
+            _ => {},
+        }
+    }
+
+    pub async fn current_channel(&self, guild_id: GuildId) -> Option<ChannelId> {
+        let call = self.songbird.get(guild_id)?;
+        let channel_id = call.lock().await.current_channel()?;
+        Some(ChannelId::new(channel_id.0.get()))
+    }
+
+    // a bot can only listen to one channel per guild, so the first room wins.
+    pub async fn join(&self, guild_id: GuildId, channel_id: ChannelId) {
+        if self.current_channel(guild_id).await.is_some() {
+            return;
+        }
+
+        let call = match self.songbird.join(guild_id, channel_id).await {
+            Ok(call) => call,
+            Err(err) => {
+                error!("Failed to join voice channel {}: {}", channel_id, err);
+                return;
+            }
+        };
+
+        let receiver = SpeakingReceiver::new(channel_id, self.room_manager.clone());
+        let mut call = call.lock().await;
+        call.add_global_event(CoreEvent::SpeakingStateUpdate.into(), receiver.clone());
+        call.add_global_event(CoreEvent::VoiceTick.into(), receiver);
+        debug!("listening to voice channel {}", channel_id);
+    }
+
+    pub async fn leave(&self, guild_id: GuildId) {
+        if let Err(err) = self.songbird.remove(guild_id).await {
+            debug!("failed to leave voice channel on guild {}: {}", guild_id, err);
+        }
+    }
+}
+
+#[derive(Clone)]
+struct SpeakingReceiver {
+    channel_id: ChannelId,
+    room_manager: Arc<RoomManager>,
+    // voice packets only carry an SSRC; speaking state updates tell which user it belongs to.
+    users: Arc<Mutex<HashMap<u32, UserId>>>,
+    speaking: Arc<Mutex<HashSet<u32>>>,
+}
+
+impl SpeakingReceiver {
+    fn new(channel_id: ChannelId, room_manager: Arc<RoomManager>) -> Self {
+        SpeakingReceiver {
+            channel_id,
+            room_manager,
+            users: Arc::new(Mutex::new(HashMap::new())),
+            speaking: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    async fn handle_tick(&self, tick: &VoiceTick) {
+        let now = Instant::now();
+        let users = self.users.lock().await;
+        let mut speaking = self.speaking.lock().await;
+
+        let started: Vec<u32> = tick.speaking.keys().filter(|ssrc| !speaking.contains(ssrc)).copied().collect();
+        let stopped: Vec<u32> = speaking.iter().filter(|ssrc| !tick.speaking.contains_key(ssrc)).copied().collect();
+
+        for (ssrc, is_speaking) in started.into_iter().map(|ssrc| (ssrc, true)).chain(stopped.into_iter().map(|ssrc| (ssrc, false))) {
+            if is_speaking {
+                speaking.insert(ssrc);
+            } else {
+                speaking.remove(&ssrc);
+            }
+
+            let user_id = match users.get(&ssrc) {
+                Some(user_id) => *user_id,
+                None => continue,
+            };
+            if let Err(err) = self.room_manager.handle_speaking_event(now, self.channel_id, user_id, is_speaking).await {
+                debug!("failed to record speaking of user {}: {}", user_id, err);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl EventHandler for SpeakingReceiver {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        match ctx {
+            EventContext::SpeakingStateUpdate(speaking) => {
+                if let Some(user_id) = speaking.user_id {
+                    self.users.lock().await.insert(speaking.ssrc, UserId::new(user_id.0));
+                }
+            },
+            EventContext::VoiceTick(tick) => self.handle_tick(tick).await,
+            _ => {},
+        }
+        None
+    }
+}
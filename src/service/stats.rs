@@ -0,0 +1,300 @@
+use crate::storage::{SqliteStorage, StorageResult, StoredActivity};
+use chrono::{DateTime, Datelike, Days, DurationRound, Local, Months, NaiveDate, TimeDelta, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+use serenity::all::{GuildId, UserId};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const WEEK: Duration = Duration::from_hours(24 * 7);
+
+#[derive(Debug, Clone, Default)]
+pub struct UserStats {
+    pub period: Duration,
+    pub voice_time: Duration,
+    pub muted_time: Duration,
+    pub stream_time: Duration,
+    pub streams: u32,
+    pub video_time: Duration,
+    pub sessions: u32,
+}
+
+impl UserStats {
+    pub fn sessions_per_week(&self) -> f32 {
+        self.sessions as f32 * WEEK.as_secs_f32() / self.period.as_secs_f32().max(1.0)
+    }
+}
+
+// voice time of a guild by day of the week and hour of the day.
+#[derive(Debug, Clone, Default)]
+pub struct Heatmap {
+    // indexed by days from monday, then by hour.
+    pub cells: [[Duration; 24]; 7],
+}
+
+impl Heatmap {
+    pub fn max(&self) -> Duration {
+        self.cells.iter().flatten().copied().max().unwrap_or(Duration::ZERO)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.max().is_zero()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LeaderboardEntry {
+    pub user_id: UserId,
+    pub name: String,
+    pub face: String,
+    pub stats: UserStats,
+}
+
+// a calendar month of a guild, compiled for the monthly document.
+#[derive(Debug, Clone)]
+pub struct MonthlySummary {
+    // first day of the month.
+    pub month: NaiveDate,
+    pub voice_time: Duration,
+    pub calls: usize,
+    pub sessions: u32,
+    pub members: usize,
+    pub leaderboard: Vec<LeaderboardEntry>,
+    // voice time of each day of the month, in the guild's timezone.
+    pub days: Vec<Duration>,
+}
+
+impl MonthlySummary {
+    pub fn busiest_day(&self) -> Option<(NaiveDate, Duration)> {
+        self.days.iter()
+            .enumerate()
+            .filter(|(_, duration)| !duration.is_zero())
+            .max_by_key(|(_, duration)| **duration)
+            .and_then(|(day, duration)| Some((self.month.checked_add_days(Days::new(day as u64))?, *duration)))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.voice_time.is_zero()
+    }
+}
+
+pub struct StatsService {
+    storage: Arc<SqliteStorage>,
+}
+
+impl StatsService {
+    pub fn new(storage: Arc<SqliteStorage>) -> Self {
+        StatsService { storage }
+    }
+
+    pub async fn user_stats(&self, guild_id: GuildId, user_id: UserId, period: Duration) -> StorageResult<UserStats> {
+        let (since, until) = window(period);
+        let activities = self.storage.load_activities(guild_id, Some(user_id), since, until).await?;
+        Ok(aggregate(&activities, since, until))
+    }
+
+    // the user's persisted activities of the period, for drawing rather than aggregating.
+    pub async fn user_activities(&self, guild_id: GuildId, user_id: UserId, period: Duration) -> StorageResult<Vec<StoredActivity>> {
+        let (since, until) = window(period);
+        self.storage.load_activities(guild_id, Some(user_id), since, until).await
+    }
+
+    // ranks members of the guild by voice time.
+    pub async fn leaderboard(&self, guild_id: GuildId, period: Duration, limit: usize) -> StorageResult<Vec<LeaderboardEntry>> {
+        let (since, until) = window(period);
+        let activities = self.storage.load_activities(guild_id, None, since, until).await?;
+
+        let mut entries = rank(activities, since, until);
+        entries.truncate(limit);
+        Ok(entries)
+    }
+
+    // the calendar month starting at `month` in the guild's timezone; the current month ends now.
+    pub async fn monthly_summary(&self, guild_id: GuildId, month: NaiveDate, timezone: Option<Tz>, limit: usize) -> StorageResult<MonthlySummary> {
+        let month = month.with_day(1).unwrap_or(month);
+        let (since, next_month) = match timezone {
+            Some(timezone) => month_window(&timezone, month),
+            None => month_window(&Local, month),
+        };
+        let until = next_month.min(Utc::now().timestamp_millis());
+        let day_count = month.checked_add_months(Months::new(1)).map_or(0, |next| (next - month).num_days() as usize);
+
+        let mut summary = MonthlySummary {
+            month,
+            voice_time: Duration::ZERO,
+            calls: 0,
+            sessions: 0,
+            members: 0,
+            leaderboard: Vec::new(),
+            days: vec![Duration::ZERO; day_count],
+        };
+        if since >= until {
+            return Ok(summary)
+        }
+
+        let activities = self.storage.load_activities(guild_id, None, since, until).await?;
+        for activity in &activities {
+            let start = activity.started_at.max(since);
+            let end = activity.ended_at.unwrap_or(until).min(until);
+            match timezone {
+                Some(timezone) => add_to_days(&mut summary.days, &timezone, month, start, end),
+                None => add_to_days(&mut summary.days, &Local, month, start, end),
+            }
+        }
+        summary.calls = activities.iter().map(|activity| activity.room_id).collect::<HashSet<_>>().len();
+
+        let entries = rank(activities, since, until);
+        summary.voice_time = entries.iter().map(|entry| entry.stats.voice_time).sum();
+        summary.sessions = entries.iter().map(|entry| entry.stats.sessions).sum();
+        summary.members = entries.len();
+        summary.leaderboard = entries;
+        summary.leaderboard.truncate(limit);
+        Ok(summary)
+    }
+
+    // adds up everyone's voice time over the period; hours are those of the guild's timezone.
+    pub async fn heatmap(&self, guild_id: GuildId, period: Duration, timezone: Option<Tz>) -> StorageResult<Heatmap> {
+        let (since, until) = window(period);
+        let activities = self.storage.load_activities(guild_id, None, since, until).await?;
+
+        let mut heatmap = Heatmap::default();
+        for activity in &activities {
+            let start = activity.started_at.max(since);
+            let end = activity.ended_at.unwrap_or(until).min(until);
+            match timezone {
+                Some(timezone) => add_to_heatmap(&mut heatmap, &timezone, start, end),
+                None => add_to_heatmap(&mut heatmap, &Local, start, end),
+            }
+        }
+        Ok(heatmap)
+    }
+}
+
+// splits [start, end) at hour boundaries of the timezone, so +05:30 and the like fill their own hours.
+fn add_to_heatmap<T: TimeZone>(heatmap: &mut Heatmap, timezone: &T, start: i64, end: i64) {
+    let (start, end) = match (DateTime::<Utc>::from_timestamp_millis(start), DateTime::<Utc>::from_timestamp_millis(end)) {
+        (Some(start), Some(end)) => (start.with_timezone(timezone), end.with_timezone(timezone)),
+        _ => return,
+    };
+
+    let mut current = start;
+    while current < end {
+        let next_hour = match current.clone().duration_trunc(TimeDelta::hours(1)) {
+            Ok(hour) => hour + TimeDelta::hours(1),
+            Err(_) => return,
+        };
+        let until = next_hour.min(end.clone());
+        let duration = (until.clone() - current.clone()).to_std().unwrap_or(Duration::ZERO);
+        let day = current.weekday().num_days_from_monday() as usize;
+        heatmap.cells[day][current.hour() as usize] += duration;
+        current = until;
+    }
+}
+
+// every member of the activities, by voice time.
+fn rank(activities: Vec<StoredActivity>, since: i64, until: i64) -> Vec<LeaderboardEntry> {
+    let mut by_user: HashMap<UserId, Vec<StoredActivity>> = HashMap::new();
+    for activity in activities {
+        by_user.entry(activity.user_id).or_default().push(activity);
+    }
+
+    let mut entries: Vec<LeaderboardEntry> = by_user
+        .into_iter()
+        .filter_map(|(user_id, activities)| {
+            // the latest activity holds the most recent name and avatar.
+            let latest = activities.last()?;
+            Some(LeaderboardEntry {
+                user_id,
+                name: latest.name.clone(),
+                face: latest.face.clone(),
+                stats: aggregate(&activities, since, until),
+            })
+        })
+        .collect();
+
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.stats.voice_time));
+    entries
+}
+
+// unix milliseconds from the first midnight of the month to that of the next one.
+fn month_window<T: TimeZone>(timezone: &T, month: NaiveDate) -> (i64, i64) {
+    let start_of = |date: NaiveDate| date.and_hms_opt(0, 0, 0)
+        .and_then(|naive| timezone.from_local_datetime(&naive).earliest())
+        .map(|time| time.timestamp_millis());
+
+    let since = start_of(month).unwrap_or(0);
+    let until = month.checked_add_months(Months::new(1)).and_then(start_of).unwrap_or(since);
+    (since, until)
+}
+
+// splits [start, end) at midnights of the timezone into the days counted from `first`.
+fn add_to_days<T: TimeZone>(days: &mut [Duration], timezone: &T, first: NaiveDate, start: i64, end: i64) {
+    let (start, end) = match (DateTime::<Utc>::from_timestamp_millis(start), DateTime::<Utc>::from_timestamp_millis(end)) {
+        (Some(start), Some(end)) => (start.with_timezone(timezone), end.with_timezone(timezone)),
+        _ => return,
+    };
+
+    let mut current = start;
+    while current < end {
+        let date = current.date_naive();
+        let next_day = match date.succ_opt().and_then(|next| next.and_hms_opt(0, 0, 0)).and_then(|naive| timezone.from_local_datetime(&naive).earliest()) {
+            Some(next_day) => next_day,
+            None => return,
+        };
+        let until = next_day.min(end.clone());
+        let duration = (until.clone() - current.clone()).to_std().unwrap_or(Duration::ZERO);
+        if let Some(day) = usize::try_from((date - first).num_days()).ok().and_then(|day| days.get_mut(day)) {
+            *day += duration;
+        }
+        current = until;
+    }
+}
+
+// returns [now - period, now) as unix milliseconds.
+pub(crate) fn window(period: Duration) -> (i64, i64) {
+    let until = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as i64);
+    (until - period.as_millis() as i64, until)
+}
+
+// expects activities of a single user ordered by room and start, as `load_activities` returns them.
+pub(crate) fn aggregate(activities: &[StoredActivity], since: i64, until: i64) -> UserStats {
+    let mut stats = UserStats {
+        period: Duration::from_millis((until - since).max(0) as u64),
+        ..UserStats::default()
+    };
+
+    let mut prev: Option<&StoredActivity> = None;
+    for activity in activities {
+        let start = activity.started_at.max(since);
+        let end = activity.ended_at.unwrap_or(until).min(until);
+        let duration = Duration::from_millis((end - start).max(0) as u64);
+
+        let following = prev.filter(|prev| {
+            prev.room_id == activity.room_id
+                && prev.user_id == activity.user_id
+                && prev.ended_at == Some(activity.started_at)
+        });
+
+        stats.voice_time += duration;
+        if following.is_none() {
+            stats.sessions += 1;
+        }
+        if activity.flags.is_muted || activity.flags.is_deafened {
+            stats.muted_time += duration;
+        }
+        if activity.flags.is_sharing_screen {
+            stats.stream_time += duration;
+            if !following.is_some_and(|prev| prev.flags.is_sharing_screen) {
+                stats.streams += 1;
+            }
+        }
+
+        if activity.flags.is_video {
+            stats.video_time += duration;
+        }
+
+        prev = Some(activity);
+    }
+
+    stats
+}
@@ -0,0 +1,118 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum TemplateError {
+    #[error("unknown placeholder {{{0}}}")]
+    UnknownPlaceholder(String),
+
+    #[error("unclosed placeholder at {0}")]
+    Unclosed(usize),
+}
+
+pub type TemplateResult<T> = Result<T, TemplateError>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placeholder {
+    Channel,
+    Elapsed,
+    Participants,
+}
+
+impl Placeholder {
+    pub const ALL: [Placeholder; 3] = [Placeholder::Channel, Placeholder::Elapsed, Placeholder::Participants];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Placeholder::Channel => "channel",
+            Placeholder::Elapsed => "elapsed",
+            Placeholder::Participants => "participants",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Placeholder(Placeholder),
+}
+
+// text with `{name}` placeholders; `{{` and `}}` stand for literal braces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+// what placeholders are replaced with.
+pub struct TemplateValues {
+    // a mention, which only renders inside descriptions.
+    pub channel: String,
+    pub channel_name: String,
+    pub elapsed: String,
+    pub participants: String,
+}
+
+impl TemplateValues {
+    // the values for texts that show mentions literally, such as titles and footers.
+    pub fn plain(&self) -> TemplateValues {
+        TemplateValues {
+            channel: self.channel_name.clone(),
+            channel_name: self.channel_name.clone(),
+            elapsed: self.elapsed.clone(),
+            participants: self.participants.clone(),
+        }
+    }
+
+    fn get(&self, placeholder: Placeholder) -> &str {
+        match placeholder {
+            Placeholder::Channel => &self.channel,
+            Placeholder::Elapsed => &self.elapsed,
+            Placeholder::Participants => &self.participants,
+        }
+    }
+}
+
+impl Template {
+    pub fn parse(text: &str) -> TemplateResult<Template> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = text.char_indices().peekable();
+
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '{' if chars.peek().is_some_and(|(_, next)| *next == '{') => {
+                    chars.next();
+                    literal.push('{');
+                },
+                '}' if chars.peek().is_some_and(|(_, next)| *next == '}') => {
+                    chars.next();
+                    literal.push('}');
+                },
+                '{' => {
+                    let name: String = chars.by_ref().map(|(_, c)| c).take_while(|c| *c != '}').collect();
+                    if !text[i..].contains('}') {
+                        return Err(TemplateError::Unclosed(i))
+                    }
+                    let placeholder = Placeholder::ALL.into_iter()
+                        .find(|placeholder| placeholder.name() == name.trim())
+                        .ok_or(TemplateError::UnknownPlaceholder(name))?;
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Placeholder(placeholder));
+                },
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+        Ok(Template { segments })
+    }
+
+    pub fn render(&self, values: &TemplateValues) -> String {
+        self.segments.iter().map(|segment| match segment {
+            Segment::Literal(text) => text.as_str(),
+            Segment::Placeholder(placeholder) => values.get(*placeholder),
+        }).collect()
+    }
+}
@@ -1,33 +1,88 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::Duration;
 use serenity::all::{ChannelId, MessageId};
 use tokio::time::Instant;
 
+const EDIT_HISTORY_WINDOW: Duration = Duration::from_hours(1);
+pub const DEFAULT_MAX_TRACKS: usize = 4096;
+pub const DEFAULT_TRACK_TTL: Duration = Duration::from_hours(24);
+
+// bounds of the tracker; a track evicted early only means the next report is posted as a new message.
 #[derive(Clone, Copy, Debug)]
+pub struct TrackPolicy {
+    pub max_tracks: usize,
+    // tracks not edited for this long are dropped on cleanup.
+    pub ttl: Duration,
+}
+
+impl Default for TrackPolicy {
+    fn default() -> Self {
+        TrackPolicy {
+            max_tracks: DEFAULT_MAX_TRACKS,
+            ttl: DEFAULT_TRACK_TTL,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct Track {
     pub message_id: MessageId,
     pub last_updated_at: Instant,
+    // revision of the room the message shows; unknown for tracks restored from storage.
+    pub revision: Option<u64>,
+    edited_at: VecDeque<Instant>,
+}
+
+impl Track {
+    // counts edits within the last hour; older ones are only dropped on the next edit, so they are skipped here.
+    pub fn recent_edits(&self, now: Instant) -> usize {
+        self.edited_at.iter().filter(|edited_at| **edited_at + EDIT_HISTORY_WINDOW >= now).count()
+    }
 }
 
 pub struct Tracker {
-    tracks: HashMap<ChannelId, Track>
+    tracks: HashMap<ChannelId, Track>,
+    policy: TrackPolicy,
 }
 
 impl Tracker {
-    pub fn new() -> Self {
-        Tracker {tracks: HashMap::new()}
+    pub fn new(policy: TrackPolicy) -> Self {
+        Tracker {tracks: HashMap::new(), policy}
     }
 
-    pub fn add_track(&mut self, channel_id: ChannelId, message_id: MessageId) {
+    // returns the channel whose track was evicted to stay within the max size, if any.
+    pub fn add_track(&mut self, channel_id: ChannelId, message_id: MessageId, revision: Option<u64>) -> Option<ChannelId> {
+        let evicted = match self.tracks.contains_key(&channel_id) || self.tracks.len() < self.policy.max_tracks.max(1) {
+            true => None,
+            false => self.evict_oldest(),
+        };
         let track = Track{
             message_id,
-            last_updated_at: Instant::now()
+            last_updated_at: Instant::now(),
+            revision,
+            edited_at: VecDeque::new(),
         };
         self.tracks.insert(channel_id, track);
+        evicted
+    }
+
+    fn evict_oldest(&mut self) -> Option<ChannelId> {
+        let channel_id = self.tracks.iter()
+            .min_by_key(|(_, track)| track.last_updated_at)
+            .map(|(channel_id, _)| *channel_id)?;
+        self.tracks.remove(&channel_id);
+        Some(channel_id)
     }
 
-    pub fn update_track(&mut self, channel_id: ChannelId) {
+    pub fn update_track(&mut self, channel_id: ChannelId, revision: u64) {
         if let Some(track) = self.tracks.get_mut(&channel_id) {
-            track.last_updated_at = Instant::now();
+            let now = Instant::now();
+            track.last_updated_at = now;
+            track.revision = Some(revision);
+            track.edited_at.push_back(now);
+            while track.edited_at.front().is_some_and(|edited_at| *edited_at + EDIT_HISTORY_WINDOW < now) {
+                track.edited_at.pop_front();
+            }
         }
     }
 
@@ -35,7 +90,41 @@ impl Tracker {
         self.tracks.get(channel_id)
     }
 
+    // keeps the track of `from` for `to`, replacing the one `to` had; returns the replaced message.
+    pub fn move_track(&mut self, from: ChannelId, to: ChannelId) -> Option<MessageId> {
+        let track = self.tracks.remove(&from)?;
+        self.tracks.insert(to, track).map(|replaced| replaced.message_id)
+    }
+
     pub fn remove(&mut self, channel_id: ChannelId) {
         self.tracks.remove(&channel_id);
     }
-}
\ No newline at end of file
+
+    // drops the tracks of the given channels and those not edited within the ttl; returns the dropped channels.
+    // tracks of rooms still open are kept past the ttl, as a quiet call is not edited for a while.
+    pub fn prune(&mut self, now: Instant, removed: &[ChannelId], open: &HashSet<ChannelId>) -> Vec<ChannelId> {
+        let ttl = self.policy.ttl;
+        let mut pruned = Vec::new();
+        self.tracks.retain(|channel_id, track| {
+            let keep = !removed.contains(channel_id) && (open.contains(channel_id) || track.last_updated_at + ttl > now);
+            if !keep {
+                pruned.push(*channel_id);
+            }
+            keep
+        });
+        pruned
+    }
+
+    pub fn count(&self) -> usize {
+        self.tracks.len()
+    }
+
+    // returns the channel the message was tracked for, if any.
+    pub fn remove_by_message(&mut self, message_id: MessageId) -> Option<ChannelId> {
+        let channel_id = self.tracks.iter()
+            .find(|(_, track)| track.message_id == message_id)
+            .map(|(channel_id, _)| *channel_id)?;
+        self.tracks.remove(&channel_id);
+        Some(channel_id)
+    }
+}
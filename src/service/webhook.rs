@@ -0,0 +1,244 @@
+use crate::model::RoomEvent;
+use crate::service::config::ConfigService;
+use crate::service::lease::LeaseService;
+use crate::service::report::RoomDTO;
+use reqwest::StatusCode;
+use serde::Serialize;
+use serenity::all::{ChannelId, GuildId, Timestamp};
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::{JoinHandle, JoinSet};
+use tokio::time::Instant;
+use tracing::{debug, warn};
+
+const MAX_ATTEMPTS: u32 = 5;
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Error)]
+pub enum WebhookError {
+    #[error("Network request failed: {0}")]
+    Reqwest(#[from] reqwest::Error),
+
+    #[error("Endpoint responded with {0}")]
+    Status(StatusCode),
+
+    #[error("Endpoint is not a public https url: {0}")]
+    ForbiddenEndpoint(String),
+}
+
+pub type WebhookResult<T> = Result<T, WebhookError>;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum WebhookPayload {
+    RoomStarted {
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        timestamp: Timestamp,
+    },
+    RoomEnded {
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        timestamp: Timestamp,
+    },
+    Summary(RoomSummary),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RoomSummary {
+    pub guild_id: GuildId,
+    pub channel_id: ChannelId,
+    // as of the end of the call; None when it was never seen.
+    pub channel_name: Option<String>,
+    pub started_at: Timestamp,
+    pub ended_at: Timestamp,
+    pub duration_secs: u64,
+    pub participants: usize,
+    pub peak_participants: usize,
+    // every participant's time added up, against `duration_secs` of the room itself.
+    pub person_secs: u64,
+    pub average_concurrency: f32,
+    // url of the timeline image attached to the final report.
+    pub image_url: Option<String>,
+}
+
+impl RoomSummary {
+    pub fn from_room(now: Instant, room: &RoomDTO, image_url: Option<String>) -> Self {
+        let duration = room.wall_clock(now);
+        let ended_at = Timestamp::from_unix_timestamp(room.timestamp.unix_timestamp() + duration.as_secs() as i64)
+            .unwrap_or_else(|_| Timestamp::now());
+
+        RoomSummary {
+            guild_id: room.guild_id,
+            channel_id: room.channel_id,
+            channel_name: room.channel_name.clone(),
+            started_at: room.timestamp,
+            ended_at,
+            duration_secs: duration.as_secs(),
+            participants: room.participants.len(),
+            peak_participants: room.peak_participants,
+            person_secs: room.person_time(now).as_secs(),
+            average_concurrency: room.average_concurrency(now),
+            image_url,
+        }
+    }
+}
+
+pub struct WebhookService {
+    // must not follow redirects, which could lead past the endpoint check.
+    client: reqwest::Client,
+    config_service: Arc<ConfigService>,
+    // with other instances watching the same guilds, only the lease holder delivers.
+    lease_service: Option<Arc<LeaseService>>,
+}
+
+impl WebhookService {
+    pub fn new(client: reqwest::Client, config_service: Arc<ConfigService>) -> Self {
+        WebhookService {
+            client,
+            config_service,
+            lease_service: None,
+        }
+    }
+
+    pub fn with_lease(mut self, lease_service: Arc<LeaseService>) -> Self {
+        self.lease_service = Some(lease_service);
+        self
+    }
+
+    async fn is_active(&self, guild_id: GuildId) -> bool {
+        match &self.lease_service {
+            Some(lease_service) => lease_service.is_active(guild_id).await,
+            None => true,
+        }
+    }
+
+    // forwards room starts and ends until the room manager is dropped.
+    pub fn spawn(self: Arc<Self>, mut events: broadcast::Receiver<RoomEvent>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                let (guild_id, payload) = match events.recv().await {
+                    Ok(RoomEvent::RoomCreated { guild_id, channel_id, timestamp }) => {
+                        (guild_id, WebhookPayload::RoomStarted { guild_id, channel_id, timestamp })
+                    },
+                    Ok(RoomEvent::RoomClosed { guild_id, channel_id }) => {
+                        (guild_id, WebhookPayload::RoomEnded { guild_id, channel_id, timestamp: Timestamp::now() })
+                    },
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("webhook service lagged behind, {} room events skipped", skipped);
+                        continue;
+                    },
+                    Err(RecvError::Closed) => break,
+                };
+
+                // deliveries may back off for a while, so they must not hold up the next event.
+                let service = self.clone();
+                tokio::spawn(async move {
+                    if service.is_active(guild_id).await {
+                        service.notify(guild_id, &payload).await;
+                    }
+                });
+            }
+            debug!("webhook loop stopped");
+        })
+    }
+
+    // posts the payload to every endpoint configured for the guild.
+    pub async fn notify(&self, guild_id: GuildId, payload: &WebhookPayload) {
+        let urls = self.config_service.get(guild_id).await.webhook_urls;
+
+        let mut deliveries = JoinSet::new();
+        for url in urls {
+            let client = self.client.clone();
+            let payload = payload.clone();
+            deliveries.spawn(async move {
+                if let Err(err) = deliver(&client, &url, &payload).await {
+                    warn!("Failed to deliver webhook for guild {}: {}", guild_id, err);
+                }
+            });
+        }
+        deliveries.join_all().await;
+    }
+}
+
+// endpoints are set by guild managers, so they must not reach the bot's own network;
+// every address the host resolves to has to be public.
+pub async fn check_endpoint(url: &str) -> WebhookResult<()> {
+    let forbidden = || WebhookError::ForbiddenEndpoint(url.to_string());
+    let parsed = reqwest::Url::parse(url).map_err(|_| forbidden())?;
+    if parsed.scheme() != "https" {
+        return Err(forbidden())
+    }
+    let port = parsed.port_or_known_default().ok_or_else(forbidden)?;
+    let host = parsed.host_str().ok_or_else(forbidden)?;
+    // ipv6 hosts come bracketed.
+    let addrs: Vec<IpAddr> = match host.trim_start_matches('[').trim_end_matches(']').parse() {
+        Ok(ip) => vec![ip],
+        Err(_) => tokio::net::lookup_host((host, port)).await
+            .map_err(|_| forbidden())?
+            .map(|addr| addr.ip())
+            .collect(),
+    };
+    if addrs.is_empty() || !addrs.iter().all(is_public) {
+        return Err(forbidden())
+    }
+    Ok(())
+}
+
+fn is_public(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_v4(&ip),
+            // unique local fc00::/7 and link-local fe80::/10.
+            None => !ip.is_loopback() && !ip.is_unspecified() && !ip.is_multicast()
+                && (ip.segments()[0] & 0xfe00) != 0xfc00
+                && (ip.segments()[0] & 0xffc0) != 0xfe80,
+        },
+    }
+}
+
+fn is_public_v4(ip: &Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    // 100.64.0.0/10 is shared address space of carrier-grade NATs.
+    let shared = a == 100 && (b & 0xc0) == 64;
+    !ip.is_loopback() && !ip.is_private() && !ip.is_link_local() && !ip.is_unspecified()
+        && !ip.is_broadcast() && !ip.is_multicast() && !ip.is_documentation() && !shared
+}
+
+// retries network errors, rate limits and server errors with exponential backoff.
+async fn deliver(client: &reqwest::Client, url: &str, payload: &WebhookPayload) -> WebhookResult<()> {
+    // checked on every delivery, as the host may resolve elsewhere since it was added.
+    check_endpoint(url).await?;
+    let mut backoff = INITIAL_BACKOFF;
+    let mut attempt = 1;
+
+    loop {
+        let result = client.post(url).json(payload).timeout(REQUEST_TIMEOUT).send().await;
+        let err = match result {
+            Ok(response) if response.status().is_success() => return Ok(()),
+            Ok(response) => {
+                let status = response.status();
+                if !status.is_server_error() && status != StatusCode::TOO_MANY_REQUESTS {
+                    return Err(WebhookError::Status(status))
+                }
+                WebhookError::Status(status)
+            },
+            Err(err) => err.into(),
+        };
+
+        if attempt >= MAX_ATTEMPTS {
+            return Err(err)
+        }
+        debug!("webhook delivery attempt {} failed: {}, retry in {:?}", attempt, err, backoff);
+        tokio::time::sleep(backoff).await;
+        backoff *= 2;
+        attempt += 1;
+    }
+}
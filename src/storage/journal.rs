@@ -0,0 +1,245 @@
+use crate::model::VoiceStateFlags;
+use serde::{Deserialize, Serialize};
+use serenity::all::{ChannelId, GuildId, UserId};
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use thiserror::Error;
+use tracing::{debug, error, warn};
+
+#[derive(Debug, Error)]
+pub enum JournalError {
+    #[error("Journal I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Failed to (de)serialize journal entry: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("Journal writer has stopped")]
+    Closed,
+}
+
+pub type JournalResult<T> = Result<T, JournalError>;
+
+// a voice event as written to the journal; `at` is unix milliseconds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub at: i64,
+    #[serde(flatten)]
+    pub event: JournalEvent,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JournalEvent {
+    Connect {
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        user_id: UserId,
+        name: String,
+        face: String,
+        flags: VoiceStateFlags,
+    },
+    Disconnect {
+        channel_id: ChannelId,
+        user_id: UserId,
+    },
+    Update {
+        channel_id: ChannelId,
+        user_id: UserId,
+        flags: VoiceStateFlags,
+    },
+    // the user opted out; they are dropped from every room.
+    Forget {
+        user_id: UserId,
+    },
+    RoomClosed {
+        channel_id: ChannelId,
+    },
+    // the room of `from` moved along with its members and absorbed the room of `to`.
+    Moved {
+        from: ChannelId,
+        to: ChannelId,
+    },
+}
+
+impl JournalEvent {
+    pub fn channel_id(&self) -> Option<ChannelId> {
+        match self {
+            JournalEvent::Connect { channel_id, .. }
+            | JournalEvent::Disconnect { channel_id, .. }
+            | JournalEvent::Update { channel_id, .. }
+            | JournalEvent::RoomClosed { channel_id } => Some(*channel_id),
+            JournalEvent::Moved { to, .. } => Some(*to),
+            JournalEvent::Forget { .. } => None,
+        }
+    }
+
+    fn user_id(&self) -> Option<UserId> {
+        match self {
+            JournalEvent::Connect { user_id, .. }
+            | JournalEvent::Disconnect { user_id, .. }
+            | JournalEvent::Update { user_id, .. }
+            | JournalEvent::Forget { user_id } => Some(*user_id),
+            JournalEvent::RoomClosed { .. } | JournalEvent::Moved { .. } => None,
+        }
+    }
+}
+
+enum JournalCommand {
+    Append(JournalEntry),
+    Compact,
+}
+
+// an append-only file of voice events in JSON lines, replayed on startup to rebuild ongoing rooms.
+// a dedicated thread owns the file, so voice events never wait for the disk.
+pub struct EventJournal {
+    path: PathBuf,
+    sender: Sender<JournalCommand>,
+}
+
+impl EventJournal {
+    pub fn open(path: impl AsRef<Path>) -> JournalResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = Self::open_append(&path)?;
+        let (sender, receiver) = mpsc::channel();
+        let writer_path = path.clone();
+        std::thread::Builder::new()
+            .name(String::from("journal-writer"))
+            .spawn(move || Self::run_writer(writer_path, file, receiver))?;
+        Ok(EventJournal { path, sender })
+    }
+
+    fn open_append(path: &Path) -> std::io::Result<File> {
+        OpenOptions::new().create(true).append(true).open(path)
+    }
+
+    pub fn append(&self, at: i64, event: JournalEvent) -> JournalResult<()> {
+        self.sender.send(JournalCommand::Append(JournalEntry { at, event })).map_err(|_| JournalError::Closed)
+    }
+
+    // rewrites the journal with the entries of rooms it has not seen closed, so it only grows with open rooms.
+    // forgotten users are dropped from the entries before they were forgotten.
+    // the writer compacts what it has written so far, so events journaled meanwhile are never lost.
+    pub fn compact(&self) -> JournalResult<()> {
+        self.sender.send(JournalCommand::Compact).map_err(|_| JournalError::Closed)
+    }
+
+    // only called on startup, before any event is journaled.
+    // a torn last line of a crash is skipped along with any other unreadable line.
+    pub fn read(&self) -> JournalResult<Vec<JournalEntry>> {
+        Self::read_entries(&self.path)
+    }
+
+    // runs until the journal is dropped; errors are logged, as nobody waits for the result.
+    fn run_writer(path: PathBuf, mut file: File, receiver: Receiver<JournalCommand>) {
+        while let Ok(command) = receiver.recv() {
+            let result = match command {
+                JournalCommand::Append(entry) => {
+                    // entries queued meanwhile are written along, flushing once.
+                    let mut entries = vec![entry];
+                    let mut compact = false;
+                    while let Ok(command) = receiver.try_recv() {
+                        match command {
+                            JournalCommand::Append(entry) => entries.push(entry),
+                            JournalCommand::Compact => {
+                                compact = true;
+                                break
+                            },
+                        }
+                    }
+                    let result = Self::write_entries(&mut file, &entries);
+                    if compact {
+                        result.and_then(|_| Self::compact_file(&path, &mut file))
+                    } else {
+                        result
+                    }
+                },
+                JournalCommand::Compact => Self::compact_file(&path, &mut file),
+            };
+            if let Err(err) = result {
+                error!("Failed to write the event journal: {}", err);
+            }
+        }
+    }
+
+    fn write_entries(file: &mut File, entries: &[JournalEntry]) -> JournalResult<()> {
+        let mut lines = String::new();
+        for entry in entries {
+            lines.push_str(&serde_json::to_string(entry)?);
+            lines.push('\n');
+        }
+        file.write_all(lines.as_bytes())?;
+        file.flush()?;
+        Ok(())
+    }
+
+    fn read_entries(path: &Path) -> JournalResult<Vec<JournalEntry>> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut entries = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(entry) => entries.push(entry),
+                Err(err) => warn!("Skipping unreadable journal entry: {}", err),
+            }
+        }
+        Ok(entries)
+    }
+
+    fn compact_file(path: &Path, file: &mut File) -> JournalResult<()> {
+        let mut forgotten = HashSet::new();
+        let mut entries: Vec<JournalEntry> = Self::read_entries(path)?
+            .into_iter()
+            .rev()
+            .filter(|entry| {
+                if let JournalEvent::Forget { user_id } = entry.event {
+                    forgotten.insert(user_id);
+                    return false
+                }
+                entry.event.user_id().is_none_or(|user_id| !forgotten.contains(&user_id))
+            })
+            .collect();
+        entries.reverse();
+
+        // the entries each open room is rebuilt from; a moved room brings its entries along.
+        let mut rooms: HashMap<ChannelId, Vec<usize>> = HashMap::new();
+        for (i, entry) in entries.iter().enumerate() {
+            match entry.event {
+                JournalEvent::RoomClosed { channel_id } => {
+                    rooms.remove(&channel_id);
+                },
+                JournalEvent::Moved { from, to } => {
+                    let mut moved = rooms.remove(&from).unwrap_or_default();
+                    moved.extend(rooms.remove(&to).unwrap_or_default());
+                    moved.push(i);
+                    rooms.insert(to, moved);
+                },
+                _ => {
+                    if let Some(channel_id) = entry.event.channel_id() {
+                        rooms.entry(channel_id).or_default().push(i);
+                    }
+                },
+            }
+        }
+        let mut kept: Vec<usize> = rooms.into_values().flatten().collect();
+        kept.sort_unstable();
+        let entries: Vec<JournalEntry> = kept.into_iter().map(|i| entries[i].clone()).collect();
+
+        let temp_path = path.with_extension("tmp");
+        {
+            let mut temp = File::create(&temp_path)?;
+            Self::write_entries(&mut temp, &entries)?;
+            temp.sync_all()?;
+        }
+        std::fs::rename(&temp_path, path)?;
+        *file = Self::open_append(path)?;
+        debug!("event journal compacted to {} entries", entries.len());
+        Ok(())
+    }
+}
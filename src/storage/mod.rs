@@ -0,0 +1,73 @@
+mod sqlite;
+mod journal;
+mod room_mirror;
+#[cfg(feature = "redis-store")]
+mod redis_mirror;
+
+pub use sqlite::SqliteStorage;
+pub use journal::{EventJournal, JournalEntry, JournalError, JournalEvent, JournalResult};
+pub use room_mirror::{RoomMirror, RoomMirrorError, RoomMirrorResult};
+#[cfg(feature = "redis-store")]
+pub use redis_mirror::RedisRoomMirror;
+
+use crate::model::VoiceStateFlags;
+use serenity::all::{ChannelId, GuildId, MessageId, ScheduledEventId, UserId};
+use thiserror::Error;
+use tokio::time::Instant;
+
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+
+    #[error("Failed to (de)serialize stored value: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("Invalid stored timestamp: {0}")]
+    InvalidTimestamp(i64),
+}
+
+pub type StorageResult<T> = Result<T, StorageError>;
+
+// the room a connect is recorded in, inserted when it has no open row yet.
+#[derive(Debug, Clone, Copy)]
+pub struct OpenRoom {
+    pub guild_id: GuildId,
+    pub channel_id: ChannelId,
+    pub created_at: Instant,
+    pub session: u32,
+}
+
+// a persisted activity; times are unix milliseconds.
+#[derive(Debug, Clone)]
+pub struct StoredActivity {
+    pub room_id: i64,
+    pub channel_id: ChannelId,
+    pub user_id: UserId,
+    pub name: String,
+    pub face: String,
+    pub started_at: i64,
+    pub ended_at: Option<i64>,
+    pub flags: VoiceStateFlags,
+}
+
+// a persisted room with the number of distinct participants; times are unix milliseconds.
+#[derive(Debug, Clone)]
+pub struct StoredRoom {
+    pub id: i64,
+    pub channel_id: ChannelId,
+    pub started_at: i64,
+    pub ended_at: Option<i64>,
+    pub participants: i64,
+    // the final report posted when the room closed.
+    pub summary_message: Option<(ChannelId, MessageId)>,
+    pub scheduled_event_id: Option<ScheduledEventId>,
+}
+
+// the report message edited for an ongoing room.
+#[derive(Debug, Clone)]
+pub struct StoredReportMessage {
+    pub channel_id: ChannelId,
+    pub report_channel_id: ChannelId,
+    pub message_id: MessageId,
+}
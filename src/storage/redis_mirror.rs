@@ -0,0 +1,57 @@
+use crate::model::RoomSnapshot;
+use crate::storage::room_mirror::{RoomMirror, RoomMirrorResult};
+use redis::aio::ConnectionManager;
+use serenity::all::{ChannelId, GuildId};
+use serenity::async_trait;
+use std::time::Duration;
+
+const KEY_PREFIX: &str = "ringring";
+// rooms of a process that died without closing them expire on their own.
+const ROOM_TTL: Duration = Duration::from_hours(24);
+
+// mirrors rooms for other processes: one JSON value per room plus a set of channels per guild.
+pub struct RedisRoomMirror {
+    connection: ConnectionManager,
+}
+
+impl RedisRoomMirror {
+    pub async fn connect(url: &str) -> RoomMirrorResult<Self> {
+        let client = redis::Client::open(url)?;
+        let connection = ConnectionManager::new(client).await?;
+        Ok(RedisRoomMirror { connection })
+    }
+}
+
+fn room_key(channel_id: ChannelId) -> String {
+    format!("{}:room:{}", KEY_PREFIX, channel_id)
+}
+
+fn guild_key(guild_id: GuildId) -> String {
+    format!("{}:guild:{}:rooms", KEY_PREFIX, guild_id)
+}
+
+#[async_trait]
+impl RoomMirror for RedisRoomMirror {
+    async fn save(&self, snapshot: &RoomSnapshot) -> RoomMirrorResult<()> {
+        let value = serde_json::to_vec(snapshot)?;
+        let mut connection = self.connection.clone();
+        let () = redis::pipe()
+            .atomic()
+            .set_ex(room_key(snapshot.channel_id), value, ROOM_TTL.as_secs())
+            .sadd(guild_key(snapshot.guild_id), snapshot.channel_id.get())
+            .query_async(&mut connection)
+            .await?;
+        Ok(())
+    }
+
+    async fn remove(&self, guild_id: GuildId, channel_id: ChannelId) -> RoomMirrorResult<()> {
+        let mut connection = self.connection.clone();
+        let () = redis::pipe()
+            .atomic()
+            .del(room_key(channel_id))
+            .srem(guild_key(guild_id), channel_id.get())
+            .query_async(&mut connection)
+            .await?;
+        Ok(())
+    }
+}
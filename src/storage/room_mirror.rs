@@ -0,0 +1,25 @@
+use crate::model::RoomSnapshot;
+use serenity::all::{ChannelId, GuildId};
+use serenity::async_trait;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum RoomMirrorError {
+    #[error("Failed to (de)serialize room: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[cfg(feature = "redis-store")]
+    #[error("Redis error: {0}")]
+    Redis(#[from] redis::RedisError),
+}
+
+pub type RoomMirrorResult<T> = Result<T, RoomMirrorError>;
+
+// where this process publishes its rooms for other processes to read.
+// the rooms themselves stay in memory in `RoomManager`, which never loads them back from here.
+#[async_trait]
+pub trait RoomMirror: Send + Sync {
+    async fn save(&self, snapshot: &RoomSnapshot) -> RoomMirrorResult<()>;
+
+    async fn remove(&self, guild_id: GuildId, channel_id: ChannelId) -> RoomMirrorResult<()>;
+}
@@ -0,0 +1,676 @@
+use crate::model::{ActivitySnapshot, GuildConfig, DEFAULT_IDLE_TIMEOUT, ParticipantSnapshot, Room, RoomSnapshot, VoiceStateFlags};
+use crate::storage::{OpenRoom, StorageError, StorageResult, StoredActivity, StoredReportMessage, StoredRoom};
+use serenity::all::{ChannelId, GuildId, MessageId, ScheduledEventId, Timestamp, UserId};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions, SqliteRow};
+use sqlx::{Row, SqlitePool};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::Instant;
+use tracing::debug;
+
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE IF NOT EXISTS rooms (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        guild_id INTEGER NOT NULL,
+        channel_id INTEGER NOT NULL,
+        started_at INTEGER NOT NULL,
+        ended_at INTEGER
+    )",
+    "CREATE INDEX IF NOT EXISTS idx_rooms_channel_id ON rooms (channel_id, ended_at)",
+    "CREATE TABLE IF NOT EXISTS activities (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        room_id INTEGER NOT NULL REFERENCES rooms (id),
+        user_id INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        face TEXT NOT NULL,
+        started_at INTEGER NOT NULL,
+        ended_at INTEGER,
+        is_muted INTEGER NOT NULL,
+        is_deafened INTEGER NOT NULL,
+        is_sharing_screen INTEGER NOT NULL
+    )",
+    "CREATE INDEX IF NOT EXISTS idx_activities_room_id ON activities (room_id, user_id)",
+    "CREATE TABLE IF NOT EXISTS guild_configs (
+        guild_id INTEGER PRIMARY KEY,
+        config TEXT NOT NULL
+    )",
+    "ALTER TABLE activities ADD COLUMN is_audience INTEGER NOT NULL DEFAULT 0",
+    "CREATE TABLE IF NOT EXISTS opt_outs (
+        user_id INTEGER PRIMARY KEY
+    )",
+    "ALTER TABLE activities ADD COLUMN is_video INTEGER NOT NULL DEFAULT 0",
+    "CREATE TABLE IF NOT EXISTS user_colors (
+        user_id INTEGER PRIMARY KEY,
+        color INTEGER NOT NULL
+    )",
+    "CREATE TABLE IF NOT EXISTS report_messages (
+        channel_id INTEGER PRIMARY KEY,
+        report_channel_id INTEGER NOT NULL,
+        message_id INTEGER NOT NULL
+    )",
+    "CREATE TABLE IF NOT EXISTS digests (
+        guild_id INTEGER PRIMARY KEY,
+        sent_at INTEGER NOT NULL
+    )",
+    "ALTER TABLE rooms ADD COLUMN summary_channel_id INTEGER",
+    "ALTER TABLE rooms ADD COLUMN summary_message_id INTEGER",
+    "ALTER TABLE rooms ADD COLUMN scheduled_event_id INTEGER",
+    "CREATE TABLE IF NOT EXISTS nicknames (
+        guild_id INTEGER NOT NULL,
+        user_id INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        PRIMARY KEY (guild_id, user_id)
+    )",
+    "CREATE TABLE IF NOT EXISTS report_leases (
+        guild_id INTEGER PRIMARY KEY,
+        holder TEXT NOT NULL,
+        expires_at INTEGER NOT NULL
+    )",
+    "ALTER TABLE rooms ADD COLUMN session INTEGER NOT NULL DEFAULT 1",
+];
+
+pub struct SqliteStorage {
+    pool: SqlitePool,
+    // `Instant` is monotonic and cannot be persisted, so every conversion goes through this pair.
+    anchor_instant: Instant,
+    anchor_system: SystemTime,
+}
+
+impl SqliteStorage {
+    pub async fn connect(url: &str) -> StorageResult<Self> {
+        let options = SqliteConnectOptions::from_str(url)?.create_if_missing(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(4)
+            .connect_with(options)
+            .await?;
+
+        // user_version records how many migrations have been applied to this database.
+        let applied: i64 = sqlx::query_scalar("PRAGMA user_version").fetch_one(&pool).await?;
+        for migration in MIGRATIONS.iter().skip(applied as usize) {
+            sqlx::query(migration).execute(&pool).await?;
+        }
+        sqlx::query(&format!("PRAGMA user_version = {}", MIGRATIONS.len()))
+            .execute(&pool)
+            .await?;
+
+        Ok(SqliteStorage {
+            pool,
+            anchor_instant: Instant::now(),
+            anchor_system: SystemTime::now(),
+        })
+    }
+
+    pub fn pool(&self) -> &SqlitePool {
+        &self.pool
+    }
+
+    pub fn to_unix_millis(&self, instant: Instant) -> i64 {
+        let system = match instant.checked_duration_since(self.anchor_instant) {
+            Some(after) => self.anchor_system + after,
+            None => self.anchor_system - self.anchor_instant.duration_since(instant),
+        };
+        system.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as i64)
+    }
+
+    pub fn from_unix_millis(&self, millis: i64) -> Instant {
+        let system = UNIX_EPOCH + Duration::from_millis(millis.max(0) as u64);
+        match system.duration_since(self.anchor_system) {
+            Ok(after) => self.anchor_instant + after,
+            Err(err) => self.anchor_instant.checked_sub(err.duration()).unwrap_or(self.anchor_instant),
+        }
+    }
+
+    async fn find_open_room(&self, channel_id: ChannelId) -> StorageResult<Option<i64>> {
+        let row = sqlx::query("SELECT id FROM rooms WHERE channel_id = ? AND ended_at IS NULL ORDER BY id DESC LIMIT 1")
+            .bind(channel_id.get() as i64)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.map(|row| row.get("id")))
+    }
+
+    pub async fn record_connect(&self, room: OpenRoom, now: Instant, user_id: UserId, name: &str, face: &str, flags: VoiceStateFlags) -> StorageResult<()> {
+        let room_id = match self.find_open_room(room.channel_id).await? {
+            Some(room_id) => room_id,
+            None => {
+                debug!("no open room stored, insert new room");
+                sqlx::query("INSERT INTO rooms (guild_id, channel_id, started_at, session) VALUES (?, ?, ?, ?)")
+                    .bind(room.guild_id.get() as i64)
+                    .bind(room.channel_id.get() as i64)
+                    .bind(self.to_unix_millis(room.created_at))
+                    .bind(room.session as i64)
+                    .execute(&self.pool)
+                    .await?
+                    .last_insert_rowid()
+            }
+        };
+
+        self.insert_activity(room_id, user_id, name, face, now, flags).await
+    }
+
+    pub async fn record_disconnect(&self, channel_id: ChannelId, now: Instant, user_id: UserId) -> StorageResult<()> {
+        sqlx::query(
+            "UPDATE activities SET ended_at = ?
+             WHERE user_id = ? AND ended_at IS NULL
+               AND room_id IN (SELECT id FROM rooms WHERE channel_id = ? AND ended_at IS NULL)",
+        )
+            .bind(self.to_unix_millis(now))
+            .bind(user_id.get() as i64)
+            .bind(channel_id.get() as i64)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    // mirrors a merged rejoin: the user's last activity is reopened instead of inserting a new one.
+    pub async fn record_reconnect(&self, channel_id: ChannelId, now: Instant, user_id: UserId, flags: VoiceStateFlags) -> StorageResult<()> {
+        sqlx::query(
+            "UPDATE activities SET ended_at = NULL
+             WHERE id = (
+                SELECT a.id FROM activities a JOIN rooms r ON a.room_id = r.id
+                WHERE r.channel_id = ? AND r.ended_at IS NULL AND a.user_id = ?
+                ORDER BY a.id DESC LIMIT 1
+             )",
+        )
+            .bind(channel_id.get() as i64)
+            .bind(user_id.get() as i64)
+            .execute(&self.pool)
+            .await?;
+
+        self.record_update(channel_id, now, user_id, flags).await
+    }
+
+    pub async fn record_update(&self, channel_id: ChannelId, now: Instant, user_id: UserId, flags: VoiceStateFlags) -> StorageResult<()> {
+        let row = sqlx::query(
+            "SELECT a.id, a.room_id, a.name, a.face, a.is_muted, a.is_deafened, a.is_sharing_screen, a.is_audience, a.is_video
+             FROM activities a JOIN rooms r ON a.room_id = r.id
+             WHERE r.channel_id = ? AND r.ended_at IS NULL AND a.user_id = ? AND a.ended_at IS NULL
+             ORDER BY a.id DESC LIMIT 1",
+        )
+            .bind(channel_id.get() as i64)
+            .bind(user_id.get() as i64)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        let row = match row {
+            Some(row) => row,
+            None => {
+                debug!("no open activity stored to update");
+                return Ok(())
+            }
+        };
+
+        // mirrors `Participant::update`: unchanged flags do not split the activity.
+        if flags_from_row(&row) == flags {
+            return Ok(())
+        }
+
+        let activity_id: i64 = row.get("id");
+        let room_id: i64 = row.get("room_id");
+        let name: String = row.get("name");
+        let face: String = row.get("face");
+
+        sqlx::query("UPDATE activities SET ended_at = ? WHERE id = ?")
+            .bind(self.to_unix_millis(now))
+            .bind(activity_id)
+            .execute(&self.pool)
+            .await?;
+
+        self.insert_activity(room_id, user_id, &name, &face, now, flags).await
+    }
+
+    pub async fn close_room(&self, channel_id: ChannelId, now: Instant) -> StorageResult<()> {
+        let now_millis = self.to_unix_millis(now);
+        sqlx::query(
+            "UPDATE activities SET ended_at = ?
+             WHERE ended_at IS NULL
+               AND room_id IN (SELECT id FROM rooms WHERE channel_id = ? AND ended_at IS NULL)",
+        )
+            .bind(now_millis)
+            .bind(channel_id.get() as i64)
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query("UPDATE rooms SET ended_at = ? WHERE channel_id = ? AND ended_at IS NULL")
+            .bind(now_millis)
+            .bind(channel_id.get() as i64)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    // takes the latest room of the channel back after it ended, when a call continues it.
+    pub async fn reopen_room(&self, channel_id: ChannelId) -> StorageResult<()> {
+        sqlx::query("UPDATE rooms SET ended_at = NULL WHERE id = (SELECT id FROM rooms WHERE channel_id = ? ORDER BY id DESC LIMIT 1)")
+            .bind(channel_id.get() as i64)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    // continues the open room of `from` in `to`, taking over the activities of the room opened there.
+    pub async fn migrate_room(&self, from: ChannelId, to: ChannelId) -> StorageResult<()> {
+        let from_room_id = match self.find_open_room(from).await? {
+            Some(room_id) => room_id,
+            None => return Ok(()),
+        };
+        let to_room_id = self.find_open_room(to).await?;
+
+        let mut tx = self.pool.begin().await?;
+        if let Some(to_room_id) = to_room_id {
+            sqlx::query("UPDATE activities SET room_id = ? WHERE room_id = ?")
+                .bind(from_room_id)
+                .bind(to_room_id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM rooms WHERE id = ?")
+                .bind(to_room_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        sqlx::query("UPDATE rooms SET channel_id = ? WHERE id = ?")
+            .bind(to.get() as i64)
+            .bind(from_room_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // Loads every room that was still open when the bot stopped.
+    // Activities left open are closed at `now`; members still in voice get reconnected by `cache_ready`.
+    pub async fn restore_rooms(&self, now: Instant) -> StorageResult<Vec<Room>> {
+        sqlx::query(
+            "UPDATE activities SET ended_at = ?
+             WHERE ended_at IS NULL
+               AND room_id IN (SELECT id FROM rooms WHERE ended_at IS NULL)",
+        )
+            .bind(self.to_unix_millis(now))
+            .execute(&self.pool)
+            .await?;
+
+        let room_rows = sqlx::query("SELECT id, guild_id, channel_id, started_at, session FROM rooms WHERE ended_at IS NULL")
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut rooms = Vec::with_capacity(room_rows.len());
+        for room_row in room_rows {
+            let started_at: i64 = room_row.get("started_at");
+            let snapshot = self.load_room_snapshot(&room_row).await?;
+            rooms.push(Room::from_snapshot(snapshot, self.from_unix_millis(started_at), now));
+        }
+
+        Ok(rooms)
+    }
+
+    // loads a room of the guild with the instant it ended at, or `now` if it is still open.
+    pub async fn load_room(&self, guild_id: GuildId, room_id: i64, now: Instant) -> StorageResult<Option<(Room, Instant)>> {
+        let room_row = sqlx::query("SELECT id, guild_id, channel_id, started_at, ended_at, session FROM rooms WHERE id = ? AND guild_id = ?")
+            .bind(room_id)
+            .bind(guild_id.get() as i64)
+            .fetch_optional(&self.pool)
+            .await?;
+        let room_row = match room_row {
+            Some(room_row) => room_row,
+            None => return Ok(None),
+        };
+
+        let started_at: i64 = room_row.get("started_at");
+        let ended_at = room_row.get::<Option<i64>, _>("ended_at").map_or(now, |millis| self.from_unix_millis(millis));
+        let snapshot = self.load_room_snapshot(&room_row).await?;
+        Ok(Some((Room::from_snapshot(snapshot, self.from_unix_millis(started_at), ended_at), ended_at)))
+    }
+
+    // expects the id, guild_id, channel_id, started_at and session columns of a room row.
+    async fn load_room_snapshot(&self, room_row: &SqliteRow) -> StorageResult<RoomSnapshot> {
+        let room_id: i64 = room_row.get("id");
+        let started_at: i64 = room_row.get("started_at");
+        let timestamp = Timestamp::from_unix_timestamp(started_at / 1000)
+            .map_err(|_| StorageError::InvalidTimestamp(started_at))?;
+
+        let activity_rows = sqlx::query(
+            "SELECT user_id, name, face, started_at, ended_at, is_muted, is_deafened, is_sharing_screen, is_audience, is_video
+             FROM activities WHERE room_id = ? ORDER BY id",
+        )
+            .bind(room_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        // keep participants in join order, as `Room` does.
+        let offset = |millis: i64| (millis - started_at).max(0) as u64;
+        let mut participants: Vec<ParticipantSnapshot> = Vec::new();
+        for row in activity_rows {
+            let user_id = UserId::new(row.get::<i64, _>("user_id") as u64);
+            let activity = ActivitySnapshot {
+                start_ms: offset(row.get("started_at")),
+                end_ms: row.get::<Option<i64>, _>("ended_at").map(offset),
+                flags: flags_from_row(&row),
+            };
+
+            match participants.iter_mut().find(|participant| participant.user_id == user_id) {
+                Some(participant) => participant.activities.push(activity),
+                None => participants.push(ParticipantSnapshot {
+                    user_id,
+                    name: row.get("name"),
+                    face: row.get("face"),
+                    departed: false,
+                    activities: vec![activity],
+                }),
+            }
+        }
+
+        Ok(RoomSnapshot {
+            guild_id: GuildId::new(room_row.get::<i64, _>("guild_id") as u64),
+            channel_id: ChannelId::new(room_row.get::<i64, _>("channel_id") as u64),
+            timestamp,
+            // the manager applies the guild's own timeout after restoring.
+            idle_timeout_secs: DEFAULT_IDLE_TIMEOUT.as_secs(),
+            session: room_row.get::<i64, _>("session") as u32,
+            participants,
+        })
+    }
+
+    // links the final report to the room of the channel that started at `started_at`.
+    pub async fn save_summary_message(&self, channel_id: ChannelId, started_at: i64, report_channel_id: ChannelId, message_id: MessageId) -> StorageResult<()> {
+        sqlx::query("UPDATE rooms SET summary_channel_id = ?, summary_message_id = ? WHERE channel_id = ? AND started_at = ?")
+            .bind(report_channel_id.get() as i64)
+            .bind(message_id.get() as i64)
+            .bind(channel_id.get() as i64)
+            .bind(started_at)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    // tags the open room of the channel with the scheduled event held in it.
+    pub async fn save_scheduled_event(&self, channel_id: ChannelId, started_at: i64, event_id: ScheduledEventId) -> StorageResult<()> {
+        sqlx::query("UPDATE rooms SET scheduled_event_id = ? WHERE channel_id = ? AND started_at = ?")
+            .bind(event_id.get() as i64)
+            .bind(channel_id.get() as i64)
+            .bind(started_at)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn load_guild_config(&self, guild_id: GuildId) -> StorageResult<Option<GuildConfig>> {
+        let row = sqlx::query("SELECT config FROM guild_configs WHERE guild_id = ?")
+            .bind(guild_id.get() as i64)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        match row {
+            Some(row) => Ok(Some(serde_json::from_str(row.get("config"))?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn save_guild_config(&self, guild_id: GuildId, config: &GuildConfig) -> StorageResult<()> {
+        sqlx::query("INSERT INTO guild_configs (guild_id, config) VALUES (?, ?) ON CONFLICT (guild_id) DO UPDATE SET config = excluded.config")
+            .bind(guild_id.get() as i64)
+            .bind(serde_json::to_string(config)?)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn load_configured_guilds(&self) -> StorageResult<Vec<GuildId>> {
+        let rows = sqlx::query("SELECT guild_id FROM guild_configs")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.iter().map(|row| GuildId::new(row.get::<i64, _>("guild_id") as u64)).collect())
+    }
+
+    // unix milliseconds of the last digest posted for the guild.
+    pub async fn load_digest_sent_at(&self, guild_id: GuildId) -> StorageResult<Option<i64>> {
+        let row = sqlx::query("SELECT sent_at FROM digests WHERE guild_id = ?")
+            .bind(guild_id.get() as i64)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(|row| row.get("sent_at")))
+    }
+
+    pub async fn save_digest_sent_at(&self, guild_id: GuildId, sent_at: i64) -> StorageResult<()> {
+        sqlx::query("INSERT INTO digests (guild_id, sent_at) VALUES (?, ?) ON CONFLICT (guild_id) DO UPDATE SET sent_at = excluded.sent_at")
+            .bind(guild_id.get() as i64)
+            .bind(sent_at)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn is_opted_out(&self, user_id: UserId) -> StorageResult<bool> {
+        let row = sqlx::query("SELECT 1 FROM opt_outs WHERE user_id = ?")
+            .bind(user_id.get() as i64)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.is_some())
+    }
+
+    // opting out also deletes every activity recorded for the user.
+    pub async fn set_opted_out(&self, user_id: UserId, opted_out: bool) -> StorageResult<()> {
+        let mut tx = self.pool.begin().await?;
+        if opted_out {
+            sqlx::query("INSERT OR IGNORE INTO opt_outs (user_id) VALUES (?)")
+                .bind(user_id.get() as i64)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM activities WHERE user_id = ?")
+                .bind(user_id.get() as i64)
+                .execute(&mut *tx)
+                .await?;
+        } else {
+            sqlx::query("DELETE FROM opt_outs WHERE user_id = ?")
+                .bind(user_id.get() as i64)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // colors are stored as 0xRRGGBB.
+    pub async fn load_user_color(&self, user_id: UserId) -> StorageResult<Option<u32>> {
+        let row = sqlx::query("SELECT color FROM user_colors WHERE user_id = ?")
+            .bind(user_id.get() as i64)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(|row| row.get::<i64, _>("color") as u32))
+    }
+
+    // None removes the custom color.
+    pub async fn save_user_color(&self, user_id: UserId, color: Option<u32>) -> StorageResult<()> {
+        match color {
+            Some(color) => {
+                sqlx::query("INSERT INTO user_colors (user_id, color) VALUES (?, ?) ON CONFLICT (user_id) DO UPDATE SET color = excluded.color")
+                    .bind(user_id.get() as i64)
+                    .bind(color as i64)
+                    .execute(&self.pool)
+                    .await?;
+            },
+            None => {
+                sqlx::query("DELETE FROM user_colors WHERE user_id = ?")
+                    .bind(user_id.get() as i64)
+                    .execute(&self.pool)
+                    .await?;
+            },
+        }
+        Ok(())
+    }
+
+    pub async fn load_nickname(&self, guild_id: GuildId, user_id: UserId) -> StorageResult<Option<String>> {
+        let row = sqlx::query("SELECT name FROM nicknames WHERE guild_id = ? AND user_id = ?")
+            .bind(guild_id.get() as i64)
+            .bind(user_id.get() as i64)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(|row| row.get("name")))
+    }
+
+    // None removes the nickname.
+    pub async fn save_nickname(&self, guild_id: GuildId, user_id: UserId, name: Option<&str>) -> StorageResult<()> {
+        match name {
+            Some(name) => {
+                sqlx::query("INSERT INTO nicknames (guild_id, user_id, name) VALUES (?, ?, ?) ON CONFLICT (guild_id, user_id) DO UPDATE SET name = excluded.name")
+                    .bind(guild_id.get() as i64)
+                    .bind(user_id.get() as i64)
+                    .bind(name)
+                    .execute(&self.pool)
+                    .await?;
+            },
+            None => {
+                sqlx::query("DELETE FROM nicknames WHERE guild_id = ? AND user_id = ?")
+                    .bind(guild_id.get() as i64)
+                    .bind(user_id.get() as i64)
+                    .execute(&self.pool)
+                    .await?;
+            },
+        }
+        Ok(())
+    }
+
+    // takes or renews the lease unless another holder's one is still valid; returns whether `holder` has it.
+    pub async fn try_acquire_lease(&self, guild_id: GuildId, holder: &str, now: i64, ttl: Duration) -> StorageResult<bool> {
+        let result = sqlx::query(
+            "INSERT INTO report_leases (guild_id, holder, expires_at) VALUES (?, ?, ?)
+             ON CONFLICT (guild_id) DO UPDATE SET holder = excluded.holder, expires_at = excluded.expires_at
+             WHERE report_leases.holder = excluded.holder OR report_leases.expires_at <= ?",
+        )
+            .bind(guild_id.get() as i64)
+            .bind(holder)
+            .bind(now + ttl.as_millis() as i64)
+            .bind(now)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn release_leases(&self, holder: &str) -> StorageResult<()> {
+        sqlx::query("DELETE FROM report_leases WHERE holder = ?")
+            .bind(holder)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn load_report_messages(&self) -> StorageResult<Vec<StoredReportMessage>> {
+        let rows = sqlx::query("SELECT channel_id, report_channel_id, message_id FROM report_messages")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.iter().map(|row| StoredReportMessage {
+            channel_id: ChannelId::new(row.get::<i64, _>("channel_id") as u64),
+            report_channel_id: ChannelId::new(row.get::<i64, _>("report_channel_id") as u64),
+            message_id: MessageId::new(row.get::<i64, _>("message_id") as u64),
+        }).collect())
+    }
+
+    pub async fn save_report_message(&self, channel_id: ChannelId, report_channel_id: ChannelId, message_id: MessageId) -> StorageResult<()> {
+        sqlx::query(
+            "INSERT INTO report_messages (channel_id, report_channel_id, message_id) VALUES (?, ?, ?)
+             ON CONFLICT (channel_id) DO UPDATE SET report_channel_id = excluded.report_channel_id, message_id = excluded.message_id",
+        )
+            .bind(channel_id.get() as i64)
+            .bind(report_channel_id.get() as i64)
+            .bind(message_id.get() as i64)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete_report_message(&self, channel_id: ChannelId) -> StorageResult<()> {
+        sqlx::query("DELETE FROM report_messages WHERE channel_id = ?")
+            .bind(channel_id.get() as i64)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    // loads rooms of a guild started in [since, until), newest first.
+    pub async fn load_rooms(&self, guild_id: GuildId, since: i64, until: i64) -> StorageResult<Vec<StoredRoom>> {
+        let rows = sqlx::query(
+            "SELECT r.id, r.channel_id, r.started_at, r.ended_at, r.summary_channel_id, r.summary_message_id, r.scheduled_event_id, COUNT(DISTINCT a.user_id) AS participants
+             FROM rooms r LEFT JOIN activities a ON a.room_id = r.id
+             WHERE r.guild_id = ? AND r.started_at >= ? AND r.started_at < ?
+             GROUP BY r.id
+             ORDER BY r.started_at DESC",
+        )
+            .bind(guild_id.get() as i64)
+            .bind(since)
+            .bind(until)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter().map(|row| StoredRoom {
+            id: row.get("id"),
+            channel_id: ChannelId::new(row.get::<i64, _>("channel_id") as u64),
+            started_at: row.get("started_at"),
+            ended_at: row.get("ended_at"),
+            participants: row.get("participants"),
+            summary_message: match (row.get::<Option<i64>, _>("summary_channel_id"), row.get::<Option<i64>, _>("summary_message_id")) {
+                (Some(channel_id), Some(message_id)) => Some((ChannelId::new(channel_id as u64), MessageId::new(message_id as u64))),
+                _ => None,
+            },
+            scheduled_event_id: row.get::<Option<i64>, _>("scheduled_event_id").map(|event_id| ScheduledEventId::new(event_id as u64)),
+        }).collect())
+    }
+
+    // loads activities of a guild overlapping [since, until), ordered by room, user and start.
+    pub async fn load_activities(&self, guild_id: GuildId, user_id: Option<UserId>, since: i64, until: i64) -> StorageResult<Vec<StoredActivity>> {
+        let user_id = user_id.map(|user_id| user_id.get() as i64);
+        let rows = sqlx::query(
+            "SELECT a.room_id, r.channel_id, a.user_id, a.name, a.face, a.started_at, a.ended_at, a.is_muted, a.is_deafened, a.is_sharing_screen, a.is_audience, a.is_video
+             FROM activities a JOIN rooms r ON a.room_id = r.id
+             WHERE r.guild_id = ? AND (? IS NULL OR a.user_id = ?)
+               AND a.started_at < ? AND (a.ended_at IS NULL OR a.ended_at >= ?)
+             ORDER BY a.room_id, a.user_id, a.started_at, a.id",
+        )
+            .bind(guild_id.get() as i64)
+            .bind(user_id)
+            .bind(user_id)
+            .bind(until)
+            .bind(since)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter().map(|row| StoredActivity {
+            room_id: row.get("room_id"),
+            channel_id: ChannelId::new(row.get::<i64, _>("channel_id") as u64),
+            user_id: UserId::new(row.get::<i64, _>("user_id") as u64),
+            name: row.get("name"),
+            face: row.get("face"),
+            started_at: row.get("started_at"),
+            ended_at: row.get("ended_at"),
+            flags: flags_from_row(row),
+        }).collect())
+    }
+
+    async fn insert_activity(&self, room_id: i64, user_id: UserId, name: &str, face: &str, now: Instant, flags: VoiceStateFlags) -> StorageResult<()> {
+        sqlx::query(
+            "INSERT INTO activities (room_id, user_id, name, face, started_at, is_muted, is_deafened, is_sharing_screen, is_audience, is_video)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+            .bind(room_id)
+            .bind(user_id.get() as i64)
+            .bind(name)
+            .bind(face)
+            .bind(self.to_unix_millis(now))
+            .bind(flags.is_muted)
+            .bind(flags.is_deafened)
+            .bind(flags.is_sharing_screen)
+            .bind(flags.is_audience)
+            .bind(flags.is_video)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+fn flags_from_row(row: &SqliteRow) -> VoiceStateFlags {
+    VoiceStateFlags {
+        is_muted: row.get("is_muted"),
+        is_deafened: row.get("is_deafened"),
+        is_sharing_screen: row.get("is_sharing_screen"),
+        is_audience: row.get("is_audience"),
+        is_video: row.get("is_video"),
+    }
+}